@@ -0,0 +1,165 @@
+// Fills the arena with 10,000 small drifting boxes, to see how collision
+// detection holds up with a crowd. The CollisionSystem only tests the boxes
+// sharing a SpatialGrid cell with the player, so flying through the crowd
+// costs about the same as flying through a handful of ships. The frame
+// watchdog prints the slowest system whenever a frame goes over budget.
+//
+//     cargo run --release --example collision_stress
+//
+// Once a second it prints how long collision took per update, the grid's
+// broad phase and collision systems together. With `--brute-force` it also
+// tests every player against every box on the same world the way collision
+// used to, a join nested in a join, and prints how long that took alongside.
+//
+//     cargo run --release --example collision_stress -- --brute-force
+use ggez::nalgebra;
+use ggez_specs::{CollisionBox, ControllableTag, GameBuilder, Position, SystemTimes, Velocity};
+use specs::{Builder, Entities, Join, Read, ReadStorage, System, WorldExt, Write};
+use std::env;
+use std::time::Duration;
+
+const COUNT: usize = 10_000;
+const PER_ROW: usize = 100;
+const SIZE: f32 = 4.0;
+
+// the systems timed for each way of finding collisions, as the profiler names
+// them
+const GRID: &[&str] = &["broad phase", "collision"];
+const BRUTE_FORCE: &[&str] = &["brute force collision"];
+const REPORT: &str = "collision report";
+
+// updates between reports
+const REPORT_EVERY: u32 = 60;
+
+fn overlaps(a: &CollisionBox, b: &CollisionBox) -> bool {
+    a.origin.x < b.origin.x + b.width
+        && a.origin.x + a.width > b.origin.x
+        && a.origin.y < b.origin.y + b.height
+        && a.origin.y + a.height > b.origin.y
+}
+
+// how many boxes the brute force pass found touching a player last update
+#[derive(Clone, Default)]
+struct Touching {
+    count: usize,
+}
+
+// Collision as it was before the SpatialGrid: every player's box against the
+// box of every entity that isn't a player. It only counts what it finds, the
+// built-in CollisionSystem still handles what actually touched.
+struct BruteForceSystem;
+
+impl<'a> System<'a> for BruteForceSystem {
+    type SystemData = (
+        Write<'a, Touching>,
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, ControllableTag>,
+    );
+
+    fn run(&mut self, (mut touching, entities, pos, coll_box, controlled): Self::SystemData) {
+        touching.count = 0;
+        for (player_box, _) in (&coll_box, &controlled).join() {
+            for (_, _, coll_box, _) in (&entities, &pos, &coll_box, !&controlled).join() {
+                if overlaps(player_box, coll_box) {
+                    touching.count += 1;
+                }
+            }
+        }
+    }
+}
+
+// Adds up how long each way of finding collisions took per update and prints
+// the averages every REPORT_EVERY updates. The SystemTimes are per frame, and
+// a frame that catches up runs more than one update, so it only takes what
+// was added since it last looked, starting again from nothing on a new frame.
+struct ReportSystem {
+    brute_force: bool,
+    updates: u32,
+    grid: Duration,
+    grid_seen: Duration,
+    brute: Duration,
+    brute_seen: Duration,
+}
+
+fn total(times: &SystemTimes, names: &[&str]) -> Duration {
+    names.iter().filter_map(|name| times.get(name)).sum()
+}
+
+impl<'a> System<'a> for ReportSystem {
+    type SystemData = (Read<'a, SystemTimes>, Read<'a, Touching>);
+
+    fn run(&mut self, (times, touching): Self::SystemData) {
+        // this system is timed after it runs, so it's missing on a new frame
+        if times.get(REPORT).is_none() {
+            self.grid_seen = Duration::default();
+            self.brute_seen = Duration::default();
+        }
+        let grid = total(&times, GRID);
+        let brute = total(&times, BRUTE_FORCE);
+        self.grid += grid - self.grid_seen;
+        self.brute += brute - self.brute_seen;
+        self.grid_seen = grid;
+        self.brute_seen = brute;
+
+        self.updates += 1;
+        if self.updates < REPORT_EVERY {
+            return;
+        }
+        let millis = |time: Duration| time.as_secs_f64() * 1000.0 / f64::from(self.updates);
+        if self.brute_force {
+            println!(
+                "collision per update: grid {:.3}ms, brute force {:.3}ms ({} touching)",
+                millis(self.grid),
+                millis(self.brute),
+                touching.count
+            );
+        } else {
+            println!("collision per update: grid {:.3}ms", millis(self.grid));
+        }
+        self.updates = 0;
+        self.grid = Duration::default();
+        self.brute = Duration::default();
+    }
+}
+
+fn main() {
+    let brute_force = env::args().any(|arg| arg == "--brute-force");
+
+    let mut builder = GameBuilder::new()
+        .seed(1)
+        .with_resource(Touching::default())
+        .with_setup(|world| {
+            for i in 0..COUNT {
+                let origin =
+                    nalgebra::Point2::new((i % PER_ROW) as f32 * 8.0, (i / PER_ROW) as f32 * 6.0);
+                // a slow drift, different for every box
+                let velocity =
+                    nalgebra::Vector2::new((i % 7) as f32 * 4.0 - 12.0, (i % 5) as f32 * 4.0 - 8.0);
+                world
+                    .create_entity()
+                    .with(Position { position: origin })
+                    .with(Velocity { velocity })
+                    .with(CollisionBox {
+                        origin,
+                        width: SIZE,
+                        height: SIZE,
+                    })
+                    .build();
+            }
+        });
+    if brute_force {
+        builder = builder.with_system(BRUTE_FORCE[0], |_| BruteForceSystem);
+    }
+    builder
+        .with_system(REPORT, move |_| ReportSystem {
+            brute_force,
+            updates: 0,
+            grid: Duration::default(),
+            grid_seen: Duration::default(),
+            brute: Duration::default(),
+            brute_seen: Duration::default(),
+        })
+        .run();
+}
@@ -0,0 +1,350 @@
+use crate::faction::{self, Faction};
+use crate::quality::Quality;
+use crate::rng::GameRng;
+use crate::stealth::{self, Cloaked, Revealed};
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::{BoxOffset, CollisionBox, Position, Rotation, DESIRED_FPS};
+use ggez::nalgebra;
+use rand::distributions::Normal;
+use rand::Rng;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::saveload::ConvertSaveload;
+use specs::*;
+use specs_derive::*;
+use std::collections::VecDeque;
+
+// how far away an AI ship will engage a target
+const ENGAGE_RANGE: f32 = 500.0;
+// the AI pulls the trigger once it is pointing within this angle of where it
+// wants to shoot
+const FIRE_ANGLE: f32 = 0.1;
+// seconds between fresh aim error rolls, so the aim drifts rather than jitters
+const AIM_WOBBLE_TIME: f32 = 0.5;
+// pixels per second an AI ship flies at
+const AI_SPEED: f32 = 120.0;
+// spreads the think ticks of different ships across this many frames
+const THINK_STAGGER: u64 = 17;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::Normal
+    }
+}
+
+impl Difficulty {
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Difficulty::Easy),
+            "normal" => Some(Difficulty::Normal),
+            "hard" => Some(Difficulty::Hard),
+            _ => None,
+        }
+    }
+}
+
+// Makes a ship fly itself. Rather than tracking its target perfectly the AI
+// only sees where the target was reaction_time seconds ago, and every shot is
+// thrown off by a normally distributed aim error. Harder difficulties shrink
+// both, but never to zero, so even a hard AI can be dodged.
+//
+// Looking for targets and deciding what to do is done a few times a second
+// rather than every frame, on a think tick. Steering, aiming and firing at the
+// chosen target still happen every frame.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub(crate) struct AiControlled {
+    // seconds between the target moving and the AI noticing
+    pub(crate) reaction_time: f32,
+    // standard deviation of the aim error, in radians
+    pub(crate) aim_error: f32,
+    // radians per second the ship can turn
+    pub(crate) turn_rate: f32,
+    // seconds between think ticks
+    pub(crate) think_interval: f32,
+    // set by the ThinkSystem on frames when this AI gets to think
+    pub(crate) thinking: bool,
+    // the ship picked as a target on the last think tick
+    pub(crate) target: Option<Entity>,
+    // what the AI has seen of its target, newest first, with how long ago it
+    // was seen
+    seen: VecDeque<(f32, nalgebra::Point2<f32>)>,
+    aim_offset: f32,
+    wobble: f32,
+    // whether to go after the nearest target at all. Ships without a behavior
+    // always do, a behavior tree can turn this off and on.
+    pub(crate) engage: bool,
+    // the direction to fly in, zero to stay put
+    pub(crate) steer: nalgebra::Vector2<f32>,
+    // read by the FireSystem, the AI's version of the fire button
+    pub(crate) firing: bool,
+}
+
+impl AiControlled {
+    pub(crate) fn new(difficulty: Difficulty) -> Self {
+        let (reaction_time, aim_error, turn_rate, think_interval) = match difficulty {
+            Difficulty::Easy => (0.6, 0.25, 1.5, 0.3),
+            Difficulty::Normal => (0.35, 0.12, 2.5, 0.2),
+            Difficulty::Hard => (0.2, 0.05, 4.0, 0.1),
+        };
+        AiControlled {
+            reaction_time,
+            aim_error,
+            turn_rate,
+            think_interval,
+            thinking: false,
+            target: None,
+            seen: VecDeque::new(),
+            aim_offset: 0.0,
+            wobble: 0.0,
+            engage: true,
+            steer: nalgebra::Vector2::new(0.0, 0.0),
+            firing: false,
+        }
+    }
+
+    // remember where the target is now, forget anything too old to matter
+    // and return the newest sighting the AI has had time to react to
+    fn observe(&mut self, target: nalgebra::Point2<f32>, dt: f32) -> Option<nalgebra::Point2<f32>> {
+        for (age, _) in self.seen.iter_mut() {
+            *age += dt;
+        }
+        self.seen.push_front((0.0, target));
+
+        let reaction_time = self.reaction_time;
+        let reacted = self.seen.iter().position(|(age, _)| *age >= reaction_time);
+        match reacted {
+            Some(i) => {
+                self.seen.truncate(i + 1);
+                self.seen.back().map(|(_, point)| *point)
+            }
+            None => None,
+        }
+    }
+}
+
+// An AiControlled as it's quicksaved, with its target by their marker
+#[derive(Serialize, Deserialize)]
+pub(crate) struct AiData<M> {
+    reaction_time: f32,
+    aim_error: f32,
+    turn_rate: f32,
+    think_interval: f32,
+    thinking: bool,
+    target: Option<M>,
+    seen: VecDeque<(f32, nalgebra::Point2<f32>)>,
+    aim_offset: f32,
+    wobble: f32,
+    engage: bool,
+    steer: nalgebra::Vector2<f32>,
+    firing: bool,
+}
+
+impl<M: Serialize + DeserializeOwned> ConvertSaveload<M> for AiControlled {
+    type Data = AiData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        Ok(AiData {
+            reaction_time: self.reaction_time,
+            aim_error: self.aim_error,
+            turn_rate: self.turn_rate,
+            think_interval: self.think_interval,
+            thinking: self.thinking,
+            target: self.target.and_then(ids),
+            seen: self.seen.clone(),
+            aim_offset: self.aim_offset,
+            wobble: self.wobble,
+            engage: self.engage,
+            steer: self.steer,
+            firing: self.firing,
+        })
+    }
+
+    fn convert_from<F>(data: Self::Data, ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        Ok(AiControlled {
+            reaction_time: data.reaction_time,
+            aim_error: data.aim_error,
+            turn_rate: data.turn_rate,
+            think_interval: data.think_interval,
+            thinking: data.thinking,
+            target: data.target.and_then(ids),
+            seen: data.seen,
+            aim_offset: data.aim_offset,
+            wobble: data.wobble,
+            engage: data.engage,
+            steer: data.steer,
+            firing: data.firing,
+        })
+    }
+}
+
+// The nearest hostile ship that entity can see within range
+fn nearest_hostile(
+    entity: Entity,
+    entities: &Entities,
+    coll_box: &ReadStorage<CollisionBox>,
+    factions: &ReadStorage<Faction>,
+    cloaked: &ReadStorage<Cloaked>,
+    revealed: &ReadStorage<Revealed>,
+) -> Option<Entity> {
+    let origin = coll_box.get(entity)?.center();
+    let side = factions.get(entity);
+
+    (entities, coll_box, cloaked.maybe(), revealed.maybe())
+        .join()
+        .filter(|(other, _, cloak, reveal)| {
+            *other != entity
+                && !stealth::is_hidden(*cloak, *reveal)
+                && faction::hostile(side, factions.get(*other))
+        })
+        .map(|(other, other_box, _, _)| (other, (other_box.center() - origin).norm()))
+        .filter(|(_, distance)| *distance <= ENGAGE_RANGE)
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(other, _)| other)
+}
+
+// Decides which AI ships think this frame, and gives those that do a fresh
+// look for targets. Each ship thinks every think_interval seconds, offset by
+// its entity id so a crowd of them doesn't all think on the same frame.
+// Lower quality presets stretch the interval.
+#[derive(Default)]
+pub(crate) struct ThinkSystem {
+    frame: u64,
+}
+
+impl<'a> System<'a> for ThinkSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Quality>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, Cloaked>,
+        ReadStorage<'a, Revealed>,
+        WriteStorage<'a, AiControlled>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, quality, coll_box, factions, cloaked, revealed, mut ai) = data;
+        self.frame += 1;
+
+        for (entity, ai) in (&entities, &mut ai).join() {
+            let think_interval = ai.think_interval * quality.think_scale;
+            let interval = ((think_interval * DESIRED_FPS as f32).round() as u64).max(1);
+            let offset = u64::from(entity.id()) * THINK_STAGGER;
+            ai.thinking = (self.frame + offset) % interval == 0;
+            if ai.thinking {
+                ai.target =
+                    nearest_hostile(entity, &entities, &coll_box, &factions, &cloaked, &revealed);
+            }
+        }
+    }
+}
+
+// Flies AI ships where they have been steered, points them at their target
+// and fires when lined up
+pub(crate) struct AiSystem;
+
+impl<'a> System<'a> for AiSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, GameRng>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, CollisionBox>,
+        ReadStorage<'a, BoxOffset>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Rotation>,
+        WriteStorage<'a, AiControlled>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut rng,
+            delta,
+            time,
+            multipliers,
+            mut coll_box,
+            offsets,
+            mut pos,
+            mut rotation,
+            mut ai,
+        ) = data;
+
+        // movement first, the same way the MovementSystem moves the player
+        for (entity, pos, coll_box, ai) in (&entities, &mut pos, &mut coll_box, &ai).join() {
+            let dt = time.scaled(delta.seconds, multipliers.get(entity));
+            if ai.steer.norm() > 0.0 {
+                pos.position += ai.steer.normalize() * AI_SPEED * dt;
+                coll_box.origin = pos.position + BoxOffset::of(offsets.get(entity));
+            }
+        }
+
+        for (entity, rotation, ai) in (&entities, &mut rotation, &mut ai).join() {
+            let dt = time.scaled(delta.seconds, multipliers.get(entity));
+            let origin = match coll_box.get(entity) {
+                Some(own_box) => own_box.center(),
+                None => continue,
+            };
+            // the target is only picked on think ticks, but where it is gets
+            // looked up every frame
+            let target = match (ai.engage, ai.target) {
+                (true, Some(target)) => coll_box.get(target).map(|b| b.center()),
+                _ => None,
+            };
+
+            let target = match target {
+                Some(target) => target,
+                None => {
+                    ai.seen.clear();
+                    ai.firing = false;
+                    continue;
+                }
+            };
+            let seen = match ai.observe(target, dt) {
+                Some(seen) => seen,
+                None => {
+                    // still reacting to the target turning up
+                    ai.firing = false;
+                    continue;
+                }
+            };
+
+            ai.wobble -= dt;
+            if ai.wobble <= 0.0 {
+                ai.wobble = AIM_WOBBLE_TIME;
+                let error = Normal::new(0.0, f64::from(ai.aim_error));
+                ai.aim_offset = rng.sample(error) as f32;
+            }
+
+            let wanted = Rotation::facing(seen - origin).angle + ai.aim_offset;
+            let mut offset = wanted - rotation.angle;
+            // take the short way round
+            while offset > std::f32::consts::PI {
+                offset -= 2.0 * std::f32::consts::PI;
+            }
+            while offset < -std::f32::consts::PI {
+                offset += 2.0 * std::f32::consts::PI;
+            }
+            let max_turn = ai.turn_rate * dt;
+            rotation.angle += offset.max(-max_turn).min(max_turn);
+            ai.firing = offset.abs() <= FIRE_ANGLE;
+        }
+    }
+}
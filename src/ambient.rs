@@ -0,0 +1,203 @@
+use crate::budget::View;
+use crate::time::{DeltaTime, TimeScale};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use specs::*;
+
+// how big a patch of fog is, in pixels across
+const FOG_SIZE: f32 = 500.0;
+const FOG_PATCHES: usize = 6;
+// how quickly a lightning flash fades, per second
+const FLASH_FADE: f32 = 4.0;
+
+// The ambient effects a level asks for in its level file. Everything is off
+// unless the level turns it on.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct AmbientConfig {
+    // scales how much of everything there is, from 0 to 1
+    pub(crate) intensity: f32,
+    pub(crate) dust: Option<Dust>,
+    pub(crate) fog: Option<Fog>,
+    pub(crate) lightning: Option<Lightning>,
+}
+
+// Specks drifting across the screen
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Dust {
+    // at full intensity
+    pub(crate) count: usize,
+    // pixels per second
+    pub(crate) drift: (f32, f32),
+    pub(crate) color: (f32, f32, f32, f32),
+}
+
+// Big soft patches of nebula sliding slowly past
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Fog {
+    pub(crate) drift: (f32, f32),
+    pub(crate) color: (f32, f32, f32, f32),
+}
+
+// The whole screen lighting up now and then
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Lightning {
+    // seconds between flashes on average, at full intensity
+    pub(crate) every: f32,
+    pub(crate) color: (f32, f32, f32),
+}
+
+fn color((r, g, b, a): (f32, f32, f32, f32)) -> graphics::Color {
+    graphics::Color::new(r, g, b, a)
+}
+
+// Where the ambient effects are up to. They are only for looks, so they roll
+// their own dice rather than taking them from the GameRng and changing how the
+// game plays out.
+pub(crate) struct Ambient {
+    pub(crate) config: AmbientConfig,
+    // can be turned up and down while playing, starts as the level's
+    pub(crate) intensity: f32,
+    // offsets within the view, which they wrap around
+    dust: Vec<nalgebra::Point2<f32>>,
+    fog: Vec<nalgebra::Point2<f32>>,
+    // brightness of the current lightning flash, from 1 fading to 0
+    flash: f32,
+    rng: StdRng,
+}
+
+impl Default for Ambient {
+    fn default() -> Self {
+        Ambient::new(AmbientConfig::default())
+    }
+}
+
+impl Ambient {
+    pub(crate) fn new(config: AmbientConfig) -> Self {
+        let mut rng = StdRng::seed_from_u64(0);
+        let view = View::default().rect;
+        let mut scatter = |count: usize| -> Vec<nalgebra::Point2<f32>> {
+            (0..count)
+                .map(|_| {
+                    nalgebra::Point2::new(rng.gen_range(0.0, view.w), rng.gen_range(0.0, view.h))
+                })
+                .collect()
+        };
+        let dust = scatter(config.dust.as_ref().map_or(0, |dust| dust.count));
+        let fog = scatter(if config.fog.is_some() { FOG_PATCHES } else { 0 });
+        Ambient {
+            intensity: config.intensity,
+            config,
+            dust,
+            fog,
+            flash: 0.0,
+            rng,
+        }
+    }
+}
+
+// wraps a drifting offset back round into the view
+fn drift(point: &mut nalgebra::Point2<f32>, (x, y): (f32, f32), dt: f32, w: f32, h: f32) {
+    point.x = (point.x + x * dt).rem_euclid(w);
+    point.y = (point.y + y * dt).rem_euclid(h);
+}
+
+// Moves the dust and fog along and sets off lightning. Runs on scaled time, so
+// the weather slows down with everything else in bullet time.
+pub(crate) struct AmbientSystem;
+
+impl<'a> System<'a> for AmbientSystem {
+    type SystemData = (
+        Write<'a, Ambient>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Read<'a, View>,
+    );
+
+    fn run(&mut self, (mut ambient, delta, time, view): Self::SystemData) {
+        let ambient = &mut *ambient;
+        let dt = time.scaled(delta.seconds, None);
+        let (w, h) = (view.rect.w, view.rect.h);
+
+        if let Some(dust) = &ambient.config.dust {
+            for speck in ambient.dust.iter_mut() {
+                drift(speck, dust.drift, dt, w, h);
+            }
+        }
+        if let Some(fog) = &ambient.config.fog {
+            // patches wrap a whole patch beyond the edge, so they don't pop
+            for patch in ambient.fog.iter_mut() {
+                drift(patch, fog.drift, dt, w + FOG_SIZE, h + FOG_SIZE);
+            }
+        }
+
+        ambient.flash = (ambient.flash - FLASH_FADE * dt).max(0.0);
+        if let Some(lightning) = &ambient.config.lightning {
+            let chance = dt * ambient.intensity / lightning.every.max(dt);
+            if ambient.rng.gen::<f32>() < chance {
+                ambient.flash = 1.0;
+            }
+        }
+    }
+}
+
+// Drawn over the background and under the entities
+pub(crate) fn draw_ambient(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let ambient = world.read_resource::<Ambient>();
+    if ambient.intensity <= 0.0 {
+        return Ok(());
+    }
+    let view = graphics::screen_coordinates(ctx);
+    let mut mesh = graphics::MeshBuilder::new();
+    let mut empty = true;
+
+    if let Some(fog) = &ambient.config.fog {
+        let mut fog_color = color(fog.color);
+        fog_color.a *= ambient.intensity;
+        for patch in ambient.fog.iter() {
+            mesh.circle(
+                graphics::DrawMode::fill(),
+                nalgebra::Point2::new(
+                    view.x + patch.x - FOG_SIZE / 2.0,
+                    view.y + patch.y - FOG_SIZE / 2.0,
+                ),
+                FOG_SIZE / 2.0,
+                2.0,
+                fog_color,
+            );
+            empty = false;
+        }
+    }
+
+    if let Some(dust) = &ambient.config.dust {
+        let shown = (ambient.dust.len() as f32 * ambient.intensity.min(1.0)) as usize;
+        for speck in ambient.dust.iter().take(shown) {
+            mesh.rectangle(
+                graphics::DrawMode::fill(),
+                graphics::Rect::new(view.x + speck.x, view.y + speck.y, 1.5, 1.5),
+                color(dust.color),
+            );
+            empty = false;
+        }
+    }
+
+    if let (Some(lightning), true) = (&ambient.config.lightning, ambient.flash > 0.0) {
+        let (r, g, b) = lightning.color;
+        mesh.rectangle(
+            graphics::DrawMode::fill(),
+            view,
+            graphics::Color::new(r, g, b, 0.4 * ambient.flash * ambient.intensity.min(1.0)),
+        );
+        empty = false;
+    }
+
+    // a MeshBuilder with nothing in it can't be built
+    if empty {
+        return Ok(());
+    }
+    let mesh = mesh.build(ctx)?;
+    graphics::draw(ctx, &mesh, graphics::DrawParam::default())
+}
@@ -0,0 +1,165 @@
+use crate::events::Publish;
+use crate::health::{self, DamageEvent, Health};
+use crate::status::{self, Status, StatusEffects};
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::CollisionBox;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+
+// the storm starts out just big enough to cover the default window
+const START_RADIUS: f32 = 500.0;
+const MIN_RADIUS: f32 = 60.0;
+// each stage closes the circle to this fraction of its size
+const STAGE_SHRINK: f32 = 0.6;
+// pixels per second the edge moves while closing
+const SHRINK_SPEED: f32 = 20.0;
+// seconds the circle holds still between stages
+const STAGE_HOLD: f32 = 15.0;
+// health lost per second outside, going up by the same again every stage
+const STAGE_DAMAGE: f32 = 5.0;
+// how long something keeps burning after it gets back inside
+const BURN_TIME: f32 = 2.0;
+
+// A circle of safety that closes in on the middle of the arena in stages, the
+// way the storm does in a battle royale. Anything with health caught outside
+// it takes damage every second until it gets back in. It does nothing until
+// something starts it, sudden death being the first thing that does.
+#[derive(Debug)]
+pub(crate) struct ShrinkingBounds {
+    pub(crate) active: bool,
+    pub(crate) center: nalgebra::Point2<f32>,
+    pub(crate) radius: f32,
+    // the size the current stage is closing toward
+    pub(crate) target_radius: f32,
+    // seconds until the next stage starts closing
+    hold: f32,
+    // health lost per second outside
+    damage: f32,
+}
+
+impl Default for ShrinkingBounds {
+    fn default() -> Self {
+        ShrinkingBounds {
+            active: false,
+            center: nalgebra::Point2::new(400.0, 300.0),
+            radius: START_RADIUS,
+            target_radius: START_RADIUS,
+            hold: 0.0,
+            damage: 0.0,
+        }
+    }
+}
+
+impl ShrinkingBounds {
+    // start closing in, after holding still for the given number of seconds
+    pub(crate) fn start(&mut self, hold: f32) {
+        *self = ShrinkingBounds {
+            active: true,
+            center: self.center,
+            hold,
+            damage: STAGE_DAMAGE,
+            ..ShrinkingBounds::default()
+        };
+    }
+
+    pub(crate) fn stop(&mut self) {
+        *self = ShrinkingBounds {
+            center: self.center,
+            ..ShrinkingBounds::default()
+        };
+    }
+
+    pub(crate) fn contains(&self, point: nalgebra::Point2<f32>) -> bool {
+        !self.active || (point - self.center).norm() <= self.radius
+    }
+}
+
+pub(crate) struct ShrinkingBoundsSystem;
+
+impl<'a> System<'a> for ShrinkingBoundsSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Write<'a, ShrinkingBounds>,
+        Publish<'a, DamageEvent>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Health>,
+        WriteStorage<'a, StatusEffects>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            delta,
+            time,
+            mut bounds,
+            mut damage_events,
+            coll_box,
+            multipliers,
+            mut health,
+            mut statuses,
+        ) = data;
+        if !bounds.active {
+            return;
+        }
+        let dt = time.scaled(delta.seconds, None);
+
+        if bounds.radius > bounds.target_radius {
+            bounds.radius = (bounds.radius - SHRINK_SPEED * dt).max(bounds.target_radius);
+        } else if bounds.hold > 0.0 {
+            bounds.hold -= dt;
+        } else if bounds.radius > MIN_RADIUS {
+            // move on to the next stage, and make staying out hurt more
+            bounds.target_radius = (bounds.radius * STAGE_SHRINK).max(MIN_RADIUS);
+            bounds.hold = STAGE_HOLD;
+            bounds.damage += STAGE_DAMAGE;
+        }
+
+        let outside: Vec<Entity> = (&entities, &coll_box, &health)
+            .join()
+            .filter(|(_, coll_box, _)| !bounds.contains(coll_box.center()))
+            .map(|(entity, _, _)| entity)
+            .collect();
+        for entity in outside {
+            health::deal_damage(
+                &mut health,
+                &mut damage_events,
+                entity,
+                None,
+                bounds.damage * time.scaled(delta.seconds, multipliers.get(entity)),
+            );
+            status::apply(&mut statuses, entity, Status::Burning, BURN_TIME);
+        }
+    }
+}
+
+// Draws the edge of the storm, and faintly where it is closing to
+pub(crate) fn draw_bounds(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let bounds = world.read_resource::<ShrinkingBounds>();
+    if !bounds.active {
+        return Ok(());
+    }
+
+    let mut rings = graphics::MeshBuilder::new();
+    rings.circle(
+        graphics::DrawMode::stroke(3.0),
+        bounds.center,
+        bounds.radius,
+        0.5,
+        graphics::Color::new(1.0, 0.2, 0.2, 0.8),
+    );
+    if bounds.target_radius < bounds.radius {
+        rings.circle(
+            graphics::DrawMode::stroke(1.0),
+            bounds.center,
+            bounds.target_radius,
+            0.5,
+            graphics::Color::new(1.0, 1.0, 1.0, 0.4),
+        );
+    }
+    let rings = rings.build(ctx)?;
+    graphics::draw(ctx, &rings, graphics::DrawParam::default())
+}
@@ -0,0 +1,95 @@
+use ggez::{graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+use std::collections::HashMap;
+
+// Which of the Assets' images an entity is drawn with. It's only an index, so
+// any number of entities can share an image without holding on to it, and
+// changing the image in the Assets changes every one of them.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct ImageHandle(usize);
+
+// Every image the game draws sprites with, loaded once and kept under a key,
+// the path it was loaded from or a name for one made in code, e.g.
+//
+//     let ship = assets.load_image(ctx, "/ship.PNG")?;
+//     world.create_entity().with(ship).build();
+//
+// Sounds aren't kept here, they can't be shared between the systems' threads
+// the way the world's resources have to be, so MainState holds on to them.
+#[derive(Default)]
+pub struct Assets {
+    images: Vec<graphics::Image>,
+    keys: HashMap<String, ImageHandle>,
+}
+
+impl Assets {
+    // Loads the image at the path, unless it has been already
+    pub(crate) fn load_image(&mut self, ctx: &mut Context, path: &str) -> GameResult<ImageHandle> {
+        if let Some(handle) = self.keys.get(path) {
+            return Ok(*handle);
+        }
+        let image = graphics::Image::new(ctx, path)?;
+        Ok(self.insert_image(path, image))
+    }
+
+    // Reads the image at the path again, for when the file has changed
+    #[cfg(feature = "dev-tools")]
+    pub(crate) fn reload_image(
+        &mut self,
+        ctx: &mut Context,
+        path: &str,
+    ) -> GameResult<ImageHandle> {
+        let image = graphics::Image::new(ctx, path)?;
+        Ok(self.insert_image(path, image))
+    }
+
+    // Keeps the image under the key. An image already there is replaced, and
+    // everything drawn with it is drawn with the new one from then on.
+    pub(crate) fn insert_image(&mut self, key: &str, image: graphics::Image) -> ImageHandle {
+        match self.keys.get(key) {
+            Some(handle) => {
+                self.images[handle.0] = image;
+                *handle
+            }
+            None => {
+                let handle = ImageHandle(self.images.len());
+                self.images.push(image);
+                self.keys.insert(key.to_owned(), handle);
+                handle
+            }
+        }
+    }
+
+    // the handle for the image kept under the key, if there is one
+    pub fn image(&self, key: &str) -> Option<ImageHandle> {
+        self.keys.get(key).copied()
+    }
+
+    // the keys of every image, the i'th for ImageHandle i
+    pub(crate) fn keys(&self) -> Vec<String> {
+        let mut keys = vec![String::new(); self.images.len()];
+        for (key, handle) in &self.keys {
+            keys[handle.0] = key.clone();
+        }
+        keys
+    }
+
+    // the handle now for what was ImageHandle i when the keys were listed,
+    // if the image is still kept
+    pub(crate) fn relisted(&self, keys: &[String], handle: ImageHandle) -> Option<ImageHandle> {
+        keys.get(handle.0).and_then(|key| self.image(key))
+    }
+
+    pub fn get(&self, handle: ImageHandle) -> &graphics::Image {
+        &self.images[handle.0]
+    }
+
+    // width and height in pixels
+    pub fn size(&self, handle: ImageHandle) -> (f32, f32) {
+        let image = self.get(handle);
+        (image.width() as f32, image.height() as f32)
+    }
+}
@@ -0,0 +1,71 @@
+use crate::data;
+use ggez::{graphics, Context, GameResult};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// An atlas as described in its RON file: the image and, by name, the pixel
+// rectangle of every small image packed into it
+#[derive(Deserialize)]
+pub(crate) struct AtlasFile {
+    image: String,
+    regions: HashMap<String, PixelRect>,
+}
+
+#[derive(Clone, Copy, Deserialize)]
+struct PixelRect {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+// Where one image is in the atlas, ready to hand to DrawParam::src, and how big
+// it is in pixels
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Region {
+    pub(crate) src: graphics::Rect,
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+}
+
+// Lots of small images packed into one, so they can all be drawn with a
+// single sprite batch
+#[derive(Debug)]
+pub(crate) struct Atlas {
+    pub(crate) image: graphics::Image,
+    regions: HashMap<String, Region>,
+}
+
+impl Atlas {
+    // Reads an atlas description from a RON file in the resources directory,
+    // along with the image it points to
+    pub(crate) fn load(ctx: &mut Context, path: &str) -> GameResult<Atlas> {
+        let description: AtlasFile = data::load(ctx, path)?;
+        let image = graphics::Image::new(ctx, &description.image)?;
+
+        let (sheet_w, sheet_h) = (f32::from(image.width()), f32::from(image.height()));
+        let regions = description
+            .regions
+            .into_iter()
+            .map(|(name, rect)| {
+                let region = Region {
+                    src: graphics::Rect::new(
+                        rect.x as f32 / sheet_w,
+                        rect.y as f32 / sheet_h,
+                        rect.width as f32 / sheet_w,
+                        rect.height as f32 / sheet_h,
+                    ),
+                    width: rect.width as f32,
+                    height: rect.height as f32,
+                };
+                (name, region)
+            })
+            .collect();
+
+        Ok(Atlas { image, regions })
+    }
+
+    pub(crate) fn region(&self, name: &str) -> Option<Region> {
+        self.regions.get(name).cloned()
+    }
+}
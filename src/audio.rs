@@ -0,0 +1,165 @@
+use crate::events::{self, Subscribe, TrackedReader};
+use crate::listener::{Cue, Listener};
+use crate::platform::{self, Sound};
+use crate::scene::{Hazard, Pickup};
+use crate::{CollisionBox, CollisionEvent};
+use ggez::nalgebra;
+use ggez::{Context, GameResult};
+use specs::*;
+use std::collections::hash_map::{Entry, HashMap};
+
+const BUMP: &str = "/sounds/bump.wav";
+const BUMP_VOLUME: f32 = 0.6;
+
+// A sound to play, by its path under resources
+#[derive(Clone, Debug)]
+pub struct PlaySound {
+    pub path: String,
+    // from 0 to 1
+    pub volume: f32,
+    // where in the world it's heard from, or None for the same everywhere
+    pub at: Option<nalgebra::Point2<f32>>,
+}
+
+// Sounds any system wants played this update, its own included, e.g.
+//
+//     type SystemData = Write<'a, SoundQueue>;
+//
+//     queue.play("/sounds/shield.wav", 1.0);
+//
+// Like the SoundCues, MainState plays and clears them once the world has
+// been stepped, after any AudioSystem a game added has had them first.
+#[derive(Clone, Debug, Default)]
+pub struct SoundQueue {
+    pub sounds: Vec<PlaySound>,
+}
+
+impl SoundQueue {
+    pub fn play(&mut self, path: &str, volume: f32) {
+        self.sounds.push(PlaySound {
+            path: path.to_owned(),
+            volume,
+            at: None,
+        });
+    }
+
+    pub fn play_at(&mut self, path: &str, volume: f32, at: nalgebra::Point2<f32>) {
+        self.sounds.push(PlaySound {
+            path: path.to_owned(),
+            volume,
+            at: Some(at),
+        });
+    }
+}
+
+// A piece of music to loop in place of the layered music, by its path under
+// resources. The layers fade back in once it's None again.
+#[derive(Clone, Debug, Default)]
+pub struct MusicTrack {
+    pub path: Option<String>,
+}
+
+// Plays sounds for a game built with GameBuilder::with_audio. Playing a sound
+// needs the ggez Context, which specs systems never see, so this isn't a
+// System. MainState runs each one in the order they were added once the world
+// has been stepped, then plays whatever they left in the SoundQueue itself.
+// One can play some of the queued sounds its own way by draining them, add
+// sounds for the built-in playback to play, or take them all to replace it.
+pub trait AudioSystem {
+    fn play(&mut self, ctx: &mut Context, queue: &mut SoundQueue) -> GameResult<()>;
+}
+
+// The sounds asked for through the SoundQueue, each loaded the first time
+// it's played and kept. One that won't load is reported the once and left
+// silent.
+#[derive(Default)]
+pub(crate) struct SoundBank {
+    sounds: HashMap<String, Box<dyn Sound>>,
+}
+
+impl SoundBank {
+    pub(crate) fn play(
+        &mut self,
+        ctx: &mut Context,
+        queued: Vec<PlaySound>,
+        listener: &Listener,
+    ) -> GameResult<()> {
+        for request in queued {
+            let (pitch, volume) = match request.at {
+                Some(at) => {
+                    let cue = Cue {
+                        at,
+                        velocity: nalgebra::Vector2::zeros(),
+                    };
+                    (
+                        listener.doppler(&cue),
+                        listener.volume(&cue) * request.volume,
+                    )
+                }
+                None => (1.0, request.volume),
+            };
+            if volume <= 0.0 {
+                continue;
+            }
+            let sound = match self.sounds.entry(request.path) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let sound = platform::try_load_sound(ctx, entry.key());
+                    entry.insert(sound)
+                }
+            };
+            sound.play(pitch, volume)?;
+        }
+        Ok(())
+    }
+}
+
+// Bumps when the player's ship first touches something, a solid included.
+// The CollisionSystem sends an event every update the two touch, so only the
+// first update of it counts. Pickups and hazards are flown through rather
+// than bumped into.
+pub(crate) struct BumpSystem {
+    collisions: TrackedReader<CollisionEvent>,
+    // what was touching as of the last update
+    touching: Vec<(Entity, Entity)>,
+}
+
+impl BumpSystem {
+    pub(crate) fn new(world: &World) -> Self {
+        BumpSystem {
+            collisions: events::subscribe::<CollisionEvent>(world, "bump"),
+            touching: Vec::new(),
+        }
+    }
+}
+
+impl<'a> System<'a> for BumpSystem {
+    type SystemData = (
+        Write<'a, SoundQueue>,
+        Subscribe<'a, CollisionEvent>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Pickup>,
+        ReadStorage<'a, Hazard>,
+    );
+
+    fn run(&mut self, (mut queue, collisions, coll_box, pickups, hazards): Self::SystemData) {
+        let mut touching = Vec::new();
+        for collision in collisions.read(&mut self.collisions) {
+            if pickups.contains(collision.b) || hazards.contains(collision.b) {
+                continue;
+            }
+            let pair = (collision.a, collision.b);
+            if touching.contains(&pair) {
+                continue;
+            }
+            touching.push(pair);
+            if self.touching.contains(&pair) {
+                continue;
+            }
+            if let Some(player_box) = coll_box.get(collision.a) {
+                queue.play_at(BUMP, BUMP_VOLUME, player_box.center());
+            }
+        }
+        self.touching = touching;
+    }
+}
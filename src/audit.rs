@@ -0,0 +1,207 @@
+use crate::ai::AiControlled;
+use crate::audio::SoundQueue;
+use crate::combo::Combo;
+use crate::cooldowns::Cooldowns;
+use crate::faction::Faction;
+use crate::health::{Damage, Health};
+use crate::lifetime::Lifetime;
+use crate::listener::SoundCues;
+use crate::melee::Attack;
+use crate::notifications::Notifications;
+use crate::status::StatusEffects;
+use crate::stealth::{Cloaked, Revealed};
+use crate::targeting::Homing;
+use crate::time::TimeMultiplier;
+use crate::weapons::{Projectile, Weapon};
+use crate::{
+    Acceleration, Animation, Bounded, BoxOffset, Collider, CollisionBox, ControllableTag,
+    MainState, Position, Rotation, Scale, Solid, Velocity, DESIRED_FPS,
+};
+use ggez::event::{Axis, Button, EventHandler, GamepadId, KeyCode, KeyMods, MouseButton};
+use ggez::{timer, Context, GameResult};
+use specs::*;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
+
+// Hashes every component of one type along with the entity it belongs to. The
+// Debug output is hashed rather than the values, it prints floats exactly and
+// saves every component having to implement Hash.
+fn storage_hash<T: Component + Debug>(world: &World) -> u64 {
+    let entities = world.entities();
+    let storage = world.read_storage::<T>();
+    let mut hasher = DefaultHasher::new();
+    for (entity, component) in (&entities, &storage).join() {
+        entity.id().hash(&mut hasher);
+        format!("{:?}", component).hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+// A hash of each component the simulation depends on, by name
+fn world_hashes(world: &World) -> Vec<(&'static str, u64)> {
+    vec![
+        ("Position", storage_hash::<Position>(world)),
+        ("Velocity", storage_hash::<Velocity>(world)),
+        ("Acceleration", storage_hash::<Acceleration>(world)),
+        ("CollisionBox", storage_hash::<CollisionBox>(world)),
+        ("Collider", storage_hash::<Collider>(world)),
+        ("Rotation", storage_hash::<Rotation>(world)),
+        ("Scale", storage_hash::<Scale>(world)),
+        ("BoxOffset", storage_hash::<BoxOffset>(world)),
+        ("Animation", storage_hash::<Animation>(world)),
+        ("Solid", storage_hash::<Solid>(world)),
+        ("Bounded", storage_hash::<Bounded>(world)),
+        ("ControllableTag", storage_hash::<ControllableTag>(world)),
+        ("Faction", storage_hash::<Faction>(world)),
+        ("Health", storage_hash::<Health>(world)),
+        ("Damage", storage_hash::<Damage>(world)),
+        ("Weapon", storage_hash::<Weapon>(world)),
+        ("Cooldowns", storage_hash::<Cooldowns>(world)),
+        ("Projectile", storage_hash::<Projectile>(world)),
+        ("Homing", storage_hash::<Homing>(world)),
+        ("Lifetime", storage_hash::<Lifetime>(world)),
+        ("AiControlled", storage_hash::<AiControlled>(world)),
+        ("Attack", storage_hash::<Attack>(world)),
+        ("StatusEffects", storage_hash::<StatusEffects>(world)),
+        ("TimeMultiplier", storage_hash::<TimeMultiplier>(world)),
+        ("Cloaked", storage_hash::<Cloaked>(world)),
+        ("Revealed", storage_hash::<Revealed>(world)),
+    ]
+}
+
+// Determinism audit mode, started with --audit. Two copies of the game are
+// built from the same seed and fed the same input, only the first is drawn.
+// After every update the components of both worlds are hashed, and the first
+// tick where they differ is reported along with the component that differs.
+// Anything that sneaks in outside the seeded GameRng (HashMap order, the clock,
+// reading the other world's state) shows up as a divergence.
+pub(crate) struct Auditor {
+    primary: MainState,
+    shadow: MainState,
+    tick: u64,
+    diverged: bool,
+}
+
+impl Auditor {
+    pub(crate) fn new(primary: MainState, shadow: MainState) -> Self {
+        Auditor {
+            primary,
+            shadow,
+            tick: 0,
+            diverged: false,
+        }
+    }
+
+    fn compare(&mut self) {
+        let primary = world_hashes(&self.primary.specs_world);
+        let shadow = world_hashes(&self.shadow.specs_world);
+        let differs = primary
+            .iter()
+            .zip(shadow.iter())
+            .find(|(a, b)| a.1 != b.1)
+            .map(|(a, _)| a.0);
+
+        if let Some(component) = differs {
+            self.diverged = true;
+            let message = format!(
+                "Determinism audit: worlds diverged at tick {} in {}",
+                self.tick, component
+            );
+            println!("{}", message);
+            self.primary
+                .specs_world
+                .write_resource::<Notifications>()
+                .push(&message);
+        }
+    }
+}
+
+impl EventHandler for Auditor {
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.primary.check_gamepads(ctx);
+        self.shadow.check_gamepads(ctx);
+
+        while timer::check_update_time(ctx, DESIRED_FPS) {
+            self.primary.step();
+            self.shadow.step();
+            self.tick += 1;
+
+            // only the first divergence is worth reporting, everything after
+            // it follows on
+            if !self.diverged {
+                self.compare();
+            }
+        }
+
+        // the shadow game's sounds are thrown away with its frames
+        self.shadow
+            .specs_world
+            .write_resource::<Combo>()
+            .chimes
+            .clear();
+        *self.shadow.specs_world.write_resource::<SoundCues>() = SoundCues::default();
+        *self.shadow.specs_world.write_resource::<SoundQueue>() = SoundQueue::default();
+        self.primary.play_sounds(ctx)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.primary.draw(ctx)
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        keymod: KeyMods,
+        repeat: bool,
+    ) {
+        self.primary.key_down_event(ctx, keycode, keymod, repeat);
+        self.shadow.key_down_event(ctx, keycode, keymod, repeat);
+    }
+
+    fn key_up_event(&mut self, ctx: &mut Context, keycode: KeyCode, keymod: KeyMods) {
+        self.primary.key_up_event(ctx, keycode, keymod);
+        self.shadow.key_up_event(ctx, keycode, keymod);
+    }
+
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, dx: f32, dy: f32) {
+        self.primary.mouse_motion_event(ctx, x, y, dx, dy);
+        self.shadow.mouse_motion_event(ctx, x, y, dx, dy);
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        self.primary.mouse_wheel_event(ctx, x, y);
+        self.shadow.mouse_wheel_event(ctx, x, y);
+    }
+
+    fn text_input_event(&mut self, ctx: &mut Context, character: char) {
+        self.primary.text_input_event(ctx, character);
+        self.shadow.text_input_event(ctx, character);
+    }
+
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        self.primary.mouse_button_down_event(ctx, button, x, y);
+        self.shadow.mouse_button_down_event(ctx, button, x, y);
+    }
+
+    fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        self.primary.mouse_button_up_event(ctx, button, x, y);
+        self.shadow.mouse_button_up_event(ctx, button, x, y);
+    }
+
+    fn gamepad_button_down_event(&mut self, ctx: &mut Context, btn: Button, id: GamepadId) {
+        self.primary.gamepad_button_down_event(ctx, btn, id);
+        self.shadow.gamepad_button_down_event(ctx, btn, id);
+    }
+
+    fn gamepad_button_up_event(&mut self, ctx: &mut Context, btn: Button, id: GamepadId) {
+        self.primary.gamepad_button_up_event(ctx, btn, id);
+        self.shadow.gamepad_button_up_event(ctx, btn, id);
+    }
+
+    fn gamepad_axis_event(&mut self, ctx: &mut Context, axis: Axis, value: f32, id: GamepadId) {
+        self.primary.gamepad_axis_event(ctx, axis, value, id);
+        self.shadow.gamepad_axis_event(ctx, axis, value, id);
+    }
+}
@@ -0,0 +1,303 @@
+use crate::ai::AiControlled;
+use crate::data;
+use crate::fixed::{self, Real};
+use crate::health::Health;
+use crate::stealth::Cloaked;
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::CollisionBox;
+use ggez::nalgebra;
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+use std::sync::Arc;
+
+// how close Approach gets before it counts as done
+const APPROACH_DISTANCE: f32 = 150.0;
+
+// Behavior trees for the ships that need more than the plain AI: elites and
+// bosses. A tree is made of composite nodes that decide which children run,
+// decorators that change how a child's result is reported, and leaves that
+// either check something about the ship or issue a command to it. The leaves
+// don't act directly, they set the AiControlled fields (where to fly, whether
+// to engage) or add and remove components, and the usual systems carry that out.
+//
+// Trees are loaded from RON files in resources/behaviors, for example
+//
+//     Selector([
+//         Sequence([Check(HealthBelow(30.0)), Do(Retreat)]),
+//         Do(Attack),
+//     ])
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) enum Node {
+    // runs children in order until one doesn't succeed
+    Sequence(Vec<Node>),
+    // runs children in order until one doesn't fail
+    Selector(Vec<Node>),
+    // swaps success and failure
+    Invert(Box<Node>),
+    // always reports success, unless the child is still running
+    Succeed(Box<Node>),
+    // once the child succeeds it fails for the next few seconds
+    Cooldown {
+        seconds: f32,
+        child: Box<Node>,
+        #[serde(default)]
+        slot: usize,
+    },
+    Check(Condition),
+    Do(Action),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub(crate) enum Condition {
+    HasTarget,
+    TargetCloserThan(f32),
+    HealthBelow(f32),
+    IsCloaked,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+pub(crate) enum Action {
+    // let the AI aim at and shoot the nearest target
+    Attack,
+    // fly toward the target until close
+    Approach,
+    // fly away from the target
+    Retreat,
+    Cloak,
+    Uncloak,
+    // sit still and hold fire
+    Wait,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Status {
+    Success,
+    Failure,
+    Running,
+}
+
+impl Node {
+    // give every cooldown its own timer slot, returning how many there are
+    fn number_slots(&mut self, next: usize) -> usize {
+        match self {
+            Node::Sequence(children) | Node::Selector(children) => children
+                .iter_mut()
+                .fold(next, |next, child| child.number_slots(next)),
+            Node::Invert(child) | Node::Succeed(child) => child.number_slots(next),
+            Node::Cooldown { child, slot, .. } => {
+                *slot = next;
+                child.number_slots(next + 1)
+            }
+            Node::Check(_) | Node::Do(_) => next,
+        }
+    }
+}
+
+// Reads a tree from a RON file in the resources directory
+pub(crate) fn load(ctx: &mut Context, path: &str) -> GameResult<Node> {
+    data::load(ctx, path)
+}
+
+// A behavior attached to an entity. The tree itself is shared between every
+// entity using it, only the cooldown timers are per entity.
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(crate) struct BehaviorTree {
+    root: Arc<Node>,
+    cooldowns: Vec<f32>,
+}
+
+impl BehaviorTree {
+    pub(crate) fn new(mut root: Node) -> Self {
+        let slots = root.number_slots(0);
+        BehaviorTree {
+            root: Arc::new(root),
+            cooldowns: vec![0.0; slots],
+        }
+    }
+}
+
+// What a tree can see about its entity for one tick
+struct Blackboard {
+    origin: nalgebra::Point2<f32>,
+    target: Option<nalgebra::Point2<f32>>,
+    health: Option<Real>,
+    cloaked: bool,
+}
+
+// The commands leaves issue that aren't AiControlled fields, applied once the
+// whole tree has run
+#[derive(Default)]
+struct Commands {
+    cloak: Option<bool>,
+}
+
+fn tick(
+    node: &Node,
+    board: &Blackboard,
+    cooldowns: &mut [f32],
+    ai: &mut AiControlled,
+    commands: &mut Commands,
+) -> Status {
+    match node {
+        Node::Sequence(children) => {
+            for child in children {
+                let status = tick(child, board, cooldowns, ai, commands);
+                if status != Status::Success {
+                    return status;
+                }
+            }
+            Status::Success
+        }
+        Node::Selector(children) => {
+            for child in children {
+                let status = tick(child, board, cooldowns, ai, commands);
+                if status != Status::Failure {
+                    return status;
+                }
+            }
+            Status::Failure
+        }
+        Node::Invert(child) => match tick(child, board, cooldowns, ai, commands) {
+            Status::Success => Status::Failure,
+            Status::Failure => Status::Success,
+            Status::Running => Status::Running,
+        },
+        Node::Succeed(child) => match tick(child, board, cooldowns, ai, commands) {
+            Status::Running => Status::Running,
+            _ => Status::Success,
+        },
+        Node::Cooldown {
+            seconds,
+            child,
+            slot,
+        } => {
+            if cooldowns[*slot] > 0.0 {
+                return Status::Failure;
+            }
+            let status = tick(child, board, cooldowns, ai, commands);
+            if status == Status::Success {
+                cooldowns[*slot] = *seconds;
+            }
+            status
+        }
+        Node::Check(condition) => {
+            let passed = match *condition {
+                Condition::HasTarget => board.target.is_some(),
+                Condition::TargetCloserThan(distance) => board
+                    .target
+                    .map_or(false, |target| (target - board.origin).norm() < distance),
+                Condition::HealthBelow(amount) => {
+                    board.health.map_or(false, |h| h < fixed::real(amount))
+                }
+                Condition::IsCloaked => board.cloaked,
+            };
+            if passed {
+                Status::Success
+            } else {
+                Status::Failure
+            }
+        }
+        Node::Do(action) => {
+            let target = match (*action, board.target) {
+                (Action::Cloak, _) => {
+                    commands.cloak = Some(true);
+                    return Status::Success;
+                }
+                (Action::Uncloak, _) => {
+                    commands.cloak = Some(false);
+                    return Status::Success;
+                }
+                (Action::Wait, _) => return Status::Running,
+                (_, Some(target)) => target,
+                (_, None) => return Status::Failure,
+            };
+            let to_target = target - board.origin;
+            match action {
+                Action::Attack => {
+                    ai.engage = true;
+                    Status::Success
+                }
+                Action::Approach if to_target.norm() > APPROACH_DISTANCE => {
+                    ai.steer = to_target;
+                    Status::Running
+                }
+                Action::Approach => Status::Success,
+                Action::Retreat => {
+                    ai.steer = -to_target;
+                    Status::Running
+                }
+                _ => Status::Failure,
+            }
+        }
+    }
+}
+
+// Runs the behavior tree of every AI that is thinking this frame, which decides
+// what it does until it next thinks. Runs before the AiSystem, which carries out
+// the decisions.
+pub(crate) struct BehaviorSystem;
+
+impl<'a> System<'a> for BehaviorSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, LazyUpdate>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        ReadStorage<'a, TimeMultiplier>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Cloaked>,
+        WriteStorage<'a, AiControlled>,
+        WriteStorage<'a, BehaviorTree>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            updater,
+            delta,
+            time,
+            multipliers,
+            coll_box,
+            health,
+            cloaked,
+            mut ai,
+            mut trees,
+        ) = data;
+
+        for (entity, own_box, ai, tree) in (&entities, &coll_box, &mut ai, &mut trees).join() {
+            let dt = time.scaled(delta.seconds, multipliers.get(entity));
+            for cooldown in tree.cooldowns.iter_mut() {
+                *cooldown = (*cooldown - dt).max(0.0);
+            }
+            if !ai.thinking {
+                continue;
+            }
+
+            let board = Blackboard {
+                origin: own_box.center(),
+                target: ai
+                    .target
+                    .and_then(|target| coll_box.get(target))
+                    .map(|b| b.center()),
+                health: health.get(entity).map(|h| h.current),
+                cloaked: cloaked.get(entity).is_some(),
+            };
+
+            // the tree has to ask again for anything it wants
+            ai.engage = false;
+            ai.steer = nalgebra::Vector2::new(0.0, 0.0);
+            let mut commands = Commands::default();
+            tick(&tree.root, &board, &mut tree.cooldowns, ai, &mut commands);
+
+            match commands.cloak {
+                Some(true) if !board.cloaked => updater.insert(entity, Cloaked::default()),
+                Some(false) if board.cloaked => updater.remove::<Cloaked>(entity),
+                _ => (),
+            }
+        }
+    }
+}
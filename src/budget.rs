@@ -0,0 +1,124 @@
+use crate::floating_text::FloatingText;
+use crate::graze::Spark;
+use crate::lifetime::Lifetime;
+use crate::settings::Settings;
+use crate::targeting::Homing;
+use crate::weapons::{Projectile, ProjectilePool};
+use crate::Position;
+use ggez::graphics;
+use serde::{Deserialize, Serialize};
+use specs::*;
+use std::cmp::Ordering;
+
+// The most of each kind of short lived entity the game keeps around at once.
+// They are part of the settings so a slow machine can turn them down.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Budget {
+    // projectiles in flight. The dev-tools F10 stress test fires 10,000 at
+    // once.
+    pub(crate) projectiles: usize,
+    // sparks and floating text
+    pub(crate) particles: usize,
+}
+
+impl Default for Budget {
+    fn default() -> Self {
+        Budget {
+            projectiles: 12_000,
+            particles: 500,
+        }
+    }
+}
+
+// The part of the world on screen, as of the last frame drawn
+#[derive(Debug)]
+pub(crate) struct View {
+    pub(crate) rect: graphics::Rect,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        View {
+            rect: graphics::Rect::new(0.0, 0.0, 800.0, 600.0),
+        }
+    }
+}
+
+// Which of a group of entities to let go of first: anything off screen, then
+// whatever has the least time left
+fn eviction_order(a: &(Entity, bool, f32), b: &(Entity, bool, f32)) -> Ordering {
+    b.1.cmp(&a.1)
+        .then(a.2.partial_cmp(&b.2).unwrap_or(Ordering::Equal))
+}
+
+// Keeps the number of projectiles and particles within the Budget. When there
+// are too many the extra ones are evicted, off screen ones first and then the
+// oldest. Projectiles go back to the pool, particles are deleted.
+//
+// Dead ships are deleted by the HealthSystem straight away, there are no
+// corpses to cap.
+pub(crate) struct BudgetSystem;
+
+impl<'a> System<'a> for BudgetSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Settings>,
+        Read<'a, View>,
+        Write<'a, ProjectilePool>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Lifetime>,
+        ReadStorage<'a, Spark>,
+        ReadStorage<'a, FloatingText>,
+        WriteStorage<'a, Projectile>,
+        WriteStorage<'a, Homing>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            settings,
+            view,
+            mut pool,
+            pos,
+            lifetimes,
+            sparks,
+            floating_text,
+            mut projectiles,
+            mut homing,
+        ) = data;
+        let budget = &settings.budget;
+        let off_screen = |pos: &Position| !view.rect.contains(pos.position);
+
+        let mut in_flight: Vec<(Entity, bool, f32)> = (&entities, &pos, &projectiles)
+            .join()
+            .filter(|(_, _, projectile)| projectile.active)
+            .map(|(entity, pos, projectile)| (entity, off_screen(pos), projectile.time_left))
+            .collect();
+        if in_flight.len() > budget.projectiles {
+            in_flight.sort_by(eviction_order);
+            let excess = in_flight.len() - budget.projectiles;
+            for (entity, _, _) in in_flight.into_iter().take(excess) {
+                if let Some(projectile) = projectiles.get_mut(entity) {
+                    homing.remove(entity);
+                    pool.release(entity, projectile);
+                }
+            }
+        }
+
+        let mut particles: Vec<(Entity, bool, f32)> = (&entities, &pos, &lifetimes)
+            .join()
+            .filter(|(entity, _, _)| sparks.contains(*entity) || floating_text.contains(*entity))
+            .map(|(entity, pos, lifetime)| (entity, off_screen(pos), lifetime.remaining))
+            .collect();
+        if particles.len() > budget.particles {
+            particles.sort_by(eviction_order);
+            let excess = particles.len() - budget.particles;
+            for (entity, _, _) in particles.into_iter().take(excess) {
+                entities
+                    .delete(entity)
+                    .unwrap_or_else(|err| println!("delete error {:?}", err));
+            }
+        }
+    }
+}
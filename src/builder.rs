@@ -0,0 +1,297 @@
+use crate::ai::Difficulty;
+use crate::audio::AudioSystem;
+use crate::rng::GameRng;
+use crate::settings::{self, Settings};
+use crate::{audit, data, front, game_mode, platform, storage, telemetry, MainState};
+use ggez::{conf, event, ContextBuilder};
+use specs::shred::Resource;
+use specs::*;
+use std::env;
+use std::rc::Rc;
+
+// Changes a new game's world, e.g. adding a component or resource
+type Setup = Box<dyn Fn(&mut World)>;
+
+// Makes one of a consumer's systems for a new game, from the world it will
+// run on
+type SystemFactory = Box<dyn Fn(&World) -> Box<dyn for<'a> RunNow<'a>>>;
+
+// Makes a consumer's AudioSystem for a new game
+type AudioFactory = Box<dyn Fn(&World) -> Box<dyn AudioSystem>>;
+
+// a system made for a game, with the name the profiler shows it as
+pub(crate) type NamedSystem = (&'static str, Box<dyn for<'a> RunNow<'a>>);
+
+// What a consumer of the crate added on top of the built-in game, applied to
+// every game started, including the demo and resumed saves
+#[derive(Default)]
+pub(crate) struct Extensions {
+    // run on the world once the built-in components and resources are in
+    setup: Vec<Setup>,
+    // run in order after the built-in systems on every update
+    systems: Vec<(&'static str, SystemFactory)>,
+    // run in order before the built-in sound playback on every update
+    audio: Vec<AudioFactory>,
+}
+
+impl Extensions {
+    pub(crate) fn setup(&self, world: &mut World) {
+        for setup in &self.setup {
+            setup(world);
+        }
+    }
+
+    // The consumer's systems for a new game. Each system's setup registers
+    // whatever it reads or writes that isn't in the world yet.
+    pub(crate) fn systems(&self, world: &mut World) -> Vec<NamedSystem> {
+        self.systems
+            .iter()
+            .map(|(name, make)| {
+                let mut system = make(world);
+                system.setup(world);
+                (*name, system)
+            })
+            .collect()
+    }
+
+    // the consumer's AudioSystems for a new game
+    pub(crate) fn audio(&self, world: &World) -> Vec<Box<dyn AudioSystem>> {
+        self.audio.iter().map(|make| make(world)).collect()
+    }
+}
+
+// Sets up and runs the game, with anything a consumer adds on top: their own
+// components, resources and systems run alongside the built-in movement,
+// collision and rendering. e.g.
+//
+//     GameBuilder::new()
+//         .mode("ctf")
+//         .with_component::<Shield>()
+//         .with_system("shields", |_| ShieldSystem)
+//         .run();
+//
+// The command line still has the last word, so `--mode` and `--seed` override
+// what the builder was given.
+pub struct GameBuilder {
+    mode: String,
+    seed: Option<u64>,
+    extensions: Extensions,
+}
+
+impl Default for GameBuilder {
+    fn default() -> Self {
+        GameBuilder::new()
+    }
+}
+
+impl GameBuilder {
+    pub fn new() -> Self {
+        GameBuilder {
+            mode: "skirmish".to_owned(),
+            seed: None,
+            extensions: Extensions::default(),
+        }
+    }
+
+    // the game mode to play, one of skirmish, ctf, koth, tutorial, sector,
+    // daily, endless, trial and sandbox
+    pub fn mode(mut self, name: &str) -> Self {
+        self.mode = name.to_owned();
+        self
+    }
+
+    // plays the first game from this seed rather than the clock
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    pub fn with_component<C: Component>(self) -> Self
+    where
+        C::Storage: Default,
+    {
+        self.with_setup(|world| world.register::<C>())
+    }
+
+    // Every game starts with its own copy of the resource
+    pub fn with_resource<R: Resource + Clone>(self, resource: R) -> Self {
+        self.with_setup(move |world| world.insert(resource.clone()))
+    }
+
+    // For anything else that needs doing to a new game's world, e.g. creating
+    // entities. Runs after the built-in ships and the game mode are set up.
+    pub fn with_setup<F: Fn(&mut World) + 'static>(mut self, setup: F) -> Self {
+        self.extensions.setup.push(Box::new(setup));
+        self
+    }
+
+    // Adds a system to run after the built-in ones, made afresh for every
+    // game by the given function. The name is what the profiler shows it as.
+    pub fn with_system<S, F>(mut self, name: &'static str, make: F) -> Self
+    where
+        S: for<'a> RunNow<'a> + 'static,
+        F: Fn(&World) -> S + 'static,
+    {
+        self.extensions.systems.push((
+            name,
+            Box::new(move |world| -> Box<dyn for<'a> RunNow<'a>> { Box::new(make(world)) }),
+        ));
+        self
+    }
+
+    // Adds an AudioSystem to run before the built-in sound playback, made
+    // afresh for every game by the given function
+    pub fn with_audio<A, F>(mut self, make: F) -> Self
+    where
+        A: AudioSystem + 'static,
+        F: Fn(&World) -> A + 'static,
+    {
+        self.extensions
+            .audio
+            .push(Box::new(move |world| -> Box<dyn AudioSystem> {
+                Box::new(make(world))
+            }));
+        self
+    }
+
+    // Opens the window and plays until it is closed
+    pub fn run(self) {
+        // `--validate-data` checks the data files under resources and exits,
+        // failing if any of them has a problem
+        if env::args().any(|arg| arg == "--validate-data") {
+            if data::validate() > 0 {
+                std::process::exit(1);
+            }
+            return;
+        }
+
+        let builder = self.with_demos();
+        let extensions = Rc::new(builder.extensions);
+
+        let resource_dir = platform::resource_dir();
+        println!("Resource dir: {:?}", resource_dir);
+
+        // create a context to start the main loop
+        let mut c = conf::Conf::new();
+
+        let win_setup = conf::WindowSetup {
+            title: "GGEZ and specs test".to_owned(),
+            samples: conf::NumSamples::Zero,
+            vsync: true,
+            icon: "".to_owned(),
+            srgb: true,
+        };
+
+        c.window_setup = win_setup;
+
+        let (ref mut ctx, ref mut event_loop) = ContextBuilder::new("ggez/specs", "Fudance")
+            .conf(c)
+            .add_resource_path(resource_dir)
+            .build()
+            .unwrap();
+
+        // pick the game mode from the command line, e.g. `cargo run -- --mode ctf`
+        let mode_name = arg_value("--mode").unwrap_or(builder.mode);
+        if game_mode::from_name(&mode_name).is_none() {
+            println!(
+                "Unknown game mode {}, modes are skirmish, ctf, koth, tutorial, sector, daily, endless, trial and sandbox",
+                mode_name
+            );
+        }
+        let new_mode = || {
+            game_mode::from_name(&mode_name)
+                .unwrap_or_else(|| Box::new(game_mode::Skirmish::default()))
+        };
+
+        let storage = platform::storage(ctx);
+        let mut settings = settings::load(&*storage).unwrap_or_else(|err| {
+            println!("settings error {:?}", err);
+            Settings::default()
+        });
+        if let Some(name) = arg_value("--difficulty") {
+            match Difficulty::from_name(&name) {
+                Some(difficulty) => settings.difficulty = difficulty,
+                None => println!("Unknown difficulty {}, use easy, normal or hard", name),
+            }
+        }
+
+        // `--telemetry` logs gameplay for balancing this run, whatever the setting
+        // says. The logs are added up with `--telemetry-summary` or gathered into
+        // one file with `--telemetry-export <file>`.
+        if env::args().any(|arg| arg == "--telemetry") {
+            settings.telemetry = true;
+        }
+        if env::args().any(|arg| arg == "--telemetry-summary") {
+            telemetry::print_summary(&*storage);
+            return;
+        }
+        if let Some(file) = arg_value("--telemetry-export") {
+            telemetry::export(&*storage, &file).unwrap_or_else(|err| {
+                println!("telemetry error {:?}", err);
+            });
+            return;
+        }
+
+        // the same seed plays out the same game, e.g. `cargo run -- --seed 1234`
+        let seed = arg_value("--seed")
+            .and_then(|seed| seed.parse().ok())
+            .or(builder.seed);
+
+        // `--audit` runs a second copy of the game alongside to check the
+        // simulation is deterministic. It goes straight into the game.
+        if env::args().any(|arg| arg == "--audit") {
+            let seed = new_mode()
+                .seed()
+                .or(seed)
+                .unwrap_or_else(platform::clock_seed);
+            println!("Seed: {}", seed);
+            let state = MainState::new(
+                ctx,
+                new_mode(),
+                settings.clone(),
+                GameRng::new(seed),
+                storage,
+                extensions.clone(),
+            )
+            .unwrap();
+            // the shadow keeps its settings to itself
+            let shadow = MainState::new(
+                ctx,
+                new_mode(),
+                settings,
+                GameRng::new(seed),
+                Rc::new(storage::MemoryStorage::default()),
+                extensions.clone(),
+            )
+            .unwrap();
+            let auditor = &mut audit::Auditor::new(state, shadow);
+            event::run(ctx, event_loop, auditor).unwrap();
+            return;
+        }
+
+        // start the main loop with the splash screen, the game starts from the
+        // title screen after it
+        let front =
+            &mut front::Front::new(ctx, storage, extensions, mode_name, seed, settings).unwrap();
+        event::run(ctx, event_loop, front).unwrap();
+    }
+
+    // `--spawn-demo` queues up a ship whenever the player fires, to show off
+    // the SpawnQueue
+    fn with_demos(self) -> Self {
+        #[cfg(feature = "dev-tools")]
+        {
+            if env::args().any(|arg| arg == "--spawn-demo") {
+                return self.with_system("spawn demo", |_| {
+                    crate::spawn_queue::demo::SpawnDemoSystem::default()
+                });
+            }
+        }
+        self
+    }
+}
+
+// the value following a command line flag, e.g. "ctf" for `--mode ctf`
+fn arg_value(flag: &str) -> Option<String> {
+    env::args().skip_while(|arg| arg != flag).nth(1)
+}
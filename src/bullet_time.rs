@@ -0,0 +1,86 @@
+use crate::score::PlayerScore;
+use crate::time::{DeltaTime, TimeScale};
+use crate::tweakables::{self, Tweak, Tweakables};
+use specs::*;
+
+// how slow everything but the player runs during bullet time, compared to
+// the usual speed
+const SLOW_SCALE: f32 = 0.3;
+// energy used per real second, and the least needed to start
+const ENERGY_PER_SECOND: Tweak = Tweak {
+    name: "bullet time energy per second",
+    default: 20.0,
+    min: 0.0,
+    max: 100.0,
+    step: 1.0,
+};
+const MIN_ENERGY: f32 = 10.0;
+// how much of the gap to the wanted time scale is closed each frame, so time
+// eases in and out of slow motion rather than snapping
+const EASE: f32 = 0.15;
+
+// The player's slow motion ability, paid for with energy
+#[derive(Debug, Default)]
+pub(crate) struct BulletTime {
+    pub(crate) active: bool,
+    // set by the input handler, the system turns bullet time on or off
+    pub(crate) toggle_requested: bool,
+}
+
+impl BulletTime {
+    // how far into slow motion the game is, from 0 to 1, which the
+    // desaturation follows
+    pub(crate) fn depth(time: &TimeScale) -> f32 {
+        ((1.0 - time.scale / time.base) / (1.0 - SLOW_SCALE))
+            .max(0.0)
+            .min(1.0)
+    }
+}
+
+// Turns bullet time on and off, drains energy while it's on and eases the
+// global TimeScale toward where it should be. Energy drains in real time, not
+// scaled time.
+pub(crate) struct BulletTimeSystem;
+
+impl BulletTimeSystem {
+    pub(crate) fn new(world: &World) -> Self {
+        tweakables::register(world, &ENERGY_PER_SECOND);
+        BulletTimeSystem
+    }
+}
+
+impl<'a> System<'a> for BulletTimeSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Read<'a, Tweakables>,
+        Write<'a, BulletTime>,
+        Write<'a, TimeScale>,
+        Write<'a, PlayerScore>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (delta, tweakables, mut bullet_time, mut time, mut score) = data;
+
+        if bullet_time.toggle_requested {
+            bullet_time.toggle_requested = false;
+            bullet_time.active = !bullet_time.active && score.energy >= MIN_ENERGY;
+        }
+        if bullet_time.active {
+            score.energy -= tweakables.get(&ENERGY_PER_SECOND) * delta.seconds;
+            if score.energy <= 0.0 {
+                score.energy = 0.0;
+                bullet_time.active = false;
+            }
+        }
+
+        let wanted = if bullet_time.active {
+            SLOW_SCALE * time.base
+        } else {
+            time.base
+        };
+        time.scale += (wanted - time.scale) * EASE;
+        if (wanted - time.scale).abs() < 0.01 {
+            time.scale = wanted;
+        }
+    }
+}
@@ -0,0 +1,60 @@
+use crate::{CollisionBox, ImageHandle, Position, Rotation};
+use ggez::nalgebra;
+use specs::*;
+
+// A group of components that only make sense together. Adding them as a bundle
+// means an entity can't end up with half of one, like a Position the collision
+// system doesn't know about.
+pub(crate) trait Bundle {
+    fn add_to<B: Builder>(self, builder: B) -> B;
+}
+
+// Something that is somewhere and takes up space, with the CollisionBox
+// starting out at the Position so the two agree from the first frame
+#[derive(Debug)]
+pub(crate) struct PhysicsBundle {
+    pub(crate) position: Position,
+    pub(crate) collision_box: CollisionBox,
+}
+
+impl PhysicsBundle {
+    // with its top left corner at origin
+    pub(crate) fn new(origin: nalgebra::Point2<f32>, width: f32, height: f32) -> Self {
+        PhysicsBundle {
+            position: Position { position: origin },
+            collision_box: CollisionBox {
+                origin,
+                height,
+                width,
+            },
+        }
+    }
+}
+
+impl Bundle for PhysicsBundle {
+    fn add_to<B: Builder>(self, builder: B) -> B {
+        builder.with(self.position).with(self.collision_box)
+    }
+}
+
+// Something drawn with an image, facing up the screen to start with
+#[derive(Debug)]
+pub(crate) struct SpriteBundle {
+    pub(crate) image: ImageHandle,
+    pub(crate) rotation: Rotation,
+}
+
+impl SpriteBundle {
+    pub(crate) fn new(image: ImageHandle) -> Self {
+        SpriteBundle {
+            image,
+            rotation: Rotation { angle: 0.0 },
+        }
+    }
+}
+
+impl Bundle for SpriteBundle {
+    fn add_to<B: Builder>(self, builder: B) -> B {
+        builder.with(self.image).with(self.rotation)
+    }
+}
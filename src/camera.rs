@@ -0,0 +1,166 @@
+use crate::interpolation::{Interpolation, PreviousPosition};
+use crate::resources::DeltaTime;
+use crate::tweakables::{self, Tweak, Tweakables};
+use crate::{CollisionBox, ControllableTag};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+
+// how quickly the camera catches up with the player, the higher the snappier
+const FOLLOW_RATE: Tweak = Tweak {
+    name: "camera follow rate",
+    default: 6.0,
+    min: 0.5,
+    max: 30.0,
+    step: 0.5,
+};
+
+// Which part of the world is on screen. The world is drawn through the
+// camera, so a level can be bigger than the window: the camera looks at a
+// window sized piece of it around its center, closer in the more it's zoomed,
+// and never past the edge of the level. The HUD and menus are drawn straight
+// onto the screen as before.
+//
+// A level the size of the window, which is any level that doesn't give a
+// size, has nowhere for the camera to go, so it stays put.
+#[derive(Clone, Debug)]
+pub(crate) struct Camera {
+    pub(crate) center: nalgebra::Point2<f32>,
+    // where it was looking before the last update, see Interpolation
+    pub(crate) previous_center: nalgebra::Point2<f32>,
+    pub(crate) zoom: f32,
+    // the screen the world is drawn on, in screen co-ordinates
+    pub(crate) viewport: graphics::Rect,
+    // the level, which the camera doesn't look outside of
+    pub(crate) bounds: graphics::Rect,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera::new(graphics::Rect::new(0.0, 0.0, 800.0, 600.0))
+    }
+}
+
+impl Camera {
+    // looking at the middle of a level of the given bounds, until the player
+    // is found
+    pub(crate) fn new(bounds: graphics::Rect) -> Self {
+        let center = nalgebra::Point2::new(bounds.x + bounds.w / 2.0, bounds.y + bounds.h / 2.0);
+        Camera {
+            center,
+            previous_center: center,
+            zoom: 1.0,
+            viewport: graphics::Rect::new(0.0, 0.0, 800.0, 600.0),
+            bounds,
+        }
+    }
+
+    // the part of the world on screen
+    pub(crate) fn view(&self) -> graphics::Rect {
+        let (width, height) = (self.viewport.w / self.zoom, self.viewport.h / self.zoom);
+        graphics::Rect::new(
+            self.center.x - width / 2.0,
+            self.center.y - height / 2.0,
+            width,
+            height,
+        )
+    }
+
+    pub(crate) fn to_world(&self, point: nalgebra::Point2<f32>) -> nalgebra::Point2<f32> {
+        let view = self.view();
+        nalgebra::Point2::new(
+            view.x + (point.x - self.viewport.x) / self.zoom,
+            view.y + (point.y - self.viewport.y) / self.zoom,
+        )
+    }
+
+    pub(crate) fn to_screen(&self, point: nalgebra::Point2<f32>) -> nalgebra::Point2<f32> {
+        let view = self.view();
+        nalgebra::Point2::new(
+            self.viewport.x + (point.x - view.x) * self.zoom,
+            self.viewport.y + (point.y - view.y) * self.zoom,
+        )
+    }
+
+    // Looks at the point, or as near it as the camera gets without showing
+    // anything past the edge of the level. A level narrower than the view is
+    // kept in the middle of it.
+    pub(crate) fn look_at(&mut self, point: nalgebra::Point2<f32>) {
+        let view = self.view();
+        let clamp = |at: f32, start: f32, length: f32, seen: f32| {
+            if length <= seen {
+                start + length / 2.0
+            } else {
+                at.max(start + seen / 2.0).min(start + length - seen / 2.0)
+            }
+        };
+        self.center = nalgebra::Point2::new(
+            clamp(point.x, self.bounds.x, self.bounds.w, view.w),
+            clamp(point.y, self.bounds.y, self.bounds.h, view.h),
+        );
+    }
+
+    // the camera part of the way from where it was looking before the last
+    // update to where it is now, to draw the frame through
+    pub(crate) fn interpolated(&self, interpolation: &Interpolation) -> Camera {
+        let previous = PreviousPosition {
+            position: self.previous_center,
+        };
+        Camera {
+            center: interpolation.blend(Some(&previous), self.center),
+            ..self.clone()
+        }
+    }
+
+    // Draws everything from here until pop through the camera
+    pub(crate) fn push(&self, ctx: &mut Context) -> GameResult<()> {
+        let view = self.view();
+        let transform = nalgebra::Matrix4::new_translation(&nalgebra::Vector3::new(
+            self.viewport.x,
+            self.viewport.y,
+            0.0,
+        )) * nalgebra::Matrix4::new_scaling(self.zoom)
+            * nalgebra::Matrix4::new_translation(&nalgebra::Vector3::new(-view.x, -view.y, 0.0));
+        graphics::push_transform(ctx, Some(transform));
+        graphics::apply_transformations(ctx)
+    }
+
+    // back to drawing straight onto the screen
+    pub(crate) fn pop(ctx: &mut Context) -> GameResult<()> {
+        graphics::pop_transform(ctx);
+        graphics::apply_transformations(ctx)
+    }
+}
+
+// Keeps the camera on the player's ship, catching up with it smoothly rather
+// than jerking along with every move
+pub(crate) struct CameraFollowSystem;
+
+impl CameraFollowSystem {
+    pub(crate) fn new(world: &World) -> Self {
+        tweakables::register(world, &FOLLOW_RATE);
+        CameraFollowSystem
+    }
+}
+
+impl<'a> System<'a> for CameraFollowSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Read<'a, Tweakables>,
+        Write<'a, Camera>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, ControllableTag>,
+    );
+
+    fn run(&mut self, (delta, tweakables, mut camera, coll_box, controlled): Self::SystemData) {
+        let player = match (&coll_box, &controlled).join().next() {
+            Some((player_box, _)) => player_box.center(),
+            None => return,
+        };
+        // the same share of the way there each second, however long the
+        // update
+        let catch_up = 1.0 - (-tweakables.get(&FOLLOW_RATE) * delta.seconds).exp();
+        let center = camera.center;
+        camera.look_at(center + (player - center) * catch_up);
+    }
+}
@@ -0,0 +1,125 @@
+use crate::controls::Aim;
+use crate::hitbox::Hurtbox;
+use crate::notifications::Notifications;
+use crate::score::PlayerScore;
+use crate::status::{Status, StatusEffects};
+use crate::waves::WaveDirector;
+use crate::{BoxOffset, CollisionBox, ControllableTag, Position};
+use ggez::event::{KeyCode, KeyMods};
+use specs::*;
+use std::collections::HashMap;
+
+// points handed out by the give score cheat
+const GIVE_SCORE: u32 = 1000;
+
+// Shortcuts for testing, only built with the dev-tools feature. Each is a key
+// held with Ctrl and Shift:
+//
+//     G  god mode, the player takes no damage
+//     P  give the player some score
+//     W  send in the next wave now
+//     T  teleport the player to the mouse cursor
+//     N  noclip, shots and attacks pass straight through the player
+//
+// Cheats change the world behind the replay's back, so a game they were used
+// in won't play back the same.
+#[derive(Default)]
+pub(crate) struct Cheats {
+    god_mode: bool,
+    // the player's hurtboxes, kept here while noclip has them off
+    noclip: HashMap<Entity, Hurtbox>,
+}
+
+impl Cheats {
+    // Carries out the cheat for a key, returning false if it isn't one
+    pub(crate) fn key_down(&mut self, world: &World, keycode: KeyCode, keymod: KeyMods) -> bool {
+        if !keymod.contains(KeyMods::CTRL) || !keymod.contains(KeyMods::SHIFT) {
+            return false;
+        }
+        let message = match keycode {
+            KeyCode::G => self.toggle_god_mode(world),
+            KeyCode::P => {
+                world.write_resource::<PlayerScore>().points += GIVE_SCORE;
+                format!("Cheat: {} points", GIVE_SCORE)
+            }
+            KeyCode::W => {
+                world.write_resource::<WaveDirector>().send_now = true;
+                "Cheat: next wave".to_owned()
+            }
+            KeyCode::T => teleport(world),
+            KeyCode::N => self.toggle_noclip(world),
+            _ => return false,
+        };
+        world.write_resource::<Notifications>().push(&message);
+        true
+    }
+
+    // a shield that never runs out
+    fn toggle_god_mode(&mut self, world: &World) -> String {
+        self.god_mode = !self.god_mode;
+        let entities = world.entities();
+        let controlled = world.read_storage::<ControllableTag>();
+        let mut statuses = world.write_storage::<StatusEffects>();
+        for (player, _) in (&entities, &controlled).join() {
+            if self.god_mode {
+                crate::status::apply(&mut statuses, player, Status::Shielded, std::f32::INFINITY);
+            } else if let Some(effects) = statuses.get_mut(player) {
+                effects.effects.retain(|e| e.status != Status::Shielded);
+            }
+        }
+        format!("Cheat: god mode {}", on_off(self.god_mode))
+    }
+
+    fn toggle_noclip(&mut self, world: &World) -> String {
+        let entities = world.entities();
+        let controlled = world.read_storage::<ControllableTag>();
+        let mut hurtboxes = world.write_storage::<Hurtbox>();
+        if self.noclip.is_empty() {
+            for (player, _) in (&entities, &controlled).join() {
+                if let Some(hurtbox) = hurtboxes.remove(player) {
+                    self.noclip.insert(player, hurtbox);
+                }
+            }
+        } else {
+            for (player, hurtbox) in self.noclip.drain() {
+                if entities.is_alive(player) {
+                    hurtboxes.insert(player, hurtbox).unwrap_or_else(|err| {
+                        println!("cheat error {:?}", err);
+                        None
+                    });
+                }
+            }
+        }
+        format!("Cheat: noclip {}", on_off(!self.noclip.is_empty()))
+    }
+}
+
+// moves the player's ship so its middle is under the cursor
+fn teleport(world: &World) -> String {
+    let cursor = world.read_resource::<Aim>().cursor;
+    let controlled = world.read_storage::<ControllableTag>();
+    let offsets = world.read_storage::<BoxOffset>();
+    let mut positions = world.write_storage::<Position>();
+    let mut coll_boxes = world.write_storage::<CollisionBox>();
+    for (pos, coll_box, offset, _) in (
+        &mut positions,
+        &mut coll_boxes,
+        offsets.maybe(),
+        &controlled,
+    )
+        .join()
+    {
+        let half_size = coll_box.center() - coll_box.origin;
+        coll_box.origin = cursor - half_size;
+        pos.position = coll_box.origin - BoxOffset::of(offset);
+    }
+    format!("Cheat: teleport to {:.0}, {:.0}", cursor.x, cursor.y)
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
@@ -0,0 +1,135 @@
+use crate::events::{self, Subscribe, TrackedReader};
+use crate::health::{DamageEvent, DeathEvent};
+use crate::score::PlayerScore;
+use crate::theme::UiTheme;
+use crate::time::DeltaTime;
+use crate::ControllableTag;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+
+// seconds after a kill to get the next one and keep the chain going
+const COMBO_WINDOW: f32 = 3.0;
+const MAX_MULTIPLIER: u32 = 8;
+const KILL_POINTS: u32 = 100;
+// every step up the chain plays the chime this much higher
+const PITCH_STEP: f32 = 0.1;
+
+// The player's kill chain. Every kill within the window of the last one raises
+// the score multiplier, and getting hurt or letting the window run out drops
+// it back to nothing.
+#[derive(Debug, Default)]
+pub(crate) struct Combo {
+    pub(crate) kills: u32,
+    pub(crate) time_left: f32,
+    // pitches of chimes waiting to be played. Systems can't get at the
+    // Context, so MainState plays these after the update.
+    pub(crate) chimes: Vec<f32>,
+}
+
+impl Combo {
+    pub(crate) fn multiplier(&self) -> u32 {
+        self.kills.max(1).min(MAX_MULTIPLIER)
+    }
+
+    fn reset(&mut self) {
+        self.kills = 0;
+        self.time_left = 0.0;
+    }
+}
+
+// Keeps the combo up to date from the damage and death events
+pub(crate) struct ComboSystem {
+    deaths: TrackedReader<DeathEvent>,
+    damage: TrackedReader<DamageEvent>,
+}
+
+impl ComboSystem {
+    // the readers have to be registered before the first events are sent, so
+    // this needs the event channels already in the world
+    pub(crate) fn new(world: &World) -> Self {
+        ComboSystem {
+            deaths: events::subscribe::<DeathEvent>(world, "combo"),
+            damage: events::subscribe::<DamageEvent>(world, "combo"),
+        }
+    }
+}
+
+impl<'a> System<'a> for ComboSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Write<'a, Combo>,
+        Write<'a, PlayerScore>,
+        Subscribe<'a, DeathEvent>,
+        Subscribe<'a, DamageEvent>,
+        ReadStorage<'a, ControllableTag>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, delta, mut combo, mut score, deaths, damage, controlled) = data;
+        let is_player =
+            |entity: Entity| entities.is_alive(entity) && controlled.get(entity).is_some();
+
+        combo.time_left -= delta.seconds;
+        if combo.kills > 0 && combo.time_left <= 0.0 {
+            combo.reset();
+        }
+
+        for event in damage.read(&mut self.damage) {
+            if is_player(event.target) {
+                combo.reset();
+            }
+        }
+
+        for event in deaths.read(&mut self.deaths) {
+            if !event.killer.map_or(false, is_player) || is_player(event.entity) {
+                continue;
+            }
+            combo.kills += 1;
+            combo.time_left = COMBO_WINDOW;
+            let multiplier = combo.multiplier();
+            score.points += KILL_POINTS * multiplier;
+            combo
+                .chimes
+                .push(1.0 + PITCH_STEP * (multiplier - 1) as f32);
+        }
+    }
+}
+
+// Shows the multiplier under the score while a chain is going, with a bar for
+// how long is left to keep it going
+pub(crate) fn draw_combo(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let combo = world.read_resource::<Combo>();
+    if combo.kills < 2 {
+        return Ok(());
+    }
+
+    let view = graphics::screen_coordinates(ctx);
+    let corner = nalgebra::Point2::new(view.x + 10.0, view.y + 44.0);
+    // the text gets redder the longer the chain
+    let heat = combo.multiplier() as f32 / MAX_MULTIPLIER as f32;
+    let color = graphics::Color::new(1.0, 1.0 - heat * 0.7, 0.2, 1.0);
+
+    let text = world
+        .read_resource::<UiTheme>()
+        .text(format!("x{} COMBO", combo.multiplier()));
+    graphics::draw(
+        ctx,
+        &text,
+        graphics::DrawParam::default().dest(corner).color(color),
+    )?;
+
+    let bar = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(
+            corner.x,
+            corner.y + 20.0,
+            100.0 * (combo.time_left / COMBO_WINDOW).max(0.0),
+            3.0,
+        ),
+        color,
+    )?;
+    graphics::draw(ctx, &bar, graphics::DrawParam::default())
+}
@@ -0,0 +1,313 @@
+use ggez::graphics;
+use ggez::nalgebra;
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+
+pub use crate::assets::ImageHandle;
+pub use crate::lifetime::Lifetime;
+pub use crate::tasks::{Task, Tasks};
+
+// The components the built-in systems work with. Games built on top of the
+// library can put them on their own entities, and read and write them from
+// their own systems.
+
+// using VecStorage as a sensible default
+#[derive(Component, Debug, PartialEq, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Position {
+    pub position: nalgebra::Point2<f32>,
+}
+
+// How fast an entity is moving, in pixels per second. The MovementSystem moves
+// anything with one, not just the player.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Velocity {
+    pub velocity: nalgebra::Vector2<f32>,
+}
+
+impl Default for Velocity {
+    fn default() -> Self {
+        Velocity {
+            velocity: nalgebra::Vector2::zeros(),
+        }
+    }
+}
+
+// How fast an entity's velocity changes, in pixels per second per second.
+// Few things speed up on their own, so most entities don't have one.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct Acceleration {
+    pub acceleration: nalgebra::Vector2<f32>,
+}
+
+#[derive(Component, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct CollisionBox {
+    pub origin: nalgebra::Point2<f32>,
+    pub height: f32,
+    pub width: f32,
+}
+
+impl CollisionBox {
+    pub fn center(&self) -> nalgebra::Point2<f32> {
+        nalgebra::Point2::new(
+            self.origin.x + self.width / 2.0,
+            self.origin.y + self.height / 2.0,
+        )
+    }
+
+    pub fn contains(&self, point: nalgebra::Point2<f32>) -> bool {
+        point.x >= self.origin.x
+            && point.x <= self.origin.x + self.width
+            && point.y >= self.origin.y
+            && point.y <= self.origin.y + self.height
+    }
+}
+
+// The shape an entity bumps into things with, for anything that isn't the
+// rectangle of its CollisionBox. The shape is placed on the middle of the
+// box and has to fit inside it, as the box is still what's used to find what
+// might be touching. A polygon turns with the entity's Rotation, and has to be
+// convex, its points going round in order, e.g. a ship's nose and wings:
+//
+//     Polygon([(0.0, -24.0), (24.0, 24.0), (-24.0, 24.0)])
+//
+// Anything without one collides as the rectangle, as before.
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub enum Collider {
+    // the CollisionBox itself
+    Rect,
+    Circle { radius: f32 },
+    // points relative to the middle of the box, at no rotation
+    Polygon(Vec<(f32, f32)>),
+}
+
+impl Default for Collider {
+    fn default() -> Self {
+        Collider::Rect
+    }
+}
+
+// Rotation is kept in radians, clockwise, with 0 facing up the screen the same
+// way the ship sprite does
+#[derive(Component, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Rotation {
+    pub angle: f32,
+}
+
+impl Rotation {
+    pub fn facing(heading: nalgebra::Vector2<f32>) -> Self {
+        Rotation {
+            angle: heading.x.atan2(-heading.y),
+        }
+    }
+
+    // unit vector pointing the way the entity faces
+    pub fn heading(&self) -> nalgebra::Vector2<f32> {
+        nalgebra::Vector2::new(self.angle.sin(), -self.angle.cos())
+    }
+}
+
+// Plays an entity's ImageHandle as a sprite sheet, showing one frame of it at a time.
+// Each frame is a rectangle of the sheet in pixels, shown for frame_time
+// seconds of game time. A looping animation starts over after the last frame,
+// any other stops on it.
+#[derive(Component, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct Animation {
+    #[serde(with = "frames")]
+    pub frames: Vec<graphics::Rect>,
+    pub frame_time: f32,
+    pub looping: bool,
+    // the frame showing, and how long it has been showing for
+    pub frame: usize,
+    pub elapsed: f32,
+}
+
+// Animation frames are saved as (x, y, w, h)
+mod frames {
+    use ggez::graphics::Rect;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        frames: &[Rect],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let tuples: Vec<(f32, f32, f32, f32)> =
+            frames.iter().map(|r| (r.x, r.y, r.w, r.h)).collect();
+        tuples.serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<Rect>, D::Error> {
+        let tuples = Vec::<(f32, f32, f32, f32)>::deserialize(deserializer)?;
+        Ok(tuples
+            .into_iter()
+            .map(|(x, y, w, h)| Rect::new(x, y, w, h))
+            .collect())
+    }
+}
+
+impl Animation {
+    pub fn new(frames: Vec<graphics::Rect>, frame_time: f32, looping: bool) -> Self {
+        Animation {
+            frames,
+            frame_time,
+            looping,
+            frame: 0,
+            elapsed: 0.0,
+        }
+    }
+
+    // Frames of the same size laid out left to right along a strip, starting
+    // from the sheet's top left corner
+    pub fn strip(count: usize, width: f32, height: f32, frame_time: f32, looping: bool) -> Self {
+        let frames = (0..count)
+            .map(|i| graphics::Rect::new(i as f32 * width, 0.0, width, height))
+            .collect();
+        Animation::new(frames, frame_time, looping)
+    }
+
+    // whether a one-shot animation has got to its last frame
+    pub fn finished(&self) -> bool {
+        !self.looping && self.frame + 1 >= self.frames.len()
+    }
+
+    // the rectangle of the sheet showing now, if there are any frames
+    pub fn current(&self) -> Option<graphics::Rect> {
+        self.frames.get(self.frame).cloned()
+    }
+}
+
+// How many times the size of its image an entity is drawn, along each axis,
+// scaled about the middle of the sprite. Something with a Scale also has its
+// CollisionBox fitted around the sprite as it's drawn, see FitBoxSystem.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Scale {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Scale {
+    pub fn uniform(scale: f32) -> Self {
+        Scale { x: scale, y: scale }
+    }
+}
+
+impl Default for Scale {
+    fn default() -> Self {
+        Scale::uniform(1.0)
+    }
+}
+
+// How far an entity's CollisionBox sits from its Position, for a box that
+// isn't at the top left of the sprite. The FitBoxSystem keeps it up to date
+// for anything with a Scale, and the systems that move an entity move its box
+// with it by this much. Anything without one has its box at its Position.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub struct BoxOffset {
+    pub offset: nalgebra::Vector2<f32>,
+}
+
+impl BoxOffset {
+    // the offset, if there is one
+    pub fn of(offset: Option<&BoxOffset>) -> nalgebra::Vector2<f32> {
+        offset.map_or(nalgebra::Vector2::zeros(), |offset| offset.offset)
+    }
+}
+
+// Where an entity is, which way it's turned and how big it's drawn, all in one
+// place for the things that place its sprite: the RenderSystem, outlines and
+// the FitBoxSystem. The TransformSystem keeps one on everything with a
+// Position, made from its Position, Rotation and Scale, which are still what
+// gameplay reads and writes. The translation is from the world's origin to
+// the top left corner of the sprite, the same point the Position is.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Transform {
+    pub translation: nalgebra::Vector2<f32>,
+    pub rotation: f32,
+    pub scale: nalgebra::Vector2<f32>,
+}
+
+impl Transform {
+    pub fn of(position: &Position, rotation: Option<&Rotation>, scale: Option<&Scale>) -> Self {
+        let scale = scale.cloned().unwrap_or_default();
+        Transform {
+            translation: position.position.coords,
+            rotation: rotation.map_or(0.0, |rotation| rotation.angle),
+            scale: nalgebra::Vector2::new(scale.x, scale.y),
+        }
+    }
+
+    // the point the translation takes the origin to
+    pub fn position(&self) -> nalgebra::Point2<f32> {
+        nalgebra::Point2::from(self.translation)
+    }
+}
+
+// This is a tag to say something is player controllable
+// we use null storage as we're only using this as a marker component
+// see the specs book for more information:
+// (https://slide-rs.github.io/specs/11_advanced_component.html)
+// I had to derive Default to make this work
+#[derive(Component, Debug, Default, Clone, Serialize, Deserialize)]
+#[storage(NullStorage)]
+pub struct ControllableTag;
+
+// Marks something the player's ship can't fly through, like a rock. Rather
+// than passing over it, the ship is pushed back out by the MovementSystem.
+#[derive(Component, Debug, Default, Clone, Serialize, Deserialize)]
+#[storage(NullStorage)]
+pub struct Solid;
+
+// What happens to something that goes past the edge of the level, once the
+// BoundsSystem catches it
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BoundsPolicy {
+    // held at the edge
+    Clamp,
+    // off one side and back on the other, like Asteroids
+    Wrap,
+    // deleted once it's all the way off
+    Destroy,
+}
+
+// Keeps something in the level, which for a level without a size is the
+// window. Anything without one can go as far as it likes.
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Bounded {
+    pub policy: BoundsPolicy,
+}
+
+impl Bounded {
+    pub fn new(policy: BoundsPolicy) -> Self {
+        Bounded { policy }
+    }
+}
+
+// Which layer an entity's sprite is drawn in. Higher layers are drawn over
+// lower ones, and within a layer sprites further down the screen are drawn
+// over those above them. Anything without one is drawn with the ships.
+#[derive(
+    Component, Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize,
+)]
+#[storage(VecStorage)]
+pub struct ZOrder(pub i32);
+
+impl ZOrder {
+    pub const BACKGROUND: ZOrder = ZOrder(-100);
+    pub const SHIPS: ZOrder = ZOrder(0);
+    // the player's ship is never lost under an enemy's
+    pub const PLAYER: ZOrder = ZOrder(10);
+    pub const FOREGROUND: ZOrder = ZOrder(100);
+}
@@ -0,0 +1,302 @@
+use crate::input_map::InputMap;
+use crate::settings::Settings;
+use crate::{CollisionBox, ControllableTag, Direction, Rotation};
+use ggez::nalgebra;
+use ggez::{graphics, Context};
+use serde::{Deserialize, Serialize};
+use specs::*;
+use std::fmt::Debug;
+
+// Below this deflection a stick is treated as resting, otherwise a slightly
+// worn stick would keep dragging the aim or the ship around
+pub(crate) const STICK_DEAD_ZONE: f32 = 0.25;
+
+// Classic is the original scheme: the arrow keys move the ship, it turns to face
+// the way it is travelling and Space fires straight ahead.
+// TwinStick moves with WASD and aims independently at the mouse cursor (or the
+// right stick of a gamepad), so the ship can strafe while it shoots.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum ControlScheme {
+    Classic,
+    TwinStick,
+}
+
+impl Default for ControlScheme {
+    fn default() -> Self {
+        ControlScheme::Classic
+    }
+}
+
+impl ControlScheme {
+    pub(crate) fn next(self) -> Self {
+        match self {
+            ControlScheme::Classic => ControlScheme::TwinStick,
+            ControlScheme::TwinStick => ControlScheme::Classic,
+        }
+    }
+}
+
+// A device the player can control the game with. Gamepads are told apart by
+// their GUID rather than the id ggez hands out, which depends on the order they
+// were plugged in, so the same pad always finds its own profile again.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub(crate) enum Device {
+    Keyboard,
+    Gamepad(String),
+}
+
+impl Device {
+    pub(crate) fn gamepad(uuid: [u8; 16]) -> Self {
+        Device::Gamepad(uuid.iter().map(|byte| format!("{:02x}", byte)).collect())
+    }
+}
+
+// The device the player last used. MainState keeps its own copy and mirrors it
+// into the world, so prompts can show that device's keys or buttons.
+#[derive(Clone, Debug)]
+pub(crate) struct ActiveDevice(pub(crate) Device);
+
+impl Default for ActiveDevice {
+    fn default() -> Self {
+        ActiveDevice(Device::Keyboard)
+    }
+}
+
+// The one-shot commands a key or button can be bound to. Moving and firing
+// aren't in here, those follow the control scheme through the InputMap.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Action {
+    CycleScheme,
+    LockOn,
+    Radar,
+    Cloak,
+    Melee,
+    BulletTime,
+    // cycles the palette the player's ship is painted in
+    ShipColor,
+    // F10's 10,000 bullets, never bound or listed on the controls screen
+    #[cfg(feature = "dev-tools")]
+    StressTest,
+}
+
+impl Action {
+    // in the order the controls screen lists them
+    pub(crate) const ALL: [Action; 7] = [
+        Action::LockOn,
+        Action::Radar,
+        Action::Cloak,
+        Action::Melee,
+        Action::BulletTime,
+        Action::CycleScheme,
+        Action::ShipColor,
+    ];
+
+    pub(crate) fn label(self) -> &'static str {
+        match self {
+            Action::CycleScheme => "Control scheme",
+            Action::LockOn => "Lock on",
+            Action::Radar => "Radar ping",
+            Action::Cloak => "Cloak",
+            Action::Melee => "Melee",
+            Action::BulletTime => "Bullet time",
+            Action::ShipColor => "Ship colour",
+            #[cfg(feature = "dev-tools")]
+            Action::StressTest => "Stress test",
+        }
+    }
+}
+
+// How one device is set up. Keys and buttons are stored by the names ggez
+// prints for them, e.g. "Tab" or "RightThumb", so the settings file stays
+// readable and editable by hand.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct BindingProfile {
+    // what the device calls itself, filled in whenever it's seen
+    #[serde(default)]
+    pub(crate) name: String,
+    pub(crate) control_scheme: ControlScheme,
+    pub(crate) bindings: Vec<(String, Action)>,
+}
+
+impl BindingProfile {
+    pub(crate) fn new(device: &Device) -> Self {
+        let (name, control_scheme, bindings) = match device {
+            Device::Keyboard => (
+                "Keyboard",
+                ControlScheme::Classic,
+                vec![
+                    ("F2", Action::CycleScheme),
+                    ("Tab", Action::LockOn),
+                    ("R", Action::Radar),
+                    ("C", Action::Cloak),
+                    ("V", Action::Melee),
+                    ("B", Action::BulletTime),
+                    ("P", Action::ShipColor),
+                ],
+            ),
+            // a gamepad can only aim and fire with the sticks and triggers
+            Device::Gamepad(_) => (
+                "",
+                ControlScheme::TwinStick,
+                vec![
+                    ("Select", Action::CycleScheme),
+                    ("RightThumb", Action::LockOn),
+                    ("North", Action::Radar),
+                    ("East", Action::Melee),
+                    ("LeftTrigger", Action::BulletTime),
+                ],
+            ),
+        };
+        BindingProfile {
+            name: name.to_owned(),
+            control_scheme,
+            bindings: bindings
+                .into_iter()
+                .map(|(input, action)| (input.to_owned(), action))
+                .collect(),
+        }
+    }
+
+    // what a KeyCode or gamepad Button is bound to, if anything
+    pub(crate) fn action(&self, input: &impl Debug) -> Option<Action> {
+        let name = format!("{:?}", input);
+        self.bindings
+            .iter()
+            .find(|(bound, _)| *bound == name)
+            .map(|(_, action)| *action)
+    }
+
+    // the input an action is bound to, if anything
+    pub(crate) fn input(&self, action: Action) -> Option<&str> {
+        self.bindings
+            .iter()
+            .find(|(_, bound)| *bound == action)
+            .map(|(input, _)| input.as_str())
+    }
+
+    // What the control scheme already uses the input for, if anything: the
+    // InputMap's keys, and the gamepad buttons that fire and pause. Those
+    // inputs can't be bound, moving, firing and pausing would stop working.
+    pub(crate) fn reserved(&self, input_map: &InputMap, input: &str) -> Option<&'static str> {
+        if let Some(action) = input_map.named(self.control_scheme, input) {
+            return Some(action.purpose());
+        }
+        match (self.control_scheme, input) {
+            (ControlScheme::TwinStick, "RightTrigger2") => Some("firing"),
+            (_, "Start") => Some("pausing"),
+            _ => None,
+        }
+    }
+
+    // Binds the action to the input in place of whatever it was on. If another
+    // action already had the input, the two swap, so that one ends up on the
+    // action's old input rather than unbound. Returns the action that moved.
+    pub(crate) fn rebind(&mut self, action: Action, input: &str) -> Option<Action> {
+        let old = self.bindings.iter().position(|(_, bound)| *bound == action);
+        let taken = self.bindings.iter().position(|(bound, _)| bound == input);
+        match (old, taken) {
+            (_, Some(taken)) if self.bindings[taken].1 == action => None,
+            (old, Some(taken)) => {
+                let moved = self.bindings[taken].1;
+                self.bindings[taken].1 = action;
+                if let Some(old) = old {
+                    self.bindings[old].1 = moved;
+                }
+                Some(moved)
+            }
+            (Some(old), None) => {
+                self.bindings[old].0 = input.to_owned();
+                None
+            }
+            (None, None) => {
+                self.bindings.push((input.to_owned(), action));
+                None
+            }
+        }
+    }
+
+    // back to the bindings the device starts out with
+    pub(crate) fn reset(&mut self, device: &Device) {
+        self.bindings = BindingProfile::new(device).bindings;
+    }
+}
+
+// Aim sits next to Direction as player input. Like Direction, MainState owns a
+// copy that the ggez event handlers keep up to date and mirrors it into the
+// world for the systems to read.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Aim {
+    // the mouse cursor, already unprojected into world co-ordinates
+    pub(crate) cursor: nalgebra::Point2<f32>,
+    pub(crate) stick_x: f32,
+    pub(crate) stick_y: f32,
+    pub(crate) firing: bool,
+}
+
+impl Default for Aim {
+    fn default() -> Self {
+        Aim {
+            cursor: nalgebra::Point2::origin(),
+            stick_x: 0.0,
+            stick_y: 0.0,
+            firing: false,
+        }
+    }
+}
+
+impl Aim {
+    // The right stick wins over the mouse while it is pushed, so picking up a
+    // gamepad doesn't require touching the settings.
+    fn stick(&self) -> Option<nalgebra::Vector2<f32>> {
+        let stick = nalgebra::Vector2::new(self.stick_x, self.stick_y);
+        if stick.norm() > STICK_DEAD_ZONE {
+            Some(stick)
+        } else {
+            None
+        }
+    }
+}
+
+// ggez hands us the mouse in window pixels, but everything is drawn in screen
+// co-ordinates. Mapping the pixel through the current screen co-ordinates keeps
+// the mouse correct when the window is resized. The Camera takes it on from
+// there into the world.
+pub(crate) fn window_to_screen(ctx: &Context, x: f32, y: f32) -> nalgebra::Point2<f32> {
+    let view = graphics::screen_coordinates(ctx);
+    let (width, height) = graphics::drawable_size(ctx);
+    nalgebra::Point2::new(view.x + x / width * view.w, view.y + y / height * view.h)
+}
+
+// The aim system turns player controlled ships according to the control
+// scheme. It only sets the Rotation, the weapon system reads it back when it
+// decides which way to fire.
+pub(crate) struct AimSystem;
+
+impl<'a> System<'a> for AimSystem {
+    type SystemData = (
+        Read<'a, Settings>,
+        Read<'a, Direction>,
+        Read<'a, Aim>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, ControllableTag>,
+        WriteStorage<'a, Rotation>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (settings, dir, aim, coll_box, controlled, mut rotation) = data;
+
+        for (coll_box, rotation, _) in (&coll_box, &mut rotation, &controlled).join() {
+            let heading = match settings.control_scheme {
+                ControlScheme::Classic => dir.heading(),
+                ControlScheme::TwinStick => aim
+                    .stick()
+                    .unwrap_or_else(|| aim.cursor - coll_box.center()),
+            };
+
+            // with no input we keep whatever heading the ship already had
+            if heading.norm() > 0.0 {
+                *rotation = Rotation::facing(heading);
+            }
+        }
+    }
+}
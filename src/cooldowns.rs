@@ -0,0 +1,171 @@
+use crate::controls::ActiveDevice;
+use crate::glyphs::{self, Glyphs};
+use crate::settings::Settings;
+use crate::theme::UiTheme;
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::ControllableTag;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+use std::borrow::Cow;
+
+// the names of the timers the game uses
+pub(crate) const FIRE: &str = "fire";
+pub(crate) const RADAR: &str = "radar";
+
+// timers the player sees on the HUD, with what to call them and the input
+// that uses them, named as in a prompt. The fire timer comes round too quickly
+// to be worth showing.
+const HUD_TIMERS: [(&str, &str, &str); 1] = [(RADAR, "RADAR", "{Radar}")];
+// sits just above the minimap
+const HUD_BOTTOM: f32 = 170.0;
+const HUD_MARGIN: f32 = 10.0;
+const BAR_WIDTH: f32 = 60.0;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Timer {
+    name: Cow<'static, str>,
+    // seconds, counting down to 0
+    remaining: f32,
+    duration: f32,
+}
+
+// Named timers for anything an entity can only do every so often. The
+// CooldownSystem counts them all down, so the systems using them only start
+// them and check if they are ready. Kept in a Vec rather than a map, there are
+// only ever a few and they stay in the same order.
+#[derive(Component, Debug, Default, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(crate) struct Cooldowns {
+    timers: Vec<Timer>,
+}
+
+impl Cooldowns {
+    fn timer(&self, name: &str) -> Option<&Timer> {
+        self.timers.iter().find(|timer| timer.name == name)
+    }
+
+    // a timer that has never been started is ready
+    pub(crate) fn ready(&self, name: &str) -> bool {
+        self.timer(name)
+            .map_or(true, |timer| timer.remaining <= 0.0)
+    }
+
+    pub(crate) fn start(&mut self, name: &'static str, duration: f32) {
+        match self.timers.iter_mut().find(|timer| timer.name == name) {
+            Some(timer) => {
+                timer.remaining = duration;
+                timer.duration = duration;
+            }
+            None => self.timers.push(Timer {
+                name: Cow::Borrowed(name),
+                remaining: duration,
+                duration,
+            }),
+        }
+    }
+
+    // Starts the timer if it is ready, returning whether it was
+    pub(crate) fn try_use(&mut self, name: &'static str, duration: f32) -> bool {
+        if !self.ready(name) {
+            return false;
+        }
+        self.start(name, duration);
+        true
+    }
+
+    // How much of the timer is left to run, from 1 when it has just started
+    // down to 0 when it is ready
+    pub(crate) fn fraction(&self, name: &str) -> f32 {
+        match self.timer(name) {
+            Some(timer) if timer.duration > 0.0 => (timer.remaining / timer.duration).max(0.0),
+            _ => 0.0,
+        }
+    }
+}
+
+pub(crate) struct CooldownSystem;
+
+impl<'a> System<'a> for CooldownSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Cooldowns>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, delta, time, multipliers, mut cooldowns) = data;
+
+        for (entity, cooldowns) in (&entities, &mut cooldowns).join() {
+            let dt = time.scaled(delta.seconds, multipliers.get(entity));
+            for timer in cooldowns.timers.iter_mut() {
+                timer.remaining = (timer.remaining - dt).max(0.0);
+            }
+        }
+    }
+}
+
+// The player's ability timers as labelled bars that fill back up as they
+// recharge, above the minimap, each with the key or button for it
+pub(crate) fn draw_player_cooldowns(
+    ctx: &mut Context,
+    world: &World,
+    glyphs: &Glyphs,
+) -> GameResult<()> {
+    let cooldowns = world.read_storage::<Cooldowns>();
+    let controlled = world.read_storage::<ControllableTag>();
+    let player = match (&cooldowns, &controlled).join().next() {
+        Some((cooldowns, _)) => cooldowns,
+        None => return Ok(()),
+    };
+
+    let view = graphics::screen_coordinates(ctx);
+    let x = view.x + view.w - BAR_WIDTH - HUD_MARGIN;
+    let mut y = view.y + view.h - HUD_BOTTOM;
+    let device = world.read_resource::<ActiveDevice>();
+    let settings = world.read_resource::<Settings>();
+    let theme = world.read_resource::<UiTheme>();
+    let mut bars = graphics::MeshBuilder::new();
+    for (name, label, input) in HUD_TIMERS.iter() {
+        y -= 24.0;
+        let fraction = player.fraction(name);
+        let color = if fraction > 0.0 {
+            theme.dim
+        } else {
+            theme.accent
+        };
+        graphics::queue_text(
+            ctx,
+            &theme.text(*label),
+            nalgebra::Point2::new(x, y),
+            Some(color),
+        );
+        let input = glyphs::pieces(input, &device.0, &settings);
+        let (input_w, _) = glyphs.measure(ctx, &theme, &input);
+        glyphs.draw(
+            ctx,
+            &theme,
+            &input,
+            nalgebra::Point2::new(x - input_w, y),
+            color,
+        )?;
+        bars.rectangle(
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(x, y + 16.0, BAR_WIDTH * (1.0 - fraction), 3.0),
+            color,
+        );
+    }
+
+    let bars = bars.build(ctx)?;
+    graphics::draw(ctx, &bars, graphics::DrawParam::default())?;
+    graphics::draw_queued_text(
+        ctx,
+        graphics::DrawParam::default(),
+        None,
+        graphics::FilterMode::Linear,
+    )
+}
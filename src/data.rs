@@ -0,0 +1,131 @@
+use crate::atlas::AtlasFile;
+use crate::behavior::Node;
+use crate::input_map::InputMap;
+use crate::level::Level;
+use crate::patterns::Pattern;
+use crate::platform;
+use crate::prefab;
+use crate::replay::Replay;
+use crate::theme::{self, ThemeFile};
+use crate::tweakables;
+use ggez::{filesystem, Context, GameError, GameResult};
+use serde::de::DeserializeOwned;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+// Checks one data file's text, given its path for the errors
+type Check = fn(&str, &str) -> GameResult<()>;
+
+// Reads a RON data file from the resources directory. See parse for the
+// errors.
+pub(crate) fn load<T: DeserializeOwned>(ctx: &mut Context, path: &str) -> GameResult<T> {
+    let mut text = String::new();
+    filesystem::open(ctx, path)?.read_to_string(&mut text)?;
+    parse(path, &text)
+}
+
+// Reads RON data, with errors that say where in the file the problem is, e.g.
+//
+//     /levels/ctf.ron:12:23: Expected float
+//     /prefabs.ron:3:6: unknown field `hp`, expected one of `includes`, `health`, ...
+//
+// Syntax errors are placed exactly. Errors in what a value means, like an
+// unknown field, are placed just after it, as that is as far as reading got.
+pub(crate) fn parse<T: DeserializeOwned>(path: &str, text: &str) -> GameResult<T> {
+    let error = |message: String| GameError::ResourceLoadError(format!("{}:{}", path, message));
+    let mut deserializer =
+        ron::de::Deserializer::from_str(text).map_err(|err| error(err.to_string()))?;
+    match T::deserialize(&mut deserializer) {
+        Ok(value) => {
+            deserializer.end().map_err(|err| error(err.to_string()))?;
+            Ok(value)
+        }
+        Err(err @ ron::de::Error::Parser(..)) => Err(error(err.to_string())),
+        Err(err) => {
+            let read = text.len() - deserializer.remainder().len();
+            let line = text[..read].matches('\n').count() + 1;
+            let col = read - text[..read].rfind('\n').map_or(0, |newline| newline + 1) + 1;
+            Err(error(format!("{}:{}: {}", line, col, err)))
+        }
+    }
+}
+
+fn check<T: DeserializeOwned>(path: &str, text: &str) -> GameResult<()> {
+    parse::<T>(path, text).map(|_| ())
+}
+
+// What each data file or directory of data files in resources holds. A file
+// that isn't there is fine, the game has defaults for all of them.
+fn checks() -> Vec<(&'static str, Check)> {
+    vec![
+        ("/levels", check::<Level>),
+        ("/behaviors", check::<Node>),
+        ("/patterns", check::<Pattern>),
+        ("/atlas", check::<AtlasFile>),
+        ("/replays", check::<Replay>),
+        ("/input.ron", check::<InputMap>),
+        (theme::PATH, check::<ThemeFile>),
+        (tweakables::PATH, check::<BTreeMap<String, f32>>),
+        ("/prefabs.ron", |path, text| {
+            prefab::parse(path, text).map(|_| ())
+        }),
+    ]
+}
+
+// Checks every data file under resources the way the game would read it,
+// without starting the game. Each problem is printed, and how many there were
+// is returned.
+pub(crate) fn validate() -> usize {
+    let resource_dir = platform::resource_dir();
+    let mut checked = 0;
+    let mut problems = 0;
+    for (path, check) in checks() {
+        let files = if path.ends_with(".ron") {
+            vec![path.to_owned()]
+        } else {
+            ron_files(&resource_dir, path)
+        };
+        for file in files {
+            let full_path = resource_dir.join(&file[1..]);
+            if !full_path.exists() {
+                continue;
+            }
+            let text = match fs::read_to_string(full_path) {
+                Ok(text) => text,
+                Err(err) => {
+                    println!("{}: {}", file, err);
+                    problems += 1;
+                    continue;
+                }
+            };
+            checked += 1;
+            check(&file, &text).unwrap_or_else(|err| {
+                match err {
+                    GameError::ResourceLoadError(message) => println!("{}", message),
+                    err => println!("{}: {:?}", file, err),
+                }
+                problems += 1;
+            });
+        }
+    }
+    println!("Checked {} data files, {} problems", checked, problems);
+    problems
+}
+
+// the RON files in a directory of the resources, as resource paths
+fn ron_files(resource_dir: &Path, dir: &str) -> Vec<String> {
+    let entries = match fs::read_dir(resource_dir.join(&dir[1..])) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+    let mut files: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".ron"))
+        .map(|name| format!("{}/{}", dir, name))
+        .collect();
+    files.sort();
+    files
+}
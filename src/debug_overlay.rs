@@ -0,0 +1,242 @@
+use crate::health::Health;
+use crate::hitbox::{self, Placed};
+use crate::interpolation::Drawn;
+use crate::systems::CollisionStats;
+use crate::weapons::{Projectile, ProjectileStats};
+use crate::{Collider, CollisionBox, ControllableTag, Rotation, Solid};
+use ggez::nalgebra;
+use ggez::{graphics, timer, Context, GameResult};
+use specs::*;
+use std::collections::VecDeque;
+
+const OVERLAY_MARGIN: f32 = 10.0;
+// how many frames the graphs go back
+const HISTORY: usize = 240;
+const GRAPH_WIDTH: f32 = 240.0;
+const GRAPH_HEIGHT: f32 = 36.0;
+
+// What one frame looked like, for the graphs
+#[derive(Clone, Copy, Debug, Default)]
+struct Sample {
+    frame_ms: f32,
+    entities: f32,
+    draw_calls: f32,
+    collision_pairs: f32,
+}
+
+// picks one value out of a sample
+type Measure = fn(&Sample) -> f32;
+
+// F3 shows how the game is running: the frame rate and frame time, how many
+// entities there are, and every CollisionBox as an outline, so collisions can
+// be tuned by looking rather than guessing. The player's box is green,
+// anything Solid white, shots in flight yellow and the rest red. A Collider
+// that isn't the box is drawn inside it in the same color.
+//
+// Under the numbers are graphs of the last few seconds of frame time, entity
+// count, sprite draw calls and collision pairs, so a spike or a slow climb
+// shows up rather than only how things are this frame.
+#[derive(Default)]
+pub(crate) struct DebugOverlay {
+    pub(crate) visible: bool,
+    // oldest first, only kept while the overlay is up
+    history: VecDeque<Sample>,
+}
+
+impl DebugOverlay {
+    pub(crate) fn toggle(&mut self) {
+        self.visible = !self.visible;
+        self.history.clear();
+    }
+
+    // Adds this frame to the graphs, given the draw calls the sprites took
+    pub(crate) fn record(&mut self, ctx: &Context, world: &World, draw_calls: usize) {
+        if !self.visible {
+            return;
+        }
+        if self.history.len() >= HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(Sample {
+            frame_ms: timer::delta(ctx).as_secs_f32() * 1000.0,
+            entities: (&world.entities()).join().count() as f32,
+            draw_calls: draw_calls as f32,
+            collision_pairs: world.read_resource::<CollisionStats>().pairs as f32,
+        });
+    }
+}
+
+// In the world, so it's drawn through the camera along with the boxes' owners
+pub(crate) fn draw_collision_boxes(
+    ctx: &mut Context,
+    world: &World,
+    overlay: &DebugOverlay,
+) -> GameResult<()> {
+    if !overlay.visible {
+        return Ok(());
+    }
+    let entities = world.entities();
+    let coll_box = world.read_storage::<CollisionBox>();
+    let controlled = world.read_storage::<ControllableTag>();
+    let solid = world.read_storage::<Solid>();
+    let projectiles = world.read_storage::<Projectile>();
+    let colliders = world.read_storage::<Collider>();
+    let rotations = world.read_storage::<Rotation>();
+    let drawn = Drawn::new(world);
+
+    let mut mesh = graphics::MeshBuilder::new();
+    let mut any = false;
+    for (entity, coll_box) in (&entities, &coll_box).join() {
+        // drawn round the sprite, which is drawn part of the way between
+        // its last two positions
+        let coll_box = &drawn.coll_box(entity, coll_box);
+        let color = match projectiles.get(entity) {
+            // pooled shots keep their box while they wait
+            Some(projectile) if !projectile.active => continue,
+            Some(_) => graphics::Color::new(1.0, 1.0, 0.2, 0.8),
+            None if controlled.contains(entity) => graphics::Color::new(0.2, 1.0, 0.2, 0.8),
+            None if solid.contains(entity) => graphics::Color::new(1.0, 1.0, 1.0, 0.8),
+            None => graphics::Color::new(1.0, 0.2, 0.2, 0.8),
+        };
+        mesh.rectangle(
+            graphics::DrawMode::stroke(1.0),
+            graphics::Rect::new(
+                coll_box.origin.x,
+                coll_box.origin.y,
+                coll_box.width,
+                coll_box.height,
+            ),
+            color,
+        );
+        any = true;
+        let angle = rotations.get(entity).map_or(0.0, |rotation| rotation.angle);
+        match hitbox::place(colliders.get(entity), coll_box, angle) {
+            Placed::Rect(_) => (),
+            Placed::Circle { center, radius } => {
+                mesh.circle(graphics::DrawMode::stroke(1.0), center, radius, 0.5, color);
+            }
+            Placed::Polygon(points) => {
+                if points.len() >= 3 {
+                    mesh.polygon(graphics::DrawMode::stroke(1.0), &points, color)?;
+                }
+            }
+        }
+    }
+    // ggez won't build a mesh with nothing in it
+    if !any {
+        return Ok(());
+    }
+    let mesh = mesh.build(ctx)?;
+    graphics::draw(ctx, &mesh, graphics::DrawParam::default())
+}
+
+// On the screen, at the top in the middle
+pub(crate) fn draw_debug_overlay(
+    ctx: &mut Context,
+    world: &World,
+    overlay: &DebugOverlay,
+) -> GameResult<()> {
+    if !overlay.visible {
+        return Ok(());
+    }
+    let entities = (&world.entities()).join().count();
+    let ships = world.read_storage::<Health>().join().count();
+    let shots = world
+        .read_storage::<Projectile>()
+        .join()
+        .filter(|projectile| projectile.active)
+        .count();
+    let boxes = world.read_storage::<CollisionBox>().join().count();
+    // how long the shots took to update, e.g. during the F10 stress test
+    let shot_time = world.read_resource::<ProjectileStats>().update_time;
+    let text = graphics::Text::new(format!(
+        "FPS {:.1}  frame {:.1} ms\nentities {}  ships {}  shots {} ({:.2} ms)  boxes {}",
+        timer::fps(ctx),
+        timer::delta(ctx).as_secs_f64() * 1000.0,
+        entities,
+        ships,
+        shots,
+        shot_time.as_secs_f64() * 1000.0,
+        boxes
+    ));
+
+    let view = graphics::screen_coordinates(ctx);
+    let (width, height) = text.dimensions(ctx);
+    let width = (width as f32).max(GRAPH_WIDTH);
+    let graphs: [(&str, Measure); 4] = [
+        ("frame ms", |sample| sample.frame_ms),
+        ("entities", |sample| sample.entities),
+        ("draw calls", |sample| sample.draw_calls),
+        ("collision pairs", |sample| sample.collision_pairs),
+    ];
+    let graphs_height = (GRAPH_HEIGHT + OVERLAY_MARGIN) * graphs.len() as f32;
+    let corner = nalgebra::Point2::new(view.x + (view.w - width) / 2.0, view.y + OVERLAY_MARGIN);
+    let background = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(
+            corner.x - OVERLAY_MARGIN / 2.0,
+            corner.y - OVERLAY_MARGIN / 2.0,
+            width + OVERLAY_MARGIN,
+            height as f32 + graphs_height + OVERLAY_MARGIN,
+        ),
+        graphics::Color::new(0.0, 0.0, 0.0, 0.7),
+    )?;
+    graphics::draw(ctx, &background, graphics::DrawParam::default())?;
+    graphics::draw(ctx, &text, graphics::DrawParam::default().dest(corner))?;
+
+    let mut top = corner.y + height as f32 + OVERLAY_MARGIN;
+    for (label, value) in graphs.iter() {
+        let area = graphics::Rect::new(corner.x, top, width, GRAPH_HEIGHT);
+        draw_graph(ctx, &overlay.history, *value, label, area)?;
+        top += GRAPH_HEIGHT + OVERLAY_MARGIN;
+    }
+    Ok(())
+}
+
+// One value's history as a line across the area, scaled so the highest it's
+// been fills it, newest on the right
+fn draw_graph(
+    ctx: &mut Context,
+    history: &VecDeque<Sample>,
+    value: Measure,
+    label: &str,
+    area: graphics::Rect,
+) -> GameResult<()> {
+    let highest = history.iter().map(value).fold(0.0, f32::max);
+    let latest = history.back().map_or(0.0, value);
+    let mut mesh = graphics::MeshBuilder::new();
+    mesh.rectangle(
+        graphics::DrawMode::stroke(1.0),
+        area,
+        graphics::Color::new(1.0, 1.0, 1.0, 0.3),
+    );
+    // ggez won't draw a line with fewer than two points
+    if history.len() >= 2 {
+        let scale = if highest > 0.0 { 1.0 / highest } else { 0.0 };
+        let step = area.w / (HISTORY - 1) as f32;
+        let start = area.x + area.w - step * (history.len() - 1) as f32;
+        let points: Vec<nalgebra::Point2<f32>> = history
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| {
+                nalgebra::Point2::new(
+                    start + step * i as f32,
+                    area.y + area.h * (1.0 - value(sample) * scale),
+                )
+            })
+            .collect();
+        mesh.line(&points, 1.0, graphics::Color::new(0.3, 1.0, 0.3, 1.0))?;
+    }
+    let mesh = mesh.build(ctx)?;
+    graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+
+    let text = graphics::Text::new(format!("{} {:.1} (max {:.1})", label, latest, highest));
+    graphics::draw(
+        ctx,
+        &text,
+        graphics::DrawParam::default()
+            .dest(nalgebra::Point2::new(area.x + 2.0, area.y + 2.0))
+            .color(graphics::Color::new(1.0, 1.0, 1.0, 0.8)),
+    )
+}
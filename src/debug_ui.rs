@@ -0,0 +1,424 @@
+use crate::controls;
+use crate::diff;
+use crate::fixed;
+use crate::health::Health;
+use crate::profiler::SystemTimes;
+use crate::tweakables::Tweakables;
+use crate::{Position, Velocity};
+use egui::plot::{Legend, Line, Plot, Values};
+use egui::{Event, Modifiers, PointerButton, RawInput};
+use ggez::event::{KeyCode, KeyMods, MouseButton};
+use ggez::{graphics, timer, Context, GameResult};
+use specs::*;
+use std::collections::VecDeque;
+
+// how many frames the profiler's graph goes back
+const HISTORY: usize = 240;
+// how far one notch of the mouse wheel scrolls a window
+const SCROLL_LINE: f32 = 24.0;
+
+// Windows drawn with egui over the game, only built with the debug-ui
+// feature. F12 opens and closes them:
+//
+// - the inspector lists every entity, and shows the components of the one
+//   picked as the component diff prints them. Its Position, Velocity and
+//   Health can be dragged to new values.
+// - the profiler graphs how long each system took over the last few seconds,
+//   slowest first.
+// - the tweaks window has a slider for every Tweakable, and saves them to
+//   /tweakables.ron like the tweak panel does.
+//
+// While it's open the mouse and keyboard go to egui first. The game doesn't
+// see clicks on a window, or keys while a window is being typed into.
+//
+// ggez 0.5 can't clip what it draws, so a window's contents aren't cut off at
+// its edges where egui asks for them to be. Scroll areas spill over.
+pub(crate) struct DebugUi {
+    visible: bool,
+    egui: egui::CtxRef,
+    // the input since the last frame was drawn
+    input: RawInput,
+    // egui's font texture as last uploaded, with egui's version of it
+    font: Option<(u64, graphics::Image)>,
+    inspector: bool,
+    profiler: bool,
+    tweaks: bool,
+    selected: Option<Entity>,
+    // each system's time in milliseconds, a frame at a time
+    history: Vec<(&'static str, VecDeque<f64>)>,
+}
+
+impl DebugUi {
+    pub(crate) fn new() -> Self {
+        DebugUi {
+            visible: false,
+            egui: egui::CtxRef::default(),
+            input: RawInput::default(),
+            font: None,
+            inspector: true,
+            profiler: true,
+            tweaks: true,
+            selected: None,
+            history: Vec::new(),
+        }
+    }
+
+    // F12 opens and closes the windows. While they're open every key goes to
+    // egui too, returning whether the game should miss out on it.
+    pub(crate) fn key_down(&mut self, keycode: KeyCode, keymod: KeyMods, repeat: bool) -> bool {
+        if keycode == KeyCode::F12 {
+            if !repeat {
+                self.visible = !self.visible;
+                self.input = RawInput::default();
+            }
+            return true;
+        }
+        self.key(keycode, keymod, true)
+    }
+
+    // letting go is passed on to the game whatever egui makes of it, so
+    // nothing is left held down
+    pub(crate) fn key_up(&mut self, keycode: KeyCode, keymod: KeyMods) {
+        self.key(keycode, keymod, false);
+    }
+
+    fn key(&mut self, keycode: KeyCode, keymod: KeyMods, pressed: bool) -> bool {
+        if !self.visible {
+            return false;
+        }
+        self.input.modifiers = modifiers(keymod);
+        if let Some(key) = key(keycode) {
+            self.input.events.push(Event::Key {
+                key,
+                pressed,
+                modifiers: self.input.modifiers,
+            });
+        }
+        self.egui.wants_keyboard_input()
+    }
+
+    pub(crate) fn text_input(&mut self, character: char) -> bool {
+        if !self.visible {
+            return false;
+        }
+        if !character.is_control() {
+            self.input.events.push(Event::Text(character.to_string()));
+        }
+        self.egui.wants_keyboard_input()
+    }
+
+    // the rest take the mouse in window coordinates, and return whether it's
+    // over one of the windows
+    pub(crate) fn mouse_moved(&mut self, ctx: &Context, x: f32, y: f32) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let at = pos(ctx, x, y);
+        self.input.events.push(Event::PointerMoved(at));
+        self.egui.wants_pointer_input()
+    }
+
+    pub(crate) fn mouse_button(
+        &mut self,
+        ctx: &Context,
+        button: MouseButton,
+        x: f32,
+        y: f32,
+        pressed: bool,
+    ) -> bool {
+        if !self.visible {
+            return false;
+        }
+        let button = match button {
+            MouseButton::Left => PointerButton::Primary,
+            MouseButton::Right => PointerButton::Secondary,
+            MouseButton::Middle => PointerButton::Middle,
+            MouseButton::Other(_) => return false,
+        };
+        self.input.events.push(Event::PointerButton {
+            pos: pos(ctx, x, y),
+            button,
+            pressed,
+            modifiers: self.input.modifiers,
+        });
+        self.egui.wants_pointer_input()
+    }
+
+    pub(crate) fn mouse_wheel(&mut self, x: f32, y: f32) -> bool {
+        if !self.visible {
+            return false;
+        }
+        self.input.scroll_delta += egui::vec2(x, y) * SCROLL_LINE;
+        self.egui.wants_pointer_input()
+    }
+
+    // Runs egui over the input since the last frame and draws its windows,
+    // in screen coordinates over everything else
+    pub(crate) fn draw(&mut self, ctx: &mut Context, world: &World) -> GameResult<()> {
+        if !self.visible {
+            return Ok(());
+        }
+        self.record_times(world);
+
+        let view = graphics::screen_coordinates(ctx);
+        let mut input = self.input.take();
+        input.screen_rect = Some(egui::Rect::from_min_size(
+            egui::pos2(view.x, view.y),
+            egui::vec2(view.w, view.h),
+        ));
+        input.pixels_per_point = Some(1.0);
+        input.time = Some(timer::time_since_start(ctx).as_secs_f64());
+        self.egui.begin_frame(input);
+        self.windows(world);
+        let (_, shapes) = self.egui.end_frame();
+        let meshes = self.egui.tessellate(shapes);
+
+        let font = self.font_image(ctx)?;
+        for egui::ClippedMesh(_, mesh) in meshes {
+            if mesh.indices.is_empty() {
+                continue;
+            }
+            let vertices: Vec<graphics::Vertex> = mesh.vertices.iter().map(vertex).collect();
+            let mesh = graphics::Mesh::from_raw(ctx, &vertices, &mesh.indices, Some(font.clone()))?;
+            graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+        }
+        Ok(())
+    }
+
+    fn windows(&mut self, world: &World) {
+        let egui = self.egui.clone();
+        egui::Window::new("Debug")
+            .default_pos(egui::pos2(10.0, 10.0))
+            .show(&egui, |ui| {
+                ui.checkbox(&mut self.inspector, "Inspector");
+                ui.checkbox(&mut self.profiler, "Profiler");
+                ui.checkbox(&mut self.tweaks, "Tweaks");
+            });
+        let selected = &mut self.selected;
+        egui::Window::new("Inspector")
+            .open(&mut self.inspector)
+            .default_width(320.0)
+            .show(&egui, |ui| inspector(ui, world, selected));
+        let history = &self.history;
+        egui::Window::new("Profiler")
+            .open(&mut self.profiler)
+            .default_width(360.0)
+            .show(&egui, |ui| profiler(ui, history));
+        egui::Window::new("Tweaks")
+            .open(&mut self.tweaks)
+            .show(&egui, |ui| tweaks(ui, world));
+    }
+
+    // adds this frame's SystemTimes to the graphs, a system that didn't run
+    // taking no time, as it's taken before one first runs
+    fn record_times(&mut self, world: &World) {
+        let times = world.read_resource::<SystemTimes>().all();
+        let frames = self.history.first().map_or(0, |(_, samples)| samples.len());
+        for (name, _) in &times {
+            if !self.history.iter().any(|(system, _)| system == name) {
+                self.history.push((name, vec![0.0; frames].into()));
+            }
+        }
+        for (name, samples) in &mut self.history {
+            let ms = times
+                .iter()
+                .find(|(system, _)| system == name)
+                .map_or(0.0, |(_, time)| time.as_secs_f64() * 1000.0);
+            samples.push_back(ms);
+            if samples.len() > HISTORY {
+                samples.pop_front();
+            }
+        }
+    }
+
+    // egui's font texture, uploaded again whenever egui changes it
+    fn font_image(&mut self, ctx: &mut Context) -> GameResult<graphics::Image> {
+        let texture = self.egui.texture();
+        match &self.font {
+            Some((version, image)) if *version == texture.version => Ok(image.clone()),
+            _ => {
+                let rgba: Vec<u8> = texture
+                    .pixels
+                    .iter()
+                    .flat_map(|alpha| vec![255, 255, 255, *alpha])
+                    .collect();
+                let image = graphics::Image::from_rgba8(
+                    ctx,
+                    texture.width as u16,
+                    texture.height as u16,
+                    &rgba,
+                )?;
+                self.font = Some((texture.version, image.clone()));
+                Ok(image)
+            }
+        }
+    }
+}
+
+// Every entity by id, and the components of the one picked. Its Position,
+// Velocity and Health are written back as they're dragged.
+fn inspector(ui: &mut egui::Ui, world: &World, selected: &mut Option<Entity>) {
+    let entities = world.entities();
+    if selected.map_or(false, |entity| !entities.is_alive(entity)) {
+        *selected = None;
+    }
+    egui::ScrollArea::vertical()
+        .max_height(160.0)
+        .show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for entity in entities.join() {
+                    let picked = *selected == Some(entity);
+                    if ui
+                        .selectable_label(picked, entity.id().to_string())
+                        .clicked()
+                    {
+                        *selected = Some(entity);
+                    }
+                }
+            });
+        });
+    let entity = match *selected {
+        Some(entity) => entity,
+        None => {
+            ui.label("Pick an entity");
+            return;
+        }
+    };
+    ui.separator();
+
+    if let Some(position) = world.write_storage::<Position>().get_mut(entity) {
+        ui.horizontal(|ui| {
+            ui.label("Position");
+            ui.add(egui::DragValue::new(&mut position.position.x));
+            ui.add(egui::DragValue::new(&mut position.position.y));
+        });
+    }
+    if let Some(velocity) = world.write_storage::<Velocity>().get_mut(entity) {
+        ui.horizontal(|ui| {
+            ui.label("Velocity");
+            ui.add(egui::DragValue::new(&mut velocity.velocity.x));
+            ui.add(egui::DragValue::new(&mut velocity.velocity.y));
+        });
+    }
+    if let Some(health) = world.write_storage::<Health>().get_mut(entity) {
+        let max = fixed::float(health.max);
+        let mut current = fixed::float(health.current);
+        ui.horizontal(|ui| {
+            ui.label("Health");
+            ui.add(egui::Slider::new(&mut current, 0.0..=max));
+            ui.checkbox(&mut health.shielded, "shielded");
+        });
+        if current != fixed::float(health.current) {
+            health.current = fixed::real(current);
+        }
+    }
+    ui.separator();
+    for (name, text) in diff::describe(world, entity) {
+        ui.label(format!("{}: {}", name, text));
+    }
+}
+
+// a line for each system over the last few seconds, and this frame's times
+// slowest first
+fn profiler(ui: &mut egui::Ui, history: &[(&'static str, VecDeque<f64>)]) {
+    let mut plot = Plot::new("system times")
+        .height(160.0)
+        .include_y(0.0)
+        .legend(Legend::default());
+    for (name, samples) in history {
+        let values =
+            Values::from_ys_f32(&samples.iter().map(|ms| *ms as f32).collect::<Vec<f32>>());
+        plot = plot.line(Line::new(values).name(name));
+    }
+    ui.add(plot);
+
+    let mut latest: Vec<(&'static str, f64)> = history
+        .iter()
+        .map(|(name, samples)| (*name, samples.back().cloned().unwrap_or(0.0)))
+        .collect();
+    latest.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap());
+    for (name, ms) in latest {
+        ui.label(format!("{}: {:.2} ms", name, ms));
+    }
+}
+
+// a slider for each Tweakable, from its min to its max
+fn tweaks(ui: &mut egui::Ui, world: &World) {
+    let mut tweakables = world.write_resource::<Tweakables>();
+    for (tweak, value) in tweakables.tweaks_mut() {
+        ui.add(egui::Slider::new(value, tweak.min..=tweak.max).text(tweak.name));
+    }
+    if ui.button("Save").clicked() {
+        tweakables.save_noting(world);
+    }
+}
+
+// where the mouse is, in the screen coordinates the windows are laid out in
+fn pos(ctx: &Context, x: f32, y: f32) -> egui::Pos2 {
+    let point = controls::window_to_screen(ctx, x, y);
+    egui::pos2(point.x, point.y)
+}
+
+// egui's colors have their alpha multiplied in, ggez blends them without
+fn vertex(vertex: &egui::epaint::Vertex) -> graphics::Vertex {
+    let (r, g, b, a) = vertex.color.to_tuple();
+    let unmultiply = |channel: u8| {
+        if a == 0 {
+            0.0
+        } else {
+            (channel as f32 / a as f32).min(1.0)
+        }
+    };
+    graphics::Vertex {
+        pos: [vertex.pos.x, vertex.pos.y],
+        uv: [vertex.uv.x, vertex.uv.y],
+        color: [
+            unmultiply(r),
+            unmultiply(g),
+            unmultiply(b),
+            a as f32 / 255.0,
+        ],
+    }
+}
+
+fn modifiers(keymod: KeyMods) -> Modifiers {
+    let ctrl = keymod.contains(KeyMods::CTRL);
+    Modifiers {
+        alt: keymod.contains(KeyMods::ALT),
+        ctrl,
+        shift: keymod.contains(KeyMods::SHIFT),
+        mac_cmd: false,
+        command: ctrl,
+    }
+}
+
+// the keys egui's widgets use to edit and move around
+fn key(keycode: KeyCode) -> Option<egui::Key> {
+    use egui::Key;
+    Some(match keycode {
+        KeyCode::Down => Key::ArrowDown,
+        KeyCode::Left => Key::ArrowLeft,
+        KeyCode::Right => Key::ArrowRight,
+        KeyCode::Up => Key::ArrowUp,
+        KeyCode::Escape => Key::Escape,
+        KeyCode::Tab => Key::Tab,
+        KeyCode::Back => Key::Backspace,
+        KeyCode::Return => Key::Enter,
+        KeyCode::Space => Key::Space,
+        KeyCode::Insert => Key::Insert,
+        KeyCode::Delete => Key::Delete,
+        KeyCode::Home => Key::Home,
+        KeyCode::End => Key::End,
+        KeyCode::PageUp => Key::PageUp,
+        KeyCode::PageDown => Key::PageDown,
+        KeyCode::A => Key::A,
+        KeyCode::C => Key::C,
+        KeyCode::K => Key::K,
+        KeyCode::U => Key::U,
+        KeyCode::V => Key::V,
+        KeyCode::W => Key::W,
+        KeyCode::X => Key::X,
+        KeyCode::Z => Key::Z,
+        _ => return None,
+    })
+}
@@ -0,0 +1,166 @@
+use crate::controls::Device;
+use crate::glyphs::{Glyphs, Piece};
+use crate::theme::UiTheme;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+
+const DIALOG_WIDTH: f32 = 420.0;
+const ANSWER_WIDTH: f32 = 90.0;
+const ROW_HEIGHT: f32 = 28.0;
+
+// What the player said to a dialog
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Answer {
+    Yes,
+    No,
+    Cancel,
+}
+
+impl Answer {
+    fn label(self) -> &'static str {
+        match self {
+            Answer::Yes => "Yes",
+            Answer::No => "No",
+            Answer::Cancel => "Cancel",
+        }
+    }
+}
+
+// A question over everything else on the screen, which has all the input to
+// itself until it's answered. It's worked with the arrows and Enter or the
+// d-pad and South, or Y, N and C straight off. Escape and East back out,
+// which is Cancel if it's one of the answers and No if not. It starts on No,
+// so a stray Enter doesn't lose anything.
+pub(crate) struct Dialog {
+    message: String,
+    answers: Vec<Answer>,
+    selected: usize,
+}
+
+impl Dialog {
+    pub(crate) fn yes_no(message: &str) -> Self {
+        Dialog {
+            message: message.to_owned(),
+            answers: vec![Answer::Yes, Answer::No],
+            selected: 1,
+        }
+    }
+
+    pub(crate) fn yes_no_cancel(message: &str) -> Self {
+        Dialog {
+            message: message.to_owned(),
+            answers: vec![Answer::Yes, Answer::No, Answer::Cancel],
+            selected: 1,
+        }
+    }
+
+    // Takes a key or button by the name ggez prints for it, returning the
+    // answer once there is one
+    pub(crate) fn input(&mut self, input: &str) -> Option<Answer> {
+        let count = self.answers.len();
+        let answer = match input {
+            "Left" | "Up" | "DPadLeft" | "DPadUp" => {
+                self.selected = (self.selected + count - 1) % count;
+                return None;
+            }
+            "Right" | "Down" | "DPadRight" | "DPadDown" => {
+                self.selected = (self.selected + 1) % count;
+                return None;
+            }
+            "Return" | "South" => self.answers[self.selected],
+            "Y" => Answer::Yes,
+            "N" => Answer::No,
+            "C" => Answer::Cancel,
+            "Escape" | "East" if self.answers.contains(&Answer::Cancel) => Answer::Cancel,
+            "Escape" | "East" => Answer::No,
+            _ => return None,
+        };
+        if self.answers.contains(&answer) {
+            Some(answer)
+        } else {
+            None
+        }
+    }
+
+    // over whatever else was drawn this frame
+    pub(crate) fn draw(
+        &self,
+        ctx: &mut Context,
+        glyphs: &Glyphs,
+        theme: &UiTheme,
+        device: &Device,
+    ) -> GameResult<()> {
+        theme.draw_shade(ctx)?;
+
+        // a dialog is roomier than the theme's other panels
+        let padding = theme.padding * 2.0;
+        let view = graphics::screen_coordinates(ctx);
+        let mut message = theme.text(self.message.as_str());
+        message.set_bounds(
+            nalgebra::Point2::new(DIALOG_WIDTH - padding * 2.0, std::f32::INFINITY),
+            graphics::Align::Center,
+        );
+        let (_, message_height) = message.dimensions(ctx);
+        let height = padding * 3.0 + message_height as f32 + ROW_HEIGHT * 2.0;
+        let frame = graphics::Rect::new(
+            view.x + (view.w - DIALOG_WIDTH) / 2.0,
+            view.y + (view.h - height) / 2.0,
+            DIALOG_WIDTH,
+            height,
+        );
+        theme.draw_panel(ctx, frame, 1.0)?;
+        graphics::draw(
+            ctx,
+            &message,
+            graphics::DrawParam::default()
+                .dest(nalgebra::Point2::new(frame.x + padding, frame.y + padding))
+                .color(theme.message),
+        )?;
+
+        let y = frame.y + padding * 2.0 + message_height as f32;
+        let row_left = frame.x + (frame.w - ANSWER_WIDTH * self.answers.len() as f32) / 2.0;
+        for (i, answer) in self.answers.iter().enumerate() {
+            let (color, text) = if i == self.selected {
+                (theme.text, format!("[ {} ]", answer.label()))
+            } else {
+                (theme.dim, answer.label().to_owned())
+            };
+            let text = theme.text(text);
+            let (width, _) = text.dimensions(ctx);
+            let x = row_left + ANSWER_WIDTH * i as f32 + (ANSWER_WIDTH - width as f32) / 2.0;
+            graphics::draw(
+                ctx,
+                &text,
+                graphics::DrawParam::default()
+                    .dest(nalgebra::Point2::new(x, y))
+                    .color(color),
+            )?;
+        }
+
+        let help = if *device == Device::Keyboard {
+            vec![
+                Piece::Key("←".to_owned()),
+                Piece::Key("→".to_owned()),
+                Piece::Text(" choose  ".to_owned()),
+                Piece::Key("Enter".to_owned()),
+                Piece::Text(" answer".to_owned()),
+            ]
+        } else {
+            vec![
+                Piece::Button("DPadLeft".to_owned()),
+                Piece::Button("DPadRight".to_owned()),
+                Piece::Text(" choose  ".to_owned()),
+                Piece::Button("South".to_owned()),
+                Piece::Text(" answer".to_owned()),
+            ]
+        };
+        let (width, _) = glyphs.measure(ctx, theme, &help);
+        glyphs.draw(
+            ctx,
+            theme,
+            &help,
+            nalgebra::Point2::new(frame.x + (frame.w - width) / 2.0, y + ROW_HEIGHT),
+            theme.dim,
+        )
+    }
+}
@@ -0,0 +1,189 @@
+use crate::ai::AiControlled;
+use crate::cooldowns::Cooldowns;
+use crate::faction::Faction;
+use crate::health::Health;
+use crate::notifications::Notifications;
+use crate::status::StatusEffects;
+use crate::stealth::{Cloaked, Revealed};
+use crate::tween::Tween;
+use crate::weapons::{Projectile, Weapon};
+use crate::{
+    Acceleration, Animation, BoxOffset, CollisionBox, Position, Rotation, Scale, Velocity,
+};
+use ggez::event::{KeyCode, KeyMods};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+
+const OVERLAY_MARGIN: f32 = 10.0;
+// changes past this many, or past this long, are only printed in full to the
+// console
+const MAX_LINES: usize = 30;
+const MAX_CHARS: usize = 100;
+
+// every component looked at, by entity id and component name, as printed
+type Snapshot = BTreeMap<(u32, &'static str), String>;
+
+// Shows which components changed on which entities over a single update,
+// only built with the dev-tools feature. Ctrl+Shift+D opens it, and while the
+// game is paused each press of . steps the world on by one update and lists
+// what that update changed, down the right of the screen and in full on the
+// console.
+//
+// The components are compared as they print, before and after the update,
+// rather than by which were written to. Systems write to a lot of components
+// without changing them, e.g. the movement of anything at rest.
+#[derive(Default)]
+pub(crate) struct ComponentDiff {
+    visible: bool,
+    // a step has been asked for and not yet taken
+    step: bool,
+    before: Snapshot,
+    changes: Vec<String>,
+}
+
+impl ComponentDiff {
+    // Ctrl+Shift+D opens and closes the view, . asks for a step while it's open
+    pub(crate) fn key_down(&mut self, world: &World, keycode: KeyCode, keymod: KeyMods) -> bool {
+        if keycode == KeyCode::D
+            && keymod.contains(KeyMods::CTRL)
+            && keymod.contains(KeyMods::SHIFT)
+        {
+            self.visible = !self.visible;
+            self.changes.clear();
+            let message = if self.visible {
+                "Pause and press . to step an update"
+            } else {
+                "Closed component changes"
+            };
+            world.write_resource::<Notifications>().push(message);
+            return true;
+        }
+        if keycode == KeyCode::Period && self.visible {
+            self.step = true;
+            return true;
+        }
+        false
+    }
+
+    // whether to step the paused world on by one update, once per press
+    pub(crate) fn take_step(&mut self) -> bool {
+        std::mem::take(&mut self.step)
+    }
+
+    pub(crate) fn before_step(&mut self, world: &World) {
+        self.before = snapshot(world);
+    }
+
+    pub(crate) fn after_step(&mut self, world: &World) {
+        let after = snapshot(world);
+        let before = std::mem::take(&mut self.before);
+        self.changes = changes(&before, &after);
+        println!("Components changed by the update: {}", self.changes.len());
+        for change in &self.changes {
+            println!("    {}", change);
+        }
+    }
+}
+
+fn snapshot(world: &World) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    record::<Position>(world, "Position", &mut snapshot);
+    record::<Velocity>(world, "Velocity", &mut snapshot);
+    record::<Acceleration>(world, "Acceleration", &mut snapshot);
+    record::<CollisionBox>(world, "CollisionBox", &mut snapshot);
+    record::<Rotation>(world, "Rotation", &mut snapshot);
+    record::<Scale>(world, "Scale", &mut snapshot);
+    record::<BoxOffset>(world, "BoxOffset", &mut snapshot);
+    record::<Animation>(world, "Animation", &mut snapshot);
+    record::<Health>(world, "Health", &mut snapshot);
+    record::<Weapon>(world, "Weapon", &mut snapshot);
+    record::<Projectile>(world, "Projectile", &mut snapshot);
+    record::<Cooldowns>(world, "Cooldowns", &mut snapshot);
+    record::<StatusEffects>(world, "StatusEffects", &mut snapshot);
+    record::<Cloaked>(world, "Cloaked", &mut snapshot);
+    record::<Revealed>(world, "Revealed", &mut snapshot);
+    record::<Faction>(world, "Faction", &mut snapshot);
+    record::<AiControlled>(world, "AiControlled", &mut snapshot);
+    record::<Tween>(world, "Tween", &mut snapshot);
+    snapshot
+}
+
+// every component looked at on one entity, as printed, for the debug-ui's
+// inspector
+#[cfg(feature = "debug-ui")]
+pub(crate) fn describe(world: &World, entity: Entity) -> Vec<(&'static str, String)> {
+    snapshot(world)
+        .into_iter()
+        .filter(|((id, _), _)| *id == entity.id())
+        .map(|((_, name), text)| (name, text))
+        .collect()
+}
+
+fn record<T: Component + Debug>(world: &World, name: &'static str, snapshot: &mut Snapshot) {
+    let entities = world.entities();
+    let storage = world.read_storage::<T>();
+    for (entity, component) in (&entities, &storage).join() {
+        snapshot.insert((entity.id(), name), format!("{:?}", component));
+    }
+}
+
+// a line for every component added, removed or changed, in entity order
+fn changes(before: &Snapshot, after: &Snapshot) -> Vec<String> {
+    let mut keys: Vec<&(u32, &'static str)> = before.keys().chain(after.keys()).collect();
+    keys.sort();
+    keys.dedup();
+    keys.into_iter()
+        .filter_map(|key| {
+            let (id, name) = key;
+            match (before.get(key), after.get(key)) {
+                (Some(was), Some(now)) if was != now => {
+                    Some(format!("{} {}: {} -> {}", id, name, was, now))
+                }
+                (None, Some(now)) => Some(format!("{} +{}: {}", id, name, now)),
+                (Some(_), None) => Some(format!("{} -{}", id, name)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+pub(crate) fn draw_component_diff(ctx: &mut Context, diff: &ComponentDiff) -> GameResult<()> {
+    if !diff.visible {
+        return Ok(());
+    }
+
+    let mut lines: Vec<String> = diff
+        .changes
+        .iter()
+        .take(MAX_LINES)
+        .map(|change| change.chars().take(MAX_CHARS).collect())
+        .collect();
+    if diff.changes.len() > MAX_LINES {
+        lines.push(format!("and {} more", diff.changes.len() - MAX_LINES));
+    }
+    if lines.is_empty() {
+        lines.push("No changes".to_owned());
+    }
+    let text = graphics::Text::new(lines.join("\n"));
+
+    let view = graphics::screen_coordinates(ctx);
+    let (width, height) = text.dimensions(ctx);
+    let left = view.x + view.w - width as f32 - OVERLAY_MARGIN * 2.0;
+    let background = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(
+            left,
+            view.y,
+            width as f32 + OVERLAY_MARGIN * 2.0,
+            height as f32 + OVERLAY_MARGIN * 2.0,
+        ),
+        graphics::Color::new(0.0, 0.0, 0.0, 0.7),
+    )?;
+    graphics::draw(ctx, &background, graphics::DrawParam::default())?;
+    let corner = nalgebra::Point2::new(left + OVERLAY_MARGIN, view.y + OVERLAY_MARGIN);
+    graphics::draw(ctx, &text, graphics::DrawParam::default().dest(corner))
+}
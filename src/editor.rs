@@ -0,0 +1,361 @@
+use crate::camera::Camera;
+use crate::controls::Aim;
+use crate::hitbox;
+use crate::outline::{self, Selected};
+use crate::prefab::{self, FromPrefab, Placed};
+use crate::{CollisionBox, ControllableTag, Position};
+use ggez::event::{KeyCode, KeyMods};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+use std::collections::HashMap;
+
+// how far a duplicate is placed from the original
+const DUPLICATE_OFFSET: f32 = 20.0;
+// how far the arrow keys nudge the selection, and with shift held
+const NUDGE: f32 = 1.0;
+const NUDGE_FAR: f32 = 10.0;
+const HELP: &str = "Editor: drag to select, shift adds, drag the selection to move it\n\
+                    Arrows nudge, Del deletes, Ctrl+D duplicates, Ctrl+C/V copy and paste\n\
+                    Ctrl+G/U group and ungroup, F2 to play";
+
+// Entities grouped in the editor are selected together
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub(crate) struct Group(pub(crate) u32);
+
+// One entity of a copied selection
+#[derive(Serialize, Deserialize)]
+struct Copied {
+    // placed relative to the top left corner of the selection
+    placed: Placed,
+    // the group it was in, so pasted groups stay together
+    group: Option<u32>,
+    // the named prefab it was made from, if any
+    #[serde(default)]
+    from_prefab: Option<String>,
+}
+
+// Laying out a level by hand. While the editor is open the world holds still,
+// and the mouse and keyboard pick out and move entities rather than fly the
+// ship. Selections are copied in the prefab format, so only AI ships can be
+// copied, and the player's ship can't be deleted either.
+//
+// Edits change the world behind the replay's back, so a game that was edited
+// won't play back the same.
+#[derive(Default)]
+pub(crate) struct Editor {
+    pub(crate) active: bool,
+    // where a rubber band drag started, and where it has got to
+    band: Option<(nalgebra::Point2<f32>, nalgebra::Point2<f32>)>,
+    // where the cursor last was while the selection is being dragged
+    dragging: Option<nalgebra::Point2<f32>>,
+    // the last selection copied, as RON
+    clipboard: Option<String>,
+    next_group: u32,
+    // the level has been changed since it was last quicksaved, which is the
+    // only way edits are kept
+    pub(crate) edited: bool,
+}
+
+impl Editor {
+    pub(crate) fn toggle(&mut self) {
+        self.active = !self.active;
+        self.band = None;
+        self.dragging = None;
+    }
+
+    // Pressing on the selection starts dragging it, anywhere else starts a
+    // rubber band. Without shift the band replaces the selection.
+    pub(crate) fn mouse_down(&mut self, world: &World, at: nalgebra::Point2<f32>, add: bool) {
+        let on_selection = outline::pick(world, at).map_or(false, |entity| {
+            world.read_storage::<Selected>().contains(entity)
+        });
+        if on_selection {
+            self.dragging = Some(at);
+            return;
+        }
+        if !add {
+            world.write_storage::<Selected>().clear();
+        }
+        self.band = Some((at, at));
+    }
+
+    pub(crate) fn mouse_moved(&mut self, world: &World, at: nalgebra::Point2<f32>) {
+        if let Some((_, end)) = self.band.as_mut() {
+            *end = at;
+        }
+        if let Some(from) = self.dragging {
+            move_selection(world, at - from);
+            self.dragging = Some(at);
+            self.edited = true;
+        }
+    }
+
+    // Selects everything the band touches, along with the rest of their
+    // groups
+    pub(crate) fn mouse_up(&mut self, world: &World) {
+        self.dragging = None;
+        let (start, end) = match self.band.take() {
+            Some(band) => band,
+            None => return,
+        };
+        let band = band_box(start, end);
+        {
+            let entities = world.entities();
+            let coll_box = world.read_storage::<CollisionBox>();
+            let mut selected = world.write_storage::<Selected>();
+            for (entity, coll_box) in (&entities, &coll_box).join() {
+                if hitbox::overlaps(&band, coll_box) {
+                    selected.insert(entity, Selected).unwrap_or_else(|err| {
+                        println!("editor error {:?}", err);
+                        None
+                    });
+                }
+            }
+        }
+        select_groups(world);
+    }
+
+    // Carries out the editor's key for a key press, returning false if it
+    // isn't one. Anything that spawns or deletes maintains the world, as it
+    // isn't being updated while the editor is open.
+    pub(crate) fn key_down(
+        &mut self,
+        world: &mut World,
+        keycode: KeyCode,
+        keymod: KeyMods,
+    ) -> bool {
+        let ctrl = keymod.contains(KeyMods::CTRL);
+        let nudge = if keymod.contains(KeyMods::SHIFT) {
+            NUDGE_FAR
+        } else {
+            NUDGE
+        };
+        match (ctrl, keycode) {
+            (false, KeyCode::Up) => move_selection(world, nalgebra::Vector2::new(0.0, -nudge)),
+            (false, KeyCode::Down) => move_selection(world, nalgebra::Vector2::new(0.0, nudge)),
+            (false, KeyCode::Left) => move_selection(world, nalgebra::Vector2::new(-nudge, 0.0)),
+            (false, KeyCode::Right) => move_selection(world, nalgebra::Vector2::new(nudge, 0.0)),
+            (false, KeyCode::Delete) => {
+                delete_selection(world);
+                world.maintain();
+            }
+            (true, KeyCode::C) => self.copy(world),
+            (true, KeyCode::V) => {
+                let cursor = world.read_resource::<Aim>().cursor;
+                self.paste(world, cursor);
+                world.maintain();
+            }
+            (true, KeyCode::D) => {
+                let corner = selection_corner(world);
+                let clipboard = self.clipboard.take();
+                self.copy(world);
+                if let Some(corner) = corner {
+                    let offset = nalgebra::Vector2::new(DUPLICATE_OFFSET, DUPLICATE_OFFSET);
+                    self.paste(world, corner + offset);
+                    world.maintain();
+                }
+                self.clipboard = clipboard;
+            }
+            (true, KeyCode::G) => self.group(world),
+            (true, KeyCode::U) => {
+                let selected = world.read_storage::<Selected>();
+                let mut groups = world.write_storage::<Group>();
+                let entities = world.entities();
+                for (entity, _) in (&entities, &selected).join() {
+                    groups.remove(entity);
+                }
+            }
+            _ => return false,
+        }
+        // copying and grouping leave the level as it was
+        if !ctrl || keycode == KeyCode::V || keycode == KeyCode::D {
+            self.edited = true;
+        }
+        true
+    }
+
+    fn copy(&mut self, world: &World) {
+        let corner = match selection_corner(world) {
+            Some(corner) => corner,
+            None => return,
+        };
+        let entities = world.entities();
+        let positions = world.read_storage::<Position>();
+        let selected = world.read_storage::<Selected>();
+        let groups = world.read_storage::<Group>();
+        let from_prefab = world.read_storage::<FromPrefab>();
+        let copied: Vec<Copied> = (&entities, &positions, &selected)
+            .join()
+            .filter_map(|(entity, pos, _)| {
+                let prefab = prefab::capture(world, entity)?;
+                let offset = pos.position - corner;
+                Some(Copied {
+                    placed: Placed {
+                        prefab,
+                        at: (offset.x, offset.y),
+                    },
+                    group: groups.get(entity).map(|group| group.0),
+                    from_prefab: from_prefab.get(entity).map(|from| from.0.clone()),
+                })
+            })
+            .collect();
+        match ron::ser::to_string(&copied) {
+            Ok(text) => self.clipboard = Some(text),
+            Err(err) => println!("editor error {:?}", err),
+        }
+    }
+
+    // Pastes the clipboard with its top left corner at the given point. The
+    // pasted entities become the selection, in groups of their own.
+    fn paste(&mut self, world: &World, at: nalgebra::Point2<f32>) {
+        let copied: Vec<Copied> = match self.clipboard.as_ref().map(|text| ron::de::from_str(text))
+        {
+            Some(Ok(copied)) => copied,
+            Some(Err(err)) => {
+                println!("editor error {:?}", err);
+                return;
+            }
+            None => return,
+        };
+        world.write_storage::<Selected>().clear();
+        let mut new_groups = HashMap::new();
+        for copy in copied {
+            let origin = at + nalgebra::Vector2::new(copy.placed.at.0, copy.placed.at.1);
+            let entity = prefab::spawn(world, &copy.placed.prefab, origin);
+            let updater = world.read_resource::<LazyUpdate>();
+            updater.insert(entity, Selected);
+            if let Some(name) = copy.from_prefab {
+                updater.insert(entity, FromPrefab(name));
+            }
+            if let Some(group) = copy.group {
+                let next_group = &mut self.next_group;
+                let group = *new_groups.entry(group).or_insert_with(|| {
+                    *next_group += 1;
+                    *next_group
+                });
+                updater.insert(entity, Group(group));
+            }
+        }
+    }
+
+    // puts the selection in a group of its own, out of any it was in
+    fn group(&mut self, world: &World) {
+        self.next_group += 1;
+        let entities = world.entities();
+        let selected = world.read_storage::<Selected>();
+        let mut groups = world.write_storage::<Group>();
+        for (entity, _) in (&entities, &selected).join() {
+            groups
+                .insert(entity, Group(self.next_group))
+                .unwrap_or_else(|err| {
+                    println!("editor error {:?}", err);
+                    None
+                });
+        }
+    }
+}
+
+// the box between two corners of a rubber band, whichever way it was dragged
+fn band_box(start: nalgebra::Point2<f32>, end: nalgebra::Point2<f32>) -> CollisionBox {
+    CollisionBox {
+        origin: nalgebra::Point2::new(start.x.min(end.x), start.y.min(end.y)),
+        width: (end.x - start.x).abs(),
+        height: (end.y - start.y).abs(),
+    }
+}
+
+// Adds the rest of every selected entity's group to the selection
+pub(crate) fn select_groups(world: &World) {
+    let entities = world.entities();
+    let groups = world.read_storage::<Group>();
+    let mut selected = world.write_storage::<Selected>();
+    let chosen: Vec<Group> = (&groups, &selected)
+        .join()
+        .map(|(group, _)| *group)
+        .collect();
+    for (entity, group) in (&entities, &groups).join() {
+        if chosen.contains(group) {
+            selected.insert(entity, Selected).unwrap_or_else(|err| {
+                println!("editor error {:?}", err);
+                None
+            });
+        }
+    }
+}
+
+fn move_selection(world: &World, by: nalgebra::Vector2<f32>) {
+    let selected = world.read_storage::<Selected>();
+    let mut positions = world.write_storage::<Position>();
+    let mut coll_box = world.write_storage::<CollisionBox>();
+    let entities = world.entities();
+    for (entity, pos, _) in (&entities, &mut positions, &selected).join() {
+        pos.position += by;
+        if let Some(coll_box) = coll_box.get_mut(entity) {
+            coll_box.origin += by;
+        }
+    }
+}
+
+fn delete_selection(world: &World) {
+    let entities = world.entities();
+    let selected = world.read_storage::<Selected>();
+    let controlled = world.read_storage::<ControllableTag>();
+    for (entity, _, _) in (&entities, &selected, !&controlled).join() {
+        entities.delete(entity).unwrap_or_else(|err| {
+            println!("editor error {:?}", err);
+        });
+    }
+}
+
+// the top left corner of the selection, if anything is selected
+fn selection_corner(world: &World) -> Option<nalgebra::Point2<f32>> {
+    let positions = world.read_storage::<Position>();
+    let selected = world.read_storage::<Selected>();
+    (&positions, &selected)
+        .join()
+        .map(|(pos, _)| pos.position)
+        .fold(None, |corner: Option<nalgebra::Point2<f32>>, at| {
+            Some(match corner {
+                Some(corner) => nalgebra::Point2::new(corner.x.min(at.x), corner.y.min(at.y)),
+                None => at,
+            })
+        })
+}
+
+// The rubber band being dragged out and what the keys do, while the editor is
+// open
+pub(crate) fn draw_editor(ctx: &mut Context, editor: &Editor, camera: &Camera) -> GameResult<()> {
+    if !editor.active {
+        return Ok(());
+    }
+    if let Some((start, end)) = editor.band {
+        camera.push(ctx)?;
+        let band = band_box(start, end);
+        if band.width > 0.0 && band.height > 0.0 {
+            let rect = graphics::Rect::new(band.origin.x, band.origin.y, band.width, band.height);
+            let mesh = graphics::Mesh::new_rectangle(
+                ctx,
+                graphics::DrawMode::stroke(1.0),
+                rect,
+                graphics::Color::new(1.0, 0.85, 0.2, 0.8),
+            )?;
+            graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+        }
+        Camera::pop(ctx)?;
+    }
+    let view = graphics::screen_coordinates(ctx);
+    let text = graphics::Text::new(HELP);
+    let (_, height) = text.dimensions(ctx);
+    graphics::draw(
+        ctx,
+        &text,
+        graphics::DrawParam::default().dest(nalgebra::Point2::new(
+            view.x + 10.0,
+            view.y + view.h - height as f32 - 10.0,
+        )),
+    )
+}
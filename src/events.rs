@@ -0,0 +1,229 @@
+use crate::health::{DamageEvent, DeathEvent};
+#[cfg(feature = "dev-tools")]
+use crate::notifications::Notifications;
+use crate::CollisionEvent;
+#[cfg(feature = "dev-tools")]
+use ggez::event::{KeyCode, KeyMods};
+use specs::shrev::{Event, EventChannel, EventIterator, ReaderId};
+use specs::*;
+use std::fmt::Debug;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+// unread events a reader can fall behind by before it is reported as leaking
+const LEAK_BACKLOG: usize = 1000;
+
+// The kinds of event systems send each other. Each has a channel of its own in
+// the world, so systems sending different kinds can still run side by side.
+// A system sends with Publish and reads with Subscribe, e.g.
+//
+//     type SystemData = (Publish<'a, DamageEvent>, Subscribe<'a, DeathEvent>);
+//
+// with its reader made up front by subscribe. Events are held back until the
+// end of the update and then delivered all at once, so every reader sees an
+// update's events on the next one, in the order they were sent, whichever
+// order the systems ran in.
+pub(crate) trait Topic: Event + Debug {
+    const NAME: &'static str;
+}
+
+impl Topic for DamageEvent {
+    const NAME: &'static str = "DamageEvent";
+}
+
+impl Topic for DeathEvent {
+    const NAME: &'static str = "DeathEvent";
+}
+
+impl Topic for CollisionEvent {
+    const NAME: &'static str = "CollisionEvent";
+}
+
+pub(crate) type Publish<'a, E> = Write<'a, TrackedChannel<E>>;
+pub(crate) type Subscribe<'a, E> = Read<'a, TrackedChannel<E>>;
+
+// Readers have to be made before the first events are delivered, so systems
+// make theirs when they are made, with the channels already in the world
+pub(crate) fn subscribe<E: Topic>(world: &World, name: &'static str) -> TrackedReader<E> {
+    world
+        .write_resource::<TrackedChannel<E>>()
+        .register_reader(name)
+}
+
+// Puts a channel for every topic in the world
+pub(crate) fn register(world: &mut World) {
+    world.insert(Events::default());
+    world.insert(TrackedChannel::<DamageEvent>::default());
+    world.insert(TrackedChannel::<DeathEvent>::default());
+    world.insert(TrackedChannel::<CollisionEvent>::default());
+}
+
+// Counts updates for the events, and with the dev-tools feature Ctrl+Shift+E
+// has every update's events printed as they are delivered
+#[derive(Debug, Default)]
+pub(crate) struct Events {
+    pub(crate) frame: u64,
+    dumping: bool,
+}
+
+impl Events {
+    #[cfg(feature = "dev-tools")]
+    pub(crate) fn key_down(world: &World, keycode: KeyCode, keymod: KeyMods) -> bool {
+        if keycode != KeyCode::E
+            || !keymod.contains(KeyMods::CTRL)
+            || !keymod.contains(KeyMods::SHIFT)
+        {
+            return false;
+        }
+        let mut events = world.write_resource::<Events>();
+        events.dumping = !events.dumping;
+        let message = if events.dumping {
+            "Printing every update's events"
+        } else {
+            "Stopped printing events"
+        };
+        world.write_resource::<Notifications>().push(message);
+        true
+    }
+}
+
+// Hands every topic's events from this update to its readers, at the end of
+// the update once all the systems have run
+pub(crate) fn deliver(world: &World) {
+    let mut events = world.write_resource::<Events>();
+    events.frame += 1;
+    let mut dump = if events.dumping {
+        Some(Vec::new())
+    } else {
+        None
+    };
+    deliver_topic::<DamageEvent>(world, &mut dump);
+    deliver_topic::<DeathEvent>(world, &mut dump);
+    deliver_topic::<CollisionEvent>(world, &mut dump);
+    match dump {
+        Some(dump) if !dump.is_empty() => {
+            println!("Events in update {}:", events.frame);
+            for line in dump {
+                println!("    {}", line);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn deliver_topic<E: Topic>(world: &World, dump: &mut Option<Vec<String>>) {
+    let mut channel = world.write_resource::<TrackedChannel<E>>();
+    if let Some(dump) = dump {
+        dump.extend(
+            channel
+                .pending
+                .iter()
+                .map(|event| format!("{} {:?}", E::NAME, event)),
+        );
+    }
+    channel.deliver();
+}
+
+// An EventChannel that knows who is reading it. Readers register with a name,
+// and the channel keeps count of how far behind each of them is. The channel
+// has to hold on to every event until the slowest reader has seen it, so a
+// reader whose system stopped running makes it grow forever. check_leaks
+// reports those.
+pub(crate) struct TrackedChannel<E: Event> {
+    channel: EventChannel<E>,
+    // events sent this update, still to be delivered
+    pending: Vec<E>,
+    // events delivered since the channel was made
+    written: usize,
+    // each reader's name and how many events had been written the last time
+    // it read
+    readers: Vec<(&'static str, Arc<AtomicUsize>)>,
+    // readers already reported, so each is only reported once
+    leaking: Vec<&'static str>,
+}
+
+pub(crate) struct TrackedReader<E: Event> {
+    id: ReaderId<E>,
+    read_up_to: Arc<AtomicUsize>,
+}
+
+impl<E: Event> Default for TrackedChannel<E> {
+    fn default() -> Self {
+        TrackedChannel {
+            channel: EventChannel::new(),
+            pending: Vec::new(),
+            written: 0,
+            readers: Vec::new(),
+            leaking: Vec::new(),
+        }
+    }
+}
+
+impl<E: Event> TrackedChannel<E> {
+    // Readers only see events written after they registered
+    pub(crate) fn register_reader(&mut self, name: &'static str) -> TrackedReader<E> {
+        let read_up_to = Arc::new(AtomicUsize::new(self.written));
+        self.readers.push((name, read_up_to.clone()));
+        TrackedReader {
+            id: self.channel.register_reader(),
+            read_up_to,
+        }
+    }
+
+    pub(crate) fn publish(&mut self, event: E) {
+        self.pending.push(event);
+    }
+
+    fn deliver(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        self.written += pending.len();
+        self.channel.iter_write(pending);
+    }
+
+    pub(crate) fn read<'a>(&'a self, reader: &'a mut TrackedReader<E>) -> EventIterator<'a, E> {
+        reader.read_up_to.store(self.written, Ordering::Relaxed);
+        self.channel.read(&mut reader.id)
+    }
+
+    // How many events each reader has still to read. A reader that has been
+    // dropped is no longer counted.
+    pub(crate) fn backlog(&self) -> Vec<(&'static str, usize)> {
+        self.readers
+            .iter()
+            .filter(|(_, read_up_to)| Arc::strong_count(read_up_to) > 1)
+            .map(|(name, read_up_to)| (*name, self.written - read_up_to.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    // Prints a warning the first time a reader falls too far behind
+    fn check_leaks(&mut self, channel_name: &str) {
+        self.readers
+            .retain(|(_, read_up_to)| Arc::strong_count(read_up_to) > 1);
+        for (name, backlog) in self.backlog() {
+            if backlog > LEAK_BACKLOG && !self.leaking.contains(&name) {
+                println!(
+                    "{} reader {} is {} events behind, is its system still running?",
+                    channel_name, name, backlog
+                );
+                self.leaking.push(name);
+            }
+        }
+    }
+}
+
+// Looks over the game's event channels for readers that have stopped reading
+pub(crate) struct EventAuditSystem;
+
+impl<'a> System<'a> for EventAuditSystem {
+    type SystemData = (
+        Publish<'a, DamageEvent>,
+        Publish<'a, DeathEvent>,
+        Publish<'a, CollisionEvent>,
+    );
+
+    fn run(&mut self, (mut damage, mut deaths, mut collisions): Self::SystemData) {
+        damage.check_leaks(DamageEvent::NAME);
+        deaths.check_leaks(DeathEvent::NAME);
+        collisions.check_leaks(CollisionEvent::NAME);
+    }
+}
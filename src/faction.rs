@@ -0,0 +1,47 @@
+use ggez::graphics;
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+
+// Which side an entity is on. Entities without a Faction (rocks, debris) are
+// treated as hostile to everyone, so anything can shoot them.
+#[derive(
+    Component, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+#[storage(VecStorage)]
+pub(crate) enum Faction {
+    Blue,
+    Red,
+}
+
+impl Faction {
+    pub(crate) fn color(self) -> graphics::Color {
+        match self {
+            Faction::Blue => graphics::Color::new(0.3, 0.5, 1.0, 1.0),
+            Faction::Red => graphics::Color::new(1.0, 0.3, 0.2, 1.0),
+        }
+    }
+}
+
+pub(crate) fn hostile(a: Option<&Faction>, b: Option<&Faction>) -> bool {
+    match (a, b) {
+        (Some(a), Some(b)) => a != b,
+        _ => true,
+    }
+}
+
+// Whether an attack from one side should hurt the other, honouring the
+// friendly fire setting
+pub(crate) fn can_damage(
+    attacker: Option<&Faction>,
+    target: Option<&Faction>,
+    friendly_fire: bool,
+) -> bool {
+    friendly_fire || hostile(attacker, target)
+}
+
+// The collision mask: ships on the same side pass through each other rather
+// than bumping
+pub(crate) fn collides(a: Option<&Faction>, b: Option<&Faction>) -> bool {
+    hostile(a, b)
+}
@@ -0,0 +1,126 @@
+// Fixed is only used when the fixed-point-health feature is on
+#![cfg_attr(not(feature = "fixed-point-health"), allow(dead_code))]
+
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+const FRACTION_BITS: u32 = 16;
+const ONE: f32 = (1 << FRACTION_BITS) as f32;
+
+// A 16.16 fixed point number. Integer arithmetic comes out the same on every
+// machine, which floats don't promise once different CPUs, compilers and
+// optimisations get involved, so lockstep games can keep their simulations in
+// step by only ever exchanging inputs.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub(crate) struct Fixed(i32);
+
+impl Fixed {
+    pub(crate) fn from_f32(value: f32) -> Self {
+        Fixed((value * ONE).round() as i32)
+    }
+
+    pub(crate) fn to_f32(self) -> f32 {
+        self.0 as f32 / ONE
+    }
+
+    // the raw 16.16 value, stopping at the largest or smallest number there
+    // is rather than wrapping round to the other sign
+    fn saturating(raw: i64) -> Self {
+        Fixed(raw.max(i64::from(i32::MIN)).min(i64::from(i32::MAX)) as i32)
+    }
+}
+
+// Anything too big for 16.16 stops at the largest number of the right sign
+// rather than wrapping, so a big enough hit or multiplier can't turn health
+// negative into positive or the other way round.
+
+impl Add for Fixed {
+    type Output = Fixed;
+
+    fn add(self, other: Fixed) -> Fixed {
+        Fixed(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Fixed {
+    type Output = Fixed;
+
+    fn sub(self, other: Fixed) -> Fixed {
+        Fixed(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Mul for Fixed {
+    type Output = Fixed;
+
+    fn mul(self, other: Fixed) -> Fixed {
+        Fixed::saturating((i64::from(self.0) * i64::from(other.0)) >> FRACTION_BITS)
+    }
+}
+
+impl Div for Fixed {
+    type Output = Fixed;
+
+    // Dividing by zero gives the largest number of the right sign, or zero
+    // for zero, rather than panicking
+    fn div(self, other: Fixed) -> Fixed {
+        if other.0 == 0 {
+            return Fixed(self.0.signum().saturating_mul(i32::MAX));
+        }
+        let quotient = (i64::from(self.0) << FRACTION_BITS) / i64::from(other.0);
+        Fixed::saturating(quotient)
+    }
+}
+
+impl Neg for Fixed {
+    type Output = Fixed;
+
+    fn neg(self) -> Fixed {
+        Fixed(self.0.saturating_neg())
+    }
+}
+
+impl AddAssign for Fixed {
+    fn add_assign(&mut self, other: Fixed) {
+        *self = *self + other;
+    }
+}
+
+impl SubAssign for Fixed {
+    fn sub_assign(&mut self, other: Fixed) {
+        *self = *self - other;
+    }
+}
+
+// The number type Health is kept in. Plain floats unless the
+// fixed-point-health feature is on. Nothing else uses it yet, positions,
+// velocities and timers are all still f32. Values cross over to f32 through
+// real() and float() wherever they meet the rest of the game, drawing and the
+// HUD in particular.
+#[cfg(feature = "fixed-point-health")]
+pub(crate) type Real = Fixed;
+
+#[cfg(not(feature = "fixed-point-health"))]
+pub(crate) type Real = f32;
+
+#[cfg(feature = "fixed-point-health")]
+pub(crate) fn real(value: f32) -> Real {
+    Fixed::from_f32(value)
+}
+
+#[cfg(feature = "fixed-point-health")]
+pub(crate) fn float(value: Real) -> f32 {
+    value.to_f32()
+}
+
+#[cfg(not(feature = "fixed-point-health"))]
+pub(crate) fn real(value: f32) -> Real {
+    value
+}
+
+#[cfg(not(feature = "fixed-point-health"))]
+pub(crate) fn float(value: Real) -> f32 {
+    value
+}
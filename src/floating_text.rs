@@ -0,0 +1,98 @@
+use crate::interpolation::Drawn;
+use crate::lifetime::Lifetime;
+use crate::tween::Tween;
+use crate::Position;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::world::EntitiesRes;
+use specs::*;
+use specs_derive::*;
+
+// how long a popup stays on screen, in seconds, and how far it rises
+const FLOAT_DURATION: f32 = 1.0;
+const FLOAT_RISE: f32 = 40.0;
+
+// Short lived text in the world, for damage numbers, score popups and the like.
+// The text drifts upwards and fades out through a Tween and removes itself with
+// a Lifetime, so there is no floating text system of its own.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub(crate) struct FloatingText {
+    pub(crate) text: String,
+    pub(crate) color: graphics::Color,
+}
+
+impl FloatingText {
+    // Spawns a popup centred on the given point. This is usable from inside
+    // systems, the components are queued with LazyUpdate and appear when the
+    // world is next maintained.
+    pub(crate) fn spawn(
+        entities: &EntitiesRes,
+        updater: &LazyUpdate,
+        at: nalgebra::Point2<f32>,
+        text: &str,
+        color: graphics::Color,
+    ) -> Entity {
+        let entity = entities.create();
+        updater.insert(entity, Position { position: at });
+        updater.insert(
+            entity,
+            FloatingText {
+                text: text.to_owned(),
+                color,
+            },
+        );
+        updater.insert(
+            entity,
+            Tween {
+                from: at,
+                to: at - nalgebra::Vector2::new(0.0, FLOAT_RISE),
+                from_alpha: 1.0,
+                to_alpha: 0.0,
+                duration: FLOAT_DURATION,
+                elapsed: 0.0,
+            },
+        );
+        updater.insert(
+            entity,
+            Lifetime {
+                remaining: FLOAT_DURATION,
+            },
+        );
+        entity
+    }
+}
+
+// All the popups are queued up and then drawn in one batch by ggez's text
+// renderer, rather than one draw call each
+pub(crate) fn draw_floating_text(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let entities = world.entities();
+    let texts = world.read_storage::<FloatingText>();
+    let tweens = world.read_storage::<Tween>();
+    let drawn = Drawn::new(world);
+
+    for (entity, floating, tween) in (&entities, &texts, tweens.maybe()).join() {
+        let at = match drawn.at(entity) {
+            Some(at) => at,
+            None => continue,
+        };
+        let text = graphics::Text::new(floating.text.as_str());
+        let (width, height) = text.dimensions(ctx);
+        let mut color = floating.color;
+        color.a *= tween.map_or(1.0, |t| t.alpha());
+
+        graphics::queue_text(
+            ctx,
+            &text,
+            nalgebra::Point2::new(at.x - width as f32 / 2.0, at.y - height as f32 / 2.0),
+            Some(color),
+        );
+    }
+
+    graphics::draw_queued_text(
+        ctx,
+        graphics::DrawParam::default(),
+        None,
+        graphics::FilterMode::Linear,
+    )
+}
@@ -0,0 +1,771 @@
+use crate::controls::Device;
+use crate::dialog::{Answer, Dialog};
+use crate::game_mode;
+use crate::glyphs::{self, Glyphs};
+use crate::input_map::{self, InputMap};
+use crate::notifications::Notifications;
+use crate::pause;
+use crate::rebind::{Outcome, RebindMenu};
+use crate::replay::{self, Playback, Replay};
+use crate::rng::GameRng;
+use crate::saves::{self, Choice, Confirm, Purpose, Save, SaveMenu};
+use crate::settings::{self, Settings};
+use crate::storage::Storage;
+use crate::telemetry::Telemetry;
+use crate::theme::UiTheme;
+use crate::{Extensions, MainState, DESIRED_FPS};
+use ggez::event::{self, Axis, Button, EventHandler, GamepadId, KeyCode, KeyMods, MouseButton};
+use ggez::input;
+use ggez::nalgebra;
+use ggez::{graphics, timer, Context, GameResult};
+use specs::WorldExt;
+use std::rc::Rc;
+use std::time::Duration;
+
+// how long the splash screen shows for, unless a key skips it
+const SPLASH_TIME: Duration = Duration::from_millis(2500);
+// how long the title screen waits for input before the demo starts
+const ATTRACT_AFTER: Duration = Duration::from_secs(30);
+// the replay the demo plays
+const ATTRACT_REPLAY: &str = "/replays/attract.ron";
+// where the last game played is saved when the game is closed
+const LAST_REPLAY: &str = "/replays/last.ron";
+
+enum Screen {
+    Splash,
+    Title,
+    // a replay playing by itself until a key is pressed
+    Attract,
+    // rebinding the keys or buttons of one device
+    Controls(RebindMenu),
+    // saving the game being played, or loading one from the title screen
+    Saves(SaveMenu),
+    Playing,
+}
+
+// The ways out of a game, which lose whatever of it hasn't been saved
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Leaving {
+    // closing the window
+    Quit,
+    ToTitle,
+    Restart,
+}
+
+// What a dialog is asking, to carry out once it's answered
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Ask {
+    // whether to leave the game
+    Leave(Leaving),
+    // whether to quicksave the editor's changes on the way out
+    KeepEdits(Leaving),
+    // the save screen's question about a slot
+    Slot(Confirm),
+}
+
+// What runs before and around a game: the splash screen, the title screen,
+// the controls and save screens, and the attract mode demo the title screen
+// drops into when left alone. A dialog goes over all of them, the game
+// included, and has the input to itself until it's answered.
+pub(crate) struct Front {
+    screen: Screen,
+    modal: Option<(Dialog, Ask)>,
+    // the game being played, or the demo
+    game: Option<MainState>,
+    // the game mode as given to --mode, and the seed from --seed if any
+    mode: String,
+    seed: Option<u64>,
+    settings: Settings,
+    storage: Rc<dyn Storage>,
+    // what a consumer of the crate added, for every game started
+    extensions: Rc<Extensions>,
+    glyphs: Glyphs,
+    // for the front screens and dialogs, while a game has its own
+    theme: UiTheme,
+    // for the controls screen, so nothing is bound over the keys that fly the
+    // ship. A game has its own, which may have been reloaded since.
+    input_map: InputMap,
+    // the device last used, for the prompts and the controls screen
+    device: Device,
+    // when the current screen started, or the title screen last saw input
+    since: Duration,
+    // set after a long pause, e.g. loading a save, so the game doesn't try
+    // to make up for the time it took
+    skip_lag: bool,
+}
+
+impl Front {
+    pub(crate) fn new(
+        ctx: &mut Context,
+        storage: Rc<dyn Storage>,
+        extensions: Rc<Extensions>,
+        mode: String,
+        seed: Option<u64>,
+        settings: Settings,
+    ) -> GameResult<Self> {
+        Ok(Front {
+            screen: Screen::Splash,
+            modal: None,
+            game: None,
+            mode,
+            seed,
+            settings,
+            storage,
+            extensions,
+            glyphs: Glyphs::load(ctx)?,
+            theme: UiTheme::load(ctx),
+            input_map: input_map::load(ctx).unwrap_or_else(|err| {
+                println!("input map error {:?}", err);
+                InputMap::default()
+            }),
+            device: Device::Keyboard,
+            since: Duration::from_secs(0),
+            skip_lag: false,
+        })
+    }
+
+    fn show(&mut self, ctx: &Context, screen: Screen) {
+        self.screen = screen;
+        self.since = timer::time_since_start(ctx);
+    }
+
+    fn back_to_title(&mut self, ctx: &Context) {
+        if let Some(game) = self.game.as_mut() {
+            game.end_telemetry();
+        }
+        self.game = None;
+        self.show(ctx, Screen::Title);
+    }
+
+    fn start_game(&mut self, ctx: &mut Context) {
+        let mode = game_mode::from_name(&self.mode)
+            .unwrap_or_else(|| Box::new(game_mode::Skirmish::default()));
+        let seed = self.seed.take();
+        let rng = match mode.seed().or(seed) {
+            Some(seed) => GameRng::new(seed),
+            None => GameRng::from_time(),
+        };
+        println!("Seed: {}", rng.seed());
+        match MainState::new(
+            ctx,
+            mode,
+            self.settings.clone(),
+            rng,
+            self.storage.clone(),
+            self.extensions.clone(),
+        ) {
+            Ok(game) => {
+                self.game = Some(game);
+                self.show(ctx, Screen::Playing);
+            }
+            Err(err) => println!("game error {:?}", err),
+        }
+    }
+
+    // Starts the game being played again from the beginning, from the same
+    // seed so it starts out the same way
+    fn restart(&mut self, ctx: &mut Context) {
+        if let Some(game) = self.game.as_mut() {
+            game.end_telemetry();
+            self.seed = Some(game.specs_world.read_resource::<GameRng>().seed());
+        }
+        self.game = None;
+        self.start_game(ctx);
+    }
+
+    // The controls screen from a game's pause menu. It works on the game's
+    // settings, which may have changed since it started, and hands them back
+    // to the game when it's closed.
+    fn open_options(&mut self, ctx: &Context) {
+        if let Some(game) = self.game.as_ref() {
+            self.settings = Settings::clone(&game.specs_world.read_resource::<Settings>());
+            self.device = game.active_device.clone();
+            self.input_map = InputMap::clone(&game.specs_world.read_resource::<InputMap>());
+        }
+        let menu = RebindMenu::new(self.device.clone(), self.input_map.clone());
+        self.show(ctx, Screen::Controls(menu));
+    }
+
+    fn close_options(&mut self, ctx: &Context) {
+        match self.game.as_ref() {
+            Some(game) => {
+                *game.specs_world.write_resource::<Settings>() = self.settings.clone();
+                self.show(ctx, Screen::Playing);
+            }
+            None => self.show(ctx, Screen::Title),
+        }
+    }
+
+    // Plays the attract replay. Without one the title screen just carries on
+    // waiting.
+    fn start_attract(&mut self, ctx: &mut Context) {
+        self.since = timer::time_since_start(ctx);
+        let replay = match replay::load(ctx, ATTRACT_REPLAY) {
+            Ok(replay) => replay,
+            Err(err) => {
+                println!("replay error {:?}", err);
+                return;
+            }
+        };
+        let mode = match game_mode::from_name(&replay.mode) {
+            Some(mode) => mode,
+            None => {
+                println!("replay error: unknown game mode {}", replay.mode);
+                return;
+            }
+        };
+        let mut settings = self.settings.clone();
+        settings.difficulty = replay.difficulty;
+        match MainState::new(
+            ctx,
+            mode,
+            settings,
+            GameRng::new(replay.seed),
+            self.storage.clone(),
+            self.extensions.clone(),
+        ) {
+            Ok(mut game) => {
+                game.specs_world.write_resource::<Telemetry>().enabled = false;
+                game.playback = Some(Playback::new(replay.inputs));
+                self.game = Some(game);
+                self.show(ctx, Screen::Attract);
+            }
+            Err(err) => println!("replay error {:?}", err),
+        }
+    }
+
+    // Picks up a saved game by playing its replay through to where it was
+    // saved, without drawing any of it
+    fn resume(&mut self, ctx: &mut Context, slot: usize) {
+        let replay = match saves::load(&*self.storage, slot) {
+            Ok(save) => save.replay,
+            Err(err) => {
+                println!("save error {:?}", err);
+                return;
+            }
+        };
+        let mode = match game_mode::from_name(&replay.mode) {
+            Some(mode) => mode,
+            None => {
+                println!("save error: unknown game mode {}", replay.mode);
+                return;
+            }
+        };
+        let mut settings = self.settings.clone();
+        settings.difficulty = replay.difficulty;
+        let mut game = match MainState::new(
+            ctx,
+            mode,
+            settings,
+            GameRng::new(replay.seed),
+            self.storage.clone(),
+            self.extensions.clone(),
+        ) {
+            Ok(game) => game,
+            Err(err) => {
+                println!("save error {:?}", err);
+                return;
+            }
+        };
+        // the part already played was logged the first time round
+        game.specs_world.write_resource::<Telemetry>().enabled = false;
+        game.playback = Some(Playback::new(replay.inputs.clone()));
+        while !game.playback.as_ref().map_or(true, Playback::finished) {
+            game.step();
+        }
+        game.playback = None;
+        game.specs_world.write_resource::<Telemetry>().enabled = self.settings.telemetry;
+        // carries on recording from where the save left off, so saving
+        // again saves the whole game
+        game.recording = replay.inputs;
+        self.mode = replay.mode;
+        self.game = Some(game);
+        self.skip_lag = true;
+        self.show(ctx, Screen::Playing);
+    }
+
+    // Asks before leaving the game, about the editor's changes if there are
+    // any and otherwise whether to leave at all. Restarting only asks about
+    // the editor's changes.
+    fn confirm_leaving(&mut self, ctx: &mut Context, leaving: Leaving) {
+        let (edits, can_save) = match self.game.as_ref() {
+            Some(game) => (game.unsaved_edits(), game.can_save()),
+            None => return self.leave(ctx, leaving),
+        };
+        let modal = match (edits, can_save, leaving) {
+            (true, true, _) => (
+                Dialog::yes_no_cancel(
+                    "The level has been edited since it was last quicksaved. \
+                     Quicksave it first?",
+                ),
+                Ask::KeepEdits(leaving),
+            ),
+            (true, false, _) => (
+                Dialog::yes_no(
+                    "The level has been edited, and the changes will be lost. Go anyway?",
+                ),
+                Ask::Leave(leaving),
+            ),
+            (false, _, Leaving::Restart) => return self.leave(ctx, leaving),
+            (false, _, Leaving::Quit) => (
+                Dialog::yes_no("Quit the game? Anything not saved will be lost."),
+                Ask::Leave(leaving),
+            ),
+            (false, _, Leaving::ToTitle) => (
+                Dialog::yes_no("Quit to the title screen? Anything not saved will be lost."),
+                Ask::Leave(leaving),
+            ),
+        };
+        if let Some(game) = self.game.as_mut() {
+            game.hold();
+            self.device = game.active_device.clone();
+        }
+        self.modal = Some(modal);
+    }
+
+    fn leave(&mut self, ctx: &mut Context, leaving: Leaving) {
+        match leaving {
+            Leaving::Quit => {
+                self.exit();
+                event::quit(ctx);
+            }
+            Leaving::ToTitle => self.back_to_title(ctx),
+            Leaving::Restart => {
+                self.restart(ctx);
+                self.skip_lag = true;
+            }
+        }
+    }
+
+    // Works the dialog with a key or button, by the name ggez prints for it,
+    // and carries out the answer
+    fn modal_input(&mut self, ctx: &mut Context, device: Device, input: &str) {
+        self.device = device;
+        let answer = match self
+            .modal
+            .as_mut()
+            .and_then(|(dialog, _)| dialog.input(input))
+        {
+            Some(answer) => answer,
+            None => return,
+        };
+        let ask = match self.modal.take() {
+            Some((_, ask)) => ask,
+            None => return,
+        };
+        match (ask, answer) {
+            (Ask::Leave(leaving), Answer::Yes) => self.leave(ctx, leaving),
+            (Ask::KeepEdits(leaving), Answer::Yes) => {
+                if let Some(game) = self.game.as_mut() {
+                    game.quicksave();
+                    // stays put if the quicksave didn't work, which it says
+                    if game.unsaved_edits() {
+                        return;
+                    }
+                }
+                self.leave(ctx, leaving);
+            }
+            (Ask::KeepEdits(leaving), Answer::No) => self.leave(ctx, leaving),
+            (Ask::Slot(confirm), Answer::Yes) => {
+                let save = self.save_to_write();
+                let choice = match &mut self.screen {
+                    Screen::Saves(menu) => {
+                        menu.confirmed(ctx, &*self.storage, confirm, save.as_ref())
+                    }
+                    _ => return,
+                };
+                self.save_menu_choice(ctx, choice);
+            }
+            _ => (),
+        }
+    }
+
+    // whether there's a game going, as opposed to the demo
+    fn in_game(&self) -> bool {
+        match self.screen {
+            Screen::Attract => false,
+            _ => self.game.is_some(),
+        }
+    }
+
+    // The game being played is saved as a replay on the way out, so a good
+    // one can be kept, e.g. as the attract mode demo
+    fn exit(&mut self) {
+        if self.in_game() {
+            if let Some(replay) = self.replay() {
+                replay::save(&*self.storage, LAST_REPLAY, &replay).unwrap_or_else(|err| {
+                    println!("replay error {:?}", err);
+                });
+            }
+        }
+        if let Some(game) = self.game.as_mut() {
+            game.end_telemetry();
+        }
+    }
+
+    // The game being played as a replay, for saving
+    fn replay(&self) -> Option<Replay> {
+        let game = self.game.as_ref()?;
+        Some(Replay {
+            seed: game.specs_world.read_resource::<GameRng>().seed(),
+            mode: self.mode.clone(),
+            difficulty: game.specs_world.read_resource::<Settings>().difficulty,
+            inputs: game.recording.clone(),
+        })
+    }
+
+    // Anything pressed on the front screens, by the name ggez prints for it:
+    // skips the splash, starts or loads a game or opens the controls from the
+    // title, ends the demo and works the controls and save screens
+    fn pressed(&mut self, ctx: &mut Context, device: Device, input: &str) {
+        self.device = device;
+        let save = self.save_to_write();
+        match &mut self.screen {
+            Screen::Splash => self.show(ctx, Screen::Title),
+            Screen::Title => match input {
+                "Return" | "Start" => self.start_game(ctx),
+                "F1" | "Select" => {
+                    let menu = RebindMenu::new(self.device.clone(), self.input_map.clone());
+                    self.show(ctx, Screen::Controls(menu));
+                }
+                "L" | "North" => {
+                    let menu = SaveMenu::new(ctx, &*self.storage, Purpose::Load);
+                    self.show(ctx, Screen::Saves(menu));
+                }
+                _ => self.since = timer::time_since_start(ctx),
+            },
+            Screen::Attract => self.back_to_title(ctx),
+            Screen::Controls(menu) => match menu.input(&mut self.settings, &self.device, input) {
+                Outcome::Open => (),
+                Outcome::Changed => {
+                    settings::save(&*self.storage, &self.settings).unwrap_or_else(|err| {
+                        println!("settings error {:?}", err);
+                    })
+                }
+                Outcome::Closed => self.close_options(ctx),
+            },
+            Screen::Saves(menu) => {
+                let choice = menu.input(ctx, &*self.storage, input, save.as_ref());
+                self.save_menu_choice(ctx, choice);
+            }
+            // F6 opens the save screen from a game, anything else is the
+            // game's. Some modes can't be saved part way through.
+            Screen::Playing => match self.game.as_ref() {
+                Some(game) if !game.can_save() => game
+                    .specs_world
+                    .write_resource::<Notifications>()
+                    .push("This mode can't be saved"),
+                _ => {
+                    let menu = SaveMenu::new(ctx, &*self.storage, Purpose::Save);
+                    self.show(ctx, Screen::Saves(menu));
+                }
+            },
+        }
+    }
+
+    // the game to write if the save screen is open to save it
+    fn save_to_write(&self) -> Option<Save> {
+        match &self.screen {
+            Screen::Saves(menu) if menu.purpose == Purpose::Save => self.replay().map(Save::new),
+            _ => None,
+        }
+    }
+
+    fn save_menu_choice(&mut self, ctx: &mut Context, choice: Choice) {
+        match choice {
+            Choice::Open => (),
+            Choice::Closed if self.game.is_some() => self.show(ctx, Screen::Playing),
+            Choice::Closed => self.show(ctx, Screen::Title),
+            Choice::Saved => {
+                if let Some(game) = self.game.as_ref() {
+                    game.specs_world
+                        .write_resource::<Notifications>()
+                        .push("Game saved");
+                }
+                self.show(ctx, Screen::Playing);
+            }
+            Choice::Load(slot) => self.resume(ctx, slot),
+            Choice::Ask(confirm) => {
+                if let Screen::Saves(menu) = &self.screen {
+                    let dialog = Dialog::yes_no(&menu.question(confirm));
+                    self.modal = Some((dialog, Ask::Slot(confirm)));
+                }
+            }
+        }
+    }
+
+    // the front screens, when there's no game on them
+    fn draw_screen(&mut self, ctx: &mut Context) -> GameResult<()> {
+        graphics::clear(ctx, graphics::BLACK);
+        let elapsed = (timer::time_since_start(ctx) - self.since).as_secs_f32();
+        match &mut self.screen {
+            Screen::Splash => {
+                // fades in and back out again
+                let length = SPLASH_TIME.as_secs_f32();
+                let alpha = (elapsed.min(length - elapsed) * 2.0).max(0.0).min(1.0);
+                let theme = &self.theme;
+                draw_centered(ctx, theme, "Fudance", 260.0, alpha)?;
+                draw_centered(ctx, theme, "made with ggez and specs", 290.0, alpha * 0.6)?;
+            }
+            Screen::Controls(menu) => menu.draw(ctx, &self.settings, &self.glyphs, &self.theme)?,
+            Screen::Saves(menu) => {
+                let gamepad = self.device != Device::Keyboard;
+                menu.draw(ctx, &self.glyphs, &self.theme, gamepad)?;
+            }
+            _ => {
+                let theme = &self.theme;
+                draw_centered(ctx, theme, "GGEZ AND SPECS", 200.0, 1.0)?;
+                draw_centered(ctx, theme, &format!("Mode: {}", self.mode), 240.0, 0.6)?;
+                // blinks once a second
+                let blink = if elapsed.fract() < 0.6 { 1.0 } else { 0.0 };
+                let start = glyphs::pieces("Press {Start} to play", &self.device, &self.settings);
+                let (width, _) = self.glyphs.measure(ctx, theme, &start);
+                let view = graphics::screen_coordinates(ctx);
+                self.glyphs.draw(
+                    ctx,
+                    theme,
+                    &start,
+                    nalgebra::Point2::new(view.x + (view.w - width) / 2.0, view.y + 320.0),
+                    faded(theme.text, blink),
+                )?;
+                let controls = glyphs::pieces(
+                    "{Controls} controls   {Load} load game",
+                    &self.device,
+                    &self.settings,
+                );
+                let (width, _) = self.glyphs.measure(ctx, theme, &controls);
+                self.glyphs.draw(
+                    ctx,
+                    theme,
+                    &controls,
+                    nalgebra::Point2::new(view.x + (view.w - width) / 2.0, view.y + 360.0),
+                    theme.dim,
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    fn playing(&mut self) -> Option<&mut MainState> {
+        match self.screen {
+            Screen::Playing => self.game.as_mut(),
+            _ => None,
+        }
+    }
+}
+
+fn draw_centered(
+    ctx: &mut Context,
+    theme: &UiTheme,
+    text: &str,
+    y: f32,
+    alpha: f32,
+) -> GameResult<()> {
+    let view = graphics::screen_coordinates(ctx);
+    let text = theme.text(text);
+    let (width, _) = text.dimensions(ctx);
+    let corner = nalgebra::Point2::new(view.x + (view.w - width as f32) / 2.0, view.y + y);
+    graphics::draw(
+        ctx,
+        &text,
+        graphics::DrawParam::default()
+            .dest(corner)
+            .color(faded(theme.text, alpha)),
+    )
+}
+
+fn faded(color: graphics::Color, alpha: f32) -> graphics::Color {
+    graphics::Color {
+        a: color.a * alpha,
+        ..color
+    }
+}
+
+impl EventHandler for Front {
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        let elapsed = timer::time_since_start(ctx) - self.since;
+        match &mut self.screen {
+            Screen::Splash if elapsed >= SPLASH_TIME => self.show(ctx, Screen::Title),
+            Screen::Title if elapsed >= ATTRACT_AFTER => self.start_attract(ctx),
+            Screen::Controls(menu) => menu.update(timer::delta(ctx).as_secs_f32()),
+            Screen::Attract => {
+                let finished = self
+                    .game
+                    .as_ref()
+                    .and_then(|game| game.playback.as_ref())
+                    .map_or(true, Playback::finished);
+                if finished {
+                    self.back_to_title(ctx);
+                }
+            }
+            _ => (),
+        }
+
+        // whatever was picked from the game's pause menu, or the sandbox's
+        // reset, which restarts
+        let request = match (&self.screen, self.game.as_mut()) {
+            (Screen::Playing, Some(game)) => game.request.take(),
+            _ => None,
+        };
+        match request {
+            Some(pause::Choice::Restart) => self.confirm_leaving(ctx, Leaving::Restart),
+            Some(pause::Choice::Options) => self.open_options(ctx),
+            Some(pause::Choice::Save) => {
+                let menu = SaveMenu::new(ctx, &*self.storage, Purpose::Save);
+                self.show(ctx, Screen::Saves(menu));
+            }
+            Some(pause::Choice::Quit) => self.confirm_leaving(ctx, Leaving::ToTitle),
+            Some(pause::Choice::Resume) | None => (),
+        }
+        if std::mem::replace(&mut self.skip_lag, false) {
+            while timer::check_update_time(ctx, DESIRED_FPS) {}
+        }
+        match (&self.screen, self.game.as_mut()) {
+            (Screen::Playing, Some(game)) | (Screen::Attract, Some(game)) => game.update(ctx),
+            _ => {
+                // nothing is stepped on the front screens, but ggez keeps
+                // count of the updates due, and the game would start out
+                // trying to catch up on all of them
+                while timer::check_update_time(ctx, DESIRED_FPS) {}
+                Ok(())
+            }
+        }
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        match (&self.screen, self.game.as_mut()) {
+            (Screen::Playing, Some(game)) | (Screen::Attract, Some(game)) => {
+                game.draw_scene(ctx)?
+            }
+            _ => self.draw_screen(ctx)?,
+        }
+        if let Some((dialog, _)) = &self.modal {
+            dialog.draw(ctx, &self.glyphs, &self.theme, &self.device)?;
+        }
+        graphics::present(ctx)?;
+        timer::yield_now();
+        Ok(())
+    }
+
+    fn key_down_event(
+        &mut self,
+        ctx: &mut Context,
+        keycode: KeyCode,
+        keymod: KeyMods,
+        repeat: bool,
+    ) {
+        if self.modal.is_some() {
+            if !repeat {
+                self.modal_input(ctx, Device::Keyboard, &format!("{:?}", keycode));
+            }
+            return;
+        }
+        if let (Screen::Playing, KeyCode::F6, false) = (&self.screen, keycode, repeat) {
+            self.pressed(ctx, Device::Keyboard, "F6");
+            return;
+        }
+        match self.playing() {
+            Some(game) => game.key_down_event(ctx, keycode, keymod, repeat),
+            None if !repeat => self.pressed(ctx, Device::Keyboard, &format!("{:?}", keycode)),
+            None => (),
+        }
+    }
+
+    // Letting go still reaches the game under a dialog, though the dialog
+    // held it and let go of everything as it came up
+    fn key_up_event(&mut self, ctx: &mut Context, keycode: KeyCode, keymod: KeyMods) {
+        if let Some(game) = self.playing() {
+            game.key_up_event(ctx, keycode, keymod);
+        }
+    }
+
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, dx: f32, dy: f32) {
+        if self.modal.is_some() {
+            return;
+        }
+        match self.playing() {
+            Some(game) => game.mouse_motion_event(ctx, x, y, dx, dy),
+            // moving the mouse keeps the title screen awake, but doesn't end
+            // the demo
+            None => {
+                if let Screen::Title = self.screen {
+                    self.since = timer::time_since_start(ctx);
+                }
+            }
+        }
+    }
+
+    fn mouse_wheel_event(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        if let Some(game) = self.playing() {
+            game.mouse_wheel_event(ctx, x, y);
+        }
+    }
+
+    fn text_input_event(&mut self, ctx: &mut Context, character: char) {
+        if self.modal.is_some() {
+            return;
+        }
+        if let Some(game) = self.playing() {
+            game.text_input_event(ctx, character);
+        }
+    }
+
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if self.modal.is_some() {
+            return;
+        }
+        match self.playing() {
+            Some(game) => game.mouse_button_down_event(ctx, button, x, y),
+            None => self.pressed(ctx, Device::Keyboard, "Mouse"),
+        }
+    }
+
+    fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if let Some(game) = self.playing() {
+            game.mouse_button_up_event(ctx, button, x, y);
+        }
+    }
+
+    fn gamepad_button_down_event(&mut self, ctx: &mut Context, btn: Button, id: GamepadId) {
+        if self.modal.is_some() {
+            let device = Device::gamepad(input::gamepad::gamepad(ctx, id).uuid());
+            self.modal_input(ctx, device, &format!("{:?}", btn));
+            return;
+        }
+        match self.playing() {
+            Some(game) => game.gamepad_button_down_event(ctx, btn, id),
+            None => {
+                let device = Device::gamepad(input::gamepad::gamepad(ctx, id).uuid());
+                self.pressed(ctx, device, &format!("{:?}", btn))
+            }
+        }
+    }
+
+    fn gamepad_button_up_event(&mut self, ctx: &mut Context, btn: Button, id: GamepadId) {
+        if let Some(game) = self.playing() {
+            game.gamepad_button_up_event(ctx, btn, id);
+        }
+    }
+
+    fn gamepad_axis_event(&mut self, ctx: &mut Context, axis: Axis, value: f32, id: GamepadId) {
+        if self.modal.is_some() {
+            return;
+        }
+        if let Some(game) = self.playing() {
+            game.gamepad_axis_event(ctx, axis, value, id);
+        }
+    }
+
+    // Closing the window in the middle of a game asks first, and carries on
+    // until the dialog says to quit. Anywhere else it just quits.
+    fn quit_event(&mut self, ctx: &mut Context) -> bool {
+        if self.in_game() {
+            if self.modal.is_none() {
+                self.confirm_leaving(ctx, Leaving::Quit);
+            }
+            return true;
+        }
+        self.exit();
+        false
+    }
+}
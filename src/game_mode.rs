@@ -0,0 +1,446 @@
+use crate::arena::{ShrinkingBounds, ShrinkingBoundsSystem};
+use crate::faction::Faction;
+use crate::ghost::Ghost;
+use crate::health::Health;
+use crate::notifications::Notifications;
+use crate::sandbox::SandboxTools;
+use crate::theme::UiTheme;
+use crate::time::{DeltaTime, GameClock};
+use crate::transition::SceneChange;
+use crate::waves::WaveDirector;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+use std::collections::HashMap;
+
+mod capture_the_flag;
+mod daily;
+mod king_of_the_hill;
+
+pub(crate) use capture_the_flag::CaptureTheFlag;
+pub(crate) use daily::record as record_daily;
+pub(crate) use daily::{Daily, DailyRun};
+pub(crate) use king_of_the_hill::KingOfTheHill;
+
+// seconds between one round ending and the next starting
+const ROUND_BREAK: f32 = 5.0;
+// seconds before the next round that its transition starts capturing the
+// old one
+const CAPTURE_LEAD: f32 = 0.2;
+
+// A game mode is a set of rules layered on top of the usual movement, weapons
+// and collision systems. Modes share the same components (Position,
+// CollisionBox, Faction) and report what happens through the Scores resource
+// and Notifications, so they don't need systems of their own for the HUD.
+pub(crate) trait GameMode {
+    fn name(&self) -> &'static str;
+
+    // the file the mode's level is described in
+    fn level(&self) -> &'static str;
+
+    // a seed the mode is always played from, whatever --seed says
+    fn seed(&self) -> Option<u64> {
+        None
+    }
+
+    // whether the game can be saved part way through
+    fn saves(&self) -> bool {
+        true
+    }
+
+    // add the mode's resources and entities (flags, zones) to the world
+    fn setup(&mut self, world: &mut World);
+
+    // run the mode's rule systems for one update tick
+    fn run_rules(&mut self, world: &World);
+
+    // draw anything the mode owns, after the entities have been drawn
+    fn draw(&self, _ctx: &mut Context, _world: &World) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+// Picks a mode by the name given on the command line
+pub(crate) fn from_name(name: &str) -> Option<Box<dyn GameMode>> {
+    match name {
+        "skirmish" => Some(Box::new(Skirmish::default())),
+        "ctf" => Some(Box::new(CaptureTheFlag::default())),
+        "koth" => Some(Box::new(KingOfTheHill::default())),
+        "tutorial" => Some(Box::new(Tutorial::default())),
+        "sector" => Some(Box::new(Sector::default())),
+        "daily" => Some(Box::new(Daily::default())),
+        "endless" => Some(Box::new(Endless)),
+        "trial" => Some(Box::new(TimeTrial)),
+        "sandbox" => Some(Box::new(Sandbox)),
+        _ => None,
+    }
+}
+
+// Points per faction, shared by every mode. Points are for the current round,
+// whoever has the most when it ends takes the round.
+#[derive(Debug, Default)]
+pub(crate) struct Scores {
+    points: HashMap<Faction, u32>,
+    rounds: HashMap<Faction, u32>,
+}
+
+impl Scores {
+    pub(crate) fn add(&mut self, faction: Faction, points: u32) {
+        *self.points.entry(faction).or_insert(0) += points;
+    }
+
+    pub(crate) fn get(&self, faction: Faction) -> u32 {
+        self.points.get(&faction).cloned().unwrap_or(0)
+    }
+
+    // the faction with the most points, None on a draw
+    pub(crate) fn leader(&self) -> Option<Faction> {
+        let best = self.points.values().cloned().max().unwrap_or(0);
+        let mut leaders = self.points.iter().filter(|(_, p)| **p == best);
+        match (leaders.next(), leaders.next()) {
+            (Some((faction, _)), None) => Some(*faction),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn rounds_won(&self, faction: Faction) -> u32 {
+        self.rounds.get(&faction).cloned().unwrap_or(0)
+    }
+
+    fn end_round(&mut self, winner: Faction) {
+        *self.rounds.entry(winner).or_insert(0) += 1;
+    }
+
+    fn start_round(&mut self) {
+        self.points.clear();
+    }
+}
+
+// How long is left of the round, in seconds. When time runs out on a draw the
+// round goes to sudden death, the storm closes in and the round carries on
+// until one side pulls ahead.
+#[derive(Debug, Default)]
+pub(crate) struct RoundTimer {
+    pub(crate) length: f32,
+    pub(crate) remaining: f32,
+    pub(crate) over: bool,
+    pub(crate) sudden_death: bool,
+    // seconds until the next round starts, once this one is over
+    pub(crate) break_left: f32,
+}
+
+impl RoundTimer {
+    pub(crate) fn new(length: f32) -> Self {
+        RoundTimer {
+            length,
+            remaining: length,
+            ..RoundTimer::default()
+        }
+    }
+}
+
+// Adds the resources every mode's rounds need
+fn insert_round(world: &mut World, length: f32) {
+    world.insert(Scores::default());
+    world.insert(RoundTimer::new(length));
+    world.insert(ShrinkingBounds::default());
+}
+
+// Counts the round down, scores it for the winner when it ends and starts the
+// next one after a short break
+#[derive(Default)]
+pub(crate) struct RoundTimerSystem;
+
+impl<'a> System<'a> for RoundTimerSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Write<'a, RoundTimer>,
+        Write<'a, Scores>,
+        Write<'a, Notifications>,
+        Write<'a, ShrinkingBounds>,
+        Write<'a, SceneChange>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Faction>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            delta,
+            mut timer,
+            mut scores,
+            mut notifications,
+            mut bounds,
+            mut change,
+            health,
+            factions,
+        ) = data;
+        let dt = delta.seconds;
+
+        if timer.over {
+            timer.break_left -= dt;
+            change.coming = timer.break_left <= CAPTURE_LEAD;
+            if timer.break_left <= 0.0 {
+                timer.remaining = timer.length;
+                timer.over = false;
+                scores.start_round();
+                notifications.push("Next round!");
+                change.coming = false;
+                change.happened = true;
+            }
+            return;
+        }
+
+        let winner = if timer.sudden_death {
+            // sudden death also ends when only one side has ships left
+            let mut standing: Vec<Faction> = Vec::new();
+            for (_, faction) in (&health, &factions).join() {
+                if !standing.contains(faction) {
+                    standing.push(*faction);
+                }
+            }
+            match scores.leader() {
+                Some(leader) => Some(leader),
+                None if standing.len() == 1 => Some(standing[0]),
+                None => None,
+            }
+        } else {
+            timer.remaining -= dt;
+            if timer.remaining > 0.0 {
+                return;
+            }
+            timer.remaining = 0.0;
+            if scores.leader().is_none() {
+                timer.sudden_death = true;
+                bounds.start(0.0);
+                notifications.push("Sudden death!");
+            }
+            scores.leader()
+        };
+
+        if let Some(faction) = winner {
+            timer.over = true;
+            timer.sudden_death = false;
+            timer.break_left = ROUND_BREAK;
+            bounds.stop();
+            scores.end_round(faction);
+            notifications.push(&format!("Round over, {:?} wins!", faction));
+        }
+    }
+}
+
+// The timer and the storm, which every mode runs alongside its own rules
+pub(crate) struct RoundRules {
+    timer_system: RoundTimerSystem,
+    bounds_system: ShrinkingBoundsSystem,
+}
+
+impl Default for RoundRules {
+    fn default() -> Self {
+        RoundRules {
+            timer_system: RoundTimerSystem,
+            bounds_system: ShrinkingBoundsSystem,
+        }
+    }
+}
+
+impl RoundRules {
+    pub(crate) fn run(&mut self, world: &World) {
+        self.timer_system.run_now(world);
+        self.bounds_system.run_now(world);
+    }
+}
+
+// The plain mode: no objectives, just the round timer
+#[derive(Default)]
+pub(crate) struct Skirmish {
+    round: RoundRules,
+}
+
+impl GameMode for Skirmish {
+    fn name(&self) -> &'static str {
+        "Skirmish"
+    }
+
+    fn level(&self) -> &'static str {
+        "/levels/skirmish.ron"
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        insert_round(world, 300.0);
+    }
+
+    fn run_rules(&mut self, world: &World) {
+        self.round.run(world);
+    }
+}
+
+// A skirmish in a sector made up from the game's seed, so every seed plays
+// somewhere new and the same seed in the same place
+#[derive(Default)]
+pub(crate) struct Sector {
+    round: RoundRules,
+}
+
+impl GameMode for Sector {
+    fn name(&self) -> &'static str {
+        "Sector"
+    }
+
+    fn level(&self) -> &'static str {
+        "/levels/sector.ron"
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        insert_round(world, 300.0);
+    }
+
+    fn run_rules(&mut self, world: &World) {
+        self.round.run(world);
+    }
+}
+
+// Surviving wave after wave for as long as possible. There are no rounds, the
+// clock counts up how long the player has lasted, and the level's escalation
+// makes the waves bigger, faster and more varied as it goes on.
+pub(crate) struct Endless;
+
+impl GameMode for Endless {
+    fn name(&self) -> &'static str {
+        "Endless"
+    }
+
+    fn level(&self) -> &'static str {
+        "/levels/endless.ron"
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        insert_round(world, 0.0);
+    }
+
+    fn run_rules(&mut self, world: &World) {
+        let elapsed = world.read_resource::<GameClock>().elapsed;
+        world.write_resource::<RoundTimer>().remaining = elapsed as f32;
+    }
+}
+
+// A race through the rocks to the finish, against the clock and the ghost of
+// the best run so far, e.g. `cargo run -- --mode trial`. The level's Finish
+// trigger stops the clock, and the round timer shows the time so far.
+pub(crate) struct TimeTrial;
+
+impl GameMode for TimeTrial {
+    fn name(&self) -> &'static str {
+        "Trial"
+    }
+
+    fn level(&self) -> &'static str {
+        "/levels/trial.ron"
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        insert_round(world, 0.0);
+    }
+
+    fn run_rules(&mut self, world: &World) {
+        let time = match world.read_resource::<Ghost>().finished {
+            Some(time) => time,
+            None => world.read_resource::<GameClock>().elapsed as f32,
+        };
+        world.write_resource::<RoundTimer>().remaining = time;
+    }
+}
+
+// Somewhere to try things out, e.g. `cargo run -- --mode sandbox`: no waves
+// come in by themselves, and the sandbox's tools spawn anything from the
+// prefabs, slow time down or speed it up, make the player invincible and
+// start again, see SandboxTools
+pub(crate) struct Sandbox;
+
+impl GameMode for Sandbox {
+    fn name(&self) -> &'static str {
+        "Sandbox"
+    }
+
+    fn level(&self) -> &'static str {
+        "/levels/sandbox.ron"
+    }
+
+    fn saves(&self) -> bool {
+        false
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        insert_round(world, 0.0);
+        world.write_resource::<WaveDirector>().held = true;
+        world.insert(SandboxTools::default());
+    }
+
+    fn run_rules(&mut self, world: &World) {
+        let elapsed = world.read_resource::<GameClock>().elapsed;
+        world.write_resource::<RoundTimer>().remaining = elapsed as f32;
+    }
+}
+
+// A skirmish with a long round on a quiet level, whose prompts walk a new
+// player through the controls
+#[derive(Default)]
+pub(crate) struct Tutorial {
+    round: RoundRules,
+}
+
+impl GameMode for Tutorial {
+    fn name(&self) -> &'static str {
+        "Tutorial"
+    }
+
+    fn level(&self) -> &'static str {
+        "/levels/tutorial.ron"
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        insert_round(world, 600.0);
+    }
+
+    fn run_rules(&mut self, world: &World) {
+        self.round.run(world);
+    }
+}
+
+// Score line and round clock along the top of the screen, shared by all modes
+pub(crate) fn draw_scores(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let scores = world.read_resource::<Scores>();
+    let timer = world.read_resource::<RoundTimer>();
+    let theme = world.read_resource::<UiTheme>();
+    let score_line = theme.text(format!(
+        "Blue {} ({})  -  ({}) {} Red",
+        scores.get(Faction::Blue),
+        scores.rounds_won(Faction::Blue),
+        scores.rounds_won(Faction::Red),
+        scores.get(Faction::Red)
+    ));
+    let clock = if timer.sudden_death {
+        "SUDDEN DEATH".to_string()
+    } else {
+        let seconds = timer.remaining.ceil() as u32;
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    };
+    let clock = theme.text(clock);
+
+    let view = graphics::screen_coordinates(ctx);
+    let mut y = view.y + 10.0;
+    for text in [score_line, clock].iter() {
+        let (width, height) = text.dimensions(ctx);
+        graphics::draw(
+            ctx,
+            text,
+            graphics::DrawParam::default()
+                .dest(nalgebra::Point2::new(
+                    view.x + (view.w - width as f32) / 2.0,
+                    y,
+                ))
+                .color(theme.text),
+        )?;
+        y += height as f32 + 4.0;
+    }
+    Ok(())
+}
@@ -0,0 +1,190 @@
+use super::{GameMode, RoundRules, RoundTimer, Scores};
+use crate::faction::Faction;
+use crate::interpolation::Drawn;
+use crate::notifications::Notifications;
+use crate::{CollisionBox, Position};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+use specs_derive::*;
+
+// A team's flag. It sits at home until a hostile ship touches it, then follows
+// that ship around until the carrier brings it back to their own flag.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub(crate) struct Flag {
+    pub(crate) faction: Faction,
+    pub(crate) home: nalgebra::Point2<f32>,
+    pub(crate) carrier: Option<Entity>,
+}
+
+impl Flag {
+    fn at_home(&self, position: nalgebra::Point2<f32>) -> bool {
+        self.carrier.is_none() && position == self.home
+    }
+}
+
+pub(crate) struct FlagSystem;
+
+impl<'a> System<'a> for FlagSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Scores>,
+        Write<'a, Notifications>,
+        Read<'a, RoundTimer>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Faction>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Flag>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut scores,
+            mut notifications,
+            timer,
+            coll_box,
+            factions,
+            mut pos,
+            mut flags,
+        ) = data;
+        if timer.over {
+            return;
+        }
+
+        // where each team's flag is sitting at home, a carrier has to reach
+        // their own flag there to score
+        let homes: Vec<(Faction, nalgebra::Point2<f32>)> = (&pos, &flags)
+            .join()
+            .filter(|(pos, flag)| flag.at_home(pos.position))
+            .map(|(_, flag)| (flag.faction, flag.home))
+            .collect();
+
+        for (pos, flag) in (&mut pos, &mut flags).join() {
+            if let Some(carrier) = flag.carrier {
+                let carrier_box = match coll_box.get(carrier) {
+                    Some(carrier_box) => carrier_box,
+                    None => {
+                        // the carrier is gone, the flag drops where it was
+                        flag.carrier = None;
+                        notifications.push(&format!("{:?} flag dropped", flag.faction));
+                        continue;
+                    }
+                };
+                pos.position = carrier_box.center();
+
+                let carrier_side = factions.get(carrier);
+                let captured = homes.iter().any(|(faction, home)| {
+                    Some(faction) == carrier_side && carrier_box.contains(*home)
+                });
+                if let (true, Some(side)) = (captured, carrier_side) {
+                    scores.add(*side, 1);
+                    notifications
+                        .push(&format!("{:?} captured the {:?} flag!", side, flag.faction));
+                    flag.carrier = None;
+                    pos.position = flag.home;
+                }
+                continue;
+            }
+
+            for (ship, ship_box, side) in (&entities, &coll_box, &factions).join() {
+                if !ship_box.contains(pos.position) {
+                    continue;
+                }
+                if *side != flag.faction {
+                    flag.carrier = Some(ship);
+                    notifications.push(&format!("{:?} took the {:?} flag", side, flag.faction));
+                    break;
+                } else if pos.position != flag.home {
+                    pos.position = flag.home;
+                    notifications.push(&format!("{:?} flag returned", flag.faction));
+                    break;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct CaptureTheFlag {
+    flag_system: FlagSystem,
+    round: RoundRules,
+}
+
+impl Default for CaptureTheFlag {
+    fn default() -> Self {
+        CaptureTheFlag {
+            flag_system: FlagSystem,
+            round: RoundRules::default(),
+        }
+    }
+}
+
+impl GameMode for CaptureTheFlag {
+    fn name(&self) -> &'static str {
+        "Capture the Flag"
+    }
+
+    fn level(&self) -> &'static str {
+        "/levels/ctf.ron"
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        world.register::<Flag>();
+        super::insert_round(world, 600.0);
+
+        let bases = [
+            (Faction::Blue, nalgebra::Point2::new(60.0, 60.0)),
+            (Faction::Red, nalgebra::Point2::new(740.0, 540.0)),
+        ];
+        for (faction, home) in bases.iter() {
+            world
+                .create_entity()
+                .with(Position { position: *home })
+                .with(Flag {
+                    faction: *faction,
+                    home: *home,
+                    carrier: None,
+                })
+                .build();
+        }
+    }
+
+    fn run_rules(&mut self, world: &World) {
+        self.flag_system.run_now(world);
+        self.round.run(world);
+    }
+
+    fn draw(&self, ctx: &mut Context, world: &World) -> GameResult<()> {
+        let entities = world.entities();
+        let flags = world.read_storage::<Flag>();
+        let drawn = Drawn::new(world);
+
+        let mut mesh = graphics::MeshBuilder::new();
+        let mut any_flags = false;
+        for (entity, flag) in (&entities, &flags).join() {
+            // a pole with a little pennant, planted on the flag position
+            let base = match drawn.at(entity) {
+                Some(at) => at,
+                None => continue,
+            };
+            let top = base - nalgebra::Vector2::new(0.0, 24.0);
+            mesh.line(&[base, top], 2.0, graphics::WHITE)?;
+            mesh.polygon(
+                graphics::DrawMode::fill(),
+                &[
+                    top,
+                    top + nalgebra::Vector2::new(16.0, 5.0),
+                    top + nalgebra::Vector2::new(0.0, 10.0),
+                ],
+                flag.faction.color(),
+            )?;
+            any_flags = true;
+        }
+        if any_flags {
+            let mesh = mesh.build(ctx)?;
+            graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,138 @@
+use super::{insert_round, GameMode, RoundRules, RoundTimer};
+use crate::platform;
+use crate::storage::{self, Storage};
+use crate::ControllableTag;
+use ggez::GameResult;
+use serde::{Deserialize, Serialize};
+use specs::*;
+
+const SECONDS_PER_DAY: u64 = 24 * 60 * 60;
+// where every daily challenge played is recorded
+pub(crate) const LEADERBOARD: &str = "/leaderboards/daily.ron";
+
+// The same generated sector for everyone on the same day, played as a single
+// round. The seed comes from the date rather than --seed, there's no saving
+// part way through, and the score is kept on the daily leaderboard along with
+// the seed, so a run can be compared with or played again by anyone else.
+pub(crate) struct Daily {
+    round: RoundRules,
+    day: u64,
+}
+
+impl Default for Daily {
+    fn default() -> Self {
+        Daily {
+            round: RoundRules::default(),
+            day: platform::unix_time() / SECONDS_PER_DAY,
+        }
+    }
+}
+
+impl GameMode for Daily {
+    fn name(&self) -> &'static str {
+        "Daily"
+    }
+
+    fn level(&self) -> &'static str {
+        // the same sector as the sector mode, so a daily challenge can be
+        // played again with `--mode sector --seed <seed>`
+        "/levels/sector.ron"
+    }
+
+    fn seed(&self) -> Option<u64> {
+        Some(self.day)
+    }
+
+    fn saves(&self) -> bool {
+        false
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        insert_round(world, 300.0);
+        world.insert(DailyRun {
+            day: self.day,
+            ..DailyRun::default()
+        });
+    }
+
+    fn run_rules(&mut self, world: &World) {
+        self.round.run(world);
+
+        // the run is over at the end of the round, or as soon as the player
+        // has no ship left
+        let mut run = world.write_resource::<DailyRun>();
+        if !run.over {
+            let out = world.read_storage::<ControllableTag>().is_empty();
+            run.over = world.read_resource::<RoundTimer>().over || out;
+        }
+    }
+}
+
+// How today's challenge is going, for MainState to record once it's over
+#[derive(Debug, Default)]
+pub(crate) struct DailyRun {
+    pub(crate) day: u64,
+    pub(crate) over: bool,
+    // on the leaderboard already
+    pub(crate) recorded: bool,
+}
+
+// One run of a daily challenge
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Entry {
+    // e.g. "2020-04-01"
+    pub(crate) date: String,
+    // the seed the sector was made from, for playing it again
+    pub(crate) seed: u64,
+    pub(crate) points: u32,
+}
+
+// Every daily challenge run, best first within each day
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Leaderboard {
+    pub(crate) entries: Vec<Entry>,
+}
+
+// Adds the run to the leaderboard, returning where it placed among the runs
+// on the same day, 1 being the best
+pub(crate) fn record(storage: &dyn Storage, day: u64, points: u32) -> GameResult<usize> {
+    // no leaderboard yet is the same as an empty one
+    let mut board: Leaderboard = storage::load_ron(storage, LEADERBOARD).unwrap_or_default();
+    let entry = Entry {
+        date: date(day),
+        seed: day,
+        points,
+    };
+    let place = board
+        .entries
+        .iter()
+        .filter(|other| other.seed == day && other.points >= points)
+        .count()
+        + 1;
+    board.entries.push(entry);
+    board
+        .entries
+        .sort_by(|a, b| b.seed.cmp(&a.seed).then(b.points.cmp(&a.points)));
+    storage::save_ron(storage, LEADERBOARD, &board)?;
+    Ok(place)
+}
+
+// The year, month and day for a count of days since 1970-01-01
+fn date(day: u64) -> String {
+    // counted from the 1st of March in the year 0, so a leap day falls at the
+    // end of its year, and the calendar repeats every 400 years
+    let days = day as i64 + 719_468;
+    let era = days / 146_097;
+    let of_era = days - era * 146_097;
+    let year_of_era = (of_era - of_era / 1460 + of_era / 36524 - of_era / 146_096) / 365;
+    let of_year = of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_from_march = (5 * of_year + 2) / 153;
+    let day_of_month = of_year - (153 * month_from_march + 2) / 5 + 1;
+    let month = if month_from_march < 10 {
+        month_from_march + 3
+    } else {
+        month_from_march - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!("{}-{:02}-{:02}", year, month, day_of_month)
+}
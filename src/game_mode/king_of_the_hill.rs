@@ -0,0 +1,142 @@
+use super::{GameMode, RoundRules, RoundTimer, Scores};
+use crate::faction::Faction;
+use crate::interpolation::Drawn;
+use crate::time::DeltaTime;
+use crate::{CollisionBox, Position};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+use specs_derive::*;
+
+// A circular area that scores a point a second for whichever side holds it.
+// It is only held while ships from a single faction are inside, a contested
+// zone scores for nobody.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub(crate) struct Zone {
+    pub(crate) radius: f32,
+    pub(crate) holder: Option<Faction>,
+    // seconds held toward the next point
+    pub(crate) held_for: f32,
+}
+
+pub(crate) struct ZoneSystem;
+
+impl<'a> System<'a> for ZoneSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Write<'a, Scores>,
+        Read<'a, RoundTimer>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Faction>,
+        WriteStorage<'a, Zone>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (delta, mut scores, timer, pos, coll_box, factions, mut zones) = data;
+        if timer.over {
+            return;
+        }
+        let dt = delta.seconds;
+
+        for (pos, zone) in (&pos, &mut zones).join() {
+            let mut present: Vec<Faction> = Vec::new();
+            for (coll_box, faction) in (&coll_box, &factions).join() {
+                let inside = (coll_box.center() - pos.position).norm() <= zone.radius;
+                if inside && !present.contains(faction) {
+                    present.push(*faction);
+                }
+            }
+
+            let holder = if present.len() == 1 {
+                Some(present[0])
+            } else {
+                None
+            };
+            if holder != zone.holder {
+                zone.holder = holder;
+                zone.held_for = 0.0;
+            }
+
+            if let Some(holder) = zone.holder {
+                zone.held_for += dt;
+                while zone.held_for >= 1.0 {
+                    zone.held_for -= 1.0;
+                    scores.add(holder, 1);
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct KingOfTheHill {
+    zone_system: ZoneSystem,
+    round: RoundRules,
+}
+
+impl Default for KingOfTheHill {
+    fn default() -> Self {
+        KingOfTheHill {
+            zone_system: ZoneSystem,
+            round: RoundRules::default(),
+        }
+    }
+}
+
+impl GameMode for KingOfTheHill {
+    fn name(&self) -> &'static str {
+        "King of the Hill"
+    }
+
+    fn level(&self) -> &'static str {
+        "/levels/koth.ron"
+    }
+
+    fn setup(&mut self, world: &mut World) {
+        world.register::<Zone>();
+        super::insert_round(world, 300.0);
+
+        world
+            .create_entity()
+            .with(Position {
+                position: nalgebra::Point2::new(400.0, 300.0),
+            })
+            .with(Zone {
+                radius: 80.0,
+                holder: None,
+                held_for: 0.0,
+            })
+            .build();
+    }
+
+    fn run_rules(&mut self, world: &World) {
+        self.zone_system.run_now(world);
+        self.round.run(world);
+    }
+
+    fn draw(&self, ctx: &mut Context, world: &World) -> GameResult<()> {
+        let entities = world.entities();
+        let zones = world.read_storage::<Zone>();
+        let drawn = Drawn::new(world);
+
+        for (entity, zone) in (&entities, &zones).join() {
+            let at = match drawn.at(entity) {
+                Some(at) => at,
+                None => continue,
+            };
+            let mut color = zone.holder.map_or(graphics::WHITE, |holder| holder.color());
+            color.a = 0.6;
+            let ring = graphics::Mesh::new_circle(
+                ctx,
+                graphics::DrawMode::stroke(2.0),
+                at,
+                zone.radius,
+                0.5,
+                color,
+            )?;
+            graphics::draw(ctx, &ring, graphics::DrawParam::default())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,84 @@
+use crate::controls::Device;
+use crate::settings::Settings;
+use crate::theme::UiTheme;
+use ggez::event::GamepadId;
+use ggez::input::gamepad;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+use std::collections::HashMap;
+
+// Which gamepad ids belong to which devices, and whether the pad the player was
+// using has been unplugged. ggez doesn't report connects and disconnects, so
+// pads are identified on their first input and checked each frame to see if
+// they are still there.
+#[derive(Debug, Default)]
+pub(crate) struct Gamepads {
+    ids: HashMap<GamepadId, Device>,
+    // the game stays paused until this pad is back, or the player carries on
+    // with something else
+    pub(crate) disconnected: Option<Device>,
+}
+
+impl Gamepads {
+    pub(crate) fn device(&self, id: GamepadId) -> Option<&Device> {
+        self.ids.get(&id)
+    }
+
+    pub(crate) fn insert(&mut self, id: GamepadId, device: Device) {
+        self.ids.insert(id, device);
+    }
+
+    // Forgets the pads that have been unplugged, returning their devices. A pad
+    // that comes back is identified afresh, whatever id it gets this time.
+    pub(crate) fn remove_disconnected(&mut self, ctx: &Context) -> Vec<Device> {
+        let gone: Vec<GamepadId> = self
+            .ids
+            .keys()
+            .filter(|id| !gamepad::gamepad(ctx, **id).is_connected())
+            .cloned()
+            .collect();
+        gone.iter().filter_map(|id| self.ids.remove(id)).collect()
+    }
+}
+
+// the name a device was last seen with, for prompts and the settings file
+pub(crate) fn device_name(settings: &Settings, device: &Device) -> String {
+    settings
+        .profiles
+        .get(device)
+        .map(|profile| profile.name.clone())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Controller".to_owned())
+}
+
+// The prompt shown over the paused game while the player's pad is unplugged
+pub(crate) fn draw_disconnected_prompt(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let gamepads = world.read_resource::<Gamepads>();
+    let device = match gamepads.disconnected {
+        Some(ref device) => device,
+        None => return Ok(()),
+    };
+    let name = device_name(&world.read_resource::<Settings>(), device);
+
+    let theme = world.read_resource::<UiTheme>();
+    let text = theme.text(format!(
+        "{} disconnected\n\nReconnect it, or press a key to carry on with the keyboard",
+        name
+    ));
+    let (width, height) = text.dimensions(ctx);
+    let view = graphics::screen_coordinates(ctx);
+    // roomy, like a dialog, since the game's waiting on it
+    let padding = theme.padding * 2.0;
+    let box_w = width as f32 + padding * 2.0;
+    let box_h = height as f32 + padding * 2.0;
+    let x = view.x + (view.w - box_w) / 2.0;
+    let y = view.y + (view.h - box_h) / 2.0;
+
+    theme.draw_panel(ctx, graphics::Rect::new(x, y, box_w, box_h), 1.0)?;
+    graphics::draw(
+        ctx,
+        &text,
+        (nalgebra::Point2::new(x + padding, y + padding), theme.text),
+    )
+}
@@ -0,0 +1,171 @@
+use crate::assets::Assets;
+use crate::storage::{self, Storage};
+use crate::time::GameClock;
+use crate::{CollisionBox, ControllableTag, ImageHandle, Rotation};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+
+// seconds of game time between the points a run is recorded at
+const SAMPLE_INTERVAL: f64 = 0.05;
+const GHOST_ALPHA: f32 = 0.35;
+
+// Where the player's ship was at one moment of a run
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+struct Sample {
+    // seconds of game time in
+    time: f64,
+    // the middle of the ship
+    x: f32,
+    y: f32,
+    angle: f32,
+}
+
+// A finished run, the time it took and the way the player flew it
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct Run {
+    pub(crate) time: f32,
+    trace: Vec<Sample>,
+}
+
+impl Run {
+    // where the ship was that far into the run, between the points either
+    // side, or None once the run is over
+    fn at(&self, time: f64) -> Option<Sample> {
+        let next = self.trace.iter().position(|sample| sample.time >= time)?;
+        let after = self.trace[next];
+        let before = match next.checked_sub(1) {
+            Some(previous) => self.trace[previous],
+            None => return Some(after),
+        };
+        let t = ((time - before.time) / (after.time - before.time).max(f64::EPSILON)) as f32;
+        Some(Sample {
+            time,
+            x: before.x + (after.x - before.x) * t,
+            y: before.y + (after.y - before.y) * t,
+            angle: after.angle,
+        })
+    }
+}
+
+// The run being flown and the player's best run on the level, which flies
+// alongside as a see-through ghost ship. Runs are timed from the start of the
+// game to a level trigger that Finishes it, so only levels with one, like the
+// time trial, ever record a best.
+#[derive(Debug, Default)]
+pub(crate) struct Ghost {
+    pub(crate) best: Option<Run>,
+    current: Vec<Sample>,
+    // the seconds the run took, once it's finished
+    pub(crate) finished: Option<f32>,
+    // the finish has been dealt with
+    pub(crate) recorded: bool,
+}
+
+impl Ghost {
+    pub(crate) fn finish(&mut self, time: f32) {
+        if self.finished.is_none() {
+            self.finished = Some(time);
+        }
+    }
+}
+
+// the level's personal best, kept with the player's other files
+fn path(mode: &str) -> String {
+    format!("/ghosts/{}.ron", mode.to_lowercase())
+}
+
+// The best run on the level so far. None yet is the same as no ghost.
+pub(crate) fn load(storage: &dyn Storage, mode: &str) -> Ghost {
+    Ghost {
+        best: storage::load_ron(storage, &path(mode)).ok(),
+        ..Ghost::default()
+    }
+}
+
+// Keeps the finished run if it beat the best, returning whether it did
+pub(crate) fn save_if_best(storage: &dyn Storage, mode: &str, ghost: &mut Ghost) -> bool {
+    let time = match ghost.finished {
+        Some(time) => time,
+        None => return false,
+    };
+    if ghost.best.as_ref().map_or(false, |best| best.time <= time) {
+        return false;
+    }
+    let run = Run {
+        time,
+        trace: std::mem::take(&mut ghost.current),
+    };
+    storage::save_ron(storage, &path(mode), &run).unwrap_or_else(|err| {
+        println!("ghost error {:?}", err);
+    });
+    ghost.best = Some(run);
+    true
+}
+
+// Records where the player's ship is as the run goes, until it's finished
+pub(crate) struct GhostSystem;
+
+impl<'a> System<'a> for GhostSystem {
+    type SystemData = (
+        Read<'a, GameClock>,
+        Write<'a, Ghost>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Rotation>,
+        ReadStorage<'a, ControllableTag>,
+    );
+
+    fn run(&mut self, (clock, mut ghost, coll_box, rotations, controlled): Self::SystemData) {
+        if ghost.finished.is_some() {
+            return;
+        }
+        let due = ghost
+            .current
+            .last()
+            .map_or(true, |last| clock.elapsed - last.time >= SAMPLE_INTERVAL);
+        if !due {
+            return;
+        }
+        if let Some((player_box, rotation, _)) =
+            (&coll_box, rotations.maybe(), &controlled).join().next()
+        {
+            let center = player_box.center();
+            ghost.current.push(Sample {
+                time: clock.elapsed,
+                x: center.x,
+                y: center.y,
+                angle: rotation.map_or(0.0, |rotation| rotation.angle),
+            });
+        }
+    }
+}
+
+// The best run's ship where it was this far in, drawn faintly in the player's
+// ship image
+pub(crate) fn draw_ghost(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let ghost = world.read_resource::<Ghost>();
+    let sample = match &ghost.best {
+        Some(best) => match best.at(world.read_resource::<GameClock>().elapsed) {
+            Some(sample) => sample,
+            None => return Ok(()),
+        },
+        None => return Ok(()),
+    };
+    let images = world.read_storage::<ImageHandle>();
+    let controlled = world.read_storage::<ControllableTag>();
+    let image = match (&images, &controlled).join().next() {
+        Some((image, _)) => *image,
+        None => return Ok(()),
+    };
+    let assets = world.read_resource::<Assets>();
+    graphics::draw(
+        ctx,
+        assets.get(image),
+        graphics::DrawParam::default()
+            .dest(nalgebra::Point2::new(sample.x, sample.y))
+            .offset(nalgebra::Point2::new(0.5, 0.5))
+            .rotation(sample.angle)
+            .color(graphics::Color::new(0.6, 0.9, 1.0, GHOST_ALPHA)),
+    )
+}
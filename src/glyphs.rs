@@ -0,0 +1,227 @@
+use crate::atlas::Atlas;
+use crate::controls::{Action, BindingProfile, ControlScheme, Device};
+use crate::settings::Settings;
+use crate::theme::UiTheme;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+
+// space between a key's name and the edge of its cap
+const CAP_PADDING: f32 = 4.0;
+// gap left either side of a glyph in a line of text
+const GLYPH_GAP: f32 = 3.0;
+
+// A line of prompt text broken up into the words and the inputs it names
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Piece {
+    Text(String),
+    // a keyboard key, drawn as a key cap with its name on it
+    Key(String),
+    // a gamepad button, drawn with its glyph from the atlas
+    Button(String),
+}
+
+// what a key's cap says, for the keys whose names ggez prints oddly
+fn key_label(name: &str) -> &str {
+    match name {
+        "Return" => "Enter",
+        "Up" => "↑",
+        "Down" => "↓",
+        "Left" => "←",
+        "Right" => "→",
+        "Back" => "Backspace",
+        "LShift" | "RShift" => "Shift",
+        "LControl" | "RControl" => "Ctrl",
+        _ => name.trim_start_matches("Key"),
+    }
+}
+
+// The input named in a prompt on the given device. Names are actions, e.g.
+// "LockOn", or one of Move and Fire, which aren't bindable and follow the
+// control scheme instead, or Start, Controls and Load from the title screen.
+fn input(name: &str, device: &Device, settings: &Settings) -> Piece {
+    let gamepad = *device != Device::Keyboard;
+    let input = |name: &str| {
+        if gamepad {
+            Piece::Button(name.to_owned())
+        } else {
+            Piece::Key(key_label(name).to_owned())
+        }
+    };
+    match (name, gamepad, settings.control_scheme) {
+        ("Move", true, _) => input("LeftStick"),
+        ("Move", false, ControlScheme::Classic) => Piece::Key("Arrows".to_owned()),
+        ("Move", false, ControlScheme::TwinStick) => Piece::Key("WASD".to_owned()),
+        ("Fire", true, _) => input("RightTrigger2"),
+        ("Fire", false, ControlScheme::Classic) => input("Space"),
+        ("Fire", false, ControlScheme::TwinStick) => Piece::Key("Click".to_owned()),
+        ("Start", true, _) => input("Start"),
+        ("Start", false, _) => input("Return"),
+        ("Controls", true, _) => input("Select"),
+        ("Controls", false, _) => input("F1"),
+        ("Load", true, _) => input("North"),
+        ("Load", false, _) => input("L"),
+        _ => {
+            // actions are written the way the settings file writes them
+            let action: Action = match ron::de::from_str(name) {
+                Ok(action) => action,
+                Err(_) => return Piece::Text(format!("{{{}}}", name)),
+            };
+            let default_profile;
+            let profile = match settings.profiles.get(device) {
+                Some(profile) => profile,
+                None => {
+                    default_profile = BindingProfile::new(device);
+                    &default_profile
+                }
+            };
+            match profile.bindings.iter().find(|(_, bound)| *bound == action) {
+                Some((bound, _)) => input(bound),
+                None => Piece::Key("unbound".to_owned()),
+            }
+        }
+    }
+}
+
+// Breaks up prompt text naming inputs in braces, e.g. "Press {LockOn} to lock
+// on", into the words and the keys or buttons for the device in use
+pub(crate) fn pieces(text: &str, device: &Device, settings: &Settings) -> Vec<Piece> {
+    let mut pieces = Vec::new();
+    let mut rest = text;
+    while let Some(open) = rest.find('{') {
+        let close = match rest[open..].find('}') {
+            Some(close) => open + close,
+            None => break,
+        };
+        if open > 0 {
+            pieces.push(Piece::Text(rest[..open].to_owned()));
+        }
+        pieces.push(input(&rest[open + 1..close], device, settings));
+        rest = &rest[close + 1..];
+    }
+    if !rest.is_empty() {
+        pieces.push(Piece::Text(rest.to_owned()));
+    }
+    pieces
+}
+
+// Draws prompts with the keys and buttons they name, so "Press {Radar}" shows
+// an R key cap on the keyboard and the North button on a gamepad
+pub(crate) struct Glyphs {
+    buttons: Atlas,
+}
+
+impl Glyphs {
+    pub(crate) fn load(ctx: &mut Context) -> GameResult<Self> {
+        Ok(Glyphs {
+            buttons: Atlas::load(ctx, "/atlas/buttons.ron")?,
+        })
+    }
+
+    // how big a piece is drawn. A button without a glyph in the atlas is
+    // drawn as a key cap with its name instead.
+    fn size(&self, ctx: &mut Context, theme: &UiTheme, piece: &Piece) -> (f32, f32) {
+        let cap = |ctx: &mut Context, name: &str| {
+            let (w, h) = theme.text(name).dimensions(ctx);
+            (
+                w as f32 + CAP_PADDING * 2.0 + GLYPH_GAP * 2.0,
+                h as f32 + CAP_PADDING,
+            )
+        };
+        match piece {
+            Piece::Text(text) => {
+                let (w, h) = theme.text(text.as_str()).dimensions(ctx);
+                (w as f32, h as f32)
+            }
+            Piece::Key(name) => cap(ctx, name),
+            Piece::Button(name) => match self.buttons.region(name) {
+                Some(region) => (region.width + GLYPH_GAP * 2.0, region.height),
+                None => cap(ctx, name),
+            },
+        }
+    }
+
+    // how much room a line of pieces takes
+    pub(crate) fn measure(
+        &self,
+        ctx: &mut Context,
+        theme: &UiTheme,
+        pieces: &[Piece],
+    ) -> (f32, f32) {
+        pieces.iter().fold((0.0, 0.0), |(w, h), piece| {
+            let (piece_w, piece_h) = self.size(ctx, theme, piece);
+            (w + piece_w, h.max(piece_h))
+        })
+    }
+
+    // Draws a line of pieces from its top left corner, each centred on the
+    // line's height
+    pub(crate) fn draw(
+        &self,
+        ctx: &mut Context,
+        theme: &UiTheme,
+        pieces: &[Piece],
+        at: nalgebra::Point2<f32>,
+        color: graphics::Color,
+    ) -> GameResult<()> {
+        let (_, line_h) = self.measure(ctx, theme, pieces);
+        let mut x = at.x;
+        for piece in pieces {
+            let (w, h) = self.size(ctx, theme, piece);
+            let y = at.y + (line_h - h) / 2.0;
+            let glyph = match piece {
+                Piece::Button(name) => self.buttons.region(name),
+                _ => None,
+            };
+            match (piece, glyph) {
+                (Piece::Text(text), _) => {
+                    graphics::draw(
+                        ctx,
+                        &theme.text(text.as_str()),
+                        graphics::DrawParam::default()
+                            .dest(nalgebra::Point2::new(x, y))
+                            .color(color),
+                    )?;
+                }
+                (_, Some(region)) => {
+                    graphics::draw(
+                        ctx,
+                        &self.buttons.image,
+                        graphics::DrawParam::default()
+                            .src(region.src)
+                            .dest(nalgebra::Point2::new(x + GLYPH_GAP, y))
+                            .color(graphics::Color::new(1.0, 1.0, 1.0, color.a)),
+                    )?;
+                }
+                (Piece::Key(name), None) | (Piece::Button(name), None) => {
+                    let cap = graphics::Rect::new(x + GLYPH_GAP, y, w - GLYPH_GAP * 2.0, h);
+                    let background = graphics::Mesh::new_rectangle(
+                        ctx,
+                        graphics::DrawMode::fill(),
+                        cap,
+                        graphics::Color::new(0.2, 0.2, 0.25, 0.8 * color.a),
+                    )?;
+                    let edge = graphics::Mesh::new_rectangle(
+                        ctx,
+                        graphics::DrawMode::stroke(1.0),
+                        cap,
+                        color,
+                    )?;
+                    graphics::draw(ctx, &background, graphics::DrawParam::default())?;
+                    graphics::draw(ctx, &edge, graphics::DrawParam::default())?;
+                    graphics::draw(
+                        ctx,
+                        &theme.text(name.as_str()),
+                        graphics::DrawParam::default()
+                            .dest(nalgebra::Point2::new(
+                                cap.x + CAP_PADDING,
+                                cap.y + CAP_PADDING / 2.0,
+                            ))
+                            .color(color),
+                    )?;
+                }
+            }
+            x += w;
+        }
+        Ok(())
+    }
+}
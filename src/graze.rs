@@ -0,0 +1,115 @@
+use crate::interpolation::Drawn;
+use crate::lifetime::Lifetime;
+use crate::tween::Tween;
+use crate::{CollisionBox, Position};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::world::EntitiesRes;
+use specs::*;
+use specs_derive::*;
+use std::f32::consts::PI;
+
+// how far outside the player's hurtbox a shot still counts as a graze
+const GRAZE_MARGIN: f32 = 12.0;
+// what a graze is worth
+pub(crate) const GRAZE_POINTS: u32 = 10;
+pub(crate) const GRAZE_ENERGY: f32 = 2.0;
+// sparks thrown off by each graze at full quality, how far they fly and for
+// how long
+pub(crate) const SPARK_COUNT: u32 = 5;
+const SPARK_DISTANCE: f32 = 20.0;
+const SPARK_DURATION: f32 = 0.3;
+const SPARK_SIZE: f32 = 3.0;
+
+// Whether a shot at the given point is a near miss on a hurtbox: close enough
+// to be inside it once grown by the graze margin, but not actually touching it
+pub(crate) fn grazes(coll_box: &CollisionBox, point: nalgebra::Point2<f32>) -> bool {
+    let inflated = CollisionBox {
+        origin: coll_box.origin - nalgebra::Vector2::new(GRAZE_MARGIN, GRAZE_MARGIN),
+        width: coll_box.width + GRAZE_MARGIN * 2.0,
+        height: coll_box.height + GRAZE_MARGIN * 2.0,
+    };
+    inflated.contains(point) && !coll_box.contains(point)
+}
+
+// A little fleck of light from a graze. Like FloatingText it moves and fades
+// with a Tween and cleans itself up with a Lifetime.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub(crate) struct Spark {
+    pub(crate) color: graphics::Color,
+}
+
+// Throws a burst of sparks out from a point, usable from inside systems
+pub(crate) fn spawn_sparks(
+    entities: &EntitiesRes,
+    updater: &LazyUpdate,
+    at: nalgebra::Point2<f32>,
+    count: u32,
+) {
+    for i in 0..count {
+        let angle = i as f32 / count as f32 * 2.0 * PI;
+        let to = at + nalgebra::Vector2::new(angle.sin(), -angle.cos()) * SPARK_DISTANCE;
+        let entity = entities.create();
+        updater.insert(entity, Position { position: at });
+        updater.insert(
+            entity,
+            Spark {
+                color: graphics::Color::new(1.0, 0.9, 0.4, 1.0),
+            },
+        );
+        updater.insert(
+            entity,
+            Tween {
+                from: at,
+                to,
+                from_alpha: 1.0,
+                to_alpha: 0.0,
+                duration: SPARK_DURATION,
+                elapsed: 0.0,
+            },
+        );
+        updater.insert(
+            entity,
+            Lifetime {
+                remaining: SPARK_DURATION,
+            },
+        );
+    }
+}
+
+// All the sparks go into one mesh
+pub(crate) fn draw_sparks(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let entities = world.entities();
+    let sparks = world.read_storage::<Spark>();
+    let tweens = world.read_storage::<Tween>();
+    let drawn = Drawn::new(world);
+
+    let mut mesh = graphics::MeshBuilder::new();
+    let mut any_sparks = false;
+    for (entity, spark, tween) in (&entities, &sparks, tweens.maybe()).join() {
+        let at = match drawn.at(entity) {
+            Some(at) => at,
+            None => continue,
+        };
+        let mut color = spark.color;
+        color.a *= tween.map_or(1.0, |t| t.alpha());
+        mesh.rectangle(
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(
+                at.x - SPARK_SIZE / 2.0,
+                at.y - SPARK_SIZE / 2.0,
+                SPARK_SIZE,
+                SPARK_SIZE,
+            ),
+            color,
+        );
+        any_sparks = true;
+    }
+
+    if any_sparks {
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,235 @@
+use crate::events::{self, Publish, Subscribe, TrackedChannel, TrackedReader};
+use crate::faction::Faction;
+use crate::fixed::{self, Real};
+use crate::listener::{Cue, SoundCues};
+use crate::notifications::Notifications;
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::{CollisionEvent, ControllableTag, Position};
+use ggez::nalgebra;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::saveload::ConvertSaveload;
+use specs::*;
+use specs_derive::*;
+
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub(crate) struct Health {
+    pub(crate) current: Real,
+    pub(crate) max: Real,
+    // whoever last did damage, so a kill can be credited to them
+    last_hit_by: Option<Entity>,
+    // damage is ignored while this is set
+    pub(crate) shielded: bool,
+}
+
+impl Health {
+    pub(crate) fn new(max: f32) -> Self {
+        Health {
+            current: fixed::real(max),
+            max: fixed::real(max),
+            last_hit_by: None,
+            shielded: false,
+        }
+    }
+
+    // how much health is left, from 0 to 1
+    pub(crate) fn fraction(&self) -> f32 {
+        (fixed::float(self.current) / fixed::float(self.max))
+            .max(0.0)
+            .min(1.0)
+    }
+}
+
+// Health as it's quicksaved, with whoever last hit it by their marker
+#[derive(Serialize, Deserialize)]
+pub(crate) struct HealthData<M> {
+    current: Real,
+    max: Real,
+    last_hit_by: Option<M>,
+    shielded: bool,
+}
+
+impl<M: Serialize + DeserializeOwned> ConvertSaveload<M> for Health {
+    type Data = HealthData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        Ok(HealthData {
+            current: self.current,
+            max: self.max,
+            // someone that wasn't saved along with it can't be credited
+            last_hit_by: self.last_hit_by.and_then(ids),
+            shielded: self.shielded,
+        })
+    }
+
+    fn convert_from<F>(data: Self::Data, ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        Ok(Health {
+            current: data.current,
+            max: data.max,
+            last_hit_by: data.last_hit_by.and_then(ids),
+            shielded: data.shielded,
+        })
+    }
+}
+
+// Health taken off whatever an entity is touching, for as long as it's
+// touching it, so ramming a ship hurts both of them. Only what the
+// CollisionSystem finds touching counts, the player's ship against anything
+// else that isn't on its side. A Solid ship stops the player's rather than
+// being overlapped, so flying into it or sitting up against it is what
+// counts there.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(crate) struct Damage {
+    // health per second of game time
+    pub(crate) per_second: f32,
+}
+
+// Sent whenever something takes damage
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct DamageEvent {
+    pub(crate) target: Entity,
+}
+
+// Sent when the HealthSystem removes something whose health has run out.
+// The killer is whoever last damaged it, which may have died since.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct DeathEvent {
+    pub(crate) entity: Entity,
+    pub(crate) killer: Option<Entity>,
+    // what it was, as it's gone by the time the event is read
+    pub(crate) player: bool,
+    pub(crate) faction: Option<Faction>,
+    pub(crate) at: Option<nalgebra::Point2<f32>>,
+}
+
+// Takes damage off an entity's health, if it has any, and lets anything
+// listening for damage know. The source is whoever dealt it, or None for
+// things like the storm. Everything that hurts things should go through here
+// rather than changing Health directly.
+pub(crate) fn deal_damage(
+    health: &mut WriteStorage<Health>,
+    events: &mut TrackedChannel<DamageEvent>,
+    target: Entity,
+    source: Option<Entity>,
+    amount: f32,
+) {
+    if amount <= 0.0 {
+        return;
+    }
+    if let Some(health) = health.get_mut(target) {
+        if health.shielded {
+            return;
+        }
+        health.current -= fixed::real(amount);
+        if source.is_some() {
+            health.last_hit_by = source;
+        }
+        events.publish(DamageEvent { target });
+    }
+}
+
+// Deals the Damage of things touching each other, going by what the
+// CollisionSystem found. Anything killed is removed by the HealthSystem.
+pub(crate) struct DamageSystem {
+    collisions: TrackedReader<CollisionEvent>,
+}
+
+impl DamageSystem {
+    pub(crate) fn new(world: &World) -> Self {
+        DamageSystem {
+            collisions: events::subscribe::<CollisionEvent>(world, "damage"),
+        }
+    }
+}
+
+impl<'a> System<'a> for DamageSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Publish<'a, DamageEvent>,
+        Subscribe<'a, CollisionEvent>,
+        ReadStorage<'a, Damage>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Health>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, delta, time, mut damage_events, collisions, damage, multipliers, mut health) =
+            data;
+
+        for collision in collisions.read(&mut self.collisions) {
+            let pairs = [(collision.a, collision.b), (collision.b, collision.a)];
+            for &(source, target) in pairs.iter() {
+                if !entities.is_alive(target) {
+                    continue;
+                }
+                if let Some(damage) = damage.get(source) {
+                    let dt = time.scaled(delta.seconds, multipliers.get(target));
+                    deal_damage(
+                        &mut health,
+                        &mut damage_events,
+                        target,
+                        Some(source),
+                        damage.per_second * dt,
+                    );
+                }
+            }
+        }
+    }
+}
+
+// Removes anything whose health has run out
+pub(crate) struct HealthSystem;
+
+impl<'a> System<'a> for HealthSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, Notifications>,
+        Publish<'a, DeathEvent>,
+        Write<'a, SoundCues>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, ControllableTag>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut notifications, mut deaths, mut cues, health, factions, pos, controlled) =
+            data;
+
+        for (entity, health, faction) in (&entities, &health, factions.maybe()).join() {
+            if health.current <= fixed::real(0.0) {
+                if let Some(pos) = pos.get(entity) {
+                    cues.explosions.push(Cue {
+                        at: pos.position,
+                        velocity: nalgebra::Vector2::zeros(),
+                    });
+                }
+                if let Some(faction) = faction {
+                    notifications.push(&format!("{:?} ship destroyed", faction));
+                }
+                deaths.publish(DeathEvent {
+                    entity,
+                    killer: health.last_hit_by,
+                    player: controlled.get(entity).is_some(),
+                    faction: faction.copied(),
+                    at: pos.get(entity).map(|pos| pos.position),
+                });
+                entities
+                    .delete(entity)
+                    .unwrap_or_else(|err| println!("delete error {:?}", err));
+            }
+        }
+    }
+}
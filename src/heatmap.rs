@@ -0,0 +1,157 @@
+use crate::storage::Storage;
+use crate::telemetry::{self, Event};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use std::collections::HashMap;
+
+// size of the squares the level is split into, in pixels
+const CELL_SIZE: f32 = 32.0;
+const DEATH_RADIUS: f32 = 5.0;
+const LEGEND_MARGIN: f32 = 10.0;
+
+// Where players have been and died in the current level, over every session
+// of telemetry logged for it, drawn over the level to spot unfair places.
+// Toggled from the keyboard. The logs are read each time it is opened, so it
+// takes in anything logged since.
+#[derive(Default)]
+pub(crate) struct Heatmap {
+    pub(crate) visible: bool,
+    // how many position records fell in each cell
+    cells: HashMap<(i32, i32), u32>,
+    deaths: Vec<nalgebra::Point2<f32>>,
+    sessions: usize,
+    // built the first time it is drawn after being opened
+    mesh: Option<graphics::Mesh>,
+}
+
+fn point(event: &Event) -> nalgebra::Point2<f32> {
+    nalgebra::Point2::new(
+        telemetry::number(event, "x") as f32,
+        telemetry::number(event, "y") as f32,
+    )
+}
+
+impl Heatmap {
+    // the mode is the game mode's name, the level it plays on
+    pub(crate) fn toggle(&mut self, storage: &dyn Storage, mode: &str) {
+        self.visible = !self.visible;
+        self.mesh = None;
+        if !self.visible {
+            return;
+        }
+
+        self.cells.clear();
+        self.deaths.clear();
+        self.sessions = 0;
+        for (_, events) in telemetry::read_sessions(storage) {
+            let on_level = events.iter().any(|event| {
+                telemetry::text(event, "event") == "session_start"
+                    && telemetry::text(event, "mode") == mode
+            });
+            if !on_level {
+                continue;
+            }
+            self.sessions += 1;
+            for event in &events {
+                match telemetry::text(event, "event").as_str() {
+                    "position" => {
+                        let at = point(event);
+                        let cell = (
+                            (at.x / CELL_SIZE).floor() as i32,
+                            (at.y / CELL_SIZE).floor() as i32,
+                        );
+                        *self.cells.entry(cell).or_insert(0) += 1;
+                    }
+                    "death" if telemetry::text(event, "victim") == "player" => {
+                        self.deaths.push(point(event));
+                    }
+                    _ => (),
+                }
+            }
+        }
+        println!(
+            "Heatmap: {} sessions of {}, {} player deaths",
+            self.sessions,
+            mode,
+            self.deaths.len()
+        );
+    }
+
+    // Cells go from a faint blue where players rarely were to a strong red
+    // where they spent the most time. Deaths are marked with white rings.
+    fn build(&self, ctx: &mut Context) -> GameResult<graphics::Mesh> {
+        let most = self.cells.values().cloned().max().unwrap_or(1) as f32;
+        let mut mesh = graphics::MeshBuilder::new();
+        for (&(x, y), &count) in &self.cells {
+            let heat = count as f32 / most;
+            mesh.rectangle(
+                graphics::DrawMode::fill(),
+                graphics::Rect::new(
+                    x as f32 * CELL_SIZE,
+                    y as f32 * CELL_SIZE,
+                    CELL_SIZE,
+                    CELL_SIZE,
+                ),
+                graphics::Color::new(heat, 0.2, 1.0 - heat, 0.15 + heat * 0.4),
+            );
+        }
+        for death in &self.deaths {
+            mesh.circle(
+                graphics::DrawMode::stroke(2.0),
+                *death,
+                DEATH_RADIUS,
+                0.5,
+                graphics::WHITE,
+            );
+        }
+        // a mesh can't be empty, so there is always a point
+        mesh.circle(
+            graphics::DrawMode::fill(),
+            nalgebra::Point2::origin(),
+            0.1,
+            0.5,
+            graphics::Color::new(0.0, 0.0, 0.0, 0.0),
+        );
+        mesh.build(ctx)
+    }
+}
+
+// Drawn in the world under the ships
+pub(crate) fn draw_heatmap(ctx: &mut Context, heatmap: &mut Heatmap) -> GameResult<()> {
+    if !heatmap.visible {
+        return Ok(());
+    }
+    if heatmap.mesh.is_none() {
+        heatmap.mesh = Some(heatmap.build(ctx)?);
+    }
+    if let Some(mesh) = &heatmap.mesh {
+        graphics::draw(ctx, mesh, graphics::DrawParam::default())?;
+    }
+    Ok(())
+}
+
+// What the heatmap is made from, in the top right corner
+pub(crate) fn draw_heatmap_legend(ctx: &mut Context, heatmap: &Heatmap) -> GameResult<()> {
+    if !heatmap.visible {
+        return Ok(());
+    }
+    let text = graphics::Text::new(if heatmap.sessions == 0 {
+        "Heatmap: no telemetry logged for this level".to_owned()
+    } else {
+        format!(
+            "Heatmap: {} sessions, {} deaths (rings)",
+            heatmap.sessions,
+            heatmap.deaths.len()
+        )
+    });
+    let view = graphics::screen_coordinates(ctx);
+    let (width, _) = text.dimensions(ctx);
+    graphics::draw(
+        ctx,
+        &text,
+        graphics::DrawParam::default().dest(nalgebra::Point2::new(
+            view.x + view.w - width as f32 - LEGEND_MARGIN,
+            view.y + LEGEND_MARGIN,
+        )),
+    )
+}
@@ -0,0 +1,340 @@
+use crate::{Collider, CollisionBox};
+use ggez::nalgebra;
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+
+// the player only gets hurt by shots that hit this small core in the middle of
+// the ship, so weaving through dense patterns is possible
+const PLAYER_CORE: f32 = 10.0;
+// projectiles are drawn 4 pixels across and hit with the same size
+const PROJECTILE_SIZE: f32 = 4.0;
+// how close a box has to come to a solid to count as touching it, a little
+// over the rounding the MovementSystem can leave between the player's ship
+// and whatever it was pushed back out of
+pub(crate) const CONTACT: f32 = 0.01;
+
+// A box relative to an entity's Position, which is the top left of its sprite
+// for ships and the middle of the shot for projectiles
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct Shape {
+    pub(crate) offset: nalgebra::Vector2<f32>,
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+}
+
+impl Shape {
+    // a box of the given size centred on a point relative to the position
+    pub(crate) fn centered(center: nalgebra::Vector2<f32>, width: f32, height: f32) -> Self {
+        Shape {
+            offset: center - nalgebra::Vector2::new(width / 2.0, height / 2.0),
+            width,
+            height,
+        }
+    }
+
+    // where the shape is in the world for an entity at the given position
+    pub(crate) fn at(&self, position: nalgebra::Point2<f32>) -> CollisionBox {
+        CollisionBox {
+            origin: position + self.offset,
+            width: self.width,
+            height: self.height,
+        }
+    }
+}
+
+// The part of an entity that deals damage. This is separate from the
+// CollisionBox, which is what ships bump into, so shots and attacks can be
+// whatever size plays best rather than the size of the sprite.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub(crate) struct Hitbox(pub(crate) Shape);
+
+impl Hitbox {
+    pub(crate) fn projectile() -> Self {
+        Hitbox(Shape::centered(
+            nalgebra::Vector2::new(0.0, 0.0),
+            PROJECTILE_SIZE,
+            PROJECTILE_SIZE,
+        ))
+    }
+}
+
+// The part of an entity that takes damage. Only entities with one can be hit.
+#[derive(Component, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(crate) struct Hurtbox(pub(crate) Shape);
+
+impl Hurtbox {
+    // the whole of a ship of the given size
+    pub(crate) fn ship(width: f32, height: f32) -> Self {
+        Hurtbox(Shape {
+            offset: nalgebra::Vector2::new(0.0, 0.0),
+            width,
+            height,
+        })
+    }
+
+    // just the core of the player's ship of the given size
+    pub(crate) fn player(width: f32, height: f32) -> Self {
+        Hurtbox(Shape::centered(
+            nalgebra::Vector2::new(width / 2.0, height / 2.0),
+            PLAYER_CORE,
+            PLAYER_CORE,
+        ))
+    }
+}
+
+// Whether two boxes overlap
+pub(crate) fn overlaps(a: &CollisionBox, b: &CollisionBox) -> bool {
+    a.origin.x < b.origin.x + b.width
+        && a.origin.x + a.width > b.origin.x
+        && a.origin.y < b.origin.y + b.height
+        && a.origin.y + a.height > b.origin.y
+}
+
+// The box made bigger by the margin all round
+pub(crate) fn grown(coll_box: &CollisionBox, margin: f32) -> CollisionBox {
+    CollisionBox {
+        origin: coll_box.origin - nalgebra::Vector2::new(margin, margin),
+        width: coll_box.width + margin * 2.0,
+        height: coll_box.height + margin * 2.0,
+    }
+}
+
+// Whether two boxes overlap or meet, for solids, which the MovementSystem
+// never lets the player's ship overlap, only come up against
+pub(crate) fn touches(a: &CollisionBox, b: &CollisionBox) -> bool {
+    overlaps(&grown(a, CONTACT), b)
+}
+
+// A Collider where it is in the world, placed on its entity's CollisionBox
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Placed {
+    Rect(CollisionBox),
+    Circle {
+        center: nalgebra::Point2<f32>,
+        radius: f32,
+    },
+    Polygon(Vec<nalgebra::Point2<f32>>),
+}
+
+// The collider on the middle of the box, a polygon turned by the angle.
+// Without a collider it's the box.
+pub(crate) fn place(collider: Option<&Collider>, coll_box: &CollisionBox, angle: f32) -> Placed {
+    let center = coll_box.center();
+    match collider {
+        None | Some(Collider::Rect) => Placed::Rect(*coll_box),
+        Some(Collider::Circle { radius }) => Placed::Circle {
+            center,
+            radius: *radius,
+        },
+        Some(Collider::Polygon(points)) => {
+            let turn = nalgebra::Rotation2::new(angle);
+            Placed::Polygon(
+                points
+                    .iter()
+                    .map(|&(x, y)| center + turn * nalgebra::Vector2::new(x, y))
+                    .collect(),
+            )
+        }
+    }
+}
+
+// Whether two placed colliders overlap, touching edges not counting, the same
+// as boxes
+pub(crate) fn shapes_overlap(a: &Placed, b: &Placed) -> bool {
+    match (a, b) {
+        (Placed::Rect(a), Placed::Rect(b)) => overlaps(a, b),
+        (
+            Placed::Circle {
+                center: a,
+                radius: a_radius,
+            },
+            Placed::Circle {
+                center: b,
+                radius: b_radius,
+            },
+        ) => nalgebra::distance_squared(a, b) < (a_radius + b_radius).powi(2),
+        (Placed::Circle { center, radius }, Placed::Rect(rect))
+        | (Placed::Rect(rect), Placed::Circle { center, radius }) => {
+            circle_overlaps_rect(*center, *radius, rect)
+        }
+        (Placed::Polygon(a), Placed::Polygon(b)) => polygons_overlap(a, b),
+        (Placed::Polygon(polygon), Placed::Rect(rect))
+        | (Placed::Rect(rect), Placed::Polygon(polygon)) => {
+            polygons_overlap(polygon, &corners(rect))
+        }
+        (Placed::Polygon(polygon), Placed::Circle { center, radius })
+        | (Placed::Circle { center, radius }, Placed::Polygon(polygon)) => {
+            polygon_overlaps_circle(polygon, *center, *radius)
+        }
+    }
+}
+
+// the nearest point of the box to the middle of the circle is inside it
+fn circle_overlaps_rect(center: nalgebra::Point2<f32>, radius: f32, rect: &CollisionBox) -> bool {
+    let nearest = nalgebra::Point2::new(
+        center.x.max(rect.origin.x).min(rect.origin.x + rect.width),
+        center.y.max(rect.origin.y).min(rect.origin.y + rect.height),
+    );
+    nalgebra::distance_squared(&center, &nearest) < radius * radius
+}
+
+fn corners(rect: &CollisionBox) -> Vec<nalgebra::Point2<f32>> {
+    let (x, y) = (rect.origin.x, rect.origin.y);
+    vec![
+        nalgebra::Point2::new(x, y),
+        nalgebra::Point2::new(x + rect.width, y),
+        nalgebra::Point2::new(x + rect.width, y + rect.height),
+        nalgebra::Point2::new(x, y + rect.height),
+    ]
+}
+
+// the axes at right angles to each of the polygon's edges
+fn edge_normals(
+    polygon: &[nalgebra::Point2<f32>],
+) -> impl Iterator<Item = nalgebra::Vector2<f32>> + '_ {
+    polygon
+        .iter()
+        .zip(polygon.iter().cycle().skip(1))
+        .map(|(a, b)| {
+            let edge = b - a;
+            nalgebra::Vector2::new(-edge.y, edge.x)
+        })
+        .filter(|normal| normal.norm_squared() > std::f32::EPSILON)
+}
+
+// how far along the axis the polygon starts and ends
+fn project(polygon: &[nalgebra::Point2<f32>], axis: &nalgebra::Vector2<f32>) -> (f32, f32) {
+    polygon
+        .iter()
+        .map(|point| point.coords.dot(axis))
+        .fold((std::f32::MAX, std::f32::MIN), |(min, max), along| {
+            (min.min(along), max.max(along))
+        })
+}
+
+fn apart((a_min, a_max): (f32, f32), (b_min, b_max): (f32, f32)) -> bool {
+    a_max <= b_min || b_max <= a_min
+}
+
+// The separating axis test: two convex polygons overlap unless there's an
+// axis at right angles to one of their edges that they don't overlap along
+fn polygons_overlap(a: &[nalgebra::Point2<f32>], b: &[nalgebra::Point2<f32>]) -> bool {
+    if a.is_empty() || b.is_empty() {
+        return false;
+    }
+    !edge_normals(a)
+        .chain(edge_normals(b))
+        .any(|axis| apart(project(a, &axis), project(b, &axis)))
+}
+
+// The same test, with the axis from the polygon's nearest corner to the middle
+// of the circle standing in for the circle's edges
+fn polygon_overlaps_circle(
+    polygon: &[nalgebra::Point2<f32>],
+    center: nalgebra::Point2<f32>,
+    radius: f32,
+) -> bool {
+    let nearest = polygon.iter().min_by(|a, b| {
+        nalgebra::distance_squared(*a, &center)
+            .partial_cmp(&nalgebra::distance_squared(*b, &center))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    let nearest = match nearest {
+        Some(nearest) => *nearest,
+        None => return false,
+    };
+    let to_center = center - nearest;
+    let axes = edge_normals(polygon)
+        .chain(std::iter::once(to_center))
+        .filter(|axis| axis.norm_squared() > std::f32::EPSILON);
+    for axis in axes {
+        let axis = axis.normalize();
+        let along = center.coords.dot(&axis);
+        if apart(project(polygon, &axis), (along - radius, along + radius)) {
+            return false;
+        }
+    }
+    true
+}
+
+// How far box a has to move along each axis to get out of box b, towards
+// whichever side of b its middle is on, or None if they don't overlap
+pub(crate) fn separation(a: &CollisionBox, b: &CollisionBox) -> Option<nalgebra::Vector2<f32>> {
+    if !overlaps(a, b) {
+        return None;
+    }
+    let (a_center, b_center) = (a.center(), b.center());
+    let x = if a_center.x < b_center.x {
+        b.origin.x - (a.origin.x + a.width)
+    } else {
+        b.origin.x + b.width - a.origin.x
+    };
+    let y = if a_center.y < b_center.y {
+        b.origin.y - (a.origin.y + a.height)
+    } else {
+        b.origin.y + b.height - a.origin.y
+    };
+    Some(nalgebra::Vector2::new(x, y))
+}
+
+// The minimum translation vector, the shortest move that gets box a out of
+// box b, which is along whichever axis they overlap least on
+pub(crate) fn minimum_translation(
+    a: &CollisionBox,
+    b: &CollisionBox,
+) -> Option<nalgebra::Vector2<f32>> {
+    separation(a, b).map(|out| {
+        if out.x.abs() < out.y.abs() {
+            nalgebra::Vector2::new(out.x, 0.0)
+        } else {
+            nalgebra::Vector2::new(0.0, out.y)
+        }
+    })
+}
+
+// Whether a hitbox moving by the given amount this frame passed through a
+// hurtbox. Checking the whole path rather than just where the hitbox ends up
+// stops fast shots skipping over small targets. The hurtbox is grown by the
+// size of the hitbox so the hitbox's corner can be swept as a point.
+pub(crate) fn swept_hit(
+    hitbox: &CollisionBox,
+    delta: nalgebra::Vector2<f32>,
+    hurtbox: &CollisionBox,
+) -> bool {
+    let from = hitbox.origin;
+    let mut enter: f32 = 0.0;
+    let mut exit: f32 = 1.0;
+    let axes = [
+        (
+            from.x,
+            delta.x,
+            hurtbox.origin.x - hitbox.width,
+            hurtbox.origin.x + hurtbox.width,
+        ),
+        (
+            from.y,
+            delta.y,
+            hurtbox.origin.y - hitbox.height,
+            hurtbox.origin.y + hurtbox.height,
+        ),
+    ];
+    for (start, step, min, max) in axes.iter() {
+        if step.abs() < std::f32::EPSILON {
+            if start < min || start > max {
+                return false;
+            }
+            continue;
+        }
+        let a = (min - start) / step;
+        let b = (max - start) / step;
+        enter = enter.max(a.min(b));
+        exit = exit.min(a.max(b));
+        if enter > exit {
+            return false;
+        }
+    }
+    true
+}
@@ -0,0 +1,246 @@
+use crate::assets::Assets;
+use crate::fixed;
+use crate::health::Health;
+use crate::input_map::{self, InputMap};
+use crate::notifications::Notifications;
+use crate::patterns::{self, PatternLibrary};
+use crate::platform;
+use crate::prefab::{self, FromPrefab, Prefabs};
+use crate::settings::{self, Settings};
+use crate::spawner::Spawner;
+use crate::stealth::Cloaked;
+use crate::storage::Storage;
+use crate::theme::{self, UiTheme};
+use crate::weapons::Weapon;
+use ggez::event::{KeyCode, KeyMods};
+use ggez::Context;
+use specs::*;
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+// how often the files are looked at for changes
+const CHECK_EVERY: Duration = Duration::from_secs(1);
+
+const SHIP: &str = "/ship.PNG";
+
+// Picks up data files edited while the game runs, only built with the
+// dev-tools feature. About once a second the prefabs, input map, bullet
+// patterns and ship image under resources are checked, along with the
+// player's settings. A
+// file that changed is read again and replaces its resource, and a file that
+// fails to read leaves the old one in place.
+//
+// Ships already made from a prefab keep what they had, unless Ctrl+Shift+R
+// has been pressed to have them follow their prefab's health, weapon and
+// cloak as well. Like the cheats, that changes the world behind the replay's
+// back.
+pub(crate) struct HotReload {
+    last_check: Instant,
+    // when each watched file under resources was last changed
+    modified: HashMap<String, SystemTime>,
+    // the settings file as it was last seen in storage
+    settings: Option<Vec<u8>>,
+    refresh_entities: bool,
+}
+
+impl HotReload {
+    pub(crate) fn new(world: &World, storage: &dyn Storage) -> Self {
+        let modified = watched(world)
+            .into_iter()
+            .filter_map(|path| modified(&path).map(|time| (path, time)))
+            .collect();
+        HotReload {
+            last_check: Instant::now(),
+            modified,
+            settings: storage.read(settings::SETTINGS_FILE).ok(),
+            refresh_entities: false,
+        }
+    }
+
+    // Ctrl+Shift+R turns refreshing existing ships on and off
+    pub(crate) fn key_down(&mut self, world: &World, keycode: KeyCode, keymod: KeyMods) -> bool {
+        if keycode != KeyCode::R
+            || !keymod.contains(KeyMods::CTRL)
+            || !keymod.contains(KeyMods::SHIFT)
+        {
+            return false;
+        }
+        self.refresh_entities = !self.refresh_entities;
+        let message = if self.refresh_entities {
+            "Reloaded prefabs change existing ships"
+        } else {
+            "Reloaded prefabs only change new ships"
+        };
+        world.write_resource::<Notifications>().push(message);
+        true
+    }
+
+    pub(crate) fn update(&mut self, ctx: &mut Context, world: &World, storage: &dyn Storage) {
+        if self.last_check.elapsed() < CHECK_EVERY {
+            return;
+        }
+        self.last_check = Instant::now();
+
+        for path in watched(world) {
+            let time = match modified(&path) {
+                Some(time) => time,
+                None => continue,
+            };
+            if self.modified.get(&path) == Some(&time) {
+                continue;
+            }
+            self.modified.insert(path.clone(), time);
+            let reloaded = self.reload(ctx, world, &path);
+            let message = match reloaded {
+                Ok(()) => format!("Reloaded {}", path),
+                Err(err) => {
+                    println!("hot reload error {:?}", err);
+                    format!("Couldn't reload {}", path)
+                }
+            };
+            world.write_resource::<Notifications>().push(&message);
+        }
+
+        self.reload_settings(world, storage);
+    }
+
+    fn reload(&self, ctx: &mut Context, world: &World, path: &str) -> ggez::GameResult<()> {
+        match path {
+            "/prefabs.ron" => {
+                let prefabs = prefab::load(ctx)?;
+                if self.refresh_entities {
+                    refresh(world, &prefabs);
+                }
+                *world.write_resource::<Prefabs>() = prefabs;
+            }
+            "/input.ron" => *world.write_resource::<InputMap>() = input_map::load(ctx)?,
+            // the game's menus and HUD, not the front screens, which load
+            // theirs once at startup
+            theme::PATH => *world.write_resource::<UiTheme>() = UiTheme::read(ctx)?,
+            // every palette's copy is made again under the same keys, so ships
+            // already out are drawn with the new image as well
+            SHIP => {
+                let mut assets = world.write_resource::<Assets>();
+                assets.reload_image(ctx, SHIP)?;
+                let mut spawner = world.write_resource::<Spawner>();
+                let team_colors = spawner.team_colors.clone();
+                *spawner = Spawner::new(ctx, &mut assets, SHIP, team_colors)?;
+            }
+            _ => {
+                let pattern = patterns::load(ctx, path)?;
+                world
+                    .write_resource::<PatternLibrary>()
+                    .patterns
+                    .insert(pattern_name(path).to_owned(), Arc::new(pattern));
+            }
+        }
+        Ok(())
+    }
+
+    // The game saves the settings itself as they are changed, so they are
+    // only taken up when they differ from what the game already has
+    fn reload_settings(&mut self, world: &World, storage: &dyn Storage) {
+        let saved = storage.read(settings::SETTINGS_FILE).ok();
+        if saved == self.settings {
+            return;
+        }
+        self.settings = saved;
+        let mut reloaded = match settings::load(storage) {
+            Ok(reloaded) => reloaded,
+            Err(err) => {
+                println!("hot reload error {:?}", err);
+                return;
+            }
+        };
+        let mut current = world.write_resource::<Settings>();
+        reloaded.control_scheme = current.control_scheme;
+        if ron::ser::to_string(&reloaded).ok() == ron::ser::to_string(&*current).ok() {
+            return;
+        }
+        *current = reloaded;
+        world
+            .write_resource::<Notifications>()
+            .push("Reloaded settings");
+    }
+}
+
+// the prefabs, the input map, the theme, the ship image and every bullet
+// pattern in the library
+fn watched(world: &World) -> Vec<String> {
+    let mut paths = vec![
+        "/prefabs.ron".to_owned(),
+        "/input.ron".to_owned(),
+        theme::PATH.to_owned(),
+        SHIP.to_owned(),
+    ];
+    let library = world.read_resource::<PatternLibrary>();
+    let mut names: Vec<&String> = library.patterns.keys().collect();
+    names.sort();
+    paths.extend(
+        names
+            .into_iter()
+            .map(|name| format!("/patterns/{}.ron", name)),
+    );
+    paths
+}
+
+fn pattern_name(path: &str) -> &str {
+    path.trim_start_matches("/patterns/")
+        .trim_end_matches(".ron")
+}
+
+// when a file under resources was last changed, if it's there
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(platform::resource_dir().join(&path[1..]))
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+// Gives ships made from a prefab its new health, weapon and cloak. Ships keep
+// the same share of their health, and a ship that has dropped its cloak isn't
+// cloaked again unless the prefab newly has one.
+fn refresh(world: &World, prefabs: &Prefabs) {
+    let entities = world.entities();
+    let from_prefab = world.read_storage::<FromPrefab>();
+    let mut health = world.write_storage::<Health>();
+    let mut weapons = world.write_storage::<Weapon>();
+    let mut cloaked = world.write_storage::<Cloaked>();
+    let old_prefabs = world.read_resource::<Prefabs>();
+    for (entity, from) in (&entities, &from_prefab).join() {
+        let prefab = match prefabs.get(&from.0) {
+            Some(prefab) => prefab,
+            None => continue,
+        };
+        if let Some(health) = health.get_mut(entity) {
+            let fraction = health.fraction();
+            health.max = fixed::real(prefab.health);
+            health.current = fixed::real(prefab.health * fraction);
+        }
+        match &prefab.weapon {
+            Some(weapon) => {
+                weapons
+                    .insert(entity, weapon.clone())
+                    .unwrap_or_else(|err| {
+                        println!("hot reload error {:?}", err);
+                        None
+                    });
+            }
+            None => {
+                weapons.remove(entity);
+            }
+        }
+        let was_cloaked = old_prefabs.get(&from.0).map_or(false, |old| old.cloaked);
+        if prefab.cloaked && !was_cloaked {
+            cloaked
+                .insert(entity, Cloaked::default())
+                .unwrap_or_else(|err| {
+                    println!("hot reload error {:?}", err);
+                    None
+                });
+        } else if !prefab.cloaked {
+            cloaked.remove(entity);
+        }
+    }
+}
@@ -0,0 +1,111 @@
+use crate::camera::Camera;
+use crate::faction::{self, Faction};
+use crate::stealth::{self, Cloaked, Revealed};
+use crate::weapons::Projectile;
+use crate::{CollisionBox, ControllableTag, Position};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+
+// how far in from the edge of the screen the threat arrows sit
+const EDGE_MARGIN: f32 = 24.0;
+// threats further than this past the edge of the screen are drawn faintest
+const FADE_DISTANCE: f32 = 800.0;
+const MIN_ALPHA: f32 = 0.15;
+
+// Draws arrows around the edge of the screen pointing at enemies and incoming
+// projectiles that are out of view. Anything outside the part of the world the
+// Camera shows gets an arrow.
+pub(crate) fn draw_threat_indicators(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let camera = world.read_resource::<Camera>();
+    let view = camera.view();
+    let view_center = nalgebra::Point2::new(view.x + view.w / 2.0, view.y + view.h / 2.0);
+
+    let pos = world.read_storage::<Position>();
+    let coll_box = world.read_storage::<CollisionBox>();
+    let controlled = world.read_storage::<ControllableTag>();
+    let projectiles = world.read_storage::<Projectile>();
+    let cloaked = world.read_storage::<Cloaked>();
+    let revealed = world.read_storage::<Revealed>();
+    let factions = world.read_storage::<Faction>();
+    let entities = world.entities();
+
+    let (player, side) = match (&entities, &coll_box, &controlled).join().next() {
+        Some((entity, player_box, _)) => (player_box.center(), factions.get(entity).cloned()),
+        None => return Ok(()),
+    };
+
+    // other ships count as threats if they are hostile, as long as the player
+    // could know they are there
+    let mut threats: Vec<nalgebra::Point2<f32>> = (
+        &entities,
+        &coll_box,
+        !&controlled,
+        cloaked.maybe(),
+        revealed.maybe(),
+    )
+        .join()
+        .filter(|(entity, _, _, cloak, reveal)| {
+            !stealth::is_hidden(*cloak, *reveal)
+                && faction::hostile(side.as_ref(), factions.get(*entity))
+        })
+        .map(|(_, coll_box, _, _, _)| coll_box.center())
+        .collect();
+
+    // projectiles only count if a hostile ship fired them and they are heading
+    // toward the player
+    threats.extend(
+        (&pos, &projectiles)
+            .join()
+            .filter(|(pos, projectile)| {
+                projectile.active
+                    && faction::hostile(
+                        side.as_ref(),
+                        projectile.owner.and_then(|owner| factions.get(owner)),
+                    )
+                    && projectile.velocity.dot(&(player - pos.position)) > 0.0
+            })
+            .map(|(pos, _)| pos.position),
+    );
+
+    // the margin is on the screen, however far the camera is zoomed
+    let half_w = view.w / 2.0 - EDGE_MARGIN / camera.zoom;
+    let half_h = view.h / 2.0 - EDGE_MARGIN / camera.zoom;
+    let mut arrows = graphics::MeshBuilder::new();
+    let mut any_arrows = false;
+
+    for threat in threats {
+        if view.contains(threat) {
+            continue;
+        }
+
+        // walk from the middle of the screen toward the threat until we hit the
+        // inset edge, that's where the arrow goes
+        let to_threat = threat - view_center;
+        let dir = to_threat.normalize();
+        let t = (half_w / dir.x.abs()).min(half_h / dir.y.abs());
+        let tip = view_center + dir * t;
+
+        let alpha = (1.0 - (threat - tip).norm() / FADE_DISTANCE).max(MIN_ALPHA);
+        // the arrow itself is drawn on the screen
+        let tip = camera.to_screen(tip);
+        let side = nalgebra::Vector2::new(-dir.y, dir.x) * 7.0;
+        let points = [
+            tip + dir * 8.0,
+            tip - dir * 8.0 + side,
+            tip - dir * 8.0 - side,
+        ];
+        arrows.polygon(
+            graphics::DrawMode::fill(),
+            &points,
+            graphics::Color::new(1.0, 0.3, 0.2, alpha),
+        )?;
+        any_arrows = true;
+    }
+
+    if any_arrows {
+        let mesh = arrows.build(ctx)?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+    }
+    Ok(())
+}
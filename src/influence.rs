@@ -0,0 +1,174 @@
+use crate::faction::{self, Faction};
+use crate::health::Health;
+use crate::time::DeltaTime;
+use crate::weapons::Projectile;
+use crate::{CollisionBox, Position};
+use ggez::nalgebra;
+use specs::*;
+use std::collections::BTreeMap;
+
+// the grid covers the default window in 50 pixel cells
+const CELL_SIZE: f32 = 50.0;
+const COLUMNS: usize = 16;
+const ROWS: usize = 12;
+// seconds between rebuilds, positions don't need to be exact for this
+const UPDATE_INTERVAL: f32 = 0.5;
+// how far a ship's influence reaches, fading out to nothing at the edge
+const SHIP_REACH: f32 = 300.0;
+// projectiles only count for the cell they are in, and for a lot less
+const PROJECTILE_WEIGHT: f32 = 0.25;
+
+// A coarse picture of who controls which part of the arena. Every cell holds
+// how much influence each faction has there, so "how dangerous is this spot for
+// Red" is the sum of what every side hostile to Red has on it. It's rebuilt a
+// couple of times a second, which is plenty for picking where to go or spawn.
+// The layers are kept in faction order so threats are always summed the same
+// way, floats don't come out quite the same added up in a different order.
+#[derive(Debug, Default)]
+pub(crate) struct InfluenceMap {
+    layers: BTreeMap<Faction, Vec<f32>>,
+}
+
+impl InfluenceMap {
+    fn cell_center(index: usize) -> nalgebra::Point2<f32> {
+        let (column, row) = (index % COLUMNS, index / COLUMNS);
+        nalgebra::Point2::new(
+            (column as f32 + 0.5) * CELL_SIZE,
+            (row as f32 + 0.5) * CELL_SIZE,
+        )
+    }
+
+    fn cell_at(point: nalgebra::Point2<f32>) -> Option<usize> {
+        if point.x < 0.0 || point.y < 0.0 {
+            return None;
+        }
+        let (column, row) = (
+            (point.x / CELL_SIZE) as usize,
+            (point.y / CELL_SIZE) as usize,
+        );
+        if column < COLUMNS && row < ROWS {
+            Some(row * COLUMNS + column)
+        } else {
+            None
+        }
+    }
+
+    fn layer(&mut self, faction: Faction) -> &mut Vec<f32> {
+        self.layers
+            .entry(faction)
+            .or_insert_with(|| vec![0.0; COLUMNS * ROWS])
+    }
+
+    // how much danger the given side is in at a cell
+    fn cell_threat(&self, side: Option<&Faction>, index: usize) -> f32 {
+        self.layers
+            .iter()
+            .filter(|(faction, _)| faction::hostile(side, Some(faction)))
+            .map(|(_, layer)| layer[index])
+            .sum()
+    }
+
+    pub(crate) fn threat(&self, side: Option<&Faction>, point: nalgebra::Point2<f32>) -> f32 {
+        Self::cell_at(point).map_or(0.0, |index| self.cell_threat(side, index))
+    }
+
+    // The least threatened spot within range of a point, for an AI looking for
+    // somewhere safer to be
+    pub(crate) fn safest_near(
+        &self,
+        side: Option<&Faction>,
+        point: nalgebra::Point2<f32>,
+        range: f32,
+    ) -> nalgebra::Point2<f32> {
+        (0..COLUMNS * ROWS)
+            .map(|index| (index, Self::cell_center(index)))
+            .filter(|(_, center)| (center - point).norm() <= range)
+            .min_by(|a, b| {
+                self.cell_threat(side, a.0)
+                    .partial_cmp(&self.cell_threat(side, b.0))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map_or(point, |(_, center)| center)
+    }
+
+    // Somewhere for the given side to bring in new ships, as far from trouble
+    // and from the point to avoid (usually the player) as the arena allows
+    pub(crate) fn spawn_point(
+        &self,
+        side: Option<&Faction>,
+        avoid: nalgebra::Point2<f32>,
+    ) -> nalgebra::Point2<f32> {
+        let far = (CELL_SIZE * COLUMNS as f32).max(CELL_SIZE * ROWS as f32);
+        let score = |index: usize| {
+            let distance = (Self::cell_center(index) - avoid).norm() / far;
+            self.cell_threat(side, index) - distance
+        };
+        (0..COLUMNS * ROWS)
+            .min_by(|a, b| {
+                score(*a)
+                    .partial_cmp(&score(*b))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .map_or(avoid, Self::cell_center)
+    }
+}
+
+// Rebuilds the influence map from where every ship and projectile is
+#[derive(Default)]
+pub(crate) struct InfluenceSystem {
+    next_update: f32,
+}
+
+impl<'a> System<'a> for InfluenceSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Write<'a, InfluenceMap>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Projectile>,
+        ReadStorage<'a, Faction>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (delta, mut map, pos, coll_box, health, projectiles, factions) = data;
+
+        self.next_update -= delta.seconds;
+        if self.next_update > 0.0 {
+            return;
+        }
+        self.next_update = UPDATE_INTERVAL;
+
+        for layer in map.layers.values_mut() {
+            for cell in layer.iter_mut() {
+                *cell = 0.0;
+            }
+        }
+
+        // a ship's influence falls off with distance, and with the damage it
+        // has taken
+        for (coll_box, health, faction) in (&coll_box, health.maybe(), &factions).join() {
+            let center = coll_box.center();
+            let strength = health.map_or(1.0, |h| h.fraction());
+            let layer = map.layer(*faction);
+            for (index, cell) in layer.iter_mut().enumerate() {
+                let distance = (InfluenceMap::cell_center(index) - center).norm();
+                if distance < SHIP_REACH {
+                    *cell += strength * (1.0 - distance / SHIP_REACH);
+                }
+            }
+        }
+
+        for (pos, projectile) in (&pos, &projectiles).join() {
+            if !projectile.active {
+                continue;
+            }
+            if let (Some(faction), Some(index)) = (
+                projectile.owner.and_then(|owner| factions.get(owner)),
+                InfluenceMap::cell_at(pos.position),
+            ) {
+                map.layer(*faction)[index] += PROJECTILE_WEIGHT;
+            }
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use crate::controls::ControlScheme;
+use crate::data;
+use ggez::event::KeyCode;
+use ggez::{filesystem, Context, GameResult};
+use serde::{Deserialize, Serialize};
+
+const PATH: &str = "/input.ron";
+
+// What a held or pressed key means to the game, whichever key it is
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum InputAction {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Fire,
+    // holds the world still until pressed again
+    Pause,
+}
+
+impl InputAction {
+    // what the player uses the key for, as the controls screen puts it
+    pub(crate) fn purpose(self) -> &'static str {
+        match self {
+            InputAction::MoveUp
+            | InputAction::MoveDown
+            | InputAction::MoveLeft
+            | InputAction::MoveRight => "flying",
+            InputAction::Fire => "firing",
+            InputAction::Pause => "pausing",
+        }
+    }
+}
+
+// The keys that fly the ship under each control scheme, from resources/input.ron.
+// Like the binding profiles, keys go by the names ggez prints for them, e.g.
+//
+//     (
+//         classic: [("Up", MoveUp), ("Down", MoveDown), ("Space", Fire)],
+//         twin_stick: [("W", MoveUp), ("S", MoveDown), ("Escape", Pause)],
+//     )
+//
+// A scheme left out of the file keeps its default keys. TwinStick fires with
+// the mouse as well, whatever the file says.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct InputMap {
+    pub(crate) classic: Vec<(String, InputAction)>,
+    pub(crate) twin_stick: Vec<(String, InputAction)>,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        let keys = |keys: &[(&str, InputAction)]| {
+            keys.iter()
+                .map(|(key, action)| ((*key).to_owned(), *action))
+                .collect()
+        };
+        InputMap {
+            classic: keys(&[
+                ("Up", InputAction::MoveUp),
+                ("Down", InputAction::MoveDown),
+                ("Left", InputAction::MoveLeft),
+                ("Right", InputAction::MoveRight),
+                ("Space", InputAction::Fire),
+                ("Escape", InputAction::Pause),
+            ]),
+            twin_stick: keys(&[
+                ("W", InputAction::MoveUp),
+                ("S", InputAction::MoveDown),
+                ("A", InputAction::MoveLeft),
+                ("D", InputAction::MoveRight),
+                ("Escape", InputAction::Pause),
+            ]),
+        }
+    }
+}
+
+impl InputMap {
+    // what the key means under the control scheme, if anything
+    pub(crate) fn action(&self, scheme: ControlScheme, keycode: KeyCode) -> Option<InputAction> {
+        self.named(scheme, &format!("{:?}", keycode))
+    }
+
+    // the same, for a key by the name ggez prints for it
+    pub(crate) fn named(&self, scheme: ControlScheme, name: &str) -> Option<InputAction> {
+        let keys = match scheme {
+            ControlScheme::Classic => &self.classic,
+            ControlScheme::TwinStick => &self.twin_stick,
+        };
+        keys.iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, action)| *action)
+    }
+}
+
+// Without an input file the default keys are used
+pub(crate) fn load(ctx: &mut Context) -> GameResult<InputMap> {
+    if !filesystem::exists(ctx, PATH) {
+        return Ok(InputMap::default());
+    }
+    data::load(ctx, PATH)
+}
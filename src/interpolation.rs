@@ -0,0 +1,135 @@
+use crate::camera::Camera;
+use crate::components::{CollisionBox, Position};
+use crate::DESIRED_FPS;
+use ggez::nalgebra;
+use ggez::{timer, Context};
+use specs::*;
+use specs_derive::*;
+
+// anything that moved further than this in one update jumped there, e.g.
+// wrapping round the level or a pooled shot being fired again, and is drawn
+// where it is rather than sliding across
+const MAX_STEP: f32 = 100.0;
+
+// Where an entity was before the last update, so it can be drawn part of the
+// way from there to where it is now. Kept up to date by remember_positions.
+#[derive(Component, Clone, Copy, Debug)]
+#[storage(VecStorage)]
+pub(crate) struct PreviousPosition {
+    pub(crate) position: nalgebra::Point2<f32>,
+}
+
+// How far a frame is drawn between the last update and the next, from 0 to 1.
+// Updates come at a fixed rate and frames whenever the screen is ready, so
+// drawing things where the last update left them stutters whenever the two
+// rates don't line up. Drawing them part of the way between where the last
+// two updates left them, by how much of the next update's time has gone by,
+// keeps the motion smooth at any refresh rate, at the cost of showing the
+// world up to an update behind.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Interpolation {
+    pub(crate) alpha: f32,
+}
+
+impl Default for Interpolation {
+    fn default() -> Self {
+        Interpolation { alpha: 1.0 }
+    }
+}
+
+impl Interpolation {
+    // from the time ggez has put by towards the next update
+    pub(crate) fn measure(ctx: &mut Context) -> Self {
+        let remaining = timer::remaining_update_time(ctx).as_secs_f32();
+        Interpolation {
+            alpha: (remaining * DESIRED_FPS as f32).max(0.0).min(1.0),
+        }
+    }
+
+    // where to draw something that's at the position now
+    pub(crate) fn blend(
+        &self,
+        previous: Option<&PreviousPosition>,
+        position: nalgebra::Point2<f32>,
+    ) -> nalgebra::Point2<f32> {
+        match previous {
+            Some(previous) if nalgebra::distance(&previous.position, &position) <= MAX_STEP => {
+                previous.position + (position - previous.position) * self.alpha
+            }
+            _ => position,
+        }
+    }
+}
+
+// Where entities are drawn this frame, blended the same way RenderSystem
+// blends their sprites. Anything drawn over or around an entity goes through
+// it too, so it keeps up with the sprite rather than trailing an update
+// behind.
+pub(crate) struct Drawn<'a> {
+    interpolation: Interpolation,
+    positions: ReadStorage<'a, Position>,
+    previous: ReadStorage<'a, PreviousPosition>,
+}
+
+impl<'a> Drawn<'a> {
+    pub(crate) fn new(world: &'a World) -> Self {
+        Drawn {
+            interpolation: *world.read_resource::<Interpolation>(),
+            positions: world.read_storage::<Position>(),
+            previous: world.read_storage::<PreviousPosition>(),
+        }
+    }
+
+    // where the entity is drawn, if it has a Position
+    pub(crate) fn at(&self, entity: Entity) -> Option<nalgebra::Point2<f32>> {
+        self.positions.get(entity).map(|position| {
+            self.interpolation
+                .blend(self.previous.get(entity), position.position)
+        })
+    }
+
+    // how far from its Position the entity is drawn, for things placed
+    // relative to it like its CollisionBox
+    pub(crate) fn shift(&self, entity: Entity) -> nalgebra::Vector2<f32> {
+        match (self.at(entity), self.positions.get(entity)) {
+            (Some(at), Some(position)) => at - position.position,
+            _ => nalgebra::Vector2::zeros(),
+        }
+    }
+
+    // the entity's box where it's drawn
+    pub(crate) fn coll_box(&self, entity: Entity, coll_box: &CollisionBox) -> CollisionBox {
+        CollisionBox {
+            origin: coll_box.origin + self.shift(entity),
+            ..*coll_box
+        }
+    }
+}
+
+// Notes where everything is, and where the camera is looking, before an
+// update moves them. While the world is held still this is done every frame
+// instead, so nothing is drawn sliding toward where it already is.
+pub(crate) fn remember_positions(world: &World) {
+    let entities = world.entities();
+    let positions = world.read_storage::<Position>();
+    let mut previous = world.write_storage::<PreviousPosition>();
+    for (position, previous) in (&positions, &mut previous).join() {
+        previous.position = position.position;
+    }
+    // anything new since the last update
+    let new: Vec<(Entity, nalgebra::Point2<f32>)> = (&entities, &positions, !&previous)
+        .join()
+        .map(|(entity, position, _)| (entity, position.position))
+        .collect();
+    for (entity, position) in new {
+        previous
+            .insert(entity, PreviousPosition { position })
+            .unwrap_or_else(|err| {
+                println!("previous position error {:?}", err);
+                None
+            });
+    }
+
+    let mut camera = world.write_resource::<Camera>();
+    camera.previous_center = camera.center;
+}
@@ -0,0 +1,41 @@
+use crate::ambient::AmbientConfig;
+use crate::data;
+use crate::procgen::Generator;
+use crate::scene::Scene;
+use crate::triggers::{Trigger, Zone};
+use crate::tutorial::Prompt;
+use crate::waves::Escalation;
+use ggez::{filesystem, Context, GameResult};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// What a game mode's level looks like, from its RON file under
+// resources/levels. Anything left out of the file is left at its default.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub(crate) struct Level {
+    // width and height, for a level bigger than the window that the camera
+    // follows the player around
+    pub(crate) size: Option<(f32, f32)>,
+    pub(crate) ambient: AmbientConfig,
+    // hints shown to the player as they play, see tutorial.rs
+    pub(crate) tutorial: Vec<Prompt>,
+    // named areas for the triggers to refer to
+    pub(crate) zones: BTreeMap<String, Zone>,
+    // the level's script, see triggers.rs
+    pub(crate) triggers: Vec<Trigger>,
+    // rocks, hazards, pickups and enemies, see scene.rs
+    pub(crate) scene: Scene,
+    // makes up more of the scene from the game's seed, see procgen.rs
+    pub(crate) generate: Option<Generator>,
+    // how the waves grow in an endless game, see waves.rs
+    pub(crate) waves: Option<Escalation>,
+}
+
+// A mode without a level file plays in an empty level
+pub(crate) fn load(ctx: &mut Context, path: &str) -> GameResult<Level> {
+    if !filesystem::exists(ctx, path) {
+        return Ok(Level::default());
+    }
+    data::load(ctx, path)
+}
@@ -0,0 +1,1800 @@
+mod ai;
+mod ambient;
+mod arena;
+mod assets;
+mod atlas;
+mod audio;
+mod audit;
+mod behavior;
+mod budget;
+mod builder;
+mod bullet_time;
+mod bundles;
+mod camera;
+#[cfg(feature = "dev-tools")]
+mod cheats;
+mod combo;
+pub mod components;
+mod controls;
+mod cooldowns;
+mod data;
+mod debug_overlay;
+#[cfg(feature = "debug-ui")]
+mod debug_ui;
+mod dialog;
+#[cfg(feature = "dev-tools")]
+mod diff;
+mod editor;
+mod events;
+mod faction;
+mod fixed;
+mod floating_text;
+mod front;
+mod game_mode;
+mod gamepads;
+mod ghost;
+mod glyphs;
+mod graze;
+mod health;
+mod heatmap;
+mod hitbox;
+#[cfg(feature = "dev-tools")]
+mod hot_reload;
+mod hud;
+mod influence;
+mod input_map;
+mod interpolation;
+mod level;
+mod lifetime;
+mod listener;
+mod melee;
+mod memory;
+mod minimap;
+mod music;
+mod notifications;
+mod outline;
+mod palette;
+mod patterns;
+mod pause;
+mod platform;
+mod prefab;
+mod procgen;
+mod profiler;
+mod quality;
+mod quarantine;
+mod quicksave;
+mod radar;
+mod rebind;
+mod render;
+mod replay;
+pub mod resources;
+mod rng;
+mod sandbox;
+mod saves;
+mod scene;
+mod score;
+mod settings;
+// only the presets are used when there is no audio to play them on
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+mod sfxr;
+mod shaders;
+mod spatial;
+mod spawn_queue;
+mod spawner;
+mod status;
+mod stealth;
+mod storage;
+mod systems;
+mod targeting;
+mod tasks;
+mod telemetry;
+mod theme;
+mod time;
+#[cfg(feature = "touch")]
+mod touch;
+mod transition;
+mod triggers;
+mod tutorial;
+mod tweakables;
+mod tween;
+mod utility_ai;
+#[cfg(debug_assertions)]
+mod validation;
+mod watchdog;
+mod waves;
+mod weapons;
+
+use ai::{AiControlled, AiSystem, ThinkSystem};
+use ambient::{Ambient, AmbientSystem};
+use atlas::Atlas;
+pub use audio::AudioSystem;
+use audio::{BumpSystem, SoundBank};
+use behavior::{BehaviorSystem, BehaviorTree};
+use budget::{BudgetSystem, View};
+use bullet_time::{BulletTime, BulletTimeSystem};
+use camera::{Camera, CameraFollowSystem};
+use combo::{Combo, ComboSystem};
+use controls::{Action, ActiveDevice, Aim, AimSystem, ControlScheme, Device};
+use cooldowns::{CooldownSystem, Cooldowns};
+use debug_overlay::DebugOverlay;
+use editor::{Editor, Group};
+use events::EventAuditSystem;
+use faction::Faction;
+use floating_text::FloatingText;
+use game_mode::{DailyRun, GameMode};
+use gamepads::Gamepads;
+use ggez::event::{Axis, Button, GamepadId, KeyCode, KeyMods, MouseButton};
+use ggez::*;
+use ghost::{Ghost, GhostSystem};
+use glyphs::Glyphs;
+use graze::Spark;
+use health::{Damage, DamageSystem, Health, HealthSystem};
+use heatmap::Heatmap;
+use hitbox::{Hitbox, Hurtbox};
+use influence::{InfluenceMap, InfluenceSystem};
+use input_map::{InputAction, InputMap};
+use interpolation::{Interpolation, PreviousPosition};
+use lifetime::LifetimeSystem;
+use listener::{Cue, Listener, SoundCues};
+use melee::{Attack, HitStop, MeleeSystem};
+use memory::{AssetSizes, MemoryOverlay};
+use music::{Intensity, IntensitySystem, MusicDirector};
+use notifications::{NotificationSystem, Notifications};
+use outline::Selected;
+use patterns::{BulletPattern, PatternLibrary, PatternSystem};
+use pause::PauseMenu;
+use platform::Sound;
+use prefab::FromPrefab;
+pub use profiler::SystemTimes;
+use profiler::{run_timed, Timed};
+use quality::{Quality, QualityController};
+use quarantine::{NanGuard, Quarantined};
+use quicksave::{QuicksaveAllocator, QuicksaveMarker};
+use radar::{Pulse, RadarPing, RadarSystem};
+use render::RenderSystem;
+use replay::{InputLog, Playback, ReplayInput};
+use rng::GameRng;
+use sandbox::SandboxTools;
+use scene::{Hazard, Pickup, SceneSystem};
+use score::PlayerScore;
+use settings::Settings;
+use shaders::{Desaturate, Outline};
+use spatial::{BroadPhaseSystem, SpatialGrid};
+use spawner::Spawner;
+use specs::*;
+use status::{StatusEffects, StatusSystem};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Instant;
+use stealth::{CloakSystem, Cloaked, RevealSystem, Revealed};
+use storage::Storage;
+use targeting::{Homing, HomingSystem, LockOn, LockOnSystem};
+use tasks::TaskSystem;
+use telemetry::{Telemetry, TelemetrySystem};
+use theme::UiTheme;
+use time::{DeltaTime, GameClock, TimeMultiplier, TimeScale};
+#[cfg(feature = "touch")]
+use touch::TouchControls;
+use transition::{SceneChange, Transition};
+use triggers::{TriggerScriptSystem, Triggers};
+use tutorial::{PromptSystem, Prompts};
+use tweakables::Tweakables;
+use tween::{Tween, TweenSystem};
+use utility_ai::{UtilityAi, UtilityAiSystem};
+#[cfg(debug_assertions)]
+use validation::ValidationSystem;
+use watchdog::FrameWatchdog;
+use waves::{WaveDirector, WaveSystem};
+use weapons::{FireSystem, Projectile, ProjectilePool, ProjectileStats, ProjectileSystem, Weapon};
+
+const DESIRED_FPS: u32 = 60;
+
+pub use builder::GameBuilder;
+use builder::{Extensions, NamedSystem};
+#[cfg(feature = "dev-tools")]
+use cheats::Cheats;
+pub use components::*;
+#[cfg(feature = "debug-ui")]
+use debug_ui::DebugUi;
+#[cfg(feature = "dev-tools")]
+use diff::ComponentDiff;
+#[cfg(feature = "dev-tools")]
+use hot_reload::HotReload;
+pub use resources::{Assets, Direction, MusicTrack, PlaySound, SoundQueue, SpawnQueue};
+use systems::{
+    AnimationSystem, BoundsSystem, CollisionEvent, CollisionStats, CollisionSystem, FitBoxSystem,
+    MovementSystem, SolidContacts, TransformSystem,
+};
+#[cfg(feature = "dev-tools")]
+use tweakables::TweakPanel;
+
+struct MainState {
+    specs_world: World,
+    player_input: Direction,
+    player_aim: Aim,
+    // actions performed since the last update
+    actions: Vec<Action>,
+    // the input of every update so far, for saving as a replay
+    recording: InputLog,
+    // a replay being played instead of taking the player's input
+    playback: Option<Playback>,
+    // the device whose binding profile is in use
+    pub(crate) active_device: Device,
+    #[cfg(feature = "touch")]
+    touch_controls: TouchControls,
+    // the game's systems, those that run before the game mode's rules and
+    // those that run after
+    dispatcher: Dispatcher<'static, 'static>,
+    late_dispatcher: Dispatcher<'static, 'static>,
+    // the systems a consumer of the crate added, run after the rest
+    extra_systems: Vec<NamedSystem>,
+    #[cfg(debug_assertions)]
+    validation_system: ValidationSystem,
+    render_system: RenderSystem,
+    projectile_batch: graphics::spritebatch::SpriteBatch,
+    combo_sound: Box<dyn Sound>,
+    graze_sound: Box<dyn Sound>,
+    laser_sound: Box<dyn Sound>,
+    explosion_sound: Box<dyn Sound>,
+    // the AudioSystems a consumer of the crate added, run before the
+    // sound_bank
+    audio_systems: Vec<Box<dyn AudioSystem>>,
+    // whatever was asked for through the SoundQueue
+    sound_bank: SoundBank,
+    music: MusicDirector,
+    listener: Listener,
+    watchdog: FrameWatchdog,
+    memory_overlay: MemoryOverlay,
+    debug_overlay: DebugOverlay,
+    heatmap: Heatmap,
+    editor: Editor,
+    // the world holds still while paused, as it does while being edited,
+    // under the pause menu
+    pause_menu: Option<PauseMenu>,
+    // something picked that the front screens carry out, like Restart
+    pub(crate) request: Option<pause::Choice>,
+    #[cfg(feature = "dev-tools")]
+    cheats: Cheats,
+    #[cfg(feature = "dev-tools")]
+    hot_reload: HotReload,
+    #[cfg(feature = "dev-tools")]
+    component_diff: ComponentDiff,
+    #[cfg(feature = "dev-tools")]
+    tweak_panel: TweakPanel,
+    #[cfg(feature = "debug-ui")]
+    debug_ui: DebugUi,
+    quality_controller: QualityController,
+    status_atlas: Atlas,
+    glyphs: Glyphs,
+    scene_canvas: graphics::Canvas,
+    transition: Transition,
+    desaturate: graphics::Shader<Desaturate>,
+    outline: graphics::Shader<Outline>,
+    game_mode: Box<dyn GameMode>,
+    // where the settings are saved to
+    storage: Rc<dyn Storage>,
+}
+
+impl MainState {
+    fn new(
+        ctx: &mut Context,
+        mut game_mode: Box<dyn GameMode>,
+        mut settings: Settings,
+        rng: GameRng,
+        storage: Rc<dyn Storage>,
+        extensions: Rc<Extensions>,
+    ) -> GameResult<MainState> {
+        let mut asset_sizes = AssetSizes::default();
+        let mut assets = Assets::default();
+
+        // create a new world
+        let mut world = World::new();
+        world.register::<Position>();
+        world.register::<Velocity>();
+        world.register::<Acceleration>();
+        world.register::<CollisionBox>();
+        world.register::<ImageHandle>();
+        world.register::<Animation>();
+        world.register::<Tasks>();
+        world.register::<ControllableTag>();
+        world.register::<Solid>();
+        world.register::<Bounded>();
+        world.register::<Collider>();
+        world.register::<Scale>();
+        world.register::<BoxOffset>();
+        world.register::<Rotation>();
+        world.register::<Transform>();
+        world.register::<ZOrder>();
+        world.register::<Weapon>();
+        world.register::<Projectile>();
+        world.register::<Homing>();
+        world.register::<Tween>();
+        world.register::<Quarantined>();
+        world.register::<Selected>();
+        world.register::<Group>();
+        world.register::<FromPrefab>();
+        world.register::<Lifetime>();
+        world.register::<PreviousPosition>();
+        world.register::<FloatingText>();
+        world.register::<Pulse>();
+        world.register::<Cloaked>();
+        world.register::<Revealed>();
+        world.register::<Faction>();
+        world.register::<Health>();
+        world.register::<Damage>();
+        world.register::<AiControlled>();
+        world.register::<BehaviorTree>();
+        world.register::<UtilityAi>();
+        world.register::<BulletPattern>();
+        world.register::<Spark>();
+        world.register::<Hitbox>();
+        world.register::<Hurtbox>();
+        world.register::<Attack>();
+        world.register::<TimeMultiplier>();
+        world.register::<StatusEffects>();
+        world.register::<Cooldowns>();
+        world.register::<Hazard>();
+        world.register::<Pickup>();
+        world.register::<QuicksaveMarker>();
+
+        // create our spaceship Entities
+        let spawner = Spawner::new(ctx, &mut assets, "/ship.PNG", settings.team_colors.clone())?;
+        if let Some(ship) = assets.image("/ship.PNG") {
+            asset_sizes.texture("/ship.PNG", assets.get(ship));
+        }
+        spawner
+            .ship(world.create_entity())
+            .at(75.0, 100.0)
+            .weapon(0.2, 600.0, 10.0)
+            .faction(Faction::Blue)
+            .controllable()
+            .build();
+
+        // The second ship does not require the ControllableTag, the AI flies it
+        // instead. It stays put but turns to track the player and shoots at them,
+        // and the player can't fly through it.
+        spawner
+            .ship(world.create_entity())
+            .at(275.0, 100.0)
+            .weapon(0.6, 400.0, 10.0)
+            .faction(Faction::Red)
+            .with(AiControlled::new(settings.difficulty))
+            .with(Solid)
+            .build();
+
+        // A cloaked elite lurking further out, only visible when it bumps into
+        // something, a radar ping catches it or it drops the cloak to attack.
+        // Its behavior tree decides when to do that.
+        let elite = behavior::load(ctx, "/behaviors/elite.ron")?;
+        spawner
+            .ship(world.create_entity())
+            .at(475.0, 350.0)
+            .weapon(0.4, 500.0, 15.0)
+            .faction(Faction::Red)
+            .with(AiControlled::new(settings.difficulty))
+            .with(BehaviorTree::new(elite))
+            .with(Cloaked::default())
+            .build();
+
+        // A skirmisher that weighs up whether to attack, back off or circle
+        // around using the utility AI
+        spawner
+            .ship(world.create_entity())
+            .at(650.0, 150.0)
+            .weapon(1.0, 450.0, 10.0)
+            .faction(Faction::Red)
+            .with(AiControlled::new(settings.difficulty))
+            .with(UtilityAi::default())
+            .build();
+        world.insert(spawner);
+        world.insert(assets);
+
+        // Create 2 structs to manage player input
+        // One belongs to MainState and is kept up to date by the ggez event handling
+        // The other belongs to the specs world and tracks the MainState struct
+        let player_input = Direction::new();
+        let player_input_world = Direction::new();
+
+        // register the player controller with the world
+        // add_resource is deprecated TODO - PR to update the book?
+        world.insert(player_input_world);
+
+        // aiming is mirrored the same way as the Direction struct above
+        let player_aim = Aim::default();
+        world.insert(player_aim);
+        settings.control_scheme = settings.profile(&Device::Keyboard).control_scheme;
+        let quality = Quality::new(settings.quality);
+        world.insert(Telemetry::new(settings.telemetry, game_mode.name()));
+        world.insert(settings);
+        world.insert(rng);
+        world.insert(ActiveDevice::default());
+        world.insert(LockOn::default());
+        world.insert(Notifications::default());
+        world.insert(UiTheme::load(ctx));
+        world.insert(Tweakables::load(ctx));
+        world.insert(Gamepads::default());
+        world.insert(RadarPing::default());
+        world.insert(InfluenceMap::default());
+        world.insert(WaveDirector::default());
+        world.insert(ProjectilePool::default());
+        world.insert(QuicksaveAllocator::default());
+        world.insert(ProjectileStats::default());
+        world.insert(CollisionStats::default());
+        world.insert(SolidContacts::default());
+        world.insert(PlayerScore::default());
+        world.insert(HitStop::default());
+        events::register(&mut world);
+        world.insert(SpatialGrid::default());
+        world.insert(SpawnQueue::default());
+        world.insert(Combo::default());
+        world.insert(SoundCues::default());
+        world.insert(SoundQueue::default());
+        world.insert(MusicTrack::default());
+        world.insert(SceneChange::default());
+        world.insert(Intensity::default());
+        world.insert(TimeScale::default());
+        world.insert(DeltaTime::default());
+        world.insert(Interpolation::default());
+        world.insert(GameClock::default());
+        world.insert(BulletTime::default());
+        world.insert(SystemTimes::default());
+        world.insert(View::default());
+        world.insert(quality);
+
+        // bullet patterns for bosses, handed out by the wave director
+        let mut library = PatternLibrary::default();
+        library.patterns.insert(
+            "boss".to_owned(),
+            Arc::new(patterns::load(ctx, "/patterns/boss.ron")?),
+        );
+        world.insert(library);
+
+        // the game mode adds its own objectives on top of the ships
+        game_mode.setup(&mut world);
+        println!("Game mode: {}", game_mode.name());
+        let level = level::load(ctx, game_mode.level())?;
+        world.insert(Ambient::new(level.ambient));
+        world.insert(Prompts::new(level.tutorial));
+        world.insert(Triggers::new(level.zones, level.triggers));
+        world.insert(ghost::load(&*storage, game_mode.name()));
+        let bounds = match level.size {
+            Some((width, height)) => graphics::Rect::new(0.0, 0.0, width, height),
+            None => graphics::Rect::new(0.0, 0.0, 800.0, 600.0),
+        };
+        world.insert(Camera::new(bounds));
+        world.write_resource::<WaveDirector>().escalation = level.waves;
+        world.insert(input_map::load(ctx)?);
+        world.insert(prefab::load(ctx)?);
+
+        // what's in the level besides the ships, some of it made up from the
+        // seed if the level asks for that
+        let mut level_scene = level.scene;
+        if let Some(generator) = &level.generate {
+            let seed = world.read_resource::<GameRng>().seed();
+            level_scene.extend(generator.generate(seed));
+        }
+        scene::build(ctx, &mut world, &level_scene)?;
+
+        // and anything a consumer of the crate added goes on top of both
+        extensions.setup(&mut world);
+
+        // the camera starts out on the player rather than catching up with them
+        let player = {
+            let coll_box = world.read_storage::<CollisionBox>();
+            let controlled = world.read_storage::<ControllableTag>();
+            (&coll_box, &controlled)
+                .join()
+                .next()
+                .map(|(player_box, _)| player_box.center())
+        };
+        if let Some(player) = player {
+            world.write_resource::<Camera>().look_at(player);
+        }
+
+        // The systems run in phases, one after another. Within a phase,
+        // systems that don't share any data run side by side on the thread
+        // pool when the parallel feature is on, and the dependencies keep
+        // each system after the ones whose results it needs. Systems that
+        // create entities are chained one after another, so new entities get
+        // the same ids however the threads finish and replays and the audit
+        // stay deterministic. Each stage that moves things is followed by the
+        // NaN guard, so a bad number is caught before anything else reads it.
+        // Every system is timed for the frame watchdog.
+        let guard = |stage: &'static str| Timed::new(NanGuard::after(stage), "nan guard");
+        let dispatcher = DispatcherBuilder::new()
+            // time
+            .with(
+                Timed::new(BulletTimeSystem::new(&world), "bullet time"),
+                "bullet time",
+                &[],
+            )
+            .with(Timed::new(CooldownSystem, "cooldowns"), "cooldowns", &[])
+            .with_barrier()
+            // movement
+            .with(
+                Timed::new(MovementSystem::new(&world), "movement"),
+                "movement",
+                &[],
+            )
+            .with(guard("movement"), "movement guard", &["movement"])
+            .with(
+                Timed::new(BoundsSystem, "bounds"),
+                "bounds",
+                &["movement guard"],
+            )
+            .with_barrier()
+            // steering and AI
+            .with(Timed::new(AimSystem, "aim"), "aim", &[])
+            .with(
+                Timed::new(InfluenceSystem::default(), "influence"),
+                "influence",
+                &[],
+            )
+            .with(Timed::new(WaveSystem, "wave"), "wave", &["influence"])
+            .with(
+                Timed::new(ThinkSystem::default(), "think"),
+                "think",
+                &["influence"],
+            )
+            .with(
+                Timed::new(BehaviorSystem, "behavior"),
+                "behavior",
+                &["think"],
+            )
+            .with(
+                Timed::new(UtilityAiSystem, "utility ai"),
+                "utility ai",
+                &["think"],
+            )
+            .with(
+                Timed::new(AiSystem, "ai"),
+                "ai",
+                &["aim", "behavior", "utility ai"],
+            )
+            .with(guard("ai"), "ai guard", &["ai"])
+            .with_barrier()
+            // weapons and senses
+            .with(Timed::new(LockOnSystem, "lock on"), "lock on", &[])
+            .with(Timed::new(FireSystem, "fire"), "fire", &["lock on"])
+            .with(Timed::new(MeleeSystem, "melee"), "melee", &["fire"])
+            .with(guard("melee"), "melee guard", &["melee"])
+            .with(Timed::new(HomingSystem, "homing"), "homing", &["fire"])
+            .with(guard("homing"), "homing guard", &["homing"])
+            .with(Timed::new(RevealSystem, "reveal"), "reveal", &[])
+            .with(
+                Timed::new(CloakSystem::new(&world), "cloak"),
+                "cloak",
+                &["reveal"],
+            )
+            .with(
+                Timed::new(RadarSystem, "radar"),
+                "radar",
+                &["melee", "cloak"],
+            )
+            .with(Timed::new(PatternSystem, "pattern"), "pattern", &["radar"])
+            .with(
+                Timed::new(ProjectileSystem, "projectile"),
+                "projectile",
+                &["pattern", "melee guard", "homing guard"],
+            )
+            .with(guard("projectiles"), "projectile guard", &["projectile"])
+            .with_barrier()
+            // effects and collisions
+            .with(Timed::new(AmbientSystem, "ambient"), "ambient", &[])
+            .with(Timed::new(TweenSystem, "tween"), "tween", &[])
+            .with(Timed::new(AnimationSystem, "animation"), "animation", &[])
+            .with(Timed::new(TaskSystem, "tasks"), "tasks", &[])
+            .with(guard("tweens"), "tween guard", &["tween"])
+            .with(
+                Timed::new(LifetimeSystem, "lifetime"),
+                "lifetime",
+                &["tween guard"],
+            )
+            .with(
+                Timed::new(NotificationSystem, "notification"),
+                "notification",
+                &[],
+            )
+            .with(
+                Timed::new(TransformSystem, "transform"),
+                "transform",
+                &["tween guard"],
+            )
+            .with(
+                Timed::new(FitBoxSystem, "fit box"),
+                "fit box",
+                &["transform", "animation"],
+            )
+            .with(
+                Timed::new(BroadPhaseSystem, "broad phase"),
+                "broad phase",
+                &["fit box"],
+            )
+            .with(
+                Timed::new(CameraFollowSystem::new(&world), "camera"),
+                "camera",
+                &["fit box"],
+            )
+            .with(
+                Timed::new(CollisionSystem, "collision"),
+                "collision",
+                &["broad phase"],
+            )
+            .with(Timed::new(StatusSystem, "status"), "status", &["collision"])
+            .with(
+                Timed::new(DamageSystem::new(&world), "damage"),
+                "damage",
+                &["collision"],
+            )
+            .with(
+                Timed::new(SceneSystem::new(&world), "scene"),
+                "scene",
+                &["collision"],
+            );
+
+        // then the game mode's rules, and what comes of everything that
+        // happened
+        let late_dispatcher = DispatcherBuilder::new()
+            .with(guard("game mode"), "game mode guard", &[])
+            .with(
+                Timed::new(HealthSystem, "health"),
+                "health",
+                &["game mode guard"],
+            )
+            .with(
+                Timed::new(ComboSystem::new(&world), "combo"),
+                "combo",
+                &["health"],
+            )
+            .with(
+                Timed::new(IntensitySystem::new(&world), "intensity"),
+                "intensity",
+                &["health"],
+            )
+            .with(
+                Timed::new(PromptSystem::new(&world), "tutorial"),
+                "tutorial",
+                &[],
+            )
+            .with(Timed::new(BumpSystem::new(&world), "bump"), "bump", &[])
+            .with(
+                Timed::new(TriggerScriptSystem, "triggers"),
+                "triggers",
+                &["intensity"],
+            )
+            .with(Timed::new(GhostSystem, "ghost"), "ghost", &["triggers"])
+            .with(
+                Timed::new(TelemetrySystem::new(&world), "telemetry"),
+                "telemetry",
+                &["health"],
+            )
+            .with_barrier()
+            .with(Timed::new(BudgetSystem, "budget"), "budget", &[])
+            // after everything else has read its events
+            .with_barrier()
+            .with(
+                Timed::new(EventAuditSystem, "event audit"),
+                "event audit",
+                &[],
+            );
+
+        // both share one pool of threads
+        #[cfg(feature = "parallel")]
+        let (dispatcher, late_dispatcher) = {
+            let pool = Arc::new(
+                specs::rayon::ThreadPoolBuilder::new()
+                    .build()
+                    .map_err(|err| GameError::ConfigError(err.to_string()))?,
+            );
+            (
+                dispatcher.with_pool(pool.clone()),
+                late_dispatcher.with_pool(pool),
+            )
+        };
+        let mut dispatcher = dispatcher.build();
+        let mut late_dispatcher = late_dispatcher.build();
+        // anything the systems read that nothing above put in the world
+        dispatcher.setup(&mut world);
+        late_dispatcher.setup(&mut world);
+        let extra_systems = extensions.systems(&mut world);
+        let audio_systems = extensions.audio(&world);
+
+        // every projectile looks the same so they are all drawn as one batch
+        // of a single small image
+        let projectile_image = graphics::Image::solid(ctx, 4, graphics::WHITE)?;
+        asset_sizes.texture("projectile", &projectile_image);
+        let projectile_batch = graphics::spritebatch::SpriteBatch::new(projectile_image);
+        let combo_sound = platform::load_sound(ctx, "/sounds/combo.wav")?;
+        #[cfg(feature = "audio")]
+        asset_sizes.sound(ctx, "/sounds/combo.wav")?;
+        let graze_sound = platform::synth_sound(ctx, &sfxr::PICKUP)?;
+        let laser_sound = platform::synth_sound(ctx, &sfxr::LASER)?;
+        let explosion_sound = platform::synth_sound(ctx, &sfxr::EXPLOSION)?;
+        let music = MusicDirector::new(ctx)?;
+        let scene_canvas = graphics::Canvas::with_window_size(ctx)?;
+        asset_sizes.texture("scene canvas", scene_canvas.image());
+        let transition = Transition::new(ctx)?;
+        asset_sizes.texture("transition canvas", transition.outgoing.image());
+        let desaturate = shaders::desaturate(ctx)?;
+        let outline = shaders::outline(ctx)?;
+        let status_atlas = Atlas::load(ctx, "/atlas/status.ron")?;
+        let glyphs = Glyphs::load(ctx)?;
+        asset_sizes.texture("/atlas/status.ron", &status_atlas.image);
+        world.insert(asset_sizes);
+
+        #[cfg(feature = "dev-tools")]
+        let hot_reload = HotReload::new(&world, &*storage);
+
+        let ms = MainState {
+            specs_world: world,
+            player_input: player_input,
+            player_aim,
+            actions: Vec::new(),
+            recording: InputLog::default(),
+            playback: None,
+            active_device: Device::Keyboard,
+            #[cfg(feature = "touch")]
+            touch_controls: TouchControls::default(),
+            dispatcher,
+            late_dispatcher,
+            extra_systems,
+            #[cfg(debug_assertions)]
+            validation_system: ValidationSystem::default(),
+            render_system: RenderSystem::default(),
+            projectile_batch,
+            combo_sound,
+            graze_sound,
+            laser_sound,
+            explosion_sound,
+            audio_systems,
+            sound_bank: SoundBank::default(),
+            music,
+            listener: Listener::default(),
+            watchdog: FrameWatchdog::default(),
+            memory_overlay: MemoryOverlay::default(),
+            debug_overlay: DebugOverlay::default(),
+            heatmap: Heatmap::default(),
+            editor: Editor::default(),
+            pause_menu: None,
+            request: None,
+            #[cfg(feature = "dev-tools")]
+            cheats: Cheats::default(),
+            #[cfg(feature = "dev-tools")]
+            hot_reload,
+            #[cfg(feature = "dev-tools")]
+            component_diff: ComponentDiff::default(),
+            #[cfg(feature = "dev-tools")]
+            tweak_panel: TweakPanel::default(),
+            #[cfg(feature = "debug-ui")]
+            debug_ui: DebugUi::new(),
+            quality_controller: QualityController::default(),
+            status_atlas,
+            glyphs,
+            scene_canvas,
+            transition,
+            desaturate,
+            outline,
+            game_mode,
+            storage,
+        };
+
+        Ok(ms)
+    }
+
+    fn record_daily_run(&mut self) {
+        let day = match self.specs_world.try_fetch_mut::<DailyRun>() {
+            Some(mut run) if run.over && !run.recorded => {
+                run.recorded = true;
+                run.day
+            }
+            _ => return,
+        };
+        let points = self.specs_world.read_resource::<PlayerScore>().points;
+        match game_mode::record_daily(&*self.storage, day, points) {
+            Ok(place) => {
+                println!("Daily challenge, seed {}: {} points", day, points);
+                self.specs_world
+                    .write_resource::<Notifications>()
+                    .push(&format!(
+                        "Daily challenge over: {} points, #{} today",
+                        points, place
+                    ));
+            }
+            Err(err) => println!("leaderboard error {:?}", err),
+        }
+    }
+
+    // A finished run is timed against the best on the level, and kept as the
+    // ghost to race from then on if it beat it
+    fn record_ghost_run(&mut self) {
+        let mut ghost = self.specs_world.write_resource::<Ghost>();
+        let time = match ghost.finished {
+            Some(time) if !ghost.recorded => time,
+            _ => return,
+        };
+        ghost.recorded = true;
+        let previous = ghost.best.as_ref().map(|best| best.time);
+        let text = if ghost::save_if_best(&*self.storage, self.game_mode.name(), &mut ghost) {
+            format!("New personal best: {:.2}s", time)
+        } else {
+            format!("Time {:.2}s, best {:.2}s", time, previous.unwrap_or(time))
+        };
+        self.specs_world
+            .write_resource::<Notifications>()
+            .push(&text);
+    }
+
+    // where in the world the mouse is, for a position in window pixels
+    fn world_point(&self, ctx: &Context, x: f32, y: f32) -> nalgebra::Point2<f32> {
+        let point = controls::window_to_screen(ctx, x, y);
+        self.specs_world.read_resource::<Camera>().to_world(point)
+    }
+
+    // Rounds off the telemetry log when the game is left or closed
+    fn end_telemetry(&mut self) {
+        let clock = self.specs_world.read_resource::<GameClock>();
+        let mut telemetry = self.specs_world.write_resource::<Telemetry>();
+        telemetry.end(&clock);
+        telemetry.flush(&*self.storage);
+    }
+
+    fn control_scheme(&self) -> ControlScheme {
+        self.specs_world.read_resource::<Settings>().control_scheme
+    }
+
+    // The scheme is remembered for the device in use, so switching to a
+    // gamepad and back brings each one's own scheme with it
+    fn cycle_control_scheme(&mut self) {
+        {
+            let mut settings = self.specs_world.write_resource::<Settings>();
+            let scheme = settings.control_scheme.next();
+            settings.control_scheme = scheme;
+            settings.profile(&self.active_device).control_scheme = scheme;
+            self.specs_world
+                .write_resource::<Notifications>()
+                .push(&format!("Control scheme: {:?}", scheme));
+            settings::save(&*self.storage, &settings).unwrap_or_else(|err| {
+                println!("settings error {:?}", err);
+            });
+        }
+        self.release_input();
+    }
+
+    // whether the game mode lets the game be saved part way through
+    pub(crate) fn can_save(&self) -> bool {
+        self.game_mode.saves()
+    }
+
+    pub(crate) fn quicksave(&mut self) {
+        if !self.can_save() {
+            return;
+        }
+        let message = match quicksave::save_world(
+            &self.specs_world,
+            &*self.storage,
+            quicksave::QUICKSAVE,
+            self.game_mode.name(),
+        ) {
+            Ok(()) => {
+                self.editor.edited = false;
+                "Saved"
+            }
+            Err(err) => {
+                println!("quicksave error {:?}", err);
+                "Couldn't save"
+            }
+        };
+        self.specs_world
+            .write_resource::<Notifications>()
+            .push(message);
+    }
+
+    // Puts the world back as it was quicksaved. A replay being played can't
+    // be changed like this, nor a mode that can't be saved.
+    fn quickload(&mut self) {
+        if self.playback.is_some() || !self.can_save() {
+            return;
+        }
+        let message = match quicksave::load_world(
+            &mut self.specs_world,
+            &*self.storage,
+            quicksave::QUICKSAVE,
+            self.game_mode.name(),
+        ) {
+            Ok(()) => {
+                self.editor.edited = false;
+                "Loaded"
+            }
+            Err(err) => {
+                println!("quickload error {:?}", err);
+                "Couldn't load"
+            }
+        };
+        self.release_input();
+        self.specs_world
+            .write_resource::<Notifications>()
+            .push(message);
+    }
+
+    fn pause(&mut self) {
+        self.pause_menu = Some(PauseMenu::new(self.can_save()));
+        self.release_input();
+    }
+
+    // Holds the game still under a dialog, with the pause menu to come back
+    // to, unless the pause menu or the editor already has it holding still
+    pub(crate) fn hold(&mut self) {
+        if self.pause_menu.is_none() && !self.editor.active {
+            self.pause();
+        }
+    }
+
+    // whether leaving the game would lose changes made in the editor
+    pub(crate) fn unsaved_edits(&self) -> bool {
+        self.editor.edited
+    }
+
+    // Works the pause menu with a key or button, by the name ggez prints
+    // for it. Anything but resuming is the front screens' to carry out, and
+    // the menu is still there when they come back to the game.
+    fn pause_menu_input(&mut self, input: &str) {
+        let choice = match self.pause_menu.as_mut().and_then(|menu| menu.input(input)) {
+            Some(choice) => choice,
+            None => return,
+        };
+        match choice {
+            pause::Choice::Resume => {
+                self.pause_menu = None;
+                self.release_input();
+            }
+            _ => self.request = Some(choice),
+        }
+    }
+
+    // drop anything held under the old scheme or device so the ship doesn't
+    // keep moving or firing on its own
+    fn release_input(&mut self) {
+        self.player_input = Direction::new();
+        self.player_aim.firing = false;
+        *self.specs_world.write_resource::<Direction>() = self.player_input;
+        *self.specs_world.write_resource::<Aim>() = self.player_aim;
+    }
+
+    // Switches to the binding profile of the device that was just used. Using
+    // anything while the game waits for an unplugged pad carries on with that
+    // device instead, and returns false so the press doesn't also do something
+    // in the game.
+    fn use_device(&mut self, device: Device) -> bool {
+        let resumed = self
+            .specs_world
+            .write_resource::<Gamepads>()
+            .disconnected
+            .take()
+            .is_some();
+        if resumed {
+            let name =
+                gamepads::device_name(&self.specs_world.read_resource::<Settings>(), &device);
+            self.specs_world
+                .write_resource::<Notifications>()
+                .push(&format!("Continuing with {}", name));
+        }
+
+        if device != self.active_device {
+            {
+                let mut settings = self.specs_world.write_resource::<Settings>();
+                settings.control_scheme = settings.profile(&device).control_scheme;
+            }
+            self.specs_world.insert(ActiveDevice(device.clone()));
+            self.active_device = device;
+            self.release_input();
+        }
+        !resumed
+    }
+
+    // Works out which device a gamepad id belongs to, and whether this is the
+    // first the game has heard from it. A pad is looked up by GUID the first
+    // time it sends anything, so a known controller plugged in mid-game gets
+    // its own profile back.
+    fn gamepad_device(&mut self, ctx: &mut Context, id: GamepadId) -> (Device, bool) {
+        if let Some(device) = self.specs_world.read_resource::<Gamepads>().device(id) {
+            return (device.clone(), false);
+        }
+
+        let pad = input::gamepad::gamepad(ctx, id);
+        let device = Device::gamepad(pad.uuid());
+        let name = pad.name().to_owned();
+        self.specs_world
+            .write_resource::<Gamepads>()
+            .insert(id, device.clone());
+
+        let known = {
+            let mut settings = self.specs_world.write_resource::<Settings>();
+            let known = settings.profiles.contains_key(&device);
+            let profile = settings.profile(&device);
+            if profile.name != name {
+                profile.name = name.clone();
+                settings::save(&*self.storage, &settings).unwrap_or_else(|err| {
+                    println!("settings error {:?}", err);
+                });
+            }
+            known
+        };
+        let message = if known {
+            format!("Loaded controls for {}", name)
+        } else {
+            format!("New controller: {}", name)
+        };
+        self.specs_world
+            .write_resource::<Notifications>()
+            .push(&message);
+
+        (device, true)
+    }
+
+    // Notices pads being unplugged. Losing the one the player is using pauses
+    // the game until it's back.
+    fn check_gamepads(&mut self, ctx: &Context) {
+        let gone = self
+            .specs_world
+            .write_resource::<Gamepads>()
+            .remove_disconnected(ctx);
+        for device in gone {
+            if device == self.active_device {
+                self.specs_world.write_resource::<Gamepads>().disconnected = Some(device);
+                self.release_input();
+            } else {
+                let name =
+                    gamepads::device_name(&self.specs_world.read_resource::<Settings>(), &device);
+                self.specs_world
+                    .write_resource::<Notifications>()
+                    .push(&format!("{} disconnected", name));
+            }
+        }
+    }
+
+    // Passes the stick and buttons of the on-screen controls on to the player
+    // input, the same as keys being pressed
+    #[cfg(feature = "touch")]
+    fn apply_touch_controls(&mut self) {
+        self.touch_controls
+            .apply(&mut self.player_input, &mut self.player_aim);
+        *self.specs_world.write_resource::<Direction>() = self.player_input;
+        *self.specs_world.write_resource::<Aim>() = self.player_aim;
+    }
+
+    // A finger going down, returning whether it landed on the on-screen
+    // controls. Touch screens emulate the mouse for the first finger, so that
+    // is always touch 0.
+    #[cfg(feature = "touch")]
+    fn touch_down(&mut self, ctx: &mut Context, x: f32, y: f32) -> bool {
+        let view = graphics::screen_coordinates(ctx);
+        let point = controls::window_to_screen(ctx, x, y);
+        let (used, action) = self.touch_controls.touch_down(view, 0, point);
+        if let Some(action) = action {
+            self.perform(action);
+        }
+        if used {
+            self.apply_touch_controls();
+        }
+        used
+    }
+
+    #[cfg(not(feature = "touch"))]
+    fn touch_down(&mut self, _ctx: &mut Context, _x: f32, _y: f32) -> bool {
+        false
+    }
+
+    // what a key or button is bound to on the device in use, leaving out the
+    // ones the control scheme already uses
+    fn bound_action(&self, input: &impl std::fmt::Debug) -> Option<Action> {
+        let settings = self.specs_world.read_resource::<Settings>();
+        let profile = settings.profiles.get(&self.active_device)?;
+        let input_map = self.specs_world.read_resource::<InputMap>();
+        if profile
+            .reserved(&input_map, &format!("{:?}", input))
+            .is_some()
+        {
+            return None;
+        }
+        profile.action(input)
+    }
+
+    // Actions that only change settings happen straight away. The rest change
+    // the game, so they are kept for the replay as well.
+    fn perform(&mut self, action: Action) {
+        match action {
+            Action::CycleScheme => self.cycle_control_scheme(),
+            Action::ShipColor => self.cycle_ship_color(),
+            _ => {
+                self.actions.push(action);
+                self.apply_action(action);
+            }
+        }
+    }
+
+    fn apply_action(&mut self, action: Action) {
+        self.specs_world
+            .write_resource::<Prompts>()
+            .performed
+            .push(action);
+        match action {
+            Action::CycleScheme | Action::ShipColor => (),
+            Action::LockOn => {
+                self.specs_world.write_resource::<LockOn>().cycle_requested = true;
+            }
+            Action::Radar => {
+                self.specs_world.write_resource::<RadarPing>().requested = true;
+            }
+            Action::Cloak => self.toggle_player_cloak(),
+            Action::Melee => self.start_player_attack(),
+            Action::BulletTime => {
+                self.specs_world
+                    .write_resource::<BulletTime>()
+                    .toggle_requested = true;
+            }
+            #[cfg(feature = "dev-tools")]
+            Action::StressTest => weapons::stress_test(&self.specs_world),
+        }
+    }
+
+    // Repaints the player's ship in the next palette. The choice is saved, so
+    // it is the colour the ship spawns in next time too.
+    fn cycle_ship_color(&mut self) {
+        let name = {
+            let mut settings = self.specs_world.write_resource::<Settings>();
+            let name = palette::next(&settings.team_colors.player);
+            settings.team_colors.player = name.to_owned();
+            settings::save(&*self.storage, &settings).unwrap_or_else(|err| {
+                println!("settings error {:?}", err);
+            });
+            self.specs_world.write_resource::<Spawner>().team_colors = settings.team_colors.clone();
+            name
+        };
+
+        let skin = self.specs_world.read_resource::<Spawner>().ship_skin(name);
+        let controlled = self.specs_world.read_storage::<ControllableTag>();
+        let mut images = self.specs_world.write_storage::<ImageHandle>();
+        for (image, _) in (&mut images, &controlled).join() {
+            *image = skin;
+        }
+        self.specs_world
+            .write_resource::<Notifications>()
+            .push(&format!("Ship colour: {}", name));
+    }
+
+    // The player cloak is a simple toggle, adding or removing the Cloaked
+    // component on every player ship
+    fn toggle_player_cloak(&mut self) {
+        let entities = self.specs_world.entities();
+        let controlled = self.specs_world.read_storage::<ControllableTag>();
+        let mut cloaked = self.specs_world.write_storage::<Cloaked>();
+
+        for (player, _) in (&entities, &controlled).join() {
+            if cloaked.remove(player).is_none() {
+                cloaked
+                    .insert(player, Cloaked::default())
+                    .unwrap_or_else(|err| {
+                        println!("cloak error {:?}", err);
+                        None
+                    });
+            }
+        }
+    }
+
+    // Starts a melee swing, unless the player is still in the middle of one
+    fn start_player_attack(&mut self) {
+        let entities = self.specs_world.entities();
+        let controlled = self.specs_world.read_storage::<ControllableTag>();
+        let mut attacks = self.specs_world.write_storage::<Attack>();
+
+        for (player, _) in (&entities, &controlled).join() {
+            if attacks.get(player).is_none() {
+                attacks
+                    .insert(player, Attack::default())
+                    .unwrap_or_else(|err| {
+                        println!("attack error {:?}", err);
+                        None
+                    });
+            }
+        }
+    }
+
+    // Advances the world by one fixed update
+    fn step(&mut self) {
+        interpolation::remember_positions(&self.specs_world);
+
+        // every update's input is recorded, or played back from a replay
+        let replayed = match self.playback.as_mut() {
+            Some(playback) => playback.next(),
+            None => {
+                let actions = self.actions.drain(..).collect();
+                let input = ReplayInput::capture(&self.specs_world, actions);
+                self.recording.push(input);
+                None
+            }
+        };
+        if let Some(input) = replayed {
+            input.apply(&self.specs_world);
+            for action in input.actions {
+                self.apply_action(action);
+            }
+        }
+
+        // a melee hit freezes everything for a few frames so it lands, and
+        // nothing moves while waiting for an unplugged pad. Game time stops
+        // for both.
+        let frozen = self.specs_world.read_resource::<HitStop>().frames > 0;
+        let waiting = self
+            .specs_world
+            .read_resource::<Gamepads>()
+            .disconnected
+            .is_some();
+        self.specs_world.write_resource::<GameClock>().advance(
+            &self.specs_world.read_resource::<DeltaTime>(),
+            &self.specs_world.read_resource::<TimeScale>(),
+            frozen || waiting,
+        );
+
+        if frozen {
+            self.specs_world.write_resource::<HitStop>().frames -= 1;
+            return;
+        }
+        if waiting {
+            return;
+        }
+
+        let world = &self.specs_world;
+        dispatch(&mut self.dispatcher, world);
+        let started = Instant::now();
+        self.game_mode.run_rules(world);
+        world
+            .read_resource::<SystemTimes>()
+            .add("game mode", started.elapsed());
+        dispatch(&mut self.late_dispatcher, world);
+        for (name, system) in &mut self.extra_systems {
+            run_timed(&mut **system, world, name);
+        }
+        events::deliver(world);
+
+        self.specs_world.maintain();
+        spawn_queue::apply(&mut self.specs_world);
+
+        // debug builds check nothing was left half updated
+        #[cfg(debug_assertions)]
+        self.validation_system.run_now(&self.specs_world);
+    }
+
+    fn play_sounds(&mut self, ctx: &mut Context) -> GameResult<()> {
+        // each kill in a chain chimes a little higher than the last
+        let chimes: Vec<f32> = self
+            .specs_world
+            .write_resource::<Combo>()
+            .chimes
+            .drain(..)
+            .collect();
+        for pitch in chimes {
+            self.combo_sound.play(pitch, 1.0)?;
+        }
+
+        // the rest are heard from where they happened, bent by how fast
+        // whatever made them was going past
+        let view = self.specs_world.read_resource::<View>().rect;
+        let camera = nalgebra::Point2::new(view.x + view.w / 2.0, view.y + view.h / 2.0);
+        let now = self.specs_world.read_resource::<GameClock>().unscaled;
+        self.listener.follow(camera, now);
+        let cues = std::mem::take(&mut *self.specs_world.write_resource::<SoundCues>());
+        let mut sounds: [(&mut Box<dyn Sound>, &[Cue]); 3] = [
+            (&mut self.graze_sound, &cues.grazes),
+            (&mut self.laser_sound, &cues.shots),
+            (&mut self.explosion_sound, &cues.explosions),
+        ];
+        for (sound, cues) in sounds.iter_mut() {
+            for cue in cues.iter() {
+                let volume = self.listener.volume(cue);
+                if volume > 0.0 {
+                    sound.play(self.listener.doppler(cue), volume)?;
+                }
+            }
+        }
+
+        // and anything a system asked for by name, once the game's own
+        // AudioSystems have had their pick
+        let mut queue = self.specs_world.write_resource::<SoundQueue>();
+        for audio in &mut self.audio_systems {
+            audio.play(ctx, &mut queue)?;
+        }
+        let queued = std::mem::take(&mut queue.sounds);
+        self.sound_bank.play(ctx, queued, &self.listener)
+    }
+
+    // Translate a key press or release into the player input structs for the
+    // active control scheme
+    // Keys are translated through the InputMap, so what flies the ship can be
+    // changed in resources/input.ron
+    fn update_input(&mut self, keycode: KeyCode, pressed: bool) {
+        let action = self
+            .specs_world
+            .read_resource::<InputMap>()
+            .action(self.control_scheme(), keycode);
+        match action {
+            Some(InputAction::MoveUp) => self.player_input.up = pressed,
+            Some(InputAction::MoveDown) => self.player_input.down = pressed,
+            Some(InputAction::MoveLeft) => self.player_input.left = pressed,
+            Some(InputAction::MoveRight) => self.player_input.right = pressed,
+            Some(InputAction::Fire) => self.player_aim.firing = pressed,
+            Some(InputAction::Pause) if pressed => self.pause(),
+            _ => (),
+        }
+
+        // Update the world-owned input structs to match the current state of the
+        // MainState owned structs
+        *self.specs_world.write_resource::<Direction>() = self.player_input;
+        *self.specs_world.write_resource::<Aim>() = self.player_aim;
+    }
+
+    // Everything of the game drawn for a frame, short of presenting it, so
+    // the front screens can draw a dialog over the top
+    pub(crate) fn draw_scene(&mut self, ctx: &mut Context) -> GameResult<()> {
+        // While time is slowed the world is drawn to a canvas first, so it can
+        // be desaturated on the way to the screen. The HUD stays in colour.
+        // Lower quality presets skip the pass.
+        let desaturation = if self.specs_world.read_resource::<Quality>().post_process {
+            BulletTime::depth(&self.specs_world.read_resource::<TimeScale>())
+        } else {
+            0.0
+        };
+        // while the scene is about to change, the world is drawn to the
+        // transition's canvas as well, ready to be shown going out
+        let capturing = Transition::capturing(&self.specs_world);
+        let world_target = if capturing {
+            Some(&self.transition.outgoing)
+        } else {
+            None
+        };
+        if desaturation > 0.0 {
+            graphics::set_canvas(ctx, Some(&self.scene_canvas));
+        } else {
+            graphics::set_canvas(ctx, world_target);
+        }
+        graphics::clear(ctx, graphics::BLACK);
+        // everything is drawn part of the way between the last two updates
+        let interpolation = Interpolation::measure(ctx);
+        *self.specs_world.write_resource::<Interpolation>() = interpolation;
+        let camera = {
+            let mut camera = self.specs_world.write_resource::<Camera>();
+            camera.viewport = graphics::screen_coordinates(ctx);
+            camera.interpolated(&interpolation)
+        };
+        self.specs_world.write_resource::<View>().rect = camera.view();
+        ambient::draw_ambient(ctx, &self.specs_world)?;
+
+        // the world is drawn through the camera
+        camera.push(ctx)?;
+        heatmap::draw_heatmap(ctx, &mut self.heatmap)?;
+        scene::draw_hazards(ctx, &self.specs_world)?;
+        ghost::draw_ghost(ctx, &self.specs_world)?;
+
+        // anything moved since the last update, by the editor say, is drawn
+        // where it is now
+        TransformSystem.run_now(&self.specs_world);
+        outline::draw_outlines(ctx, &self.specs_world, &self.outline)?;
+
+        self.render_system.run_now(&self.specs_world);
+        render::draw_sprites(
+            ctx,
+            &self.specs_world,
+            &self.render_system,
+            &mut self.projectile_batch,
+        )?;
+
+        self.game_mode.draw(ctx, &self.specs_world)?;
+        arena::draw_bounds(ctx, &self.specs_world)?;
+        radar::draw_pulses(ctx, &self.specs_world)?;
+        targeting::draw_lock_indicator(ctx, &self.specs_world)?;
+        melee::draw_attacks(ctx, &self.specs_world)?;
+        graze::draw_sparks(ctx, &self.specs_world)?;
+        status::draw_status_icons(ctx, &self.specs_world, &self.status_atlas)?;
+        floating_text::draw_floating_text(ctx, &self.specs_world)?;
+        debug_overlay::draw_collision_boxes(ctx, &self.specs_world, &self.debug_overlay)?;
+        Camera::pop(ctx)?;
+
+        if desaturation > 0.0 {
+            graphics::set_canvas(ctx, world_target);
+            graphics::clear(ctx, graphics::BLACK);
+            self.desaturate.send(
+                ctx,
+                Desaturate {
+                    amount: desaturation,
+                },
+            )?;
+            let _lock = graphics::use_shader(ctx, &self.desaturate);
+            graphics::draw(ctx, &self.scene_canvas, graphics::DrawParam::default())?;
+        }
+        if capturing {
+            graphics::set_canvas(ctx, None);
+            graphics::clear(ctx, graphics::BLACK);
+            graphics::draw(
+                ctx,
+                &self.transition.outgoing,
+                graphics::DrawParam::default(),
+            )?;
+        }
+        self.transition.draw(ctx, &self.specs_world)?;
+
+        tutorial::draw_prompt(ctx, &self.specs_world, &self.glyphs)?;
+        hud::draw_threat_indicators(ctx, &self.specs_world)?;
+        minimap::draw_minimap(ctx, &self.specs_world)?;
+        cooldowns::draw_player_cooldowns(ctx, &self.specs_world, &self.glyphs)?;
+        game_mode::draw_scores(ctx, &self.specs_world)?;
+        score::draw_player_score(ctx, &self.specs_world)?;
+        combo::draw_combo(ctx, &self.specs_world)?;
+        sandbox::draw_sandbox(ctx, &self.specs_world)?;
+        #[cfg(feature = "touch")]
+        touch::draw_touch_controls(ctx, &self.touch_controls)?;
+        notifications::draw_notifications(ctx, &self.specs_world)?;
+        gamepads::draw_disconnected_prompt(ctx, &self.specs_world)?;
+        memory::draw_memory_overlay(ctx, &self.memory_overlay)?;
+        self.debug_overlay
+            .record(ctx, &self.specs_world, self.render_system.draw_calls());
+        debug_overlay::draw_debug_overlay(ctx, &self.specs_world, &self.debug_overlay)?;
+        #[cfg(feature = "dev-tools")]
+        diff::draw_component_diff(ctx, &self.component_diff)?;
+        #[cfg(feature = "dev-tools")]
+        tweakables::draw_tweak_panel(ctx, &self.specs_world, &self.tweak_panel)?;
+        heatmap::draw_heatmap_legend(ctx, &self.heatmap)?;
+        editor::draw_editor(
+            ctx,
+            &self.editor,
+            &self.specs_world.read_resource::<Camera>(),
+        )?;
+        if self.playback.is_some() {
+            replay::draw_demo_banner(ctx)?;
+        }
+        if let Some(menu) = &self.pause_menu {
+            let theme = self.specs_world.read_resource::<UiTheme>();
+            menu.draw(ctx, &self.glyphs, &theme, &self.active_device)?;
+        }
+        #[cfg(feature = "debug-ui")]
+        self.debug_ui.draw(ctx, &self.specs_world)?;
+
+        let frame_time = self.watchdog.end(&self.specs_world);
+        self.quality_controller
+            .record(frame_time, &self.specs_world);
+        Ok(())
+    }
+}
+
+// Runs a dispatcher's systems on the thread pool with the parallel feature,
+// and without it one after another on this thread, stage by stage in the
+// order they were added. Which one is up to this crate's feature rather than
+// shred's, which another crate could turn on behind its back, so a build
+// without parallel is always single threaded.
+fn dispatch(dispatcher: &mut Dispatcher<'static, 'static>, world: &World) {
+    #[cfg(feature = "parallel")]
+    dispatcher.dispatch_par(world);
+    #[cfg(not(feature = "parallel"))]
+    dispatcher.dispatch_seq(world);
+    dispatcher.dispatch_thread_local(world);
+}
+
+impl ggez::event::EventHandler for MainState {
+    fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.check_gamepads(ctx);
+        #[cfg(feature = "dev-tools")]
+        self.hot_reload
+            .update(ctx, &self.specs_world, &*self.storage);
+        self.watchdog.begin(&self.specs_world);
+
+        while timer::check_update_time(ctx, DESIRED_FPS) {
+            // the world holds still while it's being edited
+            if !self.editor.active && self.pause_menu.is_none() {
+                self.step();
+            }
+        }
+        if self.editor.active || self.pause_menu.is_some() {
+            interpolation::remember_positions(&self.specs_world);
+        }
+
+        // a paused world can be stepped one update at a time to see what the
+        // update changes
+        #[cfg(feature = "dev-tools")]
+        {
+            let paused = self.pause_menu.is_some();
+            if self.component_diff.take_step() && paused && !self.editor.active {
+                self.component_diff.before_step(&self.specs_world);
+                self.step();
+                self.component_diff.after_step(&self.specs_world);
+            }
+        }
+
+        // tutorial prompts the player completes are saved so they don't come
+        // up again, but not ones a replay happens to complete
+        let completed = self.specs_world.write_resource::<Prompts>().take_unsaved();
+        if completed && self.playback.is_none() {
+            let settings = self.specs_world.read_resource::<Settings>();
+            settings::save(&*self.storage, &settings).unwrap_or_else(|err| {
+                println!("settings error {:?}", err);
+            });
+        }
+
+        // a daily challenge goes on the leaderboard once it's over, and a
+        // finished run is timed against the best, but not ones a replay plays
+        // back
+        if self.playback.is_none() {
+            self.record_daily_run();
+            self.record_ghost_run();
+        }
+
+        self.specs_world
+            .write_resource::<Telemetry>()
+            .flush(&*self.storage);
+
+        self.memory_overlay.update(&self.specs_world);
+        self.music.update(ctx, &self.specs_world)?;
+        self.play_sounds(ctx)
+    }
+
+    fn draw(&mut self, ctx: &mut Context) -> GameResult<()> {
+        self.draw_scene(ctx)?;
+        graphics::present(ctx)?;
+
+        timer::yield_now();
+        Ok(())
+    }
+
+    fn key_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        keycode: KeyCode,
+        keymod: KeyMods,
+        repeat: bool,
+    ) {
+        // the debug windows get every key, repeats too for typing, and keep
+        // those they're typing with
+        #[cfg(feature = "debug-ui")]
+        {
+            if self.debug_ui.key_down(keycode, keymod, repeat) {
+                return;
+            }
+        }
+        if !repeat {
+            // we don't multiple registrations of a keypress
+            if !self.use_device(Device::Keyboard) {
+                return;
+            }
+            #[cfg(feature = "dev-tools")]
+            {
+                if self.cheats.key_down(&self.specs_world, keycode, keymod)
+                    || self.hot_reload.key_down(&self.specs_world, keycode, keymod)
+                    || events::Events::key_down(&self.specs_world, keycode, keymod)
+                    || self
+                        .component_diff
+                        .key_down(&self.specs_world, keycode, keymod)
+                    || self
+                        .tweak_panel
+                        .key_down(&self.specs_world, keycode, keymod)
+                {
+                    return;
+                }
+            }
+            // the pause menu has the keyboard while it's up
+            if self.pause_menu.is_some() {
+                self.pause_menu_input(&format!("{:?}", keycode));
+                return;
+            }
+            // F2 opens the editor, which has the keyboard to itself while it's
+            // open. Replays can't be edited.
+            if keycode == KeyCode::F2 && self.playback.is_none() {
+                self.editor.toggle();
+                self.release_input();
+                return;
+            }
+            if self.editor.active {
+                self.editor.key_down(&mut self.specs_world, keycode, keymod);
+                return;
+            }
+            // the sandbox's tools come before the usual keys
+            if self.specs_world.has_value::<SandboxTools>() {
+                if keycode == KeyCode::Back {
+                    self.request = Some(pause::Choice::Restart);
+                    return;
+                }
+                if sandbox::key_down(&self.specs_world, keycode) {
+                    return;
+                }
+            }
+            if let Some(action) = self.bound_action(&keycode) {
+                self.perform(action);
+                return;
+            }
+            #[cfg(feature = "dev-tools")]
+            {
+                if keycode == KeyCode::F10 {
+                    self.perform(Action::StressTest);
+                    return;
+                }
+            }
+            if keycode == KeyCode::F3 {
+                self.debug_overlay.toggle();
+                return;
+            }
+            if keycode == KeyCode::F7 {
+                self.memory_overlay.toggle(&self.specs_world);
+                return;
+            }
+            if keycode == KeyCode::F8 {
+                self.heatmap.toggle(&*self.storage, self.game_mode.name());
+                return;
+            }
+            if keycode == KeyCode::F5 {
+                self.quicksave();
+                return;
+            }
+            if keycode == KeyCode::F9 {
+                self.quickload();
+                return;
+            }
+            self.update_input(keycode, true);
+        }
+    }
+
+    fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymod: KeyMods) {
+        #[cfg(feature = "debug-ui")]
+        self.debug_ui.key_up(keycode, _keymod);
+        self.update_input(keycode, false);
+    }
+
+    // only the debug windows take text
+    #[cfg(feature = "debug-ui")]
+    fn text_input_event(&mut self, _ctx: &mut Context, character: char) {
+        self.debug_ui.text_input(character);
+    }
+
+    #[cfg(feature = "debug-ui")]
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, x: f32, y: f32) {
+        self.debug_ui.mouse_wheel(x, y);
+    }
+
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        // the mouse is the debug windows' while it's over them
+        #[cfg(feature = "debug-ui")]
+        {
+            if self.debug_ui.mouse_moved(ctx, x, y) {
+                return;
+            }
+        }
+        self.player_aim.cursor = self.world_point(ctx, x, y);
+        *self.specs_world.write_resource::<Aim>() = self.player_aim;
+        if self.editor.active {
+            self.editor
+                .mouse_moved(&self.specs_world, self.player_aim.cursor);
+        }
+
+        // the on-screen controls aren't used while editing
+        #[cfg(feature = "touch")]
+        {
+            if self.editor.active {
+                return;
+            }
+            let point = controls::window_to_screen(ctx, x, y);
+            self.touch_controls.touch_moved(0, point);
+            self.apply_touch_controls();
+        }
+    }
+
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        #[cfg(feature = "debug-ui")]
+        {
+            if self.debug_ui.mouse_button(ctx, button, x, y, true) {
+                return;
+            }
+        }
+        if !self.use_device(Device::Keyboard) || self.pause_menu.is_some() {
+            return;
+        }
+        if self.editor.active {
+            if button == MouseButton::Left {
+                let add = input::keyboard::is_mod_active(ctx, KeyMods::SHIFT);
+                let at = self.world_point(ctx, x, y);
+                self.editor.mouse_down(&self.specs_world, at, add);
+            }
+            return;
+        }
+
+        // anything that lands on the on-screen controls goes no further
+        if button == MouseButton::Left && self.touch_down(ctx, x, y) {
+            return;
+        }
+        if self.specs_world.has_value::<SandboxTools>() {
+            let screen = controls::window_to_screen(ctx, x, y);
+            let at = self.world_point(ctx, x, y);
+            if sandbox::mouse_down(ctx, &self.specs_world, button, screen, at) {
+                return;
+            }
+        }
+        if button == MouseButton::Left && self.control_scheme() == ControlScheme::TwinStick {
+            self.player_aim.firing = true;
+            *self.specs_world.write_resource::<Aim>() = self.player_aim;
+        }
+        if button == MouseButton::Middle {
+            let at = self.world_point(ctx, x, y);
+            outline::toggle_selected(&self.specs_world, at);
+        }
+    }
+
+    // letting go reaches the game wherever the mouse is, so firing that
+    // started in the game stops over a debug window
+    fn mouse_button_up_event(&mut self, _ctx: &mut Context, button: MouseButton, _x: f32, _y: f32) {
+        #[cfg(feature = "debug-ui")]
+        self.debug_ui.mouse_button(_ctx, button, _x, _y, false);
+        if self.editor.active {
+            if button == MouseButton::Left {
+                self.editor.mouse_up(&self.specs_world);
+            }
+            return;
+        }
+
+        #[cfg(feature = "touch")]
+        {
+            if button == MouseButton::Left {
+                self.touch_controls.touch_up(0);
+                self.apply_touch_controls();
+            }
+        }
+
+        if button == MouseButton::Left && self.control_scheme() == ControlScheme::TwinStick {
+            self.player_aim.firing = false;
+            *self.specs_world.write_resource::<Aim>() = self.player_aim;
+        }
+    }
+
+    fn gamepad_button_down_event(&mut self, ctx: &mut Context, btn: Button, id: GamepadId) {
+        let (device, _) = self.gamepad_device(ctx, id);
+        if !self.use_device(device) {
+            return;
+        }
+        if self.pause_menu.is_some() {
+            self.pause_menu_input(&format!("{:?}", btn));
+            return;
+        }
+        if btn == Button::Start {
+            self.pause();
+            return;
+        }
+        if let Some(action) = self.bound_action(&btn) {
+            self.perform(action);
+            return;
+        }
+        if btn == Button::RightTrigger2 && self.control_scheme() == ControlScheme::TwinStick {
+            self.player_aim.firing = true;
+            *self.specs_world.write_resource::<Aim>() = self.player_aim;
+        }
+    }
+
+    fn gamepad_button_up_event(&mut self, _ctx: &mut Context, btn: Button, _id: GamepadId) {
+        if btn == Button::RightTrigger2 && self.control_scheme() == ControlScheme::TwinStick {
+            self.player_aim.firing = false;
+            *self.specs_world.write_resource::<Aim>() = self.player_aim;
+        }
+    }
+
+    fn gamepad_axis_event(&mut self, ctx: &mut Context, axis: Axis, value: f32, id: GamepadId) {
+        // a pad that hasn't been seen yet is picked up by whatever it sends
+        // first, but sticks are never quite still so after that they don't
+        // switch over to it
+        let (device, new) = self.gamepad_device(ctx, id);
+        if new {
+            self.use_device(device);
+        }
+        // gamepads report up as positive, the screen treats down as positive
+        match axis {
+            Axis::LeftStickX => self.player_input.x = value,
+            Axis::LeftStickY => self.player_input.y = -value,
+            Axis::RightStickX => self.player_aim.stick_x = value,
+            Axis::RightStickY => self.player_aim.stick_y = -value,
+            _ => return,
+        }
+        *self.specs_world.write_resource::<Direction>() = self.player_input;
+        *self.specs_world.write_resource::<Aim>() = self.player_aim;
+    }
+}
@@ -0,0 +1,40 @@
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+
+// Entities with a Lifetime are removed from the world once it runs out. Useful
+// for anything short-lived like effects and text popups, a game's own shots
+// included. The built-in weapons' projectiles don't use it, they're pooled
+// and time themselves out, see Projectile.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct Lifetime {
+    // seconds left before the entity is deleted, in game time
+    pub remaining: f32,
+}
+
+pub(crate) struct LifetimeSystem;
+
+impl<'a> System<'a> for LifetimeSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Lifetime>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, delta, time, multipliers, mut lifetimes) = data;
+
+        for (entity, lifetime) in (&entities, &mut lifetimes).join() {
+            lifetime.remaining -= time.scaled(delta.seconds, multipliers.get(entity));
+            if lifetime.remaining <= 0.0 {
+                entities
+                    .delete(entity)
+                    .unwrap_or_else(|err| println!("delete error {:?}", err));
+            }
+        }
+    }
+}
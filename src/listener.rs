@@ -0,0 +1,97 @@
+use ggez::nalgebra;
+
+// pixels per second. Far slower than the real thing, so shots flying past
+// at a few hundred pixels a second are heard to bend.
+const SOUND_SPEED: f32 = 3000.0;
+// how much of the way to the camera the listener moves each update
+const FOLLOW: f32 = 0.2;
+// beyond this distance a sound can't be heard at all
+const HEARING_RANGE: f32 = 800.0;
+// the most doppler can bend a sound, either way
+const MAX_SHIFT: f32 = 2.0;
+
+// A sound that should be heard from somewhere in the world, moving at the
+// velocity it had when it was made
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Cue {
+    pub(crate) at: nalgebra::Point2<f32>,
+    pub(crate) velocity: nalgebra::Vector2<f32>,
+}
+
+// Positional sounds systems want played this update. Like the combo chimes,
+// MainState plays and clears them once the world has been stepped.
+#[derive(Debug, Default)]
+pub(crate) struct SoundCues {
+    // enemy shots that just grazed the player
+    pub(crate) grazes: Vec<Cue>,
+    // the player firing
+    pub(crate) shots: Vec<Cue>,
+    // anything destroyed
+    pub(crate) explosions: Vec<Cue>,
+}
+
+// Where the game is heard from. The listener trails the middle of the
+// camera's view rather than jumping with it, so sounds don't lurch when the
+// view does, and its own velocity counts towards the doppler shift.
+#[derive(Debug)]
+pub(crate) struct Listener {
+    position: nalgebra::Point2<f32>,
+    velocity: nalgebra::Vector2<f32>,
+    // unscaled game clock time of the last follow
+    followed_at: f64,
+    // false until the first follow, which puts the listener straight on the
+    // camera
+    placed: bool,
+}
+
+impl Default for Listener {
+    fn default() -> Self {
+        Listener {
+            position: nalgebra::Point2::origin(),
+            velocity: nalgebra::Vector2::zeros(),
+            followed_at: 0.0,
+            placed: false,
+        }
+    }
+}
+
+impl Listener {
+    // Eases toward the camera, once per update of the game clock. now is the
+    // clock's unscaled time.
+    pub(crate) fn follow(&mut self, camera: nalgebra::Point2<f32>, now: f64) {
+        let dt = (now - self.followed_at) as f32;
+        self.followed_at = now;
+        if !self.placed {
+            self.position = camera;
+            self.placed = true;
+            return;
+        }
+        if dt <= 0.0 {
+            return;
+        }
+        let previous = self.position;
+        self.position += (camera - self.position) * FOLLOW;
+        self.velocity = (self.position - previous) / dt;
+    }
+
+    // The pitch a cue is heard at. Anything closing on the listener is
+    // heard higher, anything moving away lower.
+    pub(crate) fn doppler(&self, cue: &Cue) -> f32 {
+        let offset = self.position - cue.at;
+        if offset.norm() <= std::f32::EPSILON {
+            return 1.0;
+        }
+        let toward = offset.normalize();
+        // speeds along the line between them, positive when closing
+        let source = cue.velocity.dot(&toward).min(SOUND_SPEED * 0.9);
+        let listener = -self.velocity.dot(&toward);
+        ((SOUND_SPEED + listener) / (SOUND_SPEED - source))
+            .max(1.0 / MAX_SHIFT)
+            .min(MAX_SHIFT)
+    }
+
+    // from 1 right at the listener fading to 0 at the edge of hearing
+    pub(crate) fn volume(&self, cue: &Cue) -> f32 {
+        (1.0 - (self.position - cue.at).norm() / HEARING_RANGE).max(0.0)
+    }
+}
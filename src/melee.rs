@@ -0,0 +1,298 @@
+use crate::events::Publish;
+use crate::faction::{self, Faction};
+use crate::floating_text::FloatingText;
+use crate::health::{self, DamageEvent, Health};
+use crate::hitbox::{self, Hitbox, Hurtbox, Shape};
+use crate::interpolation::Drawn;
+use crate::settings::Settings;
+use crate::status::{self, Status, StatusEffects};
+use crate::stealth::{self, Cloaked};
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::{CollisionBox, Position, Rotation, DESIRED_FPS};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::saveload::ConvertSaveload;
+use specs::*;
+use specs_derive::*;
+
+// frame counts for each part of a swing, at DESIRED_FPS
+const STARTUP_FRAMES: u32 = 6;
+const ACTIVE_FRAMES: u32 = 4;
+const RECOVERY_FRAMES: u32 = 14;
+const MELEE_DAMAGE: f32 = 25.0;
+// size of the hitbox out in front of the ship
+const MELEE_REACH: f32 = 30.0;
+// how far a hit shoves the target away, in pixels
+const KNOCKBACK: f32 = 40.0;
+// how long everything freezes when a hit lands
+const HIT_STOP_FRAMES: u32 = 4;
+// seconds a hit leaves the target staggered and slowed
+const STAGGER_TIME: f32 = 1.5;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum AttackPhase {
+    // winding up, nothing can be hit yet
+    Startup,
+    // the hitbox is out
+    Active,
+    // the hitbox is gone but the ship can't swing again until this is over
+    Recovery,
+}
+
+// A melee swing in progress. While it is active a Hitbox is attached to the
+// attacker just in front of its nose and anything hostile with a Hurtbox it
+// overlaps is hit, once per swing. The component is removed when the swing
+// has recovered, so a ship can only start a new one once this is gone.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub(crate) struct Attack {
+    pub(crate) phase: AttackPhase,
+    // counted at DESIRED_FPS in the attacker's own time, so a slowed down
+    // attacker swings slowly
+    pub(crate) frames_left: f32,
+    pub(crate) damage: f32,
+    pub(crate) knockback: f32,
+    hit: Vec<Entity>,
+}
+
+impl Default for Attack {
+    fn default() -> Self {
+        Attack {
+            phase: AttackPhase::Startup,
+            frames_left: STARTUP_FRAMES as f32,
+            damage: MELEE_DAMAGE,
+            knockback: KNOCKBACK,
+            hit: Vec::new(),
+        }
+    }
+}
+
+// An Attack as it's quicksaved, with what it has hit by their markers
+#[derive(Serialize, Deserialize)]
+pub(crate) struct AttackData<M> {
+    phase: AttackPhase,
+    frames_left: f32,
+    damage: f32,
+    knockback: f32,
+    hit: Vec<M>,
+}
+
+impl<M: Serialize + DeserializeOwned> ConvertSaveload<M> for Attack {
+    type Data = AttackData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        Ok(AttackData {
+            phase: self.phase,
+            frames_left: self.frames_left,
+            damage: self.damage,
+            knockback: self.knockback,
+            hit: self.hit.iter().cloned().filter_map(ids).collect(),
+        })
+    }
+
+    fn convert_from<F>(data: Self::Data, ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        Ok(Attack {
+            phase: data.phase,
+            frames_left: data.frames_left,
+            damage: data.damage,
+            knockback: data.knockback,
+            hit: data.hit.into_iter().filter_map(ids).collect(),
+        })
+    }
+}
+
+// Frames left to freeze the simulation for. MainState skips updates while this
+// is counting down, which gives heavy hits some weight.
+#[derive(Debug, Default)]
+pub(crate) struct HitStop {
+    pub(crate) frames: u32,
+}
+
+// Steps every swing through its phases and resolves the active ones against
+// hurtboxes
+pub(crate) struct MeleeSystem;
+
+impl<'a> System<'a> for MeleeSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Settings>,
+        Read<'a, LazyUpdate>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Write<'a, HitStop>,
+        Publish<'a, DamageEvent>,
+        ReadStorage<'a, Rotation>,
+        ReadStorage<'a, Hurtbox>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, CollisionBox>,
+        WriteStorage<'a, Hitbox>,
+        WriteStorage<'a, Attack>,
+        WriteStorage<'a, Health>,
+        WriteStorage<'a, Cloaked>,
+        WriteStorage<'a, StatusEffects>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            settings,
+            updater,
+            delta,
+            time,
+            mut hit_stop,
+            mut damage_events,
+            rotation,
+            hurtboxes,
+            factions,
+            multipliers,
+            mut pos,
+            mut coll_box,
+            mut hitboxes,
+            mut attacks,
+            mut health,
+            mut cloaked,
+            mut statuses,
+        ) = data;
+        let mut finished = Vec::new();
+        let mut swings = Vec::new();
+
+        for (attacker, own_pos, own_box, attack) in
+            (&entities, &pos, &coll_box, &mut attacks).join()
+        {
+            attack.frames_left -=
+                time.scaled(delta.seconds, multipliers.get(attacker)) * DESIRED_FPS as f32;
+            if attack.frames_left <= 0.0 {
+                match attack.phase {
+                    AttackPhase::Startup => {
+                        attack.phase = AttackPhase::Active;
+                        attack.frames_left = ACTIVE_FRAMES as f32;
+                    }
+                    AttackPhase::Active => {
+                        attack.phase = AttackPhase::Recovery;
+                        attack.frames_left = RECOVERY_FRAMES as f32;
+                        hitboxes.remove(attacker);
+                    }
+                    AttackPhase::Recovery => finished.push(attacker),
+                }
+            }
+            if attack.phase != AttackPhase::Active {
+                continue;
+            }
+
+            // keep the hitbox out in front of the nose as the ship turns
+            let heading = rotation
+                .get(attacker)
+                .map_or(nalgebra::Vector2::new(0.0, -1.0), |r| r.heading());
+            let reach = own_box.height / 2.0 + MELEE_REACH / 2.0;
+            let center = own_box.center() + heading * reach - own_pos.position;
+            let hitbox = Hitbox(Shape::centered(center, MELEE_REACH, MELEE_REACH));
+            hitboxes.insert(attacker, hitbox).unwrap_or_else(|err| {
+                println!("melee error {:?}", err);
+                None
+            });
+            swings.push((attacker, hitbox.0.at(own_pos.position), own_box.center()));
+        }
+
+        for attacker in finished {
+            attacks.remove(attacker);
+        }
+
+        for (attacker, strike, from) in swings {
+            let attack = match attacks.get_mut(attacker) {
+                Some(attack) => attack,
+                None => continue,
+            };
+            let side = factions.get(attacker);
+            let victims: Vec<Entity> = (&entities, &pos, &hurtboxes)
+                .join()
+                .filter(|(target, target_pos, hurtbox)| {
+                    *target != attacker
+                        && !attack.hit.contains(target)
+                        && faction::can_damage(side, factions.get(*target), settings.friendly_fire)
+                        && hitbox::overlaps(&strike, &hurtbox.0.at(target_pos.position))
+                })
+                .map(|(target, _, _)| target)
+                .collect();
+
+            for target in victims {
+                attack.hit.push(target);
+                stealth::disrupt(&mut cloaked, target);
+                health::deal_damage(
+                    &mut health,
+                    &mut damage_events,
+                    target,
+                    Some(attacker),
+                    attack.damage,
+                );
+                status::apply(&mut statuses, target, Status::Slowed, STAGGER_TIME);
+
+                // shove the target straight away from the attacker
+                if let Some(target_box) = coll_box.get_mut(target) {
+                    let away = target_box.center() - from;
+                    if away.norm() > std::f32::EPSILON {
+                        let shove = away.normalize() * attack.knockback;
+                        target_box.origin += shove;
+                        if let Some(target_pos) = pos.get_mut(target) {
+                            target_pos.position += shove;
+                        }
+                    }
+                }
+
+                FloatingText::spawn(
+                    &entities,
+                    &updater,
+                    strike.center(),
+                    "SMASH",
+                    graphics::Color::new(1.0, 0.6, 0.2, 1.0),
+                );
+                hit_stop.frames = HIT_STOP_FRAMES;
+            }
+        }
+    }
+}
+
+// Outlines the hitbox of every swing while it is active
+pub(crate) fn draw_attacks(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let entities = world.entities();
+    let hitboxes = world.read_storage::<Hitbox>();
+    let attacks = world.read_storage::<Attack>();
+    let drawn = Drawn::new(world);
+
+    let mut mesh = graphics::MeshBuilder::new();
+    let mut any_swings = false;
+    for (entity, hitbox, _) in (&entities, &hitboxes, &attacks).join() {
+        let strike = match drawn.at(entity) {
+            Some(at) => hitbox.0.at(at),
+            None => continue,
+        };
+        mesh.rectangle(
+            graphics::DrawMode::stroke(2.0),
+            graphics::Rect::new(
+                strike.origin.x,
+                strike.origin.y,
+                strike.width,
+                strike.height,
+            ),
+            graphics::Color::new(1.0, 0.8, 0.3, 0.8),
+        );
+        any_swings = true;
+    }
+
+    if any_swings {
+        let mesh = mesh.build(ctx)?;
+        graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+    }
+    Ok(())
+}
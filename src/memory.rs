@@ -0,0 +1,191 @@
+use crate::ai::AiControlled;
+use crate::behavior::BehaviorTree;
+use crate::events::{Topic, TrackedChannel};
+use crate::floating_text::FloatingText;
+use crate::graze::Spark;
+use crate::health::{DamageEvent, DeathEvent, Health};
+use crate::hitbox::Hitbox;
+use crate::lifetime::Lifetime;
+use crate::patterns::BulletPattern;
+use crate::status::StatusEffects;
+use crate::time::GameClock;
+use crate::tween::Tween;
+use crate::utility_ai::UtilityAi;
+use crate::weapons::{Projectile, ProjectilePool};
+use crate::{CollisionBox, CollisionEvent, ImageHandle, Position, Rotation};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+use std::mem::size_of;
+
+// how often the overlay measures again, in seconds
+const REFRESH_TIME: f64 = 1.0;
+const OVERLAY_MARGIN: f32 = 10.0;
+
+// What the assets the game loaded take up, noted as they are loaded since
+// ggez doesn't say once they are
+#[derive(Debug, Default)]
+pub(crate) struct AssetSizes {
+    sizes: Vec<(String, usize)>,
+}
+
+impl AssetSizes {
+    // four bytes a pixel on the graphics card
+    pub(crate) fn texture(&mut self, name: &str, image: &graphics::Image) {
+        let bytes = usize::from(image.width()) * usize::from(image.height()) * 4;
+        self.sizes.push((format!("texture {}", name), bytes));
+    }
+
+    // ggez keeps the whole file in memory and decodes it as it plays
+    #[cfg(feature = "audio")]
+    pub(crate) fn sound(&mut self, ctx: &mut Context, path: &str) -> GameResult<()> {
+        use ggez::filesystem;
+        use std::io::Read;
+
+        let mut bytes = Vec::new();
+        filesystem::open(ctx, path)?.read_to_end(&mut bytes)?;
+        self.sizes.push((format!("sound {}", path), bytes.len()));
+        Ok(())
+    }
+}
+
+// Roughly what a component's storage holds: how many there are at the size of
+// one. Storages also keep some slack, so this is a lower bound.
+fn storage_bytes<T: Component>(world: &World, name: &str) -> (String, usize) {
+    let count = world.read_storage::<T>().join().count();
+    (format!("{} x{}", name, count), count * size_of::<T>())
+}
+
+// The events a channel is holding on to for each of its readers
+fn channel_backlog<E: Topic>(world: &World) -> Vec<(String, usize)> {
+    world
+        .read_resource::<TrackedChannel<E>>()
+        .backlog()
+        .into_iter()
+        .map(|(reader, backlog)| {
+            (
+                format!("{} backlog {} x{}", E::NAME, reader, backlog),
+                backlog * size_of::<E>(),
+            )
+        })
+        .collect()
+}
+
+// Approximate memory use, a line per thing measured
+pub(crate) fn measure(world: &World) -> Vec<(String, usize)> {
+    let mut report = vec![
+        storage_bytes::<Position>(world, "Position"),
+        storage_bytes::<CollisionBox>(world, "CollisionBox"),
+        storage_bytes::<Rotation>(world, "Rotation"),
+        storage_bytes::<ImageHandle>(world, "ImageHandle"),
+        storage_bytes::<Health>(world, "Health"),
+        storage_bytes::<Projectile>(world, "Projectile"),
+        storage_bytes::<Hitbox>(world, "Hitbox"),
+        storage_bytes::<Lifetime>(world, "Lifetime"),
+        storage_bytes::<Tween>(world, "Tween"),
+        storage_bytes::<Spark>(world, "Spark"),
+        storage_bytes::<FloatingText>(world, "FloatingText"),
+        storage_bytes::<AiControlled>(world, "AiControlled"),
+        storage_bytes::<BehaviorTree>(world, "BehaviorTree"),
+        storage_bytes::<UtilityAi>(world, "UtilityAi"),
+        storage_bytes::<BulletPattern>(world, "BulletPattern"),
+        storage_bytes::<StatusEffects>(world, "StatusEffects"),
+    ];
+
+    // a pooled projectile keeps all of its components while it waits
+    let pooled = world.read_resource::<ProjectilePool>().free();
+    report.push((
+        format!("projectile pool x{}", pooled),
+        pooled * (size_of::<Position>() + size_of::<Projectile>() + size_of::<Hitbox>()),
+    ));
+
+    report.extend(channel_backlog::<DamageEvent>(world));
+    report.extend(channel_backlog::<DeathEvent>(world));
+    report.extend(channel_backlog::<CollisionEvent>(world));
+
+    report.extend(world.read_resource::<AssetSizes>().sizes.iter().cloned());
+    report
+}
+
+fn kilobytes(bytes: usize) -> f32 {
+    bytes as f32 / 1024.0
+}
+
+// A corner of the screen showing what measure() finds, toggled from the
+// keyboard. Opening it also prints the full report to the console.
+#[derive(Default)]
+pub(crate) struct MemoryOverlay {
+    pub(crate) visible: bool,
+    // unscaled game clock time of the next measurement
+    refresh_at: f64,
+    report: Vec<(String, usize)>,
+}
+
+impl MemoryOverlay {
+    pub(crate) fn toggle(&mut self, world: &World) {
+        self.visible = !self.visible;
+        if self.visible {
+            self.report = measure(world);
+            self.refresh_at = world.read_resource::<GameClock>().unscaled + REFRESH_TIME;
+            println!("Memory use:");
+            for (name, bytes) in self.report.iter() {
+                println!("  {:<32} {:>10.1} KB", name, kilobytes(*bytes));
+            }
+            println!("  {:<32} {:>10.1} KB", "total", kilobytes(self.total()));
+        }
+    }
+
+    fn total(&self) -> usize {
+        self.report.iter().map(|(_, bytes)| bytes).sum()
+    }
+
+    // once a frame, it only measures every REFRESH_TIME seconds
+    pub(crate) fn update(&mut self, world: &World) {
+        if !self.visible {
+            return;
+        }
+        let now = world.read_resource::<GameClock>().unscaled;
+        if now >= self.refresh_at {
+            self.refresh_at = now + REFRESH_TIME;
+            self.report = measure(world);
+        }
+    }
+}
+
+pub(crate) fn draw_memory_overlay(ctx: &mut Context, overlay: &MemoryOverlay) -> GameResult<()> {
+    if !overlay.visible {
+        return Ok(());
+    }
+
+    let mut lines: Vec<String> = overlay
+        .report
+        .iter()
+        .map(|(name, bytes)| format!("{:<32} {:>8.1} KB", name, kilobytes(*bytes)))
+        .collect();
+    lines.push(format!(
+        "{:<32} {:>8.1} KB",
+        "total",
+        kilobytes(overlay.total())
+    ));
+    let text = graphics::Text::new(lines.join("\n"));
+
+    let view = graphics::screen_coordinates(ctx);
+    let (width, height) = text.dimensions(ctx);
+    let background = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(
+            view.x,
+            view.y + view.h - height as f32 - OVERLAY_MARGIN * 2.0,
+            width as f32 + OVERLAY_MARGIN * 2.0,
+            height as f32 + OVERLAY_MARGIN * 2.0,
+        ),
+        graphics::Color::new(0.0, 0.0, 0.0, 0.7),
+    )?;
+    graphics::draw(ctx, &background, graphics::DrawParam::default())?;
+    let corner = nalgebra::Point2::new(
+        view.x + OVERLAY_MARGIN,
+        view.y + view.h - height as f32 - OVERLAY_MARGIN,
+    );
+    graphics::draw(ctx, &text, graphics::DrawParam::default().dest(corner))
+}
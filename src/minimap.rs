@@ -0,0 +1,104 @@
+use crate::faction::Faction;
+use crate::radar::Pulse;
+use crate::stealth::{self, Cloaked, Revealed};
+use crate::{CollisionBox, ControllableTag, Position};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+
+// size of the minimap on screen in pixels and how much of the world it covers,
+// in world units either side of the player
+const MINIMAP_SIZE: f32 = 150.0;
+const MINIMAP_RANGE: f32 = 1000.0;
+const MINIMAP_MARGIN: f32 = 10.0;
+
+// Draws a small map in the bottom right corner centred on the player.
+// Cloaked entities are left off the map, and so is anything out of range,
+// unless it has been revealed. Revealed entities out of range are pinned to the
+// edge of the map so the player can still see which way they are.
+pub(crate) fn draw_minimap(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let pos = world.read_storage::<Position>();
+    let coll_box = world.read_storage::<CollisionBox>();
+    let controlled = world.read_storage::<ControllableTag>();
+    let cloaked = world.read_storage::<Cloaked>();
+    let revealed = world.read_storage::<Revealed>();
+    let pulses = world.read_storage::<Pulse>();
+    let factions = world.read_storage::<Faction>();
+
+    let player = match (&coll_box, &controlled).join().next() {
+        Some((player_box, _)) => player_box.center(),
+        None => return Ok(()),
+    };
+
+    let view = graphics::screen_coordinates(ctx);
+    let map = graphics::Rect::new(
+        view.x + view.w - MINIMAP_SIZE - MINIMAP_MARGIN,
+        view.y + view.h - MINIMAP_SIZE - MINIMAP_MARGIN,
+        MINIMAP_SIZE,
+        MINIMAP_SIZE,
+    );
+    let map_center = nalgebra::Point2::new(map.x + map.w / 2.0, map.y + map.h / 2.0);
+    let scale = (MINIMAP_SIZE / 2.0) / MINIMAP_RANGE;
+
+    let mut mesh = graphics::MeshBuilder::new();
+    mesh.rectangle(
+        graphics::DrawMode::fill(),
+        map,
+        graphics::Color::new(0.0, 0.1, 0.0, 0.6),
+    );
+    mesh.rectangle(
+        graphics::DrawMode::stroke(1.0),
+        map,
+        graphics::Color::new(0.3, 1.0, 0.6, 0.8),
+    );
+
+    for (pos, pulse) in (&pos, &pulses).join() {
+        if pulse.radius > 0.0 {
+            mesh.circle(
+                graphics::DrawMode::stroke(1.0),
+                map_center + (pos.position - player) * scale,
+                pulse.radius * scale,
+                0.5,
+                graphics::Color::new(0.3, 1.0, 0.6, 0.5),
+            );
+        }
+    }
+
+    for (coll_box, player_tag, cloak, reveal, side) in (
+        &coll_box,
+        controlled.maybe(),
+        cloaked.maybe(),
+        revealed.maybe(),
+        factions.maybe(),
+    )
+        .join()
+    {
+        let offset = coll_box.center() - player;
+        let in_range = offset.x.abs() <= MINIMAP_RANGE && offset.y.abs() <= MINIMAP_RANGE;
+        let visible = in_range || reveal.is_some();
+        if !visible || stealth::is_hidden(cloak, reveal) {
+            continue;
+        }
+
+        let half = MINIMAP_RANGE;
+        let pinned =
+            nalgebra::Vector2::new(offset.x.max(-half).min(half), offset.y.max(-half).min(half));
+        // the player is always white so they can find themselves, everyone
+        // else takes their team colour
+        let color = match (player_tag, side) {
+            (Some(_), _) => graphics::WHITE,
+            (None, Some(side)) => side.color(),
+            (None, None) => graphics::Color::new(0.6, 0.6, 0.6, 1.0),
+        };
+        mesh.circle(
+            graphics::DrawMode::fill(),
+            map_center + pinned * scale,
+            2.5,
+            0.5,
+            color,
+        );
+    }
+
+    let mesh = mesh.build(ctx)?;
+    graphics::draw(ctx, &mesh, graphics::DrawParam::default())
+}
@@ -0,0 +1,206 @@
+use crate::ai::AiControlled;
+use crate::audio::MusicTrack;
+use crate::events::{self, Subscribe, TrackedReader};
+use crate::fixed;
+use crate::health::{DamageEvent, Health};
+use crate::patterns::BulletPattern;
+use crate::platform::{self, Stem};
+use crate::time::{DeltaTime, GameClock};
+use crate::ControllableTag;
+use ggez::{Context, GameResult};
+use serde::Deserialize;
+use specs::*;
+
+// this many enemies on the field counts as full intensity on their own
+const ENEMIES_FOR_FULL: f32 = 8.0;
+// how much each hit on the player adds, wearing off over a few seconds
+const HIT_SPIKE: f32 = 0.15;
+const SPIKE_FADE: f32 = 0.1;
+// how quickly intensity follows the situation, per second. It climbs faster
+// than it falls, so the music doesn't calm down between two waves.
+const RISE: f32 = 1.0;
+const FALL: f32 = 0.15;
+// combat music comes in above the first level and goes back to calm below the
+// second, so hovering around one doesn't keep switching
+const COMBAT_ABOVE: f32 = 0.4;
+const CALM_BELOW: f32 = 0.25;
+// seconds for one layer to fade out and the next in
+const CROSSFADE: f32 = 2.0;
+
+// How heated the game is right now, from 0 to 1
+#[derive(Debug, Default)]
+pub(crate) struct Intensity {
+    pub(crate) level: f32,
+    // short lived extra from the player being hit
+    spike: f32,
+    // a boss is on the field
+    pub(crate) boss: bool,
+    // the layer a level's script asked for, played whatever the intensity
+    pub(crate) scripted: Option<Layer>,
+}
+
+// Works out the Intensity from the number of enemies, how hurt the player is,
+// the player being hit and whether there is a boss about
+pub(crate) struct IntensitySystem {
+    damage: TrackedReader<DamageEvent>,
+}
+
+impl IntensitySystem {
+    // the damage channel has to be in the world already
+    pub(crate) fn new(world: &World) -> Self {
+        IntensitySystem {
+            damage: events::subscribe::<DamageEvent>(world, "music"),
+        }
+    }
+}
+
+impl<'a> System<'a> for IntensitySystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Write<'a, Intensity>,
+        Subscribe<'a, DamageEvent>,
+        ReadStorage<'a, AiControlled>,
+        ReadStorage<'a, BulletPattern>,
+        ReadStorage<'a, ControllableTag>,
+        ReadStorage<'a, Health>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (delta, mut intensity, damage, ai, patterns, controlled, health) = data;
+        let dt = delta.seconds;
+
+        let hits = damage
+            .read(&mut self.damage)
+            .filter(|event| controlled.get(event.target).is_some())
+            .count();
+        intensity.spike = (intensity.spike - SPIKE_FADE * dt + hits as f32 * HIT_SPIKE).max(0.0);
+
+        let enemies = (&ai).join().count() as f32 / ENEMIES_FOR_FULL;
+        let hurt = (&health, &controlled)
+            .join()
+            .next()
+            .map_or(0.0, |(health, _)| {
+                1.0 - fixed::float(health.current) / fixed::float(health.max)
+            });
+        let target = (enemies * 0.6 + hurt * 0.4 + intensity.spike)
+            .max(0.0)
+            .min(1.0);
+
+        let rate = if target > intensity.level { RISE } else { FALL };
+        intensity.level += (target - intensity.level) * (rate * dt).min(1.0);
+        intensity.boss = (&ai, &patterns).join().next().is_some();
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize)]
+pub(crate) enum Layer {
+    Calm,
+    Combat,
+    Boss,
+}
+
+const LAYERS: [(Layer, &str); 3] = [
+    (Layer::Calm, "/music/calm.ogg"),
+    (Layer::Combat, "/music/combat.ogg"),
+    (Layer::Boss, "/music/boss.ogg"),
+];
+
+// Plays a looping stem for each layer of music all the time, and crossfades
+// between them as the Intensity changes, so a layer always comes back in at
+// the right point in the bar. A missing stem is reported and left silent.
+//
+// A MusicTrack takes over from the layers while there is one. The track
+// playing fades out before another starts, and back to the layers when
+// there's none.
+pub(crate) struct MusicDirector {
+    stems: Vec<(Layer, Box<dyn Stem>, f32)>,
+    playing: Layer,
+    // the MusicTrack being played, and how loud it is
+    track: Option<(String, Box<dyn Stem>)>,
+    track_volume: f32,
+    // unscaled game clock time of the last update
+    updated_at: f64,
+}
+
+impl MusicDirector {
+    pub(crate) fn new(ctx: &mut Context) -> GameResult<Self> {
+        let mut stems = Vec::new();
+        for (layer, path) in LAYERS.iter() {
+            let mut stem = platform::load_stem(ctx, path);
+            stem.start()?;
+            stems.push((*layer, stem, 0.0));
+        }
+        Ok(MusicDirector {
+            stems,
+            playing: Layer::Calm,
+            track: None,
+            track_volume: 0.0,
+            updated_at: 0.0,
+        })
+    }
+
+    // Once a frame. The fades run on real time, so the music keeps moving
+    // while the game is paused or slowed.
+    pub(crate) fn update(&mut self, ctx: &mut Context, world: &World) -> GameResult<()> {
+        let now = world.read_resource::<GameClock>().unscaled;
+        let dt = (now - self.updated_at) as f32;
+        self.updated_at = now;
+
+        let intensity = world.read_resource::<Intensity>();
+        self.playing = if let Some(layer) = intensity.scripted {
+            layer
+        } else if intensity.boss {
+            Layer::Boss
+        } else if intensity.level > COMBAT_ABOVE {
+            Layer::Combat
+        } else if intensity.level < CALM_BELOW || self.playing == Layer::Boss {
+            Layer::Calm
+        } else {
+            self.playing
+        };
+
+        // a new track waits for the old one to fade out
+        let wanted = world.read_resource::<MusicTrack>().path.clone();
+        let current = self.track.as_ref().map(|(path, _)| path.clone());
+        if wanted != current && self.track_volume <= 0.0 {
+            self.track = match wanted.clone() {
+                Some(path) => {
+                    let mut stem = platform::load_stem(ctx, &path);
+                    stem.start()?;
+                    Some((path, stem))
+                }
+                None => None,
+            };
+        }
+        let track_on =
+            wanted.is_some() && wanted == self.track.as_ref().map(|(path, _)| path.clone());
+
+        let step = (dt / CROSSFADE).max(0.0);
+        for (layer, stem, volume) in self.stems.iter_mut() {
+            let target = if *layer == self.playing && wanted.is_none() {
+                1.0
+            } else {
+                0.0
+            };
+            fade(stem.as_mut(), volume, target, step);
+        }
+        if let Some((_, stem)) = self.track.as_mut() {
+            let target = if track_on { 1.0 } else { 0.0 };
+            fade(stem.as_mut(), &mut self.track_volume, target, step);
+        }
+        Ok(())
+    }
+}
+
+// moves a stem's volume a step toward where it's going
+fn fade(stem: &mut dyn Stem, volume: &mut f32, target: f32, step: f32) {
+    let faded = if target > *volume {
+        (*volume + step).min(target)
+    } else {
+        (*volume - step).max(target)
+    };
+    if faded != *volume {
+        *volume = faded;
+        stem.set_volume(faded);
+    }
+}
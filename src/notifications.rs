@@ -0,0 +1,113 @@
+use crate::theme::UiTheme;
+use crate::time::DeltaTime;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+use std::collections::VecDeque;
+
+// seconds a toast stays up for, including sliding in and out
+const TOAST_TIME: f32 = 3.0;
+const SLIDE_TIME: f32 = 0.3;
+const TOAST_MARGIN: f32 = 10.0;
+
+#[derive(Debug)]
+struct Toast {
+    text: String,
+    age: f32,
+}
+
+impl Toast {
+    // how far onto the screen the toast has slid, from 0 (hidden) to 1
+    fn shown(&self) -> f32 {
+        let slide_in = self.age / SLIDE_TIME;
+        let slide_out = (TOAST_TIME - self.age) / SLIDE_TIME;
+        slide_in.min(slide_out).max(0.0).min(1.0)
+    }
+}
+
+// Toast messages shown in the top right corner of the screen. Anything can
+// push a message, only max_visible are shown at once and the rest wait their
+// turn in the queue.
+#[derive(Debug)]
+pub(crate) struct Notifications {
+    pending: VecDeque<String>,
+    visible: Vec<Toast>,
+    pub(crate) max_visible: usize,
+}
+
+impl Default for Notifications {
+    fn default() -> Self {
+        Notifications {
+            pending: VecDeque::new(),
+            visible: Vec::new(),
+            max_visible: 3,
+        }
+    }
+}
+
+impl Notifications {
+    pub(crate) fn push(&mut self, text: &str) {
+        self.pending.push_back(text.to_owned());
+    }
+}
+
+// Ages the visible toasts, removes finished ones and brings in queued ones as
+// space frees up
+pub(crate) struct NotificationSystem;
+
+impl<'a> System<'a> for NotificationSystem {
+    type SystemData = (Read<'a, DeltaTime>, Write<'a, Notifications>);
+
+    fn run(&mut self, (delta, mut notifications): Self::SystemData) {
+        let dt = delta.seconds;
+
+        for toast in notifications.visible.iter_mut() {
+            toast.age += dt;
+        }
+        notifications.visible.retain(|toast| toast.age < TOAST_TIME);
+
+        while notifications.visible.len() < notifications.max_visible {
+            match notifications.pending.pop_front() {
+                Some(text) => notifications.visible.push(Toast { text, age: 0.0 }),
+                None => break,
+            }
+        }
+    }
+}
+
+pub(crate) fn draw_notifications(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let notifications = world.read_resource::<Notifications>();
+    if notifications.visible.is_empty() {
+        return Ok(());
+    }
+
+    let theme = world.read_resource::<UiTheme>();
+    let padding = theme.padding;
+    let view = graphics::screen_coordinates(ctx);
+    let mut y = view.y + TOAST_MARGIN;
+
+    for toast in notifications.visible.iter() {
+        let text = theme.text(toast.text.as_str());
+        let (width, height) = text.dimensions(ctx);
+        let box_w = width as f32 + padding * 2.0;
+        let box_h = height as f32 + padding * 2.0;
+
+        // slide in from just past the right hand edge of the screen
+        let x = view.x + view.w - (box_w + TOAST_MARGIN) * toast.shown();
+        theme.draw_panel(ctx, graphics::Rect::new(x, y, box_w, box_h), 1.0)?;
+        graphics::queue_text(
+            ctx,
+            &text,
+            nalgebra::Point2::new(x + padding, y + padding),
+            Some(theme.text),
+        );
+        y += box_h + TOAST_MARGIN / 2.0;
+    }
+
+    graphics::draw_queued_text(
+        ctx,
+        graphics::DrawParam::default(),
+        None,
+        graphics::FilterMode::Linear,
+    )
+}
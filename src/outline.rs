@@ -0,0 +1,164 @@
+use crate::controls::Aim;
+use crate::interpolation::Drawn;
+use crate::render::Placement;
+use crate::settings::Settings;
+use crate::shaders::Outline;
+use crate::stealth::{self, Cloaked, Revealed};
+use crate::targeting::LockOn;
+use crate::{Animation, Assets, CollisionBox, ImageHandle, Transform};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(crate) struct OutlineStyle {
+    pub(crate) color: [f32; 4],
+    // in pixels
+    pub(crate) thickness: f32,
+}
+
+// How each kind of highlighted entity is outlined, part of the settings
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Outlines {
+    // under the mouse cursor
+    pub(crate) hovered: OutlineStyle,
+    pub(crate) selected: OutlineStyle,
+    pub(crate) locked_on: OutlineStyle,
+}
+
+impl Default for Outlines {
+    fn default() -> Self {
+        Outlines {
+            hovered: OutlineStyle {
+                color: [1.0, 1.0, 1.0, 0.6],
+                thickness: 1.0,
+            },
+            selected: OutlineStyle {
+                color: [1.0, 0.85, 0.2, 1.0],
+                thickness: 2.0,
+            },
+            locked_on: OutlineStyle {
+                color: [1.0, 0.2, 0.2, 1.0],
+                thickness: 2.0,
+            },
+        }
+    }
+}
+
+// Tags entities picked out with the middle mouse button
+#[derive(Component, Debug, Default)]
+#[storage(NullStorage)]
+pub(crate) struct Selected;
+
+// The topmost sprite under a point in the world, if any. Sprites are drawn in
+// join order, so the last one found is the one on top.
+pub(crate) fn pick(world: &World, point: nalgebra::Point2<f32>) -> Option<Entity> {
+    let entities = world.entities();
+    let images = world.read_storage::<ImageHandle>();
+    let coll_box = world.read_storage::<CollisionBox>();
+    (&entities, &images, &coll_box)
+        .join()
+        .filter(|(_, _, coll_box)| coll_box.contains(point))
+        .map(|(entity, _, _)| entity)
+        .last()
+}
+
+// Selects whatever is under the point, or drops it if it was already selected
+pub(crate) fn toggle_selected(world: &World, point: nalgebra::Point2<f32>) {
+    let entity = match pick(world, point) {
+        Some(entity) => entity,
+        None => return,
+    };
+    let mut selected = world.write_storage::<Selected>();
+    if selected.remove(entity).is_none() {
+        selected.insert(entity, Selected).unwrap_or_else(|err| {
+            println!("selected error {:?}", err);
+            None
+        });
+    }
+}
+
+// The eight directions an outline is drawn out in
+const DIRECTIONS: [(f32, f32); 8] = [
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (0.7, 0.7),
+    (-0.7, 0.7),
+    (0.7, -0.7),
+    (-0.7, -0.7),
+];
+
+// Outlines the locked on, selected and hovered entities by drawing their
+// sprites as flat silhouettes nudged out in every direction, before the
+// sprites themselves are drawn over the middle. An entity that is more than
+// one of those gets the outline of whichever comes first.
+pub(crate) fn draw_outlines(
+    ctx: &mut Context,
+    world: &World,
+    shader: &graphics::Shader<Outline>,
+) -> GameResult<()> {
+    let styles = &world.read_resource::<Settings>().outlines;
+    let lock = world.read_resource::<LockOn>();
+    let hovered = pick(world, world.read_resource::<Aim>().cursor);
+    let entities = world.entities();
+    let selected = world.read_storage::<Selected>();
+
+    let mut outlined: Vec<(Entity, OutlineStyle)> = Vec::new();
+    let mut outline = |entity: Entity, style: OutlineStyle| {
+        if !outlined.iter().any(|(already, _)| *already == entity) {
+            outlined.push((entity, style));
+        }
+    };
+    if let Some(target) = lock.target {
+        outline(target, styles.locked_on);
+    }
+    for (entity, _) in (&entities, &selected).join() {
+        outline(entity, styles.selected);
+    }
+    if let Some(entity) = hovered {
+        outline(entity, styles.hovered);
+    }
+    if outlined.is_empty() {
+        return Ok(());
+    }
+
+    let drawn = Drawn::new(world);
+    let assets = world.read_resource::<Assets>();
+    let images = world.read_storage::<ImageHandle>();
+    let animations = world.read_storage::<Animation>();
+    let transforms = world.read_storage::<Transform>();
+    let cloaked = world.read_storage::<Cloaked>();
+    let revealed = world.read_storage::<Revealed>();
+
+    let _lock = graphics::use_shader(ctx, shader);
+    for (entity, style) in outlined {
+        let (at, i, transform) =
+            match (drawn.at(entity), images.get(entity), transforms.get(entity)) {
+                (Some(at), Some(i), Some(transform)) => (at, i, transform),
+                _ => continue,
+            };
+        // an outline would give a cloaked ship away
+        if stealth::is_hidden(cloaked.get(entity), revealed.get(entity)) {
+            continue;
+        }
+        shader.send(ctx, Outline { color: style.color })?;
+
+        // placed the same way the sprite itself is drawn, part of the way
+        // between its last two positions
+        let placement = Placement::new(&assets, *i, animations.get(entity), transform, at);
+        for (x, y) in DIRECTIONS.iter() {
+            let nudge = nalgebra::Vector2::new(*x, *y) * style.thickness;
+            graphics::draw(
+                ctx,
+                assets.get(*i),
+                placement.param().dest(placement.center + nudge),
+            )?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,128 @@
+use crate::faction::Faction;
+use ggez::{graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
+
+// the ship image as drawn, not swapped to any palette
+pub(crate) const ORIGINAL: &str = "Original";
+
+// Three shades a sprite is redrawn in. Every pixel keeps its brightness but
+// takes its colour from the shades, dark pixels from the shadow through the
+// base to the highlight for the lightest.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Palette {
+    pub(crate) name: &'static str,
+    shadow: [u8; 3],
+    base: [u8; 3],
+    highlight: [u8; 3],
+}
+
+pub(crate) const PALETTES: [Palette; 5] = [
+    Palette {
+        name: "Blue",
+        shadow: [10, 20, 70],
+        base: [60, 110, 230],
+        highlight: [200, 225, 255],
+    },
+    Palette {
+        name: "Red",
+        shadow: [70, 10, 10],
+        base: [220, 60, 40],
+        highlight: [255, 210, 190],
+    },
+    Palette {
+        name: "Green",
+        shadow: [10, 50, 20],
+        base: [60, 190, 80],
+        highlight: [210, 255, 200],
+    },
+    Palette {
+        name: "Gold",
+        shadow: [70, 45, 5],
+        base: [220, 170, 40],
+        highlight: [255, 245, 190],
+    },
+    Palette {
+        name: "Violet",
+        shadow: [40, 10, 60],
+        base: [150, 70, 220],
+        highlight: [235, 205, 255],
+    },
+];
+
+// The palette after the named one, going through the original image as well
+pub(crate) fn next(name: &str) -> &'static str {
+    let names: Vec<&'static str> = std::iter::once(ORIGINAL)
+        .chain(PALETTES.iter().map(|palette| palette.name))
+        .collect();
+    let at = names.iter().position(|n| *n == name).unwrap_or(0);
+    names[(at + 1) % names.len()]
+}
+
+fn mix(a: [u8; 3], b: [u8; 3], t: f32) -> [u8; 3] {
+    let channel = |i: usize| (f32::from(a[i]) + (f32::from(b[i]) - f32::from(a[i])) * t) as u8;
+    [channel(0), channel(1), channel(2)]
+}
+
+impl Palette {
+    // the colour for a brightness from 0 to 1
+    fn shade(&self, brightness: f32) -> [u8; 3] {
+        if brightness < 0.5 {
+            mix(self.shadow, self.base, brightness * 2.0)
+        } else {
+            mix(self.base, self.highlight, (brightness - 0.5) * 2.0)
+        }
+    }
+}
+
+// A copy of the image redrawn in the palette. It is done once at load, so
+// drawing a swapped sprite costs no more than drawing the original.
+pub(crate) fn recolor(
+    ctx: &mut Context,
+    image: &graphics::Image,
+    palette: &Palette,
+) -> GameResult<graphics::Image> {
+    let mut pixels = image.to_rgba8(ctx)?;
+    for pixel in pixels.chunks_mut(4) {
+        if pixel[3] == 0 {
+            continue;
+        }
+        let brightness = (0.299 * f32::from(pixel[0])
+            + 0.587 * f32::from(pixel[1])
+            + 0.114 * f32::from(pixel[2]))
+            / 255.0;
+        pixel[..3].copy_from_slice(&palette.shade(brightness));
+    }
+    graphics::Image::from_rgba8(ctx, image.width(), image.height(), &pixels)
+}
+
+// Which palette each side's ships are drawn in, by name. The player's own ship
+// can have its own, picked with the ship colour action and saved with the
+// settings.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct TeamColors {
+    pub(crate) player: String,
+    pub(crate) blue: String,
+    pub(crate) red: String,
+}
+
+impl Default for TeamColors {
+    fn default() -> Self {
+        TeamColors {
+            player: ORIGINAL.to_owned(),
+            blue: "Blue".to_owned(),
+            red: "Red".to_owned(),
+        }
+    }
+}
+
+impl TeamColors {
+    pub(crate) fn palette(&self, faction: Option<Faction>, player: bool) -> &str {
+        match (player, faction) {
+            (true, _) => &self.player,
+            (false, Some(Faction::Blue)) => &self.blue,
+            (false, Some(Faction::Red)) => &self.red,
+            (false, None) => ORIGINAL,
+        }
+    }
+}
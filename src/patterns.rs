@@ -0,0 +1,263 @@
+use crate::data;
+use crate::faction::{self, Faction};
+use crate::hitbox::Hitbox;
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::weapons::{Projectile, ProjectilePool};
+use crate::{CollisionBox, ControllableTag, Position, Rotation};
+use ggez::nalgebra;
+use ggez::{Context, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::sync::Arc;
+
+// how long pattern shots stay alive, they tend to be slow so get longer than
+// ordinary weapon fire
+const PATTERN_LIFETIME: f32 = 5.0;
+// patterns only run while a player ship is this close
+const PATTERN_RANGE: f32 = 700.0;
+
+// One step of a bullet pattern. Angles are in radians, clockwise with 0 facing
+// up the screen like Rotation, speeds are pixels per second.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) enum Emitter {
+    // a full circle of shots at once
+    Ring {
+        count: u32,
+        speed: f32,
+    },
+    // shots from evenly spaced arms, turning a little after every shot
+    Spiral {
+        arms: u32,
+        speed: f32,
+        turn: f32,
+        shots: u32,
+        interval: f32,
+    },
+    // a fan of shots centred on the player
+    AimedSpread {
+        count: u32,
+        spread: f32,
+        speed: f32,
+    },
+    // nothing for a while
+    Wait(f32),
+}
+
+// A timed sequence of emitters, run one after the other. Loaded from RON files
+// in resources/patterns, for example
+//
+//     (damage: 5.0, repeat: true, steps: [Ring(count: 24, speed: 150.0), Wait(1.0)])
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct Pattern {
+    pub(crate) damage: f32,
+    pub(crate) repeat: bool,
+    pub(crate) steps: Vec<Emitter>,
+}
+
+// Reads a pattern from a RON file in the resources directory
+pub(crate) fn load(ctx: &mut Context, path: &str) -> GameResult<Pattern> {
+    data::load(ctx, path)
+}
+
+// The patterns loaded at startup, by name, so systems can hand them out to
+// entities they spawn without going back to the filesystem
+#[derive(Debug, Default)]
+pub(crate) struct PatternLibrary {
+    pub(crate) patterns: HashMap<String, Arc<Pattern>>,
+}
+
+// A bullet hell emitter attached to an entity, working through its pattern
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(crate) struct BulletPattern {
+    pattern: Arc<Pattern>,
+    step: usize,
+    // shots fired so far in the current step
+    shot: u32,
+    // seconds until the next shot, or until a wait is over
+    timer: f32,
+    // how far a spiral has turned
+    spin: f32,
+}
+
+impl BulletPattern {
+    pub(crate) fn new(pattern: Arc<Pattern>) -> Self {
+        BulletPattern {
+            pattern,
+            step: 0,
+            shot: 0,
+            timer: 0.0,
+            spin: 0.0,
+        }
+    }
+
+    // move on to the next step, returning false once a non repeating pattern
+    // has finished
+    fn next_step(&mut self) -> bool {
+        self.step += 1;
+        self.shot = 0;
+        self.timer = 0.0;
+        if self.step >= self.pattern.steps.len() {
+            if !self.pattern.repeat {
+                return false;
+            }
+            self.step = 0;
+        }
+        true
+    }
+}
+
+fn heading(angle: f32) -> nalgebra::Vector2<f32> {
+    nalgebra::Vector2::new(angle.sin(), -angle.cos())
+}
+
+// Runs every entity's bullet pattern, firing pooled projectiles from the
+// middle of the entity
+pub(crate) struct PatternSystem;
+
+impl<'a> System<'a> for PatternSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Write<'a, ProjectilePool>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, ControllableTag>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Projectile>,
+        WriteStorage<'a, Hitbox>,
+        WriteStorage<'a, BulletPattern>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            delta,
+            time,
+            mut pool,
+            coll_box,
+            controlled,
+            factions,
+            multipliers,
+            mut pos,
+            mut projectiles,
+            mut hitboxes,
+            mut patterns,
+        ) = data;
+        let mut finished = Vec::new();
+
+        for (owner, own_box, emitter) in (&entities, &coll_box, &mut patterns).join() {
+            let origin = own_box.center();
+            let side = factions.get(owner);
+
+            // the nearest hostile player ship, which patterns aim at and
+            // need to be close to run at all
+            let player = (&entities, &coll_box, &controlled)
+                .join()
+                .filter(|(player, _, _)| faction::hostile(side, factions.get(*player)))
+                .map(|(_, player_box, _)| player_box.center())
+                .filter(|center| (center - origin).norm() <= PATTERN_RANGE)
+                .min_by(|a, b| {
+                    (a - origin)
+                        .norm()
+                        .partial_cmp(&(b - origin).norm())
+                        .unwrap_or(std::cmp::Ordering::Equal)
+                });
+            let player = match player {
+                Some(player) => player,
+                None => continue,
+            };
+
+            emitter.timer -= time.scaled(delta.seconds, multipliers.get(owner));
+            if emitter.timer > 0.0 {
+                continue;
+            }
+
+            let pattern = emitter.pattern.clone();
+            let step = match pattern.steps.get(emitter.step) {
+                Some(step) => step,
+                None => continue,
+            };
+            let mut shots: Vec<(f32, f32)> = Vec::new();
+            let done = match step {
+                Emitter::Ring { count, speed } => {
+                    for i in 0..*count {
+                        shots.push((i as f32 / *count as f32 * 2.0 * PI, *speed));
+                    }
+                    true
+                }
+                Emitter::Spiral {
+                    arms,
+                    speed,
+                    turn,
+                    shots: total,
+                    interval,
+                } => {
+                    for i in 0..*arms {
+                        shots.push((emitter.spin + i as f32 / *arms as f32 * 2.0 * PI, *speed));
+                    }
+                    emitter.spin += turn;
+                    emitter.shot += 1;
+                    emitter.timer = *interval;
+                    emitter.shot >= *total
+                }
+                Emitter::AimedSpread {
+                    count,
+                    spread,
+                    speed,
+                } => {
+                    let aim = Rotation::facing(player - origin).angle;
+                    for i in 0..*count {
+                        let t = if *count > 1 {
+                            i as f32 / (*count - 1) as f32 - 0.5
+                        } else {
+                            0.0
+                        };
+                        shots.push((aim + t * spread, *speed));
+                    }
+                    true
+                }
+                Emitter::Wait(seconds) => {
+                    if emitter.shot == 0 {
+                        emitter.shot = 1;
+                        emitter.timer = *seconds;
+                        false
+                    } else {
+                        true
+                    }
+                }
+            };
+
+            for (angle, speed) in shots {
+                pool.spawn(
+                    &entities,
+                    &mut pos,
+                    &mut projectiles,
+                    &mut hitboxes,
+                    origin,
+                    Projectile {
+                        owner: Some(owner),
+                        velocity: heading(angle) * speed,
+                        damage: pattern.damage,
+                        time_left: PATTERN_LIFETIME,
+                        active: true,
+                        grazed: false,
+                    },
+                );
+            }
+
+            if done && !emitter.next_step() {
+                finished.push(owner);
+            }
+        }
+
+        for owner in finished {
+            patterns.remove(owner);
+        }
+    }
+}
@@ -0,0 +1,130 @@
+use crate::controls::Device;
+use crate::glyphs::{Glyphs, Piece};
+use crate::theme::UiTheme;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+
+const ROW_HEIGHT: f32 = 28.0;
+const MENU_TOP: f32 = 200.0;
+
+// What the player picked from the pause menu
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Choice {
+    Resume,
+    Restart,
+    Options,
+    Save,
+    Quit,
+}
+
+impl Choice {
+    fn label(self) -> &'static str {
+        match self {
+            Choice::Resume => "Resume",
+            Choice::Restart => "Restart",
+            Choice::Options => "Options",
+            Choice::Save => "Save game",
+            Choice::Quit => "Quit to menu",
+        }
+    }
+}
+
+// The menu over a paused game. The game holds still underneath, shaded,
+// until something is picked. It's worked with the arrows and Enter or the
+// d-pad and South, and Escape, East or Start go straight back to the game.
+pub(crate) struct PauseMenu {
+    choices: Vec<Choice>,
+    selected: usize,
+}
+
+impl PauseMenu {
+    // Save game is only on the menu if the game mode can be saved
+    pub(crate) fn new(can_save: bool) -> Self {
+        let mut choices = vec![Choice::Resume, Choice::Restart, Choice::Options];
+        if can_save {
+            choices.push(Choice::Save);
+        }
+        choices.push(Choice::Quit);
+        PauseMenu {
+            choices,
+            selected: 0,
+        }
+    }
+
+    // Takes a key or button by the name ggez prints for it, returning what
+    // was picked if anything
+    pub(crate) fn input(&mut self, input: &str) -> Option<Choice> {
+        let count = self.choices.len();
+        match input {
+            "Up" | "DPadUp" => self.selected = (self.selected + count - 1) % count,
+            "Down" | "DPadDown" => self.selected = (self.selected + 1) % count,
+            "Return" | "South" => return Some(self.choices[self.selected]),
+            "Escape" | "East" | "Start" => return Some(Choice::Resume),
+            _ => (),
+        }
+        None
+    }
+
+    // over whatever was drawn of the game this frame
+    pub(crate) fn draw(
+        &self,
+        ctx: &mut Context,
+        glyphs: &Glyphs,
+        theme: &UiTheme,
+        device: &Device,
+    ) -> GameResult<()> {
+        theme.draw_shade(ctx)?;
+
+        let view = graphics::screen_coordinates(ctx);
+        let left = view.x + view.w / 2.0 - 80.0;
+        let mut y = view.y + MENU_TOP;
+        graphics::draw(
+            ctx,
+            &theme.text("PAUSED"),
+            graphics::DrawParam::default()
+                .dest(nalgebra::Point2::new(left, y))
+                .color(theme.text),
+        )?;
+        y += ROW_HEIGHT * 1.5;
+        for (i, choice) in self.choices.iter().enumerate() {
+            let color = if i == self.selected {
+                theme.text
+            } else {
+                theme.dim
+            };
+            let marker = if i == self.selected { "> " } else { "  " };
+            graphics::draw(
+                ctx,
+                &theme.text(format!("{}{}", marker, choice.label())),
+                graphics::DrawParam::default()
+                    .dest(nalgebra::Point2::new(left, y))
+                    .color(color),
+            )?;
+            y += ROW_HEIGHT;
+        }
+
+        y += ROW_HEIGHT / 2.0;
+        let help = if *device == Device::Keyboard {
+            vec![
+                Piece::Key("↑".to_owned()),
+                Piece::Key("↓".to_owned()),
+                Piece::Text(" choose  ".to_owned()),
+                Piece::Key("Enter".to_owned()),
+                Piece::Text(" pick  ".to_owned()),
+                Piece::Key("Escape".to_owned()),
+                Piece::Text(" resume".to_owned()),
+            ]
+        } else {
+            vec![
+                Piece::Button("DPadUp".to_owned()),
+                Piece::Button("DPadDown".to_owned()),
+                Piece::Text(" choose  ".to_owned()),
+                Piece::Button("South".to_owned()),
+                Piece::Text(" pick  ".to_owned()),
+                Piece::Button("East".to_owned()),
+                Piece::Text(" resume".to_owned()),
+            ]
+        };
+        glyphs.draw(ctx, theme, &help, nalgebra::Point2::new(left, y), theme.dim)
+    }
+}
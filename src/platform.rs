@@ -0,0 +1,177 @@
+use crate::sfxr;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::storage::FileStorage;
+#[cfg(target_arch = "wasm32")]
+use crate::storage::MemoryStorage;
+use crate::storage::Storage;
+#[cfg(feature = "audio")]
+use ggez::audio::{self, SoundSource};
+#[cfg(not(target_arch = "wasm32"))]
+use ggez::filesystem;
+use ggez::{Context, GameResult};
+use std::path;
+use std::rc::Rc;
+
+// The parts of the game that depend on what it is running on. Everything else
+// goes through here rather than reaching for the environment, the clock or the
+// sound card itself, so a web build (target_arch = "wasm32", e.g. on top of
+// good-web-game) only has to fill in this module. Browsers have no threads
+// either, which is what the parallel feature is for.
+
+// Where resources are loaded from. Native builds use the crate's resources
+// directory when run through cargo, the web build fetches them relative to the
+// page.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn resource_dir() -> path::PathBuf {
+    if let Ok(manifest_dir) = std::env::var("CARGO_MANIFEST_DIR") {
+        let mut path = path::PathBuf::from(manifest_dir);
+        path.push("resources");
+        path
+    } else {
+        path::PathBuf::from("./resources")
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn resource_dir() -> path::PathBuf {
+    path::PathBuf::from("resources")
+}
+
+// Seconds since the Unix epoch. The web has no SystemTime, so it is always 0
+// there until there is a browser clock to ask.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn unix_time() -> u64 {
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn unix_time() -> u64 {
+    0
+}
+
+// A seed for games started without one. Every web game starts from the same
+// seed for now.
+pub(crate) fn clock_seed() -> u64 {
+    unix_time()
+}
+
+// Where the player's files are kept. Native builds use the user data directory
+// ggez picked for the platform. The web build only keeps them until the page
+// closes, until there is a backend for the browser's local storage.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn storage(ctx: &Context) -> Rc<dyn Storage> {
+    Rc::new(FileStorage::new(filesystem::user_data_dir(ctx).to_owned()))
+}
+
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn storage(_ctx: &Context) -> Rc<dyn Storage> {
+    Rc::new(MemoryStorage::default())
+}
+
+// A sound effect the game can play, volume going from 0 to 1
+pub(crate) trait Sound {
+    fn play(&mut self, pitch: f32, volume: f32) -> GameResult<()>;
+}
+
+#[cfg(feature = "audio")]
+impl Sound for audio::Source {
+    fn play(&mut self, pitch: f32, volume: f32) -> GameResult<()> {
+        self.set_pitch(pitch);
+        SoundSource::set_volume(self, volume);
+        SoundSource::play(self)
+    }
+}
+
+// A piece of music looping for as long as the game runs, faded in and out
+// rather than stopped so it stays in time with the others
+pub(crate) trait Stem {
+    // starts looping, silent until given a volume
+    fn start(&mut self) -> GameResult<()>;
+    fn set_volume(&mut self, volume: f32);
+}
+
+#[cfg(feature = "audio")]
+impl Stem for audio::Source {
+    fn start(&mut self) -> GameResult<()> {
+        self.set_repeat(true);
+        SoundSource::set_volume(self, 0.0);
+        SoundSource::play(self)
+    }
+
+    fn set_volume(&mut self, volume: f32) {
+        SoundSource::set_volume(self, volume);
+    }
+}
+
+// Stands in for every sound when the game is built without audio, and for
+// music that couldn't be loaded
+struct Silence;
+
+impl Sound for Silence {
+    fn play(&mut self, _pitch: f32, _volume: f32) -> GameResult<()> {
+        Ok(())
+    }
+}
+
+impl Stem for Silence {
+    fn start(&mut self) -> GameResult<()> {
+        Ok(())
+    }
+
+    fn set_volume(&mut self, _volume: f32) {}
+}
+
+#[cfg(feature = "audio")]
+pub(crate) fn load_sound(ctx: &mut Context, path: &str) -> GameResult<Box<dyn Sound>> {
+    Ok(Box::new(audio::Source::new(ctx, path)?))
+}
+
+#[cfg(not(feature = "audio"))]
+pub(crate) fn load_sound(_ctx: &mut Context, _path: &str) -> GameResult<Box<dyn Sound>> {
+    Ok(Box::new(Silence))
+}
+
+// Sounds asked for by name may not be there, and are left silent
+pub(crate) fn try_load_sound(ctx: &mut Context, path: &str) -> Box<dyn Sound> {
+    load_sound(ctx, path).unwrap_or_else(|err| {
+        println!("sound error {:?}", err);
+        Box::new(Silence)
+    })
+}
+
+// Music is optional, the game plays on in silence without it
+#[cfg(feature = "audio")]
+pub(crate) fn load_stem(ctx: &mut Context, path: &str) -> Box<dyn Stem> {
+    match audio::Source::new(ctx, path) {
+        Ok(source) => Box::new(source),
+        Err(err) => {
+            println!("music error {:?}", err);
+            Box::new(Silence)
+        }
+    }
+}
+
+#[cfg(not(feature = "audio"))]
+pub(crate) fn load_stem(_ctx: &mut Context, _path: &str) -> Box<dyn Stem> {
+    Box::new(Silence)
+}
+
+// A sound generated from one of the sfxr presets instead of loaded from a file
+#[cfg(feature = "audio")]
+pub(crate) fn synth_sound(ctx: &mut Context, preset: &sfxr::Preset) -> GameResult<Box<dyn Sound>> {
+    let data = audio::SoundData::from_bytes(&sfxr::wav(preset));
+    Ok(Box::new(audio::Source::from_data(ctx, data)?))
+}
+
+#[cfg(not(feature = "audio"))]
+pub(crate) fn synth_sound(
+    _ctx: &mut Context,
+    _preset: &sfxr::Preset,
+) -> GameResult<Box<dyn Sound>> {
+    Ok(Box::new(Silence))
+}
@@ -0,0 +1,259 @@
+use crate::ai::{AiControlled, Difficulty};
+use crate::data;
+use crate::faction::Faction;
+use crate::fixed;
+use crate::health::Health;
+use crate::settings::Settings;
+use crate::spawner::{ShipBuilder, Spawner};
+use crate::stealth::Cloaked;
+use crate::utility_ai::UtilityAi;
+use crate::weapons::Weapon;
+use crate::{Collider, ControllableTag};
+use ggez::{filesystem, nalgebra, Context, GameError, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+use std::collections::BTreeMap;
+
+const PATH: &str = "/prefabs.ron";
+
+// What an AI ship is made of, written in RON. Where it goes is kept apart, so
+// the same prefab can be placed anywhere, e.g.
+//
+//     (health: 150, weapon: Some((fire_delay: 0.6, projectile_speed: 400, damage: 10)),
+//      faction: Some(Red), ai: true)
+//
+// Anything left out is left at its default, a 100 health ship that does
+// nothing on nobody's side.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Prefab {
+    pub(crate) health: f32,
+    pub(crate) weapon: Option<Weapon>,
+    pub(crate) faction: Option<Faction>,
+    // flown by the AI at the game's difficulty
+    pub(crate) ai: bool,
+    // weighs up what to do with the utility AI as well
+    pub(crate) utility_ai: bool,
+    pub(crate) cloaked: bool,
+    // a shape to bump into things with other than the ship's box
+    pub(crate) collider: Option<Collider>,
+}
+
+impl Default for Prefab {
+    fn default() -> Self {
+        Prefab {
+            health: 100.0,
+            weapon: None,
+            faction: None,
+            ai: false,
+            utility_ai: false,
+            cloaked: false,
+            collider: None,
+        }
+    }
+}
+
+// How a prefab is written in resources/prefabs.ron. A prefab can include
+// others and only give what it changes, e.g.
+//
+//     {
+//         "enemy": (health: Some(100), ai: Some(true), faction: Some(Red)),
+//         "tough_enemy": (includes: ["enemy"], health: Some(250)),
+//     }
+//
+// Includes are applied in order, so a later one wins over an earlier one, and
+// the prefab's own fields win over all of them. The weapon is overridden as a
+// whole.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct PrefabDef {
+    includes: Vec<String>,
+    health: Option<f32>,
+    weapon: Option<Weapon>,
+    faction: Option<Faction>,
+    ai: Option<bool>,
+    utility_ai: Option<bool>,
+    cloaked: Option<bool>,
+    collider: Option<Collider>,
+}
+
+impl PrefabDef {
+    // these fields, with anything left out taken from the base
+    fn over(self, base: PrefabDef) -> PrefabDef {
+        PrefabDef {
+            includes: Vec::new(),
+            health: self.health.or(base.health),
+            weapon: self.weapon.or(base.weapon),
+            faction: self.faction.or(base.faction),
+            ai: self.ai.or(base.ai),
+            utility_ai: self.utility_ai.or(base.utility_ai),
+            cloaked: self.cloaked.or(base.cloaked),
+            collider: self.collider.or(base.collider),
+        }
+    }
+
+    fn finish(self) -> Prefab {
+        let default = Prefab::default();
+        Prefab {
+            health: self.health.unwrap_or(default.health),
+            weapon: self.weapon.or(default.weapon),
+            faction: self.faction.or(default.faction),
+            ai: self.ai.unwrap_or(default.ai),
+            utility_ai: self.utility_ai.unwrap_or(default.utility_ai),
+            cloaked: self.cloaked.unwrap_or(default.cloaked),
+            collider: self.collider.or(default.collider),
+        }
+    }
+}
+
+// The named prefabs from resources/prefabs.ron, with their includes already
+// worked out
+#[derive(Debug, Default)]
+pub(crate) struct Prefabs {
+    pub(crate) prefabs: BTreeMap<String, Prefab>,
+}
+
+impl Prefabs {
+    pub(crate) fn get(&self, name: &str) -> Option<&Prefab> {
+        self.prefabs.get(name)
+    }
+}
+
+// Reads the prefabs and resolves their includes. A prefab including one that
+// doesn't exist, or ending up including itself, fails the whole file. Without
+// a prefab file there are no prefabs.
+pub(crate) fn load(ctx: &mut Context) -> GameResult<Prefabs> {
+    if !filesystem::exists(ctx, PATH) {
+        return Ok(Prefabs::default());
+    }
+    resolve_all(PATH, data::load(ctx, PATH)?)
+}
+
+// the same as load, from the text of a prefab file
+pub(crate) fn parse(path: &str, text: &str) -> GameResult<Prefabs> {
+    resolve_all(path, data::parse(path, text)?)
+}
+
+fn resolve_all(path: &str, defs: BTreeMap<String, PrefabDef>) -> GameResult<Prefabs> {
+    let mut resolved = BTreeMap::new();
+    for name in defs.keys() {
+        resolve(name, &defs, &mut resolved, &mut Vec::new())
+            .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", path, err)))?;
+    }
+    Ok(Prefabs {
+        prefabs: resolved
+            .into_iter()
+            .map(|(name, def)| (name, def.finish()))
+            .collect(),
+    })
+}
+
+// Resolves a prefab's includes, depth first. The chain of prefabs being
+// resolved is kept to spot one that includes itself, however far down.
+fn resolve(
+    name: &str,
+    defs: &BTreeMap<String, PrefabDef>,
+    resolved: &mut BTreeMap<String, PrefabDef>,
+    chain: &mut Vec<String>,
+) -> Result<PrefabDef, String> {
+    if let Some(def) = resolved.get(name) {
+        return Ok(def.clone());
+    }
+    if chain.iter().any(|included| included == name) {
+        return Err(format!(
+            "prefab {} includes itself, {} -> {}",
+            name,
+            chain.join(" -> "),
+            name
+        ));
+    }
+    let def = match defs.get(name) {
+        Some(def) => def.clone(),
+        None => match chain.last() {
+            Some(includer) => return Err(format!("{} includes unknown prefab {}", includer, name)),
+            None => return Err(format!("unknown prefab {}", name)),
+        },
+    };
+    chain.push(name.to_owned());
+    let mut base = PrefabDef::default();
+    for include in &def.includes {
+        base = resolve(include, defs, resolved, chain)?.over(base);
+    }
+    chain.pop();
+    let def = def.over(base);
+    resolved.insert(name.to_owned(), def.clone());
+    Ok(def)
+}
+
+// The prefab a ship was made from, by name
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub(crate) struct FromPrefab(pub(crate) String);
+
+// A prefab and where its top left corner goes
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Placed {
+    pub(crate) prefab: Prefab,
+    pub(crate) at: (f32, f32),
+}
+
+// The prefab an entity could be made again from. Only AI ships have one, the
+// player's ship and things like projectiles don't.
+pub(crate) fn capture(world: &World, entity: Entity) -> Option<Prefab> {
+    if world.read_storage::<ControllableTag>().contains(entity) {
+        return None;
+    }
+    let health = world.read_storage::<Health>();
+    let health = health.get(entity)?;
+    Some(Prefab {
+        health: fixed::float(health.max),
+        weapon: world.read_storage::<Weapon>().get(entity).cloned(),
+        faction: world.read_storage::<Faction>().get(entity).cloned(),
+        ai: world.read_storage::<AiControlled>().contains(entity),
+        utility_ai: world.read_storage::<UtilityAi>().contains(entity),
+        cloaked: world.read_storage::<Cloaked>().contains(entity),
+        collider: world.read_storage::<Collider>().get(entity).cloned(),
+    })
+}
+
+// Spawns a prefab with its top left corner at the origin. It is queued with
+// LazyUpdate like any other spawn, and appears when the world is next
+// maintained.
+pub(crate) fn spawn(world: &World, prefab: &Prefab, origin: nalgebra::Point2<f32>) -> Entity {
+    let spawner = world.read_resource::<Spawner>();
+    let updater = world.read_resource::<LazyUpdate>();
+    let difficulty = world.read_resource::<Settings>().difficulty;
+    let ship = spawner
+        .ship(updater.create_entity(&world.entities()))
+        .at_point(origin);
+    apply(prefab, ship, difficulty).build()
+}
+
+// Sets a ship up as the prefab says, with its AI at the given difficulty
+pub(crate) fn apply<'s, B: Builder>(
+    prefab: &Prefab,
+    mut ship: ShipBuilder<'s, B>,
+    difficulty: Difficulty,
+) -> ShipBuilder<'s, B> {
+    ship = ship.health(prefab.health);
+    if let Some(weapon) = &prefab.weapon {
+        ship = ship.weapon(weapon.fire_delay, weapon.projectile_speed, weapon.damage);
+    }
+    if let Some(faction) = prefab.faction {
+        ship = ship.faction(faction);
+    }
+    if prefab.ai {
+        ship = ship.with(AiControlled::new(difficulty));
+    }
+    if prefab.utility_ai {
+        ship = ship.with(UtilityAi::default());
+    }
+    if prefab.cloaked {
+        ship = ship.with(Cloaked::default());
+    }
+    if let Some(collider) = &prefab.collider {
+        ship = ship.with(collider.clone());
+    }
+    ship
+}
@@ -0,0 +1,133 @@
+use crate::scene::{Enemy, Placed, Scene};
+use ggez::nalgebra;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+
+// tries at finding room for each thing before it's left out
+const ATTEMPTS: usize = 50;
+
+// How to make up a level's scene, from the level's RON file, e.g.
+//
+//     generate: Some((rocks: 12, hazards: 2, pickups: 3, enemies: ["sniper"])),
+//
+// Things are scattered over the area at random, keeping a gap between each
+// other and clear of the keep_clear points, where the ships start out.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Generator {
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+    pub(crate) rocks: usize,
+    // smallest and largest rock, in pixels across
+    pub(crate) rock_size: (f32, f32),
+    pub(crate) hazards: usize,
+    pub(crate) hazard_size: (f32, f32),
+    pub(crate) pickups: usize,
+    pub(crate) pickup_size: f32,
+    // a prefab for each enemy placed
+    pub(crate) enemies: Vec<String>,
+    // space left between any two things
+    pub(crate) gap: f32,
+    pub(crate) keep_clear: Vec<(f32, f32)>,
+    pub(crate) clear_radius: f32,
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Generator {
+            width: 800.0,
+            height: 600.0,
+            rocks: 10,
+            rock_size: (16.0, 48.0),
+            hazards: 2,
+            hazard_size: (60.0, 120.0),
+            pickups: 3,
+            pickup_size: 10.0,
+            enemies: Vec::new(),
+            gap: 24.0,
+            // where the player and the first AI ship start out
+            keep_clear: vec![(75.0, 100.0), (275.0, 100.0)],
+            clear_radius: 100.0,
+        }
+    }
+}
+
+impl Generator {
+    // The same seed always makes the same scene. It has an rng of its own, so
+    // making it doesn't change the game's rolls either.
+    pub(crate) fn generate(&self, seed: u64) -> Scene {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut taken: Vec<Placed> = Vec::new();
+        let mut scene = Scene::default();
+
+        // the biggest first, while there's the most room for them
+        for _ in 0..self.hazards {
+            let size = between(&mut rng, self.hazard_size);
+            if let Some(hazard) = self.place(&mut rng, &mut taken, size) {
+                scene.hazards.push(hazard);
+            }
+        }
+        for _ in 0..self.rocks {
+            let size = between(&mut rng, self.rock_size);
+            if let Some(rock) = self.place(&mut rng, &mut taken, size) {
+                scene.rocks.push(rock);
+            }
+        }
+        for _ in 0..self.pickups {
+            if let Some(pickup) = self.place(&mut rng, &mut taken, self.pickup_size) {
+                scene.pickups.push(pickup);
+            }
+        }
+        for prefab in &self.enemies {
+            // room for a ship
+            if let Some(at) = self.place(&mut rng, &mut taken, 32.0) {
+                scene.enemies.push(Enemy {
+                    prefab: prefab.clone(),
+                    x: at.x,
+                    y: at.y,
+                });
+            }
+        }
+        scene
+    }
+
+    // Somewhere a square of the size fits that's clear of everything already
+    // placed, if one turns up
+    fn place(&self, rng: &mut StdRng, taken: &mut Vec<Placed>, size: f32) -> Option<Placed> {
+        let (max_x, max_y) = (self.width - size, self.height - size);
+        if max_x < 0.0 || max_y < 0.0 {
+            return None;
+        }
+        for _ in 0..ATTEMPTS {
+            let placed = Placed {
+                x: rng.gen_range(0.0, max_x.max(f32::EPSILON)),
+                y: rng.gen_range(0.0, max_y.max(f32::EPSILON)),
+                size,
+            };
+            let center = nalgebra::Point2::new(placed.x + size / 2.0, placed.y + size / 2.0);
+            let near_start = self.keep_clear.iter().any(|(x, y)| {
+                (center - nalgebra::Point2::new(*x, *y)).norm() < self.clear_radius + size / 2.0
+            });
+            let overlaps = taken.iter().any(|other| {
+                placed.x < other.x + other.size + self.gap
+                    && other.x < placed.x + placed.size + self.gap
+                    && placed.y < other.y + other.size + self.gap
+                    && other.y < placed.y + placed.size + self.gap
+            });
+            if !near_start && !overlaps {
+                taken.push(placed);
+                return Some(placed);
+            }
+        }
+        None
+    }
+}
+
+fn between(rng: &mut StdRng, (low, high): (f32, f32)) -> f32 {
+    if high > low {
+        rng.gen_range(low, high)
+    } else {
+        low
+    }
+}
@@ -0,0 +1,100 @@
+use specs::*;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// How long each system took over the current frame. A frame that had to catch
+// up runs the systems more than once, their times are added together. Systems
+// running side by side on the thread pool add their times at once, so the
+// list is behind a lock. A game's own systems can read it to see what the
+// built-in ones cost.
+#[derive(Debug, Default)]
+pub struct SystemTimes {
+    times: Mutex<Vec<(&'static str, Duration)>>,
+}
+
+impl SystemTimes {
+    pub(crate) fn clear(&mut self) {
+        self.times.get_mut().unwrap().clear();
+    }
+
+    pub(crate) fn add(&self, name: &'static str, time: Duration) {
+        let mut times = self.times.lock().unwrap();
+        match times.iter_mut().find(|(system, _)| *system == name) {
+            Some((_, total)) => *total += time,
+            None => times.push((name, time)),
+        }
+    }
+
+    // how long the named system has taken so far this frame, if it has run
+    pub fn get(&self, name: &str) -> Option<Duration> {
+        let times = self.times.lock().unwrap();
+        times
+            .iter()
+            .find(|(system, _)| *system == name)
+            .map(|(_, time)| *time)
+    }
+
+    pub(crate) fn slowest(&self) -> Option<(&'static str, Duration)> {
+        let times = self.times.lock().unwrap();
+        times.iter().cloned().max_by_key(|(_, time)| *time)
+    }
+
+    // every system timed this frame, in the order they first ran
+    #[cfg(feature = "debug-ui")]
+    pub(crate) fn all(&self) -> Vec<(&'static str, Duration)> {
+        self.times.lock().unwrap().clone()
+    }
+}
+
+// Runs a system the same way run_now does, adding the time it took to the
+// world's SystemTimes under the given name
+pub(crate) fn run_timed<'a, S: RunNow<'a> + ?Sized>(
+    system: &mut S,
+    world: &'a World,
+    name: &'static str,
+) {
+    let started = Instant::now();
+    system.run_now(world);
+    world
+        .read_resource::<SystemTimes>()
+        .add(name, started.elapsed());
+}
+
+// A system in a dispatcher that adds the time it took to the SystemTimes
+// under the given name. It only reads them, so it can still run alongside
+// other systems.
+pub(crate) struct Timed<S> {
+    system: S,
+    name: &'static str,
+}
+
+impl<S> Timed<S> {
+    pub(crate) fn new(system: S, name: &'static str) -> Self {
+        Timed { system, name }
+    }
+}
+
+impl<'a, S> System<'a> for Timed<S>
+where
+    S: System<'a>,
+    S::SystemData: SystemData<'a>,
+{
+    type SystemData = (Read<'a, SystemTimes>, S::SystemData);
+
+    fn run(&mut self, (times, data): Self::SystemData) {
+        let started = Instant::now();
+        self.system.run(data);
+        times.add(self.name, started.elapsed());
+    }
+
+    // passed on, so a system that fills in its own resources when it's set up
+    // still gets to
+    fn setup(&mut self, world: &mut World) {
+        <Read<SystemTimes> as SystemData>::setup(world);
+        self.system.setup(world);
+    }
+
+    fn dispose(self, world: &mut World) {
+        self.system.dispose(world);
+    }
+}
@@ -0,0 +1,139 @@
+use crate::graze;
+use crate::notifications::Notifications;
+use crate::settings::Settings;
+use crate::DESIRED_FPS;
+use serde::{Deserialize, Serialize};
+use specs::*;
+use std::time::Duration;
+
+// how much of the latest frame goes into the running average
+const AVERAGE_WEIGHT: f32 = 0.05;
+// frames to wait after a change before judging the new preset, two seconds
+const SETTLE_FRAMES: u32 = DESIRED_FPS * 2;
+// the average has to drop below this much of the budget before quality goes
+// back up, so it doesn't flip between two presets
+const HEADROOM: f32 = 0.6;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub(crate) enum QualityPreset {
+    Low,
+    Medium,
+    High,
+}
+
+impl Default for QualityPreset {
+    fn default() -> Self {
+        QualityPreset::High
+    }
+}
+
+impl QualityPreset {
+    fn lower(self) -> Option<Self> {
+        match self {
+            QualityPreset::High => Some(QualityPreset::Medium),
+            QualityPreset::Medium => Some(QualityPreset::Low),
+            QualityPreset::Low => None,
+        }
+    }
+
+    fn higher(self) -> Option<Self> {
+        match self {
+            QualityPreset::Low => Some(QualityPreset::Medium),
+            QualityPreset::Medium => Some(QualityPreset::High),
+            QualityPreset::High => None,
+        }
+    }
+}
+
+// How much of the expensive work the game does, set from a preset. Everything
+// here can be turned down on machines that can't keep up.
+#[derive(Debug)]
+pub(crate) struct Quality {
+    pub(crate) preset: QualityPreset,
+    // sparks thrown off by each graze
+    pub(crate) spark_count: u32,
+    // whether the world goes through the bullet time desaturation pass
+    pub(crate) post_process: bool,
+    // how much longer AI ships wait between think ticks
+    pub(crate) think_scale: f32,
+}
+
+impl Default for Quality {
+    fn default() -> Self {
+        Quality::new(QualityPreset::default())
+    }
+}
+
+impl Quality {
+    pub(crate) fn new(preset: QualityPreset) -> Self {
+        let (spark_count, post_process, think_scale) = match preset {
+            QualityPreset::High => (graze::SPARK_COUNT, true, 1.0),
+            QualityPreset::Medium => (graze::SPARK_COUNT / 2, true, 1.5),
+            QualityPreset::Low => (0, false, 2.0),
+        };
+        Quality {
+            preset,
+            spark_count,
+            post_process,
+            think_scale,
+        }
+    }
+}
+
+// Holds the game at DESIRED_FPS on weaker machines. It keeps a running average
+// of how long frames take, and when the adaptive_quality setting is on drops to
+// the next preset down while that average is over budget. Once there is plenty
+// of time to spare again it works back up, but never past the preset the
+// player picked.
+#[derive(Default)]
+pub(crate) struct QualityController {
+    // seconds
+    average: f32,
+    // frames since the preset last changed
+    frames: u32,
+}
+
+impl QualityController {
+    pub(crate) fn record(&mut self, frame_time: Duration, world: &World) {
+        self.average += (frame_time.as_secs_f32() - self.average) * AVERAGE_WEIGHT;
+        self.frames += 1;
+        if self.frames < SETTLE_FRAMES {
+            return;
+        }
+
+        let (adaptive, chosen) = {
+            let settings = world.read_resource::<Settings>();
+            (settings.adaptive_quality, settings.quality)
+        };
+        if !adaptive {
+            return;
+        }
+
+        let budget = 1.0 / DESIRED_FPS as f32;
+        let mut quality = world.write_resource::<Quality>();
+        let next = if self.average > budget {
+            quality.preset.lower()
+        } else if self.average < budget * HEADROOM && quality.preset < chosen {
+            quality.preset.higher()
+        } else {
+            None
+        };
+
+        if let Some(preset) = next {
+            println!(
+                "Frames averaging {:.1}ms, quality {:?} -> {:?}",
+                self.average * 1000.0,
+                quality.preset,
+                preset
+            );
+            let message = if preset < quality.preset {
+                "Lowering quality to keep up"
+            } else {
+                "Raising quality"
+            };
+            world.write_resource::<Notifications>().push(message);
+            *quality = Quality::new(preset);
+            self.frames = 0;
+        }
+    }
+}
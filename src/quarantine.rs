@@ -0,0 +1,163 @@
+use crate::arena::ShrinkingBounds;
+use crate::weapons::{Projectile, ProjectilePool};
+use crate::{BoxOffset, CollisionBox, Position, Velocity};
+use ggez::nalgebra;
+use specs::*;
+use specs_derive::*;
+
+// An entity the NaN guard has taken out of play. Its Position is pinned to a
+// point every time the guard runs, and its box to the same point moved by its
+// BoxOffset, so whatever keeps feeding it bad numbers can't spread them to
+// collision or drawing. It stays put until something removes it.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub(crate) struct Quarantined {
+    pub(crate) at: nalgebra::Point2<f32>,
+}
+
+fn finite(point: nalgebra::Point2<f32>) -> bool {
+    point.x.is_finite() && point.y.is_finite()
+}
+
+// the BoxOffset of an entity, or none at all if it has gone bad too
+fn offset(offset: Option<&BoxOffset>) -> nalgebra::Vector2<f32> {
+    let offset = BoxOffset::of(offset);
+    if offset.x.is_finite() && offset.y.is_finite() {
+        offset
+    } else {
+        nalgebra::Vector2::zeros()
+    }
+}
+
+// Runs after each stage that moves things, looking for NaN or infinite
+// positions and velocities. Projectiles found that way are spent and go back
+// to the pool, anything else is quarantined where it last made sense: where
+// its collision box puts it if that is still finite, otherwise the middle of
+// the arena.
+// Either way the entity is printed along with the stage it came out of.
+pub(crate) struct NanGuard {
+    stage: &'static str,
+}
+
+impl NanGuard {
+    pub(crate) fn after(stage: &'static str) -> Self {
+        NanGuard { stage }
+    }
+}
+
+impl<'a> System<'a> for NanGuard {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, ShrinkingBounds>,
+        Write<'a, ProjectilePool>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        WriteStorage<'a, CollisionBox>,
+        ReadStorage<'a, BoxOffset>,
+        WriteStorage<'a, Projectile>,
+        WriteStorage<'a, Quarantined>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            bounds,
+            mut pool,
+            mut pos,
+            mut vel,
+            mut coll_box,
+            offsets,
+            mut projectiles,
+            mut quarantined,
+        ) = data;
+
+        // keep the ones already caught where they were left
+        for (entity, pos, coll_box, quarantined) in
+            (&entities, &mut pos, (&mut coll_box).maybe(), &quarantined).join()
+        {
+            pos.position = quarantined.at;
+            if let Some(coll_box) = coll_box {
+                coll_box.origin = quarantined.at + offset(offsets.get(entity));
+            }
+        }
+
+        for (entity, pos, projectile, _) in
+            (&entities, &pos, &mut projectiles, !&quarantined).join()
+        {
+            if !projectile.active {
+                continue;
+            }
+            let velocity = projectile.velocity;
+            if !finite(pos.position) || !velocity.x.is_finite() || !velocity.y.is_finite() {
+                println!(
+                    "NaN guard: projectile {} had position {:?} velocity {:?} after {}, spent",
+                    entity.id(),
+                    pos.position,
+                    velocity,
+                    self.stage
+                );
+                pool.release(entity, projectile);
+            }
+        }
+
+        let mut caught = Vec::new();
+        for (entity, pos, vel, coll_box, _, _) in (
+            &entities,
+            &pos,
+            vel.maybe(),
+            coll_box.maybe(),
+            !&projectiles,
+            !&quarantined,
+        )
+            .join()
+        {
+            // the box sits its BoxOffset away from the Position
+            let box_at = coll_box.map(|coll_box| coll_box.origin - offset(offsets.get(entity)));
+            let box_finite = coll_box.map_or(true, |coll_box| {
+                finite(coll_box.origin) && coll_box.width.is_finite() && coll_box.height.is_finite()
+            });
+            let vel_finite = vel.map_or(true, |vel| {
+                vel.velocity.x.is_finite() && vel.velocity.y.is_finite()
+            });
+            if finite(pos.position) && box_finite && vel_finite {
+                continue;
+            }
+
+            let at = box_at.filter(|at| finite(*at)).unwrap_or(bounds.center);
+            println!(
+                "NaN guard: entity {} had position {:?} velocity {:?} collision box {:?} after {}, quarantined at {:?}",
+                entity.id(),
+                pos.position,
+                vel.map(|vel| vel.velocity),
+                coll_box,
+                self.stage,
+                at
+            );
+            caught.push((entity, at));
+        }
+
+        for (entity, at) in caught {
+            if let Some(pos) = pos.get_mut(entity) {
+                pos.position = at;
+            }
+            // it would only keep trying to fly off
+            if let Some(vel) = vel.get_mut(entity) {
+                vel.velocity = nalgebra::Vector2::zeros();
+            }
+            if let Some(coll_box) = coll_box.get_mut(entity) {
+                coll_box.origin = at + offset(offsets.get(entity));
+                // a box with no sensible size can't collide with anything
+                if !coll_box.width.is_finite() || !coll_box.height.is_finite() {
+                    coll_box.width = 0.0;
+                    coll_box.height = 0.0;
+                }
+            }
+            quarantined
+                .insert(entity, Quarantined { at })
+                .unwrap_or_else(|err| {
+                    println!("quarantine error {:?}", err);
+                    None
+                });
+        }
+    }
+}
@@ -0,0 +1,356 @@
+use crate::ai::AiControlled;
+use crate::assets::Assets;
+use crate::behavior::BehaviorTree;
+use crate::cooldowns::Cooldowns;
+use crate::editor::Group;
+use crate::faction::Faction;
+use crate::health::{Damage, Health};
+use crate::hitbox::{Hitbox, Hurtbox};
+use crate::melee::Attack;
+use crate::patterns::BulletPattern;
+use crate::prefab::FromPrefab;
+use crate::scene::{Hazard, Pickup};
+use crate::score::PlayerScore;
+use crate::status::StatusEffects;
+use crate::stealth::{Cloaked, Revealed};
+use crate::storage::{self, Storage};
+use crate::targeting::Homing;
+use crate::time::{GameClock, TimeMultiplier};
+use crate::utility_ai::UtilityAi;
+use crate::waves::WaveDirector;
+use crate::weapons::{Projectile, ProjectilePool, Weapon};
+use crate::{
+    Acceleration, Animation, Bounded, BoxOffset, Collider, CollisionBox, ControllableTag,
+    ImageHandle, Lifetime, Position, Rotation, Scale, Solid, Velocity, ZOrder,
+};
+use ggez::{GameError, GameResult};
+use serde::de::{DeserializeSeed, IgnoredAny, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use specs::error::NoError;
+use specs::saveload::{
+    DeserializeComponents, MarkerAllocator, SerializeComponents, SimpleMarker,
+    SimpleMarkerAllocator,
+};
+use specs::world::EntitiesRes;
+use specs::*;
+use std::fmt;
+
+pub(crate) const QUICKSAVE: &str = "/saves/quicksave.ron";
+
+// What the quicksaved entities are marked with while they're saved and
+// loaded, so the ones that refer to each other, e.g. a shot to whoever fired
+// it, still do afterwards
+pub(crate) struct Quicksave;
+pub(crate) type QuicksaveMarker = SimpleMarker<Quicksave>;
+pub(crate) type QuicksaveAllocator = SimpleMarkerAllocator<Quicksave>;
+
+// The components that are saved, in groups of up to 16, as many as specs can
+// save at once. They're fetched for writing either way so each group is only
+// listed the once.
+type Bodies<'a> = (
+    WriteStorage<'a, Position>,
+    WriteStorage<'a, Velocity>,
+    WriteStorage<'a, Acceleration>,
+    WriteStorage<'a, CollisionBox>,
+    WriteStorage<'a, Collider>,
+    WriteStorage<'a, Rotation>,
+    WriteStorage<'a, Scale>,
+    WriteStorage<'a, ImageHandle>,
+    WriteStorage<'a, Animation>,
+    WriteStorage<'a, ZOrder>,
+    WriteStorage<'a, Solid>,
+    WriteStorage<'a, Bounded>,
+    WriteStorage<'a, ControllableTag>,
+    WriteStorage<'a, Faction>,
+    WriteStorage<'a, Health>,
+    WriteStorage<'a, BoxOffset>,
+);
+type Combat<'a> = (
+    WriteStorage<'a, Damage>,
+    WriteStorage<'a, Weapon>,
+    WriteStorage<'a, Cooldowns>,
+    WriteStorage<'a, Projectile>,
+    WriteStorage<'a, Homing>,
+    WriteStorage<'a, Lifetime>,
+    WriteStorage<'a, Hitbox>,
+    WriteStorage<'a, Hurtbox>,
+    WriteStorage<'a, Attack>,
+    WriteStorage<'a, StatusEffects>,
+    WriteStorage<'a, TimeMultiplier>,
+    WriteStorage<'a, Cloaked>,
+    WriteStorage<'a, Revealed>,
+    WriteStorage<'a, Hazard>,
+    WriteStorage<'a, Pickup>,
+);
+type Minds<'a> = (
+    WriteStorage<'a, AiControlled>,
+    WriteStorage<'a, BehaviorTree>,
+    WriteStorage<'a, UtilityAi>,
+    WriteStorage<'a, BulletPattern>,
+    WriteStorage<'a, FromPrefab>,
+    WriteStorage<'a, Group>,
+);
+
+// The state of play at one moment, unlike a save under saves.rs, which is the
+// replay of a whole game. F5 saves the world to the quicksave and F9 puts it
+// back as it was, e.g. to try a fight again.
+//
+// Everything with Health is kept, along with the shots in flight and the
+// hazards, pickups and walls the level put down, with all the components in
+// the groups above. Anything else the game mode or level set up is left as
+// it is, and Tasks and the effects, sparks, floating text, radar pulses and
+// tweens, aren't kept. Images are kept by their key in the Assets, so one
+// that isn't loaded any more is left off. Like the cheats, loading changes
+// the world behind the replay's back, so the game's replay won't play back
+// the same afterwards.
+#[derive(Serialize)]
+struct WorldSave<'a, 'b> {
+    // the game mode's name, a world only loads into the mode it came from
+    mode: &'b str,
+    elapsed: f64,
+    points: u32,
+    energy: f32,
+    wave: u32,
+    // the Assets' keys, the i'th for ImageHandle i
+    images: Vec<String>,
+    bodies: Saved<'a, 'b, Bodies<'a>>,
+    combat: Saved<'a, 'b, Combat<'a>>,
+    minds: Saved<'a, 'b, Minds<'a>>,
+}
+
+// The WorldSave without the entities, read first to check it's for this
+// game mode before anything is taken out of the world
+#[derive(Deserialize)]
+struct Header {
+    mode: String,
+    elapsed: f64,
+    points: u32,
+    energy: f32,
+    wave: u32,
+    images: Vec<String>,
+}
+
+// One group's components on every marked entity
+struct Saved<'a, 'b, S> {
+    storages: S,
+    entities: &'b EntitiesRes,
+    markers: &'b ReadStorage<'a, QuicksaveMarker>,
+}
+
+impl<'a, 'b, S> Serialize for Saved<'a, 'b, S>
+where
+    S: SerializeComponents<NoError, QuicksaveMarker>,
+{
+    fn serialize<T: Serializer>(&self, serializer: T) -> Result<T::Ok, T::Error> {
+        self.storages
+            .serialize(self.entities, self.markers, serializer)
+    }
+}
+
+// Everything that's quicksaved, and with spent set, the spent shots waiting
+// in the ProjectilePool as well
+fn kept(world: &World, spent: bool) -> Vec<Entity> {
+    let entities = world.entities();
+    let health = world.read_storage::<Health>();
+    let projectiles = world.read_storage::<Projectile>();
+    let hazards = world.read_storage::<Hazard>();
+    let pickups = world.read_storage::<Pickup>();
+    let solid = world.read_storage::<Solid>();
+    (&entities)
+        .join()
+        .filter(|&entity| {
+            health.contains(entity)
+                || projectiles
+                    .get(entity)
+                    .map_or(false, |projectile| spent || projectile.active)
+                || hazards.contains(entity)
+                || pickups.contains(entity)
+                || solid.contains(entity)
+        })
+        .collect()
+}
+
+pub(crate) fn save_world(
+    world: &World,
+    storage: &dyn Storage,
+    path: &str,
+    mode: &str,
+) -> GameResult<()> {
+    {
+        let mut markers = world.write_storage::<QuicksaveMarker>();
+        let mut allocator = world.write_resource::<QuicksaveAllocator>();
+        // anything marked last time that isn't kept now is left out
+        markers.clear();
+        for entity in kept(world, false) {
+            allocator.mark(entity, &mut markers);
+        }
+    }
+
+    let entities = world.entities();
+    let markers = world.read_storage::<QuicksaveMarker>();
+    let score = world.read_resource::<PlayerScore>();
+    let save = WorldSave {
+        mode,
+        elapsed: world.read_resource::<GameClock>().elapsed,
+        points: score.points,
+        energy: score.energy,
+        wave: world.read_resource::<WaveDirector>().wave,
+        images: world.read_resource::<Assets>().keys(),
+        bodies: Saved {
+            storages: world.system_data(),
+            entities: &entities,
+            markers: &markers,
+        },
+        combat: Saved {
+            storages: world.system_data(),
+            entities: &entities,
+            markers: &markers,
+        },
+        minds: Saved {
+            storages: world.system_data(),
+            entities: &entities,
+            markers: &markers,
+        },
+    };
+    storage::save_ron(storage, path, &save)
+}
+
+pub(crate) fn load_world(
+    world: &mut World,
+    storage: &dyn Storage,
+    path: &str,
+    mode: &str,
+) -> GameResult<()> {
+    let header: Header = storage::load_ron(storage, path)?;
+    if header.mode != mode {
+        return Err(GameError::ResourceLoadError(format!(
+            "{} was saved in {}",
+            path, header.mode
+        )));
+    }
+
+    // out with what would have been saved now, so only the saved ones are left
+    for entity in kept(world, true) {
+        world
+            .entities()
+            .delete(entity)
+            .unwrap_or_else(|err| println!("quickload error {:?}", err));
+    }
+    world.maintain();
+    // and no markers left over for the saved ones to be mistaken for
+    world.write_storage::<QuicksaveMarker>().clear();
+    world
+        .write_resource::<QuicksaveAllocator>()
+        .maintain(&world.entities(), &world.read_storage());
+    // the spent shots it held were all deleted
+    *world.write_resource::<ProjectilePool>() = ProjectilePool::default();
+
+    let bytes = storage.read(path)?;
+    let loaded = ron::de::Deserializer::from_bytes(&bytes).and_then(|mut deserializer| {
+        (&mut deserializer).deserialize_struct("WorldSave", &[], Loader { world })
+    });
+    // whatever was loaded before a failure is kept, and reported with it
+    loaded.map_err(|err| GameError::ResourceLoadError(format!("{}: {}", path, err)))?;
+
+    {
+        let entities = world.entities();
+        let markers = world.read_storage::<QuicksaveMarker>();
+        let assets = world.read_resource::<Assets>();
+        let mut handles = world.write_storage::<ImageHandle>();
+        let mut missing = Vec::new();
+        for (entity, _, handle) in (&entities, &markers, &mut handles).join() {
+            match assets.relisted(&header.images, *handle) {
+                Some(relisted) => *handle = relisted,
+                None => missing.push(entity),
+            }
+        }
+        for entity in missing {
+            handles.remove(entity);
+        }
+    }
+
+    world.write_resource::<GameClock>().elapsed = header.elapsed;
+    let mut score = world.write_resource::<PlayerScore>();
+    score.points = header.points;
+    score.energy = header.energy;
+    world.write_resource::<WaveDirector>().wave = header.wave;
+    Ok(())
+}
+
+// The WorldSave's fields, the ones that aren't groups were read with the
+// Header
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "lowercase")]
+enum Field {
+    Bodies,
+    Combat,
+    Minds,
+    #[serde(other)]
+    Other,
+}
+
+// Reads the groups in a WorldSave into the world, making an entity for each
+// marker
+struct Loader<'a> {
+    world: &'a World,
+}
+
+impl<'de, 'a> Visitor<'de> for Loader<'a> {
+    type Value = ();
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "a quicksaved world")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<(), A::Error> {
+        let entities = self.world.entities();
+        let mut markers = self.world.write_storage::<QuicksaveMarker>();
+        let mut allocator = self.world.write_resource::<QuicksaveAllocator>();
+        while let Some(field) = map.next_key::<Field>()? {
+            match field {
+                Field::Bodies => map.next_value_seed(Loading {
+                    storages: &mut self.world.system_data::<Bodies>(),
+                    entities: &entities,
+                    markers: &mut markers,
+                    allocator: &mut allocator,
+                })?,
+                Field::Combat => map.next_value_seed(Loading {
+                    storages: &mut self.world.system_data::<Combat>(),
+                    entities: &entities,
+                    markers: &mut markers,
+                    allocator: &mut allocator,
+                })?,
+                Field::Minds => map.next_value_seed(Loading {
+                    storages: &mut self.world.system_data::<Minds>(),
+                    entities: &entities,
+                    markers: &mut markers,
+                    allocator: &mut allocator,
+                })?,
+                Field::Other => {
+                    map.next_value::<IgnoredAny>()?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Reads one group's components onto the entities their markers belong to
+struct Loading<'a, 'b, S> {
+    storages: &'b mut S,
+    entities: &'b EntitiesRes,
+    markers: &'b mut WriteStorage<'a, QuicksaveMarker>,
+    allocator: &'b mut QuicksaveAllocator,
+}
+
+impl<'de, 'a, 'b, S> DeserializeSeed<'de> for Loading<'a, 'b, S>
+where
+    S: DeserializeComponents<NoError, QuicksaveMarker>,
+{
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        self.storages
+            .deserialize(self.entities, self.markers, self.allocator, deserializer)
+    }
+}
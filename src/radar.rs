@@ -0,0 +1,140 @@
+use crate::cooldowns::{self, Cooldowns};
+use crate::interpolation::Drawn;
+use crate::lifetime::Lifetime;
+use crate::stealth::Revealed;
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::{CollisionBox, ControllableTag, Position};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+use specs_derive::*;
+
+// seconds between pings
+const PING_COOLDOWN: f32 = 8.0;
+// the pulse reaches well past the edge of the minimap
+const PULSE_MAX_RADIUS: f32 = 1500.0;
+// pixels per second
+const PULSE_SPEED: f32 = 1000.0;
+// how long anything caught by the pulse stays revealed, in seconds
+const REVEAL_TIME: f32 = 4.0;
+
+// Player requests for a radar ping. Like the lock on, MainState only sets the
+// request flag and the radar system decides if a ping can go out, going by
+// the player's radar cooldown.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct RadarPing {
+    pub(crate) requested: bool,
+}
+
+// An expanding ring centred on the entity's position
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub(crate) struct Pulse {
+    pub(crate) radius: f32,
+}
+
+pub(crate) struct RadarSystem;
+
+impl<'a> System<'a> for RadarSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, RadarPing>,
+        Read<'a, LazyUpdate>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, ControllableTag>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Cooldowns>,
+        WriteStorage<'a, Pulse>,
+        WriteStorage<'a, Revealed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut ping,
+            updater,
+            pos,
+            coll_box,
+            controlled,
+            delta,
+            time,
+            multipliers,
+            mut cooldowns,
+            mut pulses,
+            mut revealed,
+        ) = data;
+
+        // the ping is the player's, so it recharges in real time along with
+        // the rest of the player's cooldowns
+        if ping.requested {
+            ping.requested = false;
+            let player = (&coll_box, &mut cooldowns, &controlled).join().next();
+            if let Some((player_box, cooldowns, _)) = player {
+                if cooldowns.try_use(cooldowns::RADAR, PING_COOLDOWN) {
+                    let pulse = entities.create();
+                    updater.insert(
+                        pulse,
+                        Position {
+                            position: player_box.center(),
+                        },
+                    );
+                    updater.insert(pulse, Pulse { radius: 0.0 });
+                    updater.insert(
+                        pulse,
+                        Lifetime {
+                            remaining: PULSE_MAX_RADIUS / PULSE_SPEED,
+                        },
+                    );
+                }
+            }
+        }
+
+        // anything the ring has passed over is revealed, cloaked or not
+        for (pulse_entity, pos, pulse) in (&entities, &pos, &mut pulses).join() {
+            pulse.radius += PULSE_SPEED * time.scaled(delta.seconds, multipliers.get(pulse_entity));
+            for (entity, coll_box, _) in (&entities, &coll_box, !&controlled).join() {
+                if (coll_box.center() - pos.position).norm() <= pulse.radius {
+                    revealed
+                        .insert(
+                            entity,
+                            Revealed {
+                                remaining: REVEAL_TIME,
+                            },
+                        )
+                        .unwrap_or_else(|err| {
+                            println!("reveal error {:?}", err);
+                            None
+                        });
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn draw_pulses(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let entities = world.entities();
+    let pulses = world.read_storage::<Pulse>();
+    let drawn = Drawn::new(world);
+
+    for (entity, pulse) in (&entities, &pulses).join() {
+        let at = match drawn.at(entity) {
+            Some(at) if pulse.radius > 0.0 => at,
+            _ => continue,
+        };
+        // fade out as the ring reaches its full size
+        let alpha = 1.0 - pulse.radius / PULSE_MAX_RADIUS;
+        let ring = graphics::Mesh::new_circle(
+            ctx,
+            graphics::DrawMode::stroke(3.0),
+            nalgebra::Point2::new(0.0, 0.0),
+            pulse.radius,
+            1.0,
+            graphics::Color::new(0.3, 1.0, 0.6, alpha.max(0.0)),
+        )?;
+        graphics::draw(ctx, &ring, graphics::DrawParam::default().dest(at))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,231 @@
+use crate::controls::{Action, Device};
+use crate::glyphs::{self, Glyphs, Piece};
+use crate::input_map::InputMap;
+use crate::settings::Settings;
+use crate::theme::UiTheme;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+
+// how long the menu waits for the new input before giving up, in seconds
+const LISTEN_TIME: f32 = 5.0;
+const ROW_HEIGHT: f32 = 28.0;
+const MENU_LEFT: f32 = 200.0;
+const MENU_TOP: f32 = 120.0;
+
+// What an input does in the menu when it isn't waiting for one to bind
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Nav {
+    Up,
+    Down,
+    Rebind,
+    Reset,
+    Back,
+}
+
+fn nav(input: &str) -> Option<Nav> {
+    match input {
+        "Up" | "DPadUp" => Some(Nav::Up),
+        "Down" | "DPadDown" => Some(Nav::Down),
+        "Return" | "South" => Some(Nav::Rebind),
+        "Delete" | "West" => Some(Nav::Reset),
+        "Escape" | "East" => Some(Nav::Back),
+        _ => None,
+    }
+}
+
+// What came of an input to the menu
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Outcome {
+    Open,
+    // the bindings changed and want saving
+    Changed,
+    Closed,
+}
+
+// The controls screen. It lists the actions with what they're bound to on one
+// device, and picking one waits for the next key or button on that device to
+// bind it to.
+pub(crate) struct RebindMenu {
+    device: Device,
+    selected: usize,
+    // seconds left waiting for the input to bind the selected action to
+    listening: Option<f32>,
+    // what the last change did, e.g. which action had to move
+    message: String,
+    // the keys that fly the ship, which can't be bound
+    input_map: InputMap,
+}
+
+impl RebindMenu {
+    pub(crate) fn new(device: Device, input_map: InputMap) -> Self {
+        RebindMenu {
+            device,
+            selected: 0,
+            listening: None,
+            message: String::new(),
+            input_map,
+        }
+    }
+
+    pub(crate) fn update(&mut self, dt: f32) {
+        if let Some(left) = self.listening.as_mut() {
+            *left -= dt;
+            if *left <= 0.0 {
+                self.listening = None;
+                self.message = "Nothing pressed, left as it was".to_owned();
+            }
+        }
+    }
+
+    // Takes a key or button, by the name ggez prints for it, from whichever
+    // device pressed it
+    pub(crate) fn input(
+        &mut self,
+        settings: &mut Settings,
+        device: &Device,
+        input: &str,
+    ) -> Outcome {
+        // only the device being set up counts, the menu is navigated and
+        // bound with the same one
+        if *device != self.device {
+            return Outcome::Open;
+        }
+        if self.listening.is_some() {
+            self.listening = None;
+            return self.bind(settings, input);
+        }
+        match nav(input) {
+            Some(Nav::Up) => {
+                self.selected = (self.selected + Action::ALL.len() - 1) % Action::ALL.len();
+            }
+            Some(Nav::Down) => self.selected = (self.selected + 1) % Action::ALL.len(),
+            Some(Nav::Rebind) => {
+                self.listening = Some(LISTEN_TIME);
+                self.message.clear();
+            }
+            Some(Nav::Reset) => {
+                settings.profile(&self.device).reset(&self.device);
+                self.message = "Back to the default controls".to_owned();
+                return Outcome::Changed;
+            }
+            Some(Nav::Back) => return Outcome::Closed,
+            None => (),
+        }
+        Outcome::Open
+    }
+
+    fn bind(&mut self, settings: &mut Settings, input: &str) -> Outcome {
+        // Escape backs out, rather than being bound
+        if input == "Escape" {
+            self.message = "Left as it was".to_owned();
+            return Outcome::Open;
+        }
+        let action = Action::ALL[self.selected];
+        let profile = settings.profile(&self.device);
+        if let Some(used_for) = profile.reserved(&self.input_map, input) {
+            self.message = format!("{} is already used for {}", input, used_for);
+            return Outcome::Open;
+        }
+        self.message = match profile.rebind(action, input) {
+            Some(moved) => match profile.input(moved) {
+                Some(other) => format!("{} swapped over to {}", moved.label(), other),
+                None => format!("{} is no longer bound", moved.label()),
+            },
+            None => format!("{} set to {}", action.label(), input),
+        };
+        Outcome::Changed
+    }
+
+    pub(crate) fn draw(
+        &self,
+        ctx: &mut Context,
+        settings: &Settings,
+        glyphs: &Glyphs,
+        theme: &UiTheme,
+    ) -> GameResult<()> {
+        let view = graphics::screen_coordinates(ctx);
+        let left = view.x + MENU_LEFT;
+        let mut y = view.y + MENU_TOP;
+
+        let name = settings
+            .profiles
+            .get(&self.device)
+            .map(|profile| profile.name.as_str())
+            .filter(|name| !name.is_empty())
+            .unwrap_or("Gamepad");
+        graphics::draw(
+            ctx,
+            &theme.text(format!("CONTROLS - {}", name)),
+            graphics::DrawParam::default()
+                .dest(nalgebra::Point2::new(left, y))
+                .color(theme.text),
+        )?;
+        y += ROW_HEIGHT * 1.5;
+
+        for (i, action) in Action::ALL.iter().enumerate() {
+            let color = if i == self.selected {
+                theme.text
+            } else {
+                theme.dim
+            };
+            let marker = if i == self.selected { "> " } else { "  " };
+            graphics::draw(
+                ctx,
+                &theme.text(format!("{}{}", marker, action.label())),
+                graphics::DrawParam::default()
+                    .dest(nalgebra::Point2::new(left, y))
+                    .color(color),
+            )?;
+            let bound = match (i == self.selected, self.listening) {
+                (true, Some(left)) => vec![Piece::Text(format!("press a key... {}", left.ceil()))],
+                _ => glyphs::pieces(&format!("{{{:?}}}", action), &self.device, settings),
+            };
+            glyphs.draw(
+                ctx,
+                theme,
+                &bound,
+                nalgebra::Point2::new(left + 220.0, y - 2.0),
+                color,
+            )?;
+            y += ROW_HEIGHT;
+        }
+
+        y += ROW_HEIGHT / 2.0;
+        graphics::draw(
+            ctx,
+            &theme.text(self.message.as_str()),
+            graphics::DrawParam::default()
+                .dest(nalgebra::Point2::new(left, y))
+                .color(theme.message),
+        )?;
+        y += ROW_HEIGHT * 1.5;
+
+        let (choose, rebind, reset, back) = if self.device == Device::Keyboard {
+            (
+                vec![Piece::Key("↑".to_owned()), Piece::Key("↓".to_owned())],
+                Piece::Key("Enter".to_owned()),
+                Piece::Key("Delete".to_owned()),
+                Piece::Key("Escape".to_owned()),
+            )
+        } else {
+            (
+                vec![
+                    Piece::Button("DPadUp".to_owned()),
+                    Piece::Button("DPadDown".to_owned()),
+                ],
+                Piece::Button("South".to_owned()),
+                Piece::Button("West".to_owned()),
+                Piece::Button("East".to_owned()),
+            )
+        };
+        let mut help = choose;
+        help.push(Piece::Text(" choose  ".to_owned()));
+        help.push(rebind);
+        help.push(Piece::Text(" rebind  ".to_owned()));
+        help.push(reset);
+        help.push(Piece::Text(" reset all  ".to_owned()));
+        help.push(back);
+        help.push(Piece::Text(" done".to_owned()));
+        glyphs.draw(ctx, theme, &help, nalgebra::Point2::new(left, y), theme.dim)
+    }
+}
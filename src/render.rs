@@ -0,0 +1,213 @@
+use crate::components::{Animation, ControllableTag, ImageHandle, Position, Transform, ZOrder};
+use crate::interpolation::{Interpolation, PreviousPosition};
+use crate::resources::{Assets, GameClock};
+use crate::stealth::{self, Cloaked, Revealed};
+use crate::weapons::Projectile;
+use ggez::graphics::spritebatch::SpriteBatch;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+use std::cmp::Ordering;
+
+// Where and how an entity's sprite goes on the screen: the frame of its
+// animation if it has one, turned and scaled about its middle. Anything that
+// draws over or around a sprite, like an outline, places it with this too so
+// the two line up.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Placement {
+    // the part of the image to draw, as fractions of it
+    pub(crate) src: graphics::Rect,
+    // where the middle of the sprite goes
+    pub(crate) center: nalgebra::Point2<f32>,
+    pub(crate) rotation: f32,
+    pub(crate) scale: nalgebra::Vector2<f32>,
+    // half the frame's size, before it's scaled
+    pub(crate) half_size: nalgebra::Vector2<f32>,
+}
+
+impl Placement {
+    // for the sprite of an entity whose top left corner is drawn at the point,
+    // turned and scaled by its Transform
+    pub(crate) fn new(
+        assets: &Assets,
+        image: ImageHandle,
+        animation: Option<&Animation>,
+        transform: &Transform,
+        at: nalgebra::Point2<f32>,
+    ) -> Self {
+        // an animated sprite is the size of its frame rather than the whole
+        // sheet
+        let (image_w, image_h) = assets.size(image);
+        let frame = animation
+            .and_then(|animation| animation.current())
+            .unwrap_or_else(|| graphics::Rect::new(0.0, 0.0, image_w, image_h));
+        let half_size = nalgebra::Vector2::new(frame.w / 2.0, frame.h / 2.0);
+        Placement {
+            src: graphics::Rect::new(
+                frame.x / image_w,
+                frame.y / image_h,
+                frame.w / image_w,
+                frame.h / image_h,
+            ),
+            center: at + half_size,
+            rotation: transform.rotation,
+            scale: transform.scale,
+            half_size,
+        }
+    }
+
+    // rotated and scaled around the middle of the sprite rather than the top
+    // left corner the position refers to
+    pub(crate) fn param(&self) -> graphics::DrawParam {
+        graphics::DrawParam::default()
+            .src(self.src)
+            .dest(self.center)
+            .offset(nalgebra::Point2::new(0.5, 0.5))
+            .rotation(self.rotation)
+            .scale(self.scale)
+    }
+}
+
+// One sprite ready to be drawn
+struct Sprite {
+    image: ImageHandle,
+    placement: Placement,
+    alpha: f32,
+    layer: ZOrder,
+    // the bottom edge, sprites further down the screen are in front
+    bottom: f32,
+}
+
+// Gathers up everything with an ImageHandle to be drawn and puts it in the order it
+// is drawn in: by layer, then from the top of the screen down. Hidden entities
+// are left out, apart from the player's own cloaked ship. It is run from draw
+// rather than update, and draw_sprites draws what it gathered. Sprites are
+// placed part of the way between their last two positions, see Interpolation.
+#[derive(Default)]
+pub(crate) struct RenderSystem {
+    sprites: Vec<Sprite>,
+}
+
+impl<'a> System<'a> for RenderSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, GameClock>,
+        Read<'a, Assets>,
+        Read<'a, Interpolation>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, PreviousPosition>,
+        ReadStorage<'a, ImageHandle>,
+        ReadStorage<'a, Animation>,
+        ReadStorage<'a, ZOrder>,
+        ReadStorage<'a, ControllableTag>,
+        ReadStorage<'a, Cloaked>,
+        ReadStorage<'a, Revealed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            clock,
+            assets,
+            interpolation,
+            transforms,
+            previous,
+            images,
+            animations,
+            layers,
+            controlled,
+            cloaked,
+            revealed,
+        ) = data;
+
+        // a cloaked player ship shimmers faintly so the player can still find it
+        let shimmer = 0.25 + 0.1 * (clock.unscaled as f32 * 6.0).sin();
+
+        self.sprites.clear();
+        // the Transform has where each sprite is, how it's turned and its scale
+        for (entity, transform, i, animation, layer, player, cloak, reveal) in (
+            &entities,
+            &transforms,
+            &images,
+            animations.maybe(),
+            layers.maybe(),
+            controlled.maybe(),
+            cloaked.maybe(),
+            revealed.maybe(),
+        )
+            .join()
+        {
+            let alpha = if !stealth::is_hidden(cloak, reveal) {
+                1.0
+            } else if player.is_some() {
+                shimmer
+            } else {
+                continue;
+            };
+
+            let at = interpolation.blend(previous.get(entity), transform.position());
+            let placement = Placement::new(&assets, *i, animation, transform, at);
+            self.sprites.push(Sprite {
+                image: *i,
+                placement,
+                alpha,
+                layer: layer.cloned().unwrap_or(ZOrder::SHIPS),
+                bottom: placement.center.y + placement.half_size.y * placement.scale.y.abs(),
+            });
+        }
+
+        // the sort is stable, so sprites level with each other keep to join
+        // order
+        self.sprites.sort_by(|a, b| {
+            a.layer
+                .cmp(&b.layer)
+                .then_with(|| a.bottom.partial_cmp(&b.bottom).unwrap_or(Ordering::Equal))
+        });
+    }
+}
+
+impl RenderSystem {
+    // how many draw calls draw_sprites makes, one for each sprite and one
+    // for all the projectiles
+    pub(crate) fn draw_calls(&self) -> usize {
+        self.sprites.len() + 1
+    }
+}
+
+// Draws the sprites the RenderSystem gathered, each turned the way it faces and
+// at its scale, then every projectile in flight on top
+pub(crate) fn draw_sprites(
+    ctx: &mut Context,
+    world: &World,
+    render: &RenderSystem,
+    projectile_batch: &mut SpriteBatch,
+) -> GameResult<()> {
+    let assets = world.read_resource::<Assets>();
+    for sprite in &render.sprites {
+        graphics::draw(
+            ctx,
+            assets.get(sprite.image),
+            sprite
+                .placement
+                .param()
+                .color(graphics::Color::new(1.0, 1.0, 1.0, sprite.alpha)),
+        )
+        .unwrap_or_else(|err| println!("draw error {:?}", err));
+    }
+
+    // projectiles all go into one sprite batch, there can be thousands
+    let interpolation = world.read_resource::<Interpolation>();
+    let positions = world.read_storage::<Position>();
+    let previous = world.read_storage::<PreviousPosition>();
+    let projectiles = world.read_storage::<Projectile>();
+    projectile_batch.clear();
+    for (p, previous, projectile) in (&positions, previous.maybe(), &projectiles).join() {
+        if projectile.active {
+            let at = interpolation.blend(previous, p.position);
+            projectile_batch
+                .add(graphics::DrawParam::default().dest(at - nalgebra::Vector2::new(2.0, 2.0)));
+        }
+    }
+    graphics::draw(ctx, &*projectile_batch, graphics::DrawParam::default())?;
+    Ok(())
+}
@@ -0,0 +1,157 @@
+use crate::ai::Difficulty;
+use crate::controls::{Action, Aim, ControlScheme};
+use crate::data;
+use crate::settings::Settings;
+use crate::storage::{self, Storage};
+use crate::Direction;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+
+// The player's input for one update. The game is deterministic given its
+// seed, so the input for every update is all it takes to play a game back.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) struct ReplayInput {
+    // up, down, left, right
+    pub(crate) moving: (bool, bool, bool, bool),
+    // the left stick, older replays were recorded without it
+    #[serde(default)]
+    pub(crate) moving_stick: (f32, f32),
+    pub(crate) cursor: (f32, f32),
+    pub(crate) stick: (f32, f32),
+    pub(crate) firing: bool,
+    pub(crate) scheme: ControlScheme,
+    // the actions performed since the last update, e.g. locking on
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) actions: Vec<Action>,
+}
+
+impl ReplayInput {
+    // the input the world was given for this update
+    pub(crate) fn capture(world: &World, actions: Vec<Action>) -> Self {
+        let dir = world.read_resource::<Direction>();
+        let aim = world.read_resource::<Aim>();
+        ReplayInput {
+            moving: (dir.up, dir.down, dir.left, dir.right),
+            moving_stick: (dir.x, dir.y),
+            cursor: (aim.cursor.x, aim.cursor.y),
+            stick: (aim.stick_x, aim.stick_y),
+            firing: aim.firing,
+            scheme: world.read_resource::<Settings>().control_scheme,
+            actions,
+        }
+    }
+
+    // Gives the world this input, apart from the actions which MainState
+    // performs itself
+    pub(crate) fn apply(&self, world: &World) {
+        let (up, down, left, right) = self.moving;
+        *world.write_resource::<Direction>() = Direction {
+            up,
+            down,
+            left,
+            right,
+            x: self.moving_stick.0,
+            y: self.moving_stick.1,
+        };
+        *world.write_resource::<Aim>() = Aim {
+            cursor: nalgebra::Point2::new(self.cursor.0, self.cursor.1),
+            stick_x: self.stick.0,
+            stick_y: self.stick.1,
+            firing: self.firing,
+        };
+        world.write_resource::<Settings>().control_scheme = self.scheme;
+    }
+}
+
+// Every update's input, stored as runs of the same input repeated, since it
+// mostly stays the same for a while
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub(crate) struct InputLog {
+    runs: Vec<(u32, ReplayInput)>,
+}
+
+impl InputLog {
+    pub(crate) fn push(&mut self, input: ReplayInput) {
+        match self.runs.last_mut() {
+            Some((count, last)) if *last == input => *count += 1,
+            _ => self.runs.push((1, input)),
+        }
+    }
+
+    // how many updates the log covers
+    pub(crate) fn updates(&self) -> u32 {
+        self.runs.iter().map(|(count, _)| count).sum()
+    }
+}
+
+// Everything needed to play a game again: how it was started and what the
+// player did
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Replay {
+    pub(crate) seed: u64,
+    // the game mode as given to --mode
+    pub(crate) mode: String,
+    pub(crate) difficulty: Difficulty,
+    pub(crate) inputs: InputLog,
+}
+
+pub(crate) fn load(ctx: &mut Context, path: &str) -> GameResult<Replay> {
+    data::load(ctx, path)
+}
+
+// Replays the game ships with are loaded as resources, but the ones played are
+// saved to the player's storage
+pub(crate) fn save(storage: &dyn Storage, path: &str, replay: &Replay) -> GameResult<()> {
+    storage::save_ron(storage, path, replay)
+}
+
+// Hands out a replay's input one update at a time
+pub(crate) struct Playback {
+    inputs: InputLog,
+    run: usize,
+    // updates already played from the current run
+    played: u32,
+}
+
+impl Playback {
+    pub(crate) fn new(inputs: InputLog) -> Self {
+        Playback {
+            inputs,
+            run: 0,
+            played: 0,
+        }
+    }
+
+    // the input for the next update, None once the replay is over
+    pub(crate) fn next(&mut self) -> Option<ReplayInput> {
+        loop {
+            let (count, input) = self.inputs.runs.get(self.run)?;
+            if self.played < *count {
+                self.played += 1;
+                return Some(input.clone());
+            }
+            self.run += 1;
+            self.played = 0;
+        }
+    }
+
+    // true as soon as the last input has been handed out
+    pub(crate) fn finished(&self) -> bool {
+        let last = self.inputs.runs.len().saturating_sub(1);
+        match self.inputs.runs.get(self.run) {
+            Some((count, _)) => self.run == last && self.played >= *count,
+            None => true,
+        }
+    }
+}
+
+// Shown over a replay playing as the attract mode demo
+pub(crate) fn draw_demo_banner(ctx: &mut Context) -> GameResult<()> {
+    let view = graphics::screen_coordinates(ctx);
+    let text = graphics::Text::new("DEMO - press any key");
+    let (width, _) = text.dimensions(ctx);
+    let corner = nalgebra::Point2::new(view.x + (view.w - width as f32) / 2.0, view.y + 40.0);
+    graphics::draw(ctx, &text, graphics::DrawParam::default().dest(corner))
+}
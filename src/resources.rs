@@ -0,0 +1,67 @@
+// The resources the built-in systems share. The player's input is mirrored
+// into the world here by MainState, and the clocks are moved on once per
+// update. Systems queue up entities to create and delete in the SpawnQueue,
+// and the images sprites are drawn with are kept in the Assets. Sounds to
+// play go in the SoundQueue, and the MusicTrack can take over the music.
+use crate::controls::STICK_DEAD_ZONE;
+use ggez::nalgebra;
+
+pub use crate::assets::Assets;
+pub use crate::audio::{MusicTrack, PlaySound, SoundQueue};
+pub use crate::spawn_queue::SpawnQueue;
+pub use crate::time::{DeltaTime, GameClock, TimeScale};
+
+// Direction is passed into the MovementSystem system via a resource
+// we'll use a struct instead of an enum to capture multiple keys pressed at once
+// this is still not great, but it'll do for example purposes
+#[derive(Clone, Copy, Default)]
+pub struct Direction {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    // a gamepad's left stick, -1 to 1 each way with down the screen positive
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Direction {
+    pub fn new() -> Self {
+        Direction {
+            up: false,
+            down: false,
+            left: false,
+            right: false,
+            x: 0.0,
+            y: 0.0,
+        }
+    }
+
+    // Which way the player is steering, and how hard. Keys steer flat out and
+    // win over the stick. The stick counts from the edge of its dead zone, so
+    // a light push creeps along rather than doing nothing and then jumping.
+    pub fn heading(&self) -> nalgebra::Vector2<f32> {
+        let mut heading = nalgebra::Vector2::zeros();
+        if self.up {
+            heading.y -= 1.0;
+        }
+        if self.down {
+            heading.y += 1.0;
+        }
+        if self.left {
+            heading.x -= 1.0;
+        }
+        if self.right {
+            heading.x += 1.0;
+        }
+        if heading.norm() > 0.0 {
+            return heading;
+        }
+        let stick = nalgebra::Vector2::new(self.x, self.y);
+        let push = stick.norm().min(1.0);
+        if push < STICK_DEAD_ZONE {
+            return heading;
+        }
+        stick.normalize() * (push - STICK_DEAD_ZONE) / (1.0 - STICK_DEAD_ZONE)
+    }
+}
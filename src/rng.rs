@@ -0,0 +1,55 @@
+use crate::platform;
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+// The one source of randomness for the game. Everything that rolls dice goes
+// through this resource rather than thread_rng, so a game started with the same
+// seed plays out the same way. The seed is printed at startup and can be passed
+// back in with --seed.
+pub(crate) struct GameRng {
+    seed: u64,
+    rng: StdRng,
+}
+
+impl GameRng {
+    pub(crate) fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    // seeded from the clock, for when no seed was asked for
+    pub(crate) fn from_time() -> Self {
+        GameRng::new(platform::clock_seed())
+    }
+
+    pub(crate) fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(0)
+    }
+}
+
+// lets GameRng be handed straight to anything in the rand crate
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        self.rng.next_u32()
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng.next_u64()
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.rng.fill_bytes(dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.rng.try_fill_bytes(dest)
+    }
+}
@@ -0,0 +1,273 @@
+use crate::notifications::Notifications;
+use crate::prefab::{self, Prefabs};
+use crate::spawner::Spawner;
+use crate::status::{self, Status, StatusEffects};
+use crate::time::TimeScale;
+use crate::ControllableTag;
+use ggez::event::{KeyCode, MouseButton};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+
+// the time scales the slider goes between, and how far a key moves it
+const MIN_SCALE: f32 = 0.25;
+const MAX_SCALE: f32 = 3.0;
+const SCALE_STEP: f32 = 0.25;
+// the panel down the left of the screen, under the score
+const PANEL_WIDTH: f32 = 170.0;
+const PANEL_TOP: f32 = 100.0;
+const ROW_HEIGHT: f32 = 20.0;
+const SLIDER_HEIGHT: f32 = 8.0;
+
+// The sandbox's tools, which only the sandbox mode has in its world:
+//
+//     1-9 or click  pick a prefab from the palette
+//     right click   spawn the picked prefab at the cursor
+//     I             invincibility
+//     - and =       slow down or speed up time, or click the slider
+//     Backspace     start the sandbox again
+//
+// Like the cheats, they change the world behind the replay's back, which is
+// why the sandbox can't be saved.
+#[derive(Debug, Default)]
+pub(crate) struct SandboxTools {
+    // the prefab picked from the palette, by its place in the list
+    selected: usize,
+    invincible: bool,
+}
+
+// Carries out the tool for a key, returning false if it isn't one
+pub(crate) fn key_down(world: &World, keycode: KeyCode) -> bool {
+    let mut tools = world.write_resource::<SandboxTools>();
+    let picked = match keycode {
+        KeyCode::Key1 => 0,
+        KeyCode::Key2 => 1,
+        KeyCode::Key3 => 2,
+        KeyCode::Key4 => 3,
+        KeyCode::Key5 => 4,
+        KeyCode::Key6 => 5,
+        KeyCode::Key7 => 6,
+        KeyCode::Key8 => 7,
+        KeyCode::Key9 => 8,
+        KeyCode::I => {
+            tools.toggle_invincible(world);
+            return true;
+        }
+        KeyCode::Minus => {
+            step_time(world, -SCALE_STEP);
+            return true;
+        }
+        KeyCode::Equals => {
+            step_time(world, SCALE_STEP);
+            return true;
+        }
+        _ => return false,
+    };
+    tools.pick(world, picked);
+    true
+}
+
+// Picks from the palette or moves the slider for a left click on the panel,
+// and spawns for a right click anywhere. Returns false if the click is
+// nothing to do with the sandbox.
+pub(crate) fn mouse_down(
+    ctx: &Context,
+    world: &World,
+    button: MouseButton,
+    screen: nalgebra::Point2<f32>,
+    at: nalgebra::Point2<f32>,
+) -> bool {
+    let mut tools = world.write_resource::<SandboxTools>();
+    match button {
+        MouseButton::Right => {
+            tools.spawn(world, at);
+            true
+        }
+        MouseButton::Left => {
+            let layout = Layout::new(ctx, world);
+            if let Some(row) = layout.rows.iter().position(|row| row.contains(screen)) {
+                tools.pick(world, row);
+            } else if layout.slider.contains(screen) {
+                let along = (screen.x - layout.slider.x) / layout.slider.w;
+                set_time(world, MIN_SCALE + along * (MAX_SCALE - MIN_SCALE));
+            } else {
+                return false;
+            }
+            true
+        }
+        _ => false,
+    }
+}
+
+impl SandboxTools {
+    fn pick(&mut self, world: &World, row: usize) {
+        let prefabs = world.read_resource::<Prefabs>();
+        if let Some(name) = prefabs.prefabs.keys().nth(row) {
+            self.selected = row;
+            world
+                .write_resource::<Notifications>()
+                .push(&format!("Spawning {}", name));
+        }
+    }
+
+    // the picked prefab, with the middle of the ship on the point
+    fn spawn(&self, world: &World, at: nalgebra::Point2<f32>) {
+        let prefabs = world.read_resource::<Prefabs>();
+        let prefab = match prefabs.prefabs.values().nth(self.selected) {
+            Some(prefab) => prefab,
+            None => return,
+        };
+        let (width, height) = world.read_resource::<Spawner>().ship_size();
+        prefab::spawn(
+            world,
+            prefab,
+            at - nalgebra::Vector2::new(width / 2.0, height / 2.0),
+        );
+    }
+
+    // a shield on the player's ship that never runs out
+    fn toggle_invincible(&mut self, world: &World) {
+        self.invincible = !self.invincible;
+        let entities = world.entities();
+        let controlled = world.read_storage::<ControllableTag>();
+        let mut statuses = world.write_storage::<StatusEffects>();
+        for (player, _) in (&entities, &controlled).join() {
+            if self.invincible {
+                status::apply(&mut statuses, player, Status::Shielded, std::f32::INFINITY);
+            } else if let Some(effects) = statuses.get_mut(player) {
+                effects.effects.retain(|e| e.status != Status::Shielded);
+            }
+        }
+        let text = if self.invincible {
+            "Invincible"
+        } else {
+            "Invincibility off"
+        };
+        world.write_resource::<Notifications>().push(text);
+    }
+}
+
+fn step_time(world: &World, by: f32) {
+    let base = world.read_resource::<TimeScale>().base;
+    set_time(world, (base / SCALE_STEP).round() * SCALE_STEP + by);
+}
+
+// The speed the game runs at, which bullet time slows down from
+fn set_time(world: &World, scale: f32) {
+    let scale = scale.max(MIN_SCALE).min(MAX_SCALE);
+    world.write_resource::<TimeScale>().base = scale;
+    world
+        .write_resource::<Notifications>()
+        .push(&format!("Time x{:.2}", scale));
+}
+
+// Where the panel's palette rows and slider are on the screen
+struct Layout {
+    title: nalgebra::Point2<f32>,
+    rows: Vec<graphics::Rect>,
+    slider: graphics::Rect,
+}
+
+impl Layout {
+    fn new(ctx: &Context, world: &World) -> Self {
+        let view = graphics::screen_coordinates(ctx);
+        let x = view.x + 10.0;
+        let count = world.read_resource::<Prefabs>().prefabs.len();
+        let rows = (0..count)
+            .map(|row| {
+                let y = view.y + PANEL_TOP + ROW_HEIGHT * (row + 1) as f32;
+                graphics::Rect::new(x, y, PANEL_WIDTH, ROW_HEIGHT)
+            })
+            .collect();
+        let below = view.y + PANEL_TOP + ROW_HEIGHT * (count + 3) as f32;
+        Layout {
+            title: nalgebra::Point2::new(x, view.y + PANEL_TOP),
+            rows,
+            slider: graphics::Rect::new(x, below, PANEL_WIDTH, SLIDER_HEIGHT),
+        }
+    }
+}
+
+pub(crate) fn draw_sandbox(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let tools = match world.try_fetch::<SandboxTools>() {
+        Some(tools) => tools,
+        None => return Ok(()),
+    };
+    let layout = Layout::new(ctx, world);
+    let prefabs = world.read_resource::<Prefabs>();
+    let scale = world.read_resource::<TimeScale>().base;
+    let white = graphics::WHITE;
+    let dim = graphics::Color::new(1.0, 1.0, 1.0, 0.6);
+
+    graphics::draw(
+        ctx,
+        &graphics::Text::new("Spawn (right click)"),
+        graphics::DrawParam::default().dest(layout.title).color(dim),
+    )?;
+    for (i, (name, row)) in prefabs.prefabs.keys().zip(&layout.rows).enumerate() {
+        let label = if i < 9 {
+            format!("{} {}", i + 1, name)
+        } else {
+            format!("  {}", name)
+        };
+        let color = if i == tools.selected { white } else { dim };
+        if i == tools.selected {
+            let highlight = graphics::Mesh::new_rectangle(
+                ctx,
+                graphics::DrawMode::fill(),
+                *row,
+                graphics::Color::new(0.3, 0.8, 1.0, 0.25),
+            )?;
+            graphics::draw(ctx, &highlight, graphics::DrawParam::default())?;
+        }
+        graphics::draw(
+            ctx,
+            &graphics::Text::new(label),
+            graphics::DrawParam::default()
+                .dest(nalgebra::Point2::new(row.x + 4.0, row.y + 2.0))
+                .color(color),
+        )?;
+    }
+
+    // the time scale slider, with a mark where normal speed is
+    let slider = layout.slider;
+    let along = |scale: f32| slider.x + slider.w * (scale - MIN_SCALE) / (MAX_SCALE - MIN_SCALE);
+    let mesh = graphics::MeshBuilder::new()
+        .rectangle(graphics::DrawMode::stroke(1.0), slider, white)
+        .rectangle(
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(slider.x, slider.y, along(scale) - slider.x, slider.h),
+            graphics::Color::new(0.3, 0.8, 1.0, 1.0),
+        )
+        .line(
+            &[
+                nalgebra::Point2::new(along(1.0), slider.y - 3.0),
+                nalgebra::Point2::new(along(1.0), slider.y + slider.h + 3.0),
+            ],
+            1.0,
+            white,
+        )?
+        .build(ctx)?;
+    graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+    let lines = [
+        format!("Time x{:.2}  (- =)", scale),
+        format!(
+            "Invincible {}  (I)",
+            if tools.invincible { "on" } else { "off" }
+        ),
+        "Reset  (Backspace)".to_owned(),
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        graphics::draw(
+            ctx,
+            &graphics::Text::new(line.as_str()),
+            graphics::DrawParam::default()
+                .dest(nalgebra::Point2::new(
+                    slider.x,
+                    slider.y + slider.h + 6.0 + ROW_HEIGHT * i as f32,
+                ))
+                .color(dim),
+        )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,408 @@
+use crate::game_mode;
+use crate::glyphs::{Glyphs, Piece};
+use crate::replay::Replay;
+use crate::storage::{self, Storage};
+use crate::theme::UiTheme;
+use crate::DESIRED_FPS;
+use ggez::nalgebra;
+use ggez::{conf, graphics, Context, GameError, GameResult};
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SLOTS: usize = 3;
+// the size thumbnails are saved at, and shown at in the menu
+const THUMBNAIL_W: u16 = 160;
+const THUMBNAIL_H: u16 = 120;
+const SHOWN_SCALE: f32 = 0.6;
+const ROW_HEIGHT: f32 = 90.0;
+const MENU_LEFT: f32 = 160.0;
+const MENU_TOP: f32 = 100.0;
+
+fn path(slot: usize) -> String {
+    format!("/saves/slot{}.ron", slot + 1)
+}
+
+// Thumbnails are kept as raw pixels, the width and height as two
+// little endian u16s and then RGBA bytes, so any storage can hold them
+fn thumbnail_path(slot: usize) -> String {
+    format!("/saves/slot{}.thumb", slot + 1)
+}
+
+// What the menu shows about a save without playing it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct SaveInfo {
+    // seconds since the Unix epoch
+    pub(crate) saved_at: u64,
+    // the game mode's name, e.g. "Capture the Flag"
+    pub(crate) level: String,
+    // seconds of game time
+    pub(crate) playtime: f32,
+}
+
+// A saved game. The game is deterministic given its seed, so a save is the
+// replay of everything so far, and loading plays it back to the same point.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Save {
+    pub(crate) info: SaveInfo,
+    pub(crate) replay: Replay,
+}
+
+impl Save {
+    pub(crate) fn new(replay: Replay) -> Self {
+        let saved_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since| since.as_secs());
+        let level = game_mode::from_name(&replay.mode)
+            .map_or_else(|| replay.mode.clone(), |mode| mode.name().to_owned());
+        Save {
+            info: SaveInfo {
+                saved_at,
+                level,
+                playtime: replay.inputs.updates() as f32 / DESIRED_FPS as f32,
+            },
+            replay,
+        }
+    }
+}
+
+pub(crate) fn load(storage: &dyn Storage, slot: usize) -> GameResult<Save> {
+    storage::load_ron(storage, &path(slot))
+}
+
+// Writes the save, and a thumbnail of the screen it was saved from
+fn write(
+    ctx: &mut Context,
+    storage: &dyn Storage,
+    slot: usize,
+    save: &Save,
+    screen: Option<&graphics::Image>,
+) -> GameResult<()> {
+    storage::save_ron(storage, &path(slot), save)?;
+
+    if let Some(screen) = screen {
+        let thumbnail =
+            graphics::Canvas::new(ctx, THUMBNAIL_W, THUMBNAIL_H, conf::NumSamples::Zero)?;
+        let scale = nalgebra::Vector2::new(
+            f32::from(THUMBNAIL_W) / f32::from(screen.width()),
+            f32::from(THUMBNAIL_H) / f32::from(screen.height()),
+        );
+        graphics::set_canvas(ctx, Some(&thumbnail));
+        graphics::draw(ctx, screen, graphics::DrawParam::default().scale(scale))?;
+        graphics::set_canvas(ctx, None);
+        let mut data = Vec::new();
+        data.extend_from_slice(&THUMBNAIL_W.to_le_bytes());
+        data.extend_from_slice(&THUMBNAIL_H.to_le_bytes());
+        data.extend(thumbnail.image().to_rgba8(ctx)?);
+        storage.write(&thumbnail_path(slot), &data)?;
+    }
+    Ok(())
+}
+
+fn read_thumbnail(
+    ctx: &mut Context,
+    storage: &dyn Storage,
+    slot: usize,
+) -> GameResult<graphics::Image> {
+    let data = storage.read(&thumbnail_path(slot))?;
+    if data.len() < 4 {
+        return Err(GameError::ResourceLoadError(thumbnail_path(slot)));
+    }
+    let width = u16::from_le_bytes([data[0], data[1]]);
+    let height = u16::from_le_bytes([data[2], data[3]]);
+    graphics::Image::from_rgba8(ctx, width, height, &data[4..])
+}
+
+fn delete(storage: &dyn Storage, slot: usize) -> GameResult<()> {
+    storage.delete(&path(slot))?;
+    if storage.exists(&thumbnail_path(slot)) {
+        storage.delete(&thumbnail_path(slot))?;
+    }
+    Ok(())
+}
+
+// e.g. 2019-06-02 14:05 UTC, worked out by hand as there's no date crate
+fn date(secs: u64) -> String {
+    let days = (secs / 86400) as i64;
+    let (hour, minute) = (secs % 86400 / 3600, secs % 3600 / 60);
+    // days to a civil date, after Howard Hinnant's days_from_civil
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    format!(
+        "{}-{:02}-{:02} {:02}:{:02} UTC",
+        year, month, day, hour, minute
+    )
+}
+
+// What's in a slot, read when the menu opens
+struct Slot {
+    info: Option<SaveInfo>,
+    thumbnail: Option<graphics::Image>,
+}
+
+fn read_slot(ctx: &mut Context, storage: &dyn Storage, slot: usize) -> Slot {
+    if !storage.exists(&path(slot)) {
+        return Slot {
+            info: None,
+            thumbnail: None,
+        };
+    }
+    let info = load(storage, slot)
+        .map(|save| save.info)
+        .map_err(|err| println!("save error {:?}", err))
+        .ok();
+    let thumbnail = if storage.exists(&thumbnail_path(slot)) {
+        read_thumbnail(ctx, storage, slot)
+            .map_err(|err| println!("save error {:?}", err))
+            .ok()
+    } else {
+        None
+    };
+    Slot { info, thumbnail }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Purpose {
+    // from a game, to save it
+    Save,
+    // from the title screen, to pick up a saved game
+    Load,
+}
+
+// a question that needs a yes before anything on disk goes, asked in a
+// dialog over the menu
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Confirm {
+    Overwrite,
+    Delete,
+}
+
+// What came of an input to the menu
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Choice {
+    Open,
+    Closed,
+    // the game was saved to the slot
+    Saved,
+    Load(usize),
+    // the slot needs a yes first
+    Ask(Confirm),
+}
+
+// The save and load screen: the slots with when each was saved, what was
+// being played and for how long, and a thumbnail of the game at the time
+pub(crate) struct SaveMenu {
+    pub(crate) purpose: Purpose,
+    slots: Vec<Slot>,
+    selected: usize,
+    // the screen as the menu was opened, for the thumbnail of a new save
+    screen: Option<graphics::Image>,
+    message: String,
+}
+
+impl SaveMenu {
+    // Opening the menu to save takes a screenshot first, so it has to happen
+    // while the game is still on screen
+    pub(crate) fn new(ctx: &mut Context, storage: &dyn Storage, purpose: Purpose) -> Self {
+        let screen = match purpose {
+            Purpose::Save => graphics::screenshot(ctx)
+                .map_err(|err| println!("save error {:?}", err))
+                .ok(),
+            Purpose::Load => None,
+        };
+        SaveMenu {
+            purpose,
+            slots: (0..SLOTS)
+                .map(|slot| read_slot(ctx, storage, slot))
+                .collect(),
+            selected: 0,
+            screen,
+            message: String::new(),
+        }
+    }
+
+    fn save(&mut self, ctx: &mut Context, storage: &dyn Storage, save: &Save) -> Choice {
+        match write(ctx, storage, self.selected, save, self.screen.as_ref()) {
+            Ok(()) => Choice::Saved,
+            Err(err) => {
+                println!("save error {:?}", err);
+                self.message = "Couldn't save, see the log".to_owned();
+                Choice::Open
+            }
+        }
+    }
+
+    // Takes a key or button by the name ggez prints for it, along with the
+    // game to write when saving
+    pub(crate) fn input(
+        &mut self,
+        ctx: &mut Context,
+        storage: &dyn Storage,
+        input: &str,
+        save: Option<&Save>,
+    ) -> Choice {
+        let filled = self.slots[self.selected].info.is_some();
+        match input {
+            "Up" | "DPadUp" => self.selected = (self.selected + SLOTS - 1) % SLOTS,
+            "Down" | "DPadDown" => self.selected = (self.selected + 1) % SLOTS,
+            "Return" | "South" => match (self.purpose, filled, save) {
+                (Purpose::Save, true, _) => return Choice::Ask(Confirm::Overwrite),
+                (Purpose::Save, false, Some(save)) => return self.save(ctx, storage, save),
+                (Purpose::Load, true, _) => return Choice::Load(self.selected),
+                _ => (),
+            },
+            "Delete" | "West" if filled => return Choice::Ask(Confirm::Delete),
+            "Escape" | "East" => return Choice::Closed,
+            _ => (),
+        }
+        Choice::Open
+    }
+
+    // what the dialog asks about the selected slot
+    pub(crate) fn question(&self, confirm: Confirm) -> String {
+        match confirm {
+            Confirm::Overwrite => format!("Overwrite slot {}?", self.selected + 1),
+            Confirm::Delete => format!("Delete slot {}?", self.selected + 1),
+        }
+    }
+
+    // Carries out what was asked about once the dialog says yes
+    pub(crate) fn confirmed(
+        &mut self,
+        ctx: &mut Context,
+        storage: &dyn Storage,
+        confirm: Confirm,
+        save: Option<&Save>,
+    ) -> Choice {
+        match (confirm, save) {
+            (Confirm::Overwrite, Some(save)) => self.save(ctx, storage, save),
+            (Confirm::Overwrite, None) => Choice::Open,
+            (Confirm::Delete, _) => {
+                match delete(storage, self.selected) {
+                    Ok(()) => self.message = format!("Slot {} deleted", self.selected + 1),
+                    Err(err) => println!("save error {:?}", err),
+                }
+                self.slots[self.selected] = read_slot(ctx, storage, self.selected);
+                Choice::Open
+            }
+        }
+    }
+
+    pub(crate) fn draw(
+        &self,
+        ctx: &mut Context,
+        glyphs: &Glyphs,
+        theme: &UiTheme,
+        gamepad: bool,
+    ) -> GameResult<()> {
+        let view = graphics::screen_coordinates(ctx);
+        let left = view.x + MENU_LEFT;
+        let mut y = view.y + MENU_TOP;
+
+        let title = match self.purpose {
+            Purpose::Save => "SAVE GAME",
+            Purpose::Load => "LOAD GAME",
+        };
+        graphics::draw(
+            ctx,
+            &theme.text(title),
+            graphics::DrawParam::default()
+                .dest(nalgebra::Point2::new(left, y - 40.0))
+                .color(theme.text),
+        )?;
+
+        let thumbnail_w = f32::from(THUMBNAIL_W) * SHOWN_SCALE;
+        let thumbnail_h = f32::from(THUMBNAIL_H) * SHOWN_SCALE;
+        for (i, slot) in self.slots.iter().enumerate() {
+            let color = if i == self.selected {
+                theme.text
+            } else {
+                theme.dim
+            };
+            let frame = graphics::Rect::new(left, y, thumbnail_w, thumbnail_h);
+            match &slot.thumbnail {
+                Some(thumbnail) => graphics::draw(
+                    ctx,
+                    thumbnail,
+                    graphics::DrawParam::default()
+                        .dest(nalgebra::Point2::new(frame.x, frame.y))
+                        .scale(nalgebra::Vector2::new(
+                            thumbnail_w / f32::from(thumbnail.width()),
+                            thumbnail_h / f32::from(thumbnail.height()),
+                        )),
+                )?,
+                None => {
+                    let empty = graphics::Mesh::new_rectangle(
+                        ctx,
+                        graphics::DrawMode::fill(),
+                        frame,
+                        graphics::Color::new(0.15, 0.15, 0.2, 1.0),
+                    )?;
+                    graphics::draw(ctx, &empty, graphics::DrawParam::default())?;
+                }
+            }
+            let edge =
+                graphics::Mesh::new_rectangle(ctx, graphics::DrawMode::stroke(1.0), frame, color)?;
+            graphics::draw(ctx, &edge, graphics::DrawParam::default())?;
+
+            let text = match &slot.info {
+                Some(info) => {
+                    let seconds = info.playtime as u32;
+                    format!(
+                        "Slot {}  {}\n{}\nPlayed {}:{:02}",
+                        i + 1,
+                        info.level,
+                        date(info.saved_at),
+                        seconds / 60,
+                        seconds % 60
+                    )
+                }
+                None => format!("Slot {}  empty", i + 1),
+            };
+            graphics::draw(
+                ctx,
+                &theme.text(text),
+                graphics::DrawParam::default()
+                    .dest(nalgebra::Point2::new(left + thumbnail_w + 16.0, y))
+                    .color(color),
+            )?;
+            y += ROW_HEIGHT;
+        }
+
+        graphics::draw(
+            ctx,
+            &theme.text(self.message.as_str()),
+            graphics::DrawParam::default()
+                .dest(nalgebra::Point2::new(left, y))
+                .color(theme.message),
+        )?;
+        y += 30.0;
+
+        let input = |keyboard: &str, pad: &str| {
+            if gamepad {
+                Piece::Button(pad.to_owned())
+            } else {
+                Piece::Key(keyboard.to_owned())
+            }
+        };
+        let pick = match self.purpose {
+            Purpose::Save => " save  ",
+            Purpose::Load => " load  ",
+        };
+        let help = vec![
+            input("Enter", "South"),
+            Piece::Text(pick.to_owned()),
+            input("Delete", "West"),
+            Piece::Text(" delete  ".to_owned()),
+            input("Escape", "East"),
+            Piece::Text(" back".to_owned()),
+        ];
+        glyphs.draw(ctx, theme, &help, nalgebra::Point2::new(left, y), theme.dim)
+    }
+}
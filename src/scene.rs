@@ -0,0 +1,278 @@
+use crate::assets::Assets;
+use crate::events::{self, Publish, Subscribe, TrackedReader};
+use crate::fixed;
+use crate::health::{self, DamageEvent, Health};
+use crate::interpolation::Drawn;
+use crate::notifications::Notifications;
+use crate::prefab::{self, Prefabs};
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::{CollisionBox, CollisionEvent, ImageHandle, Position, Solid};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+
+const ROCK_COLOR: graphics::Color = graphics::Color {
+    r: 0.45,
+    g: 0.4,
+    b: 0.35,
+    a: 1.0,
+};
+const PICKUP_COLOR: graphics::Color = graphics::Color {
+    r: 0.3,
+    g: 1.0,
+    b: 0.4,
+    a: 1.0,
+};
+const HAZARD_COLOR: graphics::Color = graphics::Color {
+    r: 1.0,
+    g: 0.5,
+    b: 0.1,
+    a: 0.2,
+};
+// health lost per second in a hazard
+const HAZARD_DAMAGE: f32 = 8.0;
+// health a pickup gives back
+const PICKUP_HEALTH: f32 = 25.0;
+
+// A square placed in the level, by its top left corner
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub(crate) struct Placed {
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) size: f32,
+}
+
+// An enemy waiting in the level from the start, a prefab from
+// resources/prefabs.ron with its top left corner here
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct Enemy {
+    pub(crate) prefab: String,
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+}
+
+// What a level has in it besides the ships the game mode sets up, written out
+// in the level's file or made by the generator, e.g.
+//
+//     scene: (
+//         rocks: [(x: 300.0, y: 200.0, size: 32.0)],
+//         hazards: [(x: 500.0, y: 350.0, size: 90.0)],
+//         pickups: [(x: 120.0, y: 480.0, size: 10.0)],
+//         enemies: [(prefab: "sniper", x: 650.0, y: 80.0)],
+//     ),
+//
+// Rocks are solid, the player has to fly around them. Hazards hurt the player for as long as they
+// stay in them, and pickups give back some health.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Scene {
+    pub(crate) rocks: Vec<Placed>,
+    pub(crate) hazards: Vec<Placed>,
+    pub(crate) pickups: Vec<Placed>,
+    pub(crate) enemies: Vec<Enemy>,
+}
+
+impl Scene {
+    pub(crate) fn extend(&mut self, other: Scene) {
+        self.rocks.extend(other.rocks);
+        self.hazards.extend(other.hazards);
+        self.pickups.extend(other.pickups);
+        self.enemies.extend(other.enemies);
+    }
+}
+
+// An area that burns the player while they're in it
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub(crate) struct Hazard {
+    // health lost per second
+    pub(crate) damage: f32,
+}
+
+// Health for the player to fly over and collect
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(DenseVecStorage)]
+pub(crate) struct Pickup {
+    pub(crate) health: f32,
+}
+
+// Puts the scene's things in the world. Enemies need the prefabs and spawner
+// already there, and turn up once the world is first maintained.
+pub(crate) fn build(ctx: &mut Context, world: &mut World, scene: &Scene) -> GameResult<()> {
+    for rock in &scene.rocks {
+        let (image, size) = solid(ctx, world, "rock", rock.size, ROCK_COLOR)?;
+        let origin = nalgebra::Point2::new(rock.x, rock.y);
+        world
+            .create_entity()
+            .with(Position { position: origin })
+            .with(square(origin, size))
+            .with(image)
+            .with(Solid)
+            .build();
+    }
+
+    for hazard in &scene.hazards {
+        let origin = nalgebra::Point2::new(hazard.x, hazard.y);
+        world
+            .create_entity()
+            .with(Position { position: origin })
+            .with(square(origin, hazard.size))
+            .with(Hazard {
+                damage: HAZARD_DAMAGE,
+            })
+            .build();
+    }
+
+    for pickup in &scene.pickups {
+        let (image, size) = solid(ctx, world, "pickup", pickup.size, PICKUP_COLOR)?;
+        let origin = nalgebra::Point2::new(pickup.x, pickup.y);
+        world
+            .create_entity()
+            .with(Position { position: origin })
+            .with(square(origin, size))
+            .with(image)
+            .with(Pickup {
+                health: PICKUP_HEALTH,
+            })
+            .build();
+    }
+
+    for enemy in &scene.enemies {
+        let prefab = world.read_resource::<Prefabs>().get(&enemy.prefab).cloned();
+        match prefab {
+            Some(prefab) => {
+                prefab::spawn(world, &prefab, nalgebra::Point2::new(enemy.x, enemy.y));
+            }
+            None => println!("level error scene has unknown prefab {}", enemy.prefab),
+        }
+    }
+    Ok(())
+}
+
+// A square image of one colour, made once for each size it's needed in
+fn solid(
+    ctx: &mut Context,
+    world: &World,
+    name: &str,
+    size: f32,
+    color: graphics::Color,
+) -> GameResult<(ImageHandle, f32)> {
+    let size = size.max(1.0) as u16;
+    let key = format!("{} {}", name, size);
+    let mut assets = world.write_resource::<Assets>();
+    let image = match assets.image(&key) {
+        Some(image) => image,
+        None => {
+            let image = graphics::Image::solid(ctx, size, color)?;
+            assets.insert_image(&key, image)
+        }
+    };
+    Ok((image, f32::from(size)))
+}
+
+fn square(origin: nalgebra::Point2<f32>, size: f32) -> CollisionBox {
+    CollisionBox {
+        origin,
+        width: size,
+        height: size,
+    }
+}
+
+// Hurts the player in hazards and hands out pickups they fly over, going by
+// what the CollisionSystem found them touching
+pub(crate) struct SceneSystem {
+    collisions: TrackedReader<CollisionEvent>,
+}
+
+impl SceneSystem {
+    pub(crate) fn new(world: &World) -> Self {
+        SceneSystem {
+            collisions: events::subscribe::<CollisionEvent>(world, "scene"),
+        }
+    }
+}
+
+impl<'a> System<'a> for SceneSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Write<'a, Notifications>,
+        Publish<'a, DamageEvent>,
+        Subscribe<'a, CollisionEvent>,
+        ReadStorage<'a, Hazard>,
+        ReadStorage<'a, Pickup>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Health>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            delta,
+            time,
+            mut notifications,
+            mut damage_events,
+            collisions,
+            hazards,
+            pickups,
+            multipliers,
+            mut health,
+        ) = data;
+
+        // a pickup is gone once collected, even before it's deleted
+        let mut collected = Vec::new();
+        for collision in collisions.read(&mut self.collisions) {
+            let (player, other) = (collision.a, collision.b);
+            if !entities.is_alive(other) || collected.contains(&other) {
+                continue;
+            }
+            if let Some(hazard) = hazards.get(other) {
+                let dt = time.scaled(delta.seconds, multipliers.get(player));
+                health::deal_damage(
+                    &mut health,
+                    &mut damage_events,
+                    player,
+                    None,
+                    hazard.damage * dt,
+                );
+            }
+            if let Some(pickup) = pickups.get(other) {
+                if let Some(health) = health.get_mut(player) {
+                    health.current = (health.current + fixed::real(pickup.health)).min(health.max);
+                }
+                notifications.push(&format!("+{} health", pickup.health));
+                collected.push(other);
+                entities
+                    .delete(other)
+                    .unwrap_or_else(|err| println!("delete error {:?}", err));
+            }
+        }
+    }
+}
+
+// Hazards are drawn under everything else, as a patch of the level
+pub(crate) fn draw_hazards(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let entities = world.entities();
+    let hazards = world.read_storage::<Hazard>();
+    let coll_box = world.read_storage::<CollisionBox>();
+    let drawn = Drawn::new(world);
+    for (entity, _, coll_box) in (&entities, &hazards, &coll_box).join() {
+        let coll_box = drawn.coll_box(entity, coll_box);
+        let area = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(
+                coll_box.origin.x,
+                coll_box.origin.y,
+                coll_box.width,
+                coll_box.height,
+            ),
+            HAZARD_COLOR,
+        )?;
+        graphics::draw(ctx, &area, graphics::DrawParam::default())?;
+    }
+    Ok(())
+}
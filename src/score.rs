@@ -0,0 +1,51 @@
+use crate::theme::UiTheme;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use specs::*;
+
+const MAX_ENERGY: f32 = 100.0;
+
+// The player's own score, separate from the per-faction round Scores. Energy
+// is earned by playing dangerously, e.g. grazing enemy shots.
+#[derive(Debug, Default)]
+pub(crate) struct PlayerScore {
+    pub(crate) points: u32,
+    pub(crate) energy: f32,
+}
+
+impl PlayerScore {
+    pub(crate) fn add_energy(&mut self, energy: f32) {
+        self.energy = (self.energy + energy).min(MAX_ENERGY);
+    }
+}
+
+// Score and an energy bar in the top left corner
+pub(crate) fn draw_player_score(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let score = world.read_resource::<PlayerScore>();
+    let theme = world.read_resource::<UiTheme>();
+    let view = graphics::screen_coordinates(ctx);
+    let corner = nalgebra::Point2::new(view.x + 10.0, view.y + 10.0);
+
+    let text = theme.text(format!("Score {}", score.points));
+    graphics::draw(
+        ctx,
+        &text,
+        graphics::DrawParam::default()
+            .dest(corner)
+            .color(theme.text),
+    )?;
+
+    let bar = graphics::MeshBuilder::new()
+        .rectangle(
+            graphics::DrawMode::stroke(1.0),
+            graphics::Rect::new(corner.x, corner.y + 22.0, 100.0, 6.0),
+            theme.text,
+        )
+        .rectangle(
+            graphics::DrawMode::fill(),
+            graphics::Rect::new(corner.x, corner.y + 22.0, score.energy, 6.0),
+            theme.accent,
+        )
+        .build(ctx)?;
+    graphics::draw(ctx, &bar, graphics::DrawParam::default())
+}
@@ -0,0 +1,94 @@
+use crate::ai::Difficulty;
+use crate::budget::Budget;
+use crate::controls::{BindingProfile, ControlScheme, Device};
+use crate::outline::Outlines;
+use crate::palette::TeamColors;
+use crate::quality::QualityPreset;
+use crate::storage::{self, Storage};
+use crate::transition::TransitionKind;
+use ggez::GameResult;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// where the settings are kept in the player's storage
+pub(crate) const SETTINGS_FILE: &str = "/settings.ron";
+
+// Player facing options. Settings live in the specs world as a resource so any
+// system can check how the game has been configured without MainState having
+// to pass values around.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub(crate) struct Settings {
+    // the scheme of whichever device is in use, the saved one is kept in that
+    // device's profile
+    #[serde(skip)]
+    pub(crate) control_scheme: ControlScheme,
+    // how hard shots fired at a locked on target curve toward it, in radians
+    // per second. 0 turns aim assist off.
+    pub(crate) aim_assist: f32,
+    // whether projectiles hurt ships on the same side as whoever fired them
+    pub(crate) friendly_fire: bool,
+    // how sharp the AI's reactions and aim are
+    pub(crate) difficulty: Difficulty,
+    // the most detail the game draws with
+    pub(crate) quality: QualityPreset,
+    // whether quality is turned down while frames run late, and back up to
+    // the chosen preset when they catch up
+    pub(crate) adaptive_quality: bool,
+    // caps on short lived entities
+    pub(crate) budget: Budget,
+    // how hovered, selected and locked on entities are outlined
+    pub(crate) outlines: Outlines,
+    // the palettes ships are painted in
+    pub(crate) team_colors: TeamColors,
+    // how one scene gives way to the next
+    pub(crate) transition: TransitionKind,
+    // the bindings of every device the game has seen so far
+    pub(crate) profiles: HashMap<Device, BindingProfile>,
+    // the ids of the tutorial prompts the player has already been through
+    pub(crate) completed_prompts: Vec<String>,
+    // whether gameplay events are logged for balancing, off unless the player
+    // opts in
+    pub(crate) telemetry: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            control_scheme: ControlScheme::default(),
+            aim_assist: 1.5,
+            friendly_fire: false,
+            difficulty: Difficulty::default(),
+            quality: QualityPreset::default(),
+            adaptive_quality: false,
+            budget: Budget::default(),
+            outlines: Outlines::default(),
+            team_colors: TeamColors::default(),
+            transition: TransitionKind::default(),
+            profiles: HashMap::new(),
+            completed_prompts: Vec::new(),
+            telemetry: false,
+        }
+    }
+}
+
+impl Settings {
+    // the profile for a device, making a default one the first time it's used
+    pub(crate) fn profile(&mut self, device: &Device) -> &mut BindingProfile {
+        self.profiles
+            .entry(device.clone())
+            .or_insert_with(|| BindingProfile::new(device))
+    }
+}
+
+// Reads the settings file, or the defaults if there isn't one yet
+pub(crate) fn load(storage: &dyn Storage) -> GameResult<Settings> {
+    if !storage.exists(SETTINGS_FILE) {
+        return Ok(Settings::default());
+    }
+    storage::load_ron(storage, SETTINGS_FILE)
+}
+
+pub(crate) fn save(storage: &dyn Storage, settings: &Settings) -> GameResult<()> {
+    storage::save_ron(storage, SETTINGS_FILE, settings)
+}
@@ -0,0 +1,173 @@
+// In the spirit of sfxr: short retro effects made from a single oscillator
+// with a pitch slide and a volume envelope, generated when the game loads
+// rather than shipped as files.
+
+const SAMPLE_RATE: u32 = 44_100;
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Wave {
+    // duty is the fraction of each cycle spent high
+    Square { duty: f32 },
+    Sawtooth,
+    // a new random level every half cycle, so the frequency still sets how
+    // rough it sounds
+    Noise,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Preset {
+    pub(crate) wave: Wave,
+    // Hz at the start of the sound
+    pub(crate) frequency: f32,
+    // how many times over the frequency is multiplied each second, below 1
+    // sliding down
+    pub(crate) slide: f32,
+    // pitch never slides below this, in Hz
+    pub(crate) min_frequency: f32,
+    // after this many seconds the pitch jumps by the given multiple, for the
+    // two note blip of a pickup
+    pub(crate) jump: Option<(f32, f32)>,
+    // the envelope, in seconds: rising to full volume, holding, fading out
+    pub(crate) attack: f32,
+    pub(crate) sustain: f32,
+    pub(crate) decay: f32,
+    // from 0 to 1
+    pub(crate) volume: f32,
+}
+
+pub(crate) const LASER: Preset = Preset {
+    wave: Wave::Sawtooth,
+    frequency: 1200.0,
+    slide: 0.02,
+    min_frequency: 150.0,
+    jump: None,
+    attack: 0.0,
+    sustain: 0.05,
+    decay: 0.12,
+    volume: 0.3,
+};
+
+pub(crate) const EXPLOSION: Preset = Preset {
+    wave: Wave::Noise,
+    frequency: 900.0,
+    slide: 0.1,
+    min_frequency: 40.0,
+    jump: None,
+    attack: 0.0,
+    sustain: 0.1,
+    decay: 0.5,
+    volume: 0.6,
+};
+
+pub(crate) const PICKUP: Preset = Preset {
+    wave: Wave::Square { duty: 0.5 },
+    frequency: 900.0,
+    slide: 1.0,
+    min_frequency: 0.0,
+    jump: Some((0.05, 1.5)),
+    attack: 0.0,
+    sustain: 0.06,
+    decay: 0.14,
+    volume: 0.3,
+};
+
+// Noise has to come out the same every time the game loads, so it has its own
+// xorshift rather than borrowing the game's random numbers
+struct Noise(u32);
+
+impl Noise {
+    // from -1 to 1
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 as f32 / std::u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+fn envelope(preset: &Preset, time: f32) -> f32 {
+    if time < preset.attack {
+        time / preset.attack
+    } else if time < preset.attack + preset.sustain {
+        1.0
+    } else {
+        let fading = time - preset.attack - preset.sustain;
+        (1.0 - fading / preset.decay).max(0.0)
+    }
+}
+
+// The sound as samples from -1 to 1
+fn samples(preset: &Preset) -> Vec<f32> {
+    let length = preset.attack + preset.sustain + preset.decay;
+    let count = (length * SAMPLE_RATE as f32) as usize;
+    let dt = 1.0 / SAMPLE_RATE as f32;
+    let slide_per_sample = preset.slide.powf(dt);
+
+    let mut noise = Noise(0x2545_f491);
+    let mut noise_level = noise.next();
+    let mut frequency = preset.frequency;
+    let mut jumped = false;
+    // how far through the current cycle, from 0 to 1
+    let mut phase = 0.0;
+    let mut samples = Vec::with_capacity(count);
+    for i in 0..count {
+        let time = i as f32 * dt;
+        if let Some((at, multiple)) = preset.jump {
+            if !jumped && time >= at {
+                frequency *= multiple;
+                jumped = true;
+            }
+        }
+        frequency = (frequency * slide_per_sample).max(preset.min_frequency);
+
+        let previous = phase;
+        phase = (phase + frequency * dt) % 1.0;
+        let value = match preset.wave {
+            Wave::Square { duty } => {
+                if phase < duty {
+                    1.0
+                } else {
+                    -1.0
+                }
+            }
+            Wave::Sawtooth => phase * 2.0 - 1.0,
+            Wave::Noise => {
+                // crossed into the other half of the cycle
+                if (previous < 0.5) != (phase < 0.5) {
+                    noise_level = noise.next();
+                }
+                noise_level
+            }
+        };
+        samples.push(value * envelope(preset, time) * preset.volume);
+    }
+    samples
+}
+
+// The sound as a 16 bit mono WAV file, which is something ggez can play
+pub(crate) fn wav(preset: &Preset) -> Vec<u8> {
+    let samples = samples(preset);
+    let data_bytes = samples.len() as u32 * 2;
+
+    let mut wav = Vec::with_capacity(44 + data_bytes as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_bytes).to_le_bytes());
+    wav.extend_from_slice(b"WAVE");
+    wav.extend_from_slice(b"fmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    // uncompressed, one channel
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes());
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    // bytes a second, then bytes and bits a sample
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_bytes.to_le_bytes());
+    for sample in samples {
+        let sample = (sample.max(-1.0).min(1.0) * f32::from(std::i16::MAX)) as i16;
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    wav
+}
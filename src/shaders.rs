@@ -0,0 +1,41 @@
+use ggez::{graphics, Context, GameResult};
+// gfx_defines! needs all of gfx in scope, which is why the constant blocks for
+// the shaders live in their own module
+use gfx::*;
+
+gfx_defines! {
+    constant Desaturate {
+        amount: f32 = "u_Amount",
+    }
+
+    constant Outline {
+        color: [f32; 4] = "u_Color",
+    }
+}
+
+// Drains the colour out of whatever is drawn with it, by the given amount from
+// 0 to 1
+pub(crate) fn desaturate(ctx: &mut Context) -> GameResult<graphics::Shader<Desaturate>> {
+    graphics::Shader::new(
+        ctx,
+        "/shaders/basic_150.glslv",
+        "/shaders/desaturate_150.glslf",
+        Desaturate { amount: 0.0 },
+        "Desaturate",
+        None,
+    )
+}
+
+// Draws a sprite as a flat silhouette in the given colour, for outlines
+pub(crate) fn outline(ctx: &mut Context) -> GameResult<graphics::Shader<Outline>> {
+    graphics::Shader::new(
+        ctx,
+        "/shaders/basic_150.glslv",
+        "/shaders/outline_150.glslf",
+        Outline {
+            color: [1.0, 1.0, 1.0, 1.0],
+        },
+        "Outline",
+        None,
+    )
+}
@@ -0,0 +1,69 @@
+use crate::CollisionBox;
+use specs::*;
+use std::collections::HashMap;
+
+// Big enough that a ship sits in no more than four cells, small enough that a
+// cell rarely holds more than a handful of them
+const CELL_SIZE: f32 = 64.0;
+
+// Every collision box, filed under the cells of a uniform grid that it
+// touches. Anything that could overlap a box is in one of the same cells, so
+// collision checks only need to look there rather than at every entity.
+// Rebuilt from scratch every update by the BroadPhaseSystem.
+#[derive(Debug, Default)]
+pub(crate) struct SpatialGrid {
+    cells: HashMap<(i32, i32), Vec<Entity>>,
+}
+
+impl SpatialGrid {
+    fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    fn insert(&mut self, entity: Entity, coll_box: &CollisionBox) {
+        for cell in cells(coll_box) {
+            self.cells.entry(cell).or_default().push(entity);
+        }
+    }
+
+    // Everything in the cells the box touches, which may or may not actually
+    // overlap it. They come out in entity order, the same order a join would
+    // give, so whatever is done with them happens in the same order every run.
+    pub(crate) fn near(&self, coll_box: &CollisionBox) -> Vec<Entity> {
+        let mut near: Vec<Entity> = cells(coll_box)
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .cloned()
+            .collect();
+        near.sort_by_key(|entity| entity.id());
+        near.dedup();
+        near
+    }
+}
+
+// the cells a box touches
+fn cells(coll_box: &CollisionBox) -> impl Iterator<Item = (i32, i32)> {
+    let cell = |value: f32| (value / CELL_SIZE).floor() as i32;
+    let (left, top) = (cell(coll_box.origin.x), cell(coll_box.origin.y));
+    let right = cell(coll_box.origin.x + coll_box.width);
+    let bottom = cell(coll_box.origin.y + coll_box.height);
+    (left..=right).flat_map(move |x| (top..=bottom).map(move |y| (x, y)))
+}
+
+// Files every collision box in the SpatialGrid, once everything has moved
+pub(crate) struct BroadPhaseSystem;
+
+impl<'a> System<'a> for BroadPhaseSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, SpatialGrid>,
+        ReadStorage<'a, CollisionBox>,
+    );
+
+    fn run(&mut self, (entities, mut grid, coll_box): Self::SystemData) {
+        grid.clear();
+        for (entity, coll_box) in (&entities, &coll_box).join() {
+            grid.insert(entity, coll_box);
+        }
+    }
+}
@@ -0,0 +1,103 @@
+use specs::*;
+
+// Something to do to the world once the update's systems have all run
+type Command = Box<dyn FnOnce(&mut World) + Send + Sync>;
+
+// Entities for systems to create and delete once the update's systems are
+// done, e.g.
+//
+//     queue.spawn(move |world| {
+//         world.create_entity().with(Position { position: at }).build();
+//     });
+//
+// A spawn gets the whole world, so unlike LazyUpdate it can look at anything
+// it needs to, e.g. the Spawner. The queue is applied after the world is
+// maintained, so what it creates is there for the next update's systems and
+// what it deletes is gone before they run.
+#[derive(Default)]
+pub struct SpawnQueue {
+    spawns: Vec<Command>,
+    despawns: Vec<Entity>,
+}
+
+impl SpawnQueue {
+    pub fn spawn<F: FnOnce(&mut World) + Send + Sync + 'static>(&mut self, spawn: F) {
+        self.spawns.push(Box::new(spawn));
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        self.despawns.push(entity);
+    }
+}
+
+// Carries out everything queued, in the order it was queued, spawns first
+pub(crate) fn apply(world: &mut World) {
+    let (spawns, despawns) = {
+        let mut queue = world.write_resource::<SpawnQueue>();
+        (
+            std::mem::take(&mut queue.spawns),
+            std::mem::take(&mut queue.despawns),
+        )
+    };
+    for spawn in spawns {
+        spawn(world);
+    }
+    for entity in despawns {
+        // it may have been deleted some other way since
+        if world.is_alive(entity) {
+            world.delete_entity(entity).unwrap_or_else(|err| {
+                println!("spawn queue error {:?}", err);
+            });
+        }
+    }
+}
+
+// Shows the queue off, only built with the dev-tools feature and added with
+// `--spawn-demo`. Every press of the fire button queues a new Red ship a
+// little way above the player.
+#[cfg(feature = "dev-tools")]
+pub(crate) mod demo {
+    use super::SpawnQueue;
+    use crate::controls::Aim;
+    use crate::faction::Faction;
+    use crate::spawner::Spawner;
+    use crate::{CollisionBox, ControllableTag};
+    use ggez::nalgebra;
+    use specs::*;
+
+    // how far above the player the ship turns up
+    const DISTANCE: f32 = 120.0;
+
+    #[derive(Default)]
+    pub(crate) struct SpawnDemoSystem {
+        was_firing: bool,
+    }
+
+    impl<'a> System<'a> for SpawnDemoSystem {
+        type SystemData = (
+            Read<'a, Aim>,
+            Write<'a, SpawnQueue>,
+            ReadStorage<'a, CollisionBox>,
+            ReadStorage<'a, ControllableTag>,
+        );
+
+        fn run(&mut self, (aim, mut queue, coll_box, controlled): Self::SystemData) {
+            let pressed = aim.firing && !self.was_firing;
+            self.was_firing = aim.firing;
+            if !pressed {
+                return;
+            }
+            for (coll_box, _) in (&coll_box, &controlled).join() {
+                let at = coll_box.origin - nalgebra::Vector2::new(0.0, DISTANCE);
+                queue.spawn(move |world| {
+                    let spawner = world.read_resource::<Spawner>();
+                    spawner
+                        .ship(world.create_entity_unchecked())
+                        .at_point(at)
+                        .faction(Faction::Red)
+                        .build();
+                });
+            }
+        }
+    }
+}
@@ -0,0 +1,189 @@
+use crate::assets::Assets;
+use crate::bundles::{Bundle, PhysicsBundle, SpriteBundle};
+use crate::cooldowns::Cooldowns;
+use crate::faction::Faction;
+use crate::health::{Damage, Health};
+use crate::hitbox::Hurtbox;
+use crate::palette::{self, TeamColors};
+use crate::time::TimeMultiplier;
+use crate::weapons::Weapon;
+use crate::{Bounded, BoundsPolicy, ControllableTag, ImageHandle, Velocity, ZOrder};
+use ggez::nalgebra;
+use ggez::{Context, GameResult};
+use specs::*;
+
+// health per second a ship takes off whatever it rams, and the other way round
+const RAM_DAMAGE: f32 = 20.0;
+
+// Sets up the kinds of entity the game is made of, so spawn sites only say
+// what is different about theirs, e.g.
+//
+//     spawner.ship(world.create_entity()).at(75.0, 100.0).controllable().build()
+//
+// It works with a world's EntityBuilder as well as the LazyBuilder systems get
+// from LazyUpdate. The spawner lives in the world as a resource, so systems
+// spawn the same ships MainState does.
+//
+// Ships are drawn in their side's palette from the TeamColors. A copy of the
+// ship image is made in every palette up front and kept in the Assets, so
+// changing colours is only a matter of picking another copy.
+pub(crate) struct Spawner {
+    // the original image first, then one for each palette
+    ship_skins: Vec<(&'static str, ImageHandle)>,
+    ship_width: f32,
+    ship_height: f32,
+    pub(crate) team_colors: TeamColors,
+}
+
+impl Spawner {
+    pub(crate) fn new(
+        ctx: &mut Context,
+        assets: &mut Assets,
+        ship_path: &str,
+        team_colors: TeamColors,
+    ) -> GameResult<Self> {
+        let ship = assets.load_image(ctx, ship_path)?;
+        let mut ship_skins = vec![(palette::ORIGINAL, ship)];
+        for palette in palette::PALETTES.iter() {
+            let skin = palette::recolor(ctx, assets.get(ship), palette)?;
+            let key = format!("{} {}", ship_path, palette.name);
+            ship_skins.push((palette.name, assets.insert_image(&key, skin)));
+        }
+        let (ship_width, ship_height) = assets.size(ship);
+        Ok(Spawner {
+            ship_width,
+            ship_height,
+            ship_skins,
+            team_colors,
+        })
+    }
+
+    pub(crate) fn ship_size(&self) -> (f32, f32) {
+        (self.ship_width, self.ship_height)
+    }
+
+    // the ship image in the named palette, the original for a name it
+    // doesn't know
+    pub(crate) fn ship_skin(&self, name: &str) -> ImageHandle {
+        self.ship_skins
+            .iter()
+            .find(|(skin, _)| *skin == name)
+            .unwrap_or(&self.ship_skins[0])
+            .1
+    }
+
+    // A ship with the ship image, a collision box and hurtbox to match, 100
+    // health and its own cooldowns, at rest at the origin until told otherwise
+    pub(crate) fn ship<B: Builder>(&self, builder: B) -> ShipBuilder<'_, B> {
+        ShipBuilder {
+            builder,
+            spawner: self,
+            width: self.ship_width,
+            height: self.ship_height,
+            origin: nalgebra::Point2::origin(),
+            health: 100.0,
+            weapon: None,
+            faction: None,
+            controllable: false,
+        }
+    }
+}
+
+pub(crate) struct ShipBuilder<'s, B: Builder> {
+    builder: B,
+    spawner: &'s Spawner,
+    width: f32,
+    height: f32,
+    origin: nalgebra::Point2<f32>,
+    health: f32,
+    weapon: Option<Weapon>,
+    faction: Option<Faction>,
+    controllable: bool,
+}
+
+impl<'s, B: Builder> ShipBuilder<'s, B> {
+    // where the top left corner of the ship goes
+    pub(crate) fn at(self, x: f32, y: f32) -> Self {
+        self.at_point(nalgebra::Point2::new(x, y))
+    }
+
+    pub(crate) fn at_point(self, origin: nalgebra::Point2<f32>) -> Self {
+        ShipBuilder { origin, ..self }
+    }
+
+    pub(crate) fn health(self, health: f32) -> Self {
+        ShipBuilder { health, ..self }
+    }
+
+    pub(crate) fn weapon(self, fire_delay: f32, projectile_speed: f32, damage: f32) -> Self {
+        let weapon = Weapon {
+            fire_delay,
+            projectile_speed,
+            damage,
+        };
+        ShipBuilder {
+            weapon: Some(weapon),
+            ..self
+        }
+    }
+
+    pub(crate) fn faction(self, faction: Faction) -> Self {
+        ShipBuilder {
+            faction: Some(faction),
+            ..self
+        }
+    }
+
+    // Flown by the player, which also means the small hurtbox and ignoring
+    // bullet time
+    pub(crate) fn controllable(self) -> Self {
+        ShipBuilder {
+            controllable: true,
+            ..self
+        }
+    }
+
+    // anything else the ship needs, an AI for example
+    pub(crate) fn with<C: Component + Send + Sync>(self, component: C) -> Self {
+        ShipBuilder {
+            builder: self.builder.with(component),
+            ..self
+        }
+    }
+
+    pub(crate) fn build(self) -> Entity {
+        let hurtbox = if self.controllable {
+            Hurtbox::player(self.width, self.height)
+        } else {
+            Hurtbox::ship(self.width, self.height)
+        };
+        let builder = PhysicsBundle::new(self.origin, self.width, self.height).add_to(self.builder);
+        let palette = self
+            .spawner
+            .team_colors
+            .palette(self.faction, self.controllable);
+        let mut builder = SpriteBundle::new(self.spawner.ship_skin(palette))
+            .add_to(builder)
+            .with(hurtbox)
+            .with(Health::new(self.health))
+            .with(Damage {
+                per_second: RAM_DAMAGE,
+            })
+            .with(Velocity::default())
+            .with(Cooldowns::default());
+        if let Some(weapon) = self.weapon {
+            builder = builder.with(weapon);
+        }
+        if let Some(faction) = self.faction {
+            builder = builder.with(faction);
+        }
+        if self.controllable {
+            builder = builder
+                .with(TimeMultiplier::unscaled())
+                .with(ZOrder::PLAYER)
+                .with(Bounded::new(BoundsPolicy::Clamp))
+                .with(ControllableTag);
+        }
+        builder.build()
+    }
+}
@@ -0,0 +1,227 @@
+use crate::atlas::Atlas;
+use crate::events::Publish;
+use crate::health::{self, DamageEvent, Health};
+use crate::interpolation::Drawn;
+use crate::stealth::{self, Cloaked, Revealed};
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::CollisionBox;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+
+// health lost per second while burning
+const BURN_DAMAGE: f32 = 3.0;
+// how fast a slowed entity runs
+const SLOW_FACTOR: f32 = 0.5;
+// icons are drawn this many pixels across, in a row above the entity
+const ICON_SIZE: f32 = 12.0;
+const ICON_GAP: f32 = 2.0;
+// icons fade out over the last this many seconds of their effect
+const ICON_FADE: f32 = 1.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum Status {
+    // takes no damage
+    Shielded,
+    // takes damage over time
+    Burning,
+    // runs at half speed
+    Slowed,
+}
+
+impl Status {
+    // the name of the icon in the status atlas
+    fn icon(self) -> &'static str {
+        match self {
+            Status::Shielded => "shielded",
+            Status::Burning => "burning",
+            Status::Slowed => "slowed",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(crate) struct StatusEffect {
+    pub(crate) status: Status,
+    // seconds left
+    pub(crate) remaining: f32,
+}
+
+// Timed effects on an entity. Each status is only in the list once, applying
+// it again tops up the time left.
+#[derive(Component, Debug, Default, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(crate) struct StatusEffects {
+    pub(crate) effects: Vec<StatusEffect>,
+    // whether the StatusSystem has slowed the entity's TimeMultiplier, so it
+    // knows to put it back
+    slowing: bool,
+}
+
+impl StatusEffects {
+    pub(crate) fn with(status: Status, duration: f32) -> Self {
+        let mut effects = StatusEffects::default();
+        effects.apply(status, duration);
+        effects
+    }
+
+    pub(crate) fn apply(&mut self, status: Status, duration: f32) {
+        match self.effects.iter_mut().find(|e| e.status == status) {
+            Some(effect) => effect.remaining = effect.remaining.max(duration),
+            None => self.effects.push(StatusEffect {
+                status,
+                remaining: duration,
+            }),
+        }
+    }
+
+    pub(crate) fn has(&self, status: Status) -> bool {
+        self.effects.iter().any(|e| e.status == status)
+    }
+}
+
+// Applies an effect from inside a system, adding the StatusEffects component
+// if the entity doesn't have one yet
+pub(crate) fn apply(
+    statuses: &mut WriteStorage<StatusEffects>,
+    entity: Entity,
+    status: Status,
+    duration: f32,
+) {
+    match statuses.get_mut(entity) {
+        Some(effects) => effects.apply(status, duration),
+        None => {
+            statuses
+                .insert(entity, StatusEffects::with(status, duration))
+                .unwrap_or_else(|err| {
+                    println!("status error {:?}", err);
+                    None
+                });
+        }
+    }
+}
+
+// Counts effects down and carries them out: burning hurts, shields turn damage
+// away and slowing changes the entity's TimeMultiplier
+pub(crate) struct StatusSystem;
+
+impl<'a> System<'a> for StatusSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Publish<'a, DamageEvent>,
+        WriteStorage<'a, StatusEffects>,
+        WriteStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Health>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, delta, time, mut damage_events, mut statuses, mut multipliers, mut health) =
+            data;
+
+        for (entity, effects) in (&entities, &mut statuses).join() {
+            let dt = time.scaled(delta.seconds, multipliers.get(entity));
+            for effect in effects.effects.iter_mut() {
+                effect.remaining -= dt;
+            }
+            effects.effects.retain(|e| e.remaining > 0.0);
+
+            if let Some(health) = health.get_mut(entity) {
+                health.shielded = effects.has(Status::Shielded);
+            }
+            if effects.has(Status::Burning) {
+                health::deal_damage(
+                    &mut health,
+                    &mut damage_events,
+                    entity,
+                    None,
+                    BURN_DAMAGE * dt,
+                );
+            }
+
+            let slowed = effects.has(Status::Slowed);
+            if slowed != effects.slowing {
+                effects.slowing = slowed;
+                let factor = if slowed { SLOW_FACTOR } else { 1.0 };
+                match multipliers.get_mut(entity) {
+                    Some(multiplier) => multiplier.factor = factor,
+                    None => {
+                        multipliers
+                            .insert(
+                                entity,
+                                TimeMultiplier {
+                                    factor,
+                                    global: true,
+                                },
+                            )
+                            .unwrap_or_else(|err| {
+                                println!("status error {:?}", err);
+                                None
+                            });
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Draws a row of icons above everything with status effects, fading each one
+// out as it runs down. The icons all come from one atlas, so they go into a
+// single sprite batch.
+pub(crate) fn draw_status_icons(ctx: &mut Context, world: &World, atlas: &Atlas) -> GameResult<()> {
+    let entities = world.entities();
+    let coll_box = world.read_storage::<CollisionBox>();
+    let statuses = world.read_storage::<StatusEffects>();
+    let cloaked = world.read_storage::<Cloaked>();
+    let revealed = world.read_storage::<Revealed>();
+    let drawn = Drawn::new(world);
+
+    let mut batch = graphics::spritebatch::SpriteBatch::new(atlas.image.clone());
+    let mut any_icons = false;
+
+    for (entity, coll_box, effects, cloak, reveal) in (
+        &entities,
+        &coll_box,
+        &statuses,
+        cloaked.maybe(),
+        revealed.maybe(),
+    )
+        .join()
+    {
+        if effects.effects.is_empty() || stealth::is_hidden(cloak, reveal) {
+            continue;
+        }
+        let coll_box = drawn.coll_box(entity, coll_box);
+
+        let count = effects.effects.len() as f32;
+        let row_width = count * ICON_SIZE + (count - 1.0) * ICON_GAP;
+        let mut x = coll_box.center().x - row_width / 2.0;
+        let y = coll_box.origin.y - ICON_SIZE - 4.0;
+
+        for effect in effects.effects.iter() {
+            if let Some(region) = atlas.region(effect.status.icon()) {
+                let alpha = (effect.remaining / ICON_FADE).min(1.0);
+                batch.add(
+                    graphics::DrawParam::default()
+                        .src(region.src)
+                        .dest(nalgebra::Point2::new(x, y))
+                        .scale(nalgebra::Vector2::new(
+                            ICON_SIZE / region.width,
+                            ICON_SIZE / region.height,
+                        ))
+                        .color(graphics::Color::new(1.0, 1.0, 1.0, alpha)),
+                );
+                any_icons = true;
+            }
+            x += ICON_SIZE + ICON_GAP;
+        }
+    }
+
+    if any_icons {
+        graphics::draw(ctx, &batch, graphics::DrawParam::default())?;
+    }
+    Ok(())
+}
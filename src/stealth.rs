@@ -0,0 +1,107 @@
+use crate::events::{self, Subscribe, TrackedReader};
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::CollisionEvent;
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+
+// how long a cloak flickers off after firing or bumping into something
+pub(crate) const CLOAK_DISRUPT_TIME: f32 = 1.5;
+
+// Cloaked entities aren't drawn, don't appear on the minimap and can't be
+// targeted. Firing or colliding disrupts the cloak for a moment, and a radar
+// ping reveals them regardless.
+#[derive(Component, Debug, Default, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(crate) struct Cloaked {
+    // seconds until the cloak hides the entity again
+    pub(crate) disrupted: f32,
+}
+
+// The single check everything that "looks" at entities should use, so cloaks
+// behave the same for rendering, targeting and the HUD
+pub(crate) fn is_hidden(cloak: Option<&Cloaked>, reveal: Option<&Revealed>) -> bool {
+    cloak.map_or(false, |cloak| cloak.disrupted <= 0.0) && reveal.is_none()
+}
+
+pub(crate) fn disrupt(cloaked: &mut WriteStorage<Cloaked>, entity: Entity) {
+    if let Some(cloak) = cloaked.get_mut(entity) {
+        cloak.disrupted = CLOAK_DISRUPT_TIME;
+    }
+}
+
+// Temporarily exposes an entity that would otherwise be hidden, either because
+// it is cloaked or because it is too far away for the minimap
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(crate) struct Revealed {
+    // seconds until the entity is hidden again
+    pub(crate) remaining: f32,
+}
+
+// Counts cloak disruptions down, and disrupts the cloaks of anything that
+// bumped into something
+pub(crate) struct CloakSystem {
+    collisions: TrackedReader<CollisionEvent>,
+}
+
+impl CloakSystem {
+    pub(crate) fn new(world: &World) -> Self {
+        CloakSystem {
+            collisions: events::subscribe::<CollisionEvent>(world, "cloak"),
+        }
+    }
+}
+
+impl<'a> System<'a> for CloakSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Subscribe<'a, CollisionEvent>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Cloaked>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, delta, time, collisions, multipliers, mut cloaked) = data;
+
+        // bumping into something shakes both cloaks loose
+        for collision in collisions.read(&mut self.collisions) {
+            disrupt(&mut cloaked, collision.a);
+            disrupt(&mut cloaked, collision.b);
+        }
+
+        for (entity, cloak) in (&entities, &mut cloaked).join() {
+            let dt = time.scaled(delta.seconds, multipliers.get(entity));
+            cloak.disrupted = (cloak.disrupted - dt).max(0.0);
+        }
+    }
+}
+
+pub(crate) struct RevealSystem;
+
+impl<'a> System<'a> for RevealSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Revealed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, delta, time, multipliers, mut revealed) = data;
+
+        let mut expired = Vec::new();
+        for (entity, reveal) in (&entities, &mut revealed).join() {
+            reveal.remaining -= time.scaled(delta.seconds, multipliers.get(entity));
+            if reveal.remaining <= 0.0 {
+                expired.push(entity);
+            }
+        }
+        for entity in expired {
+            revealed.remove(entity);
+        }
+    }
+}
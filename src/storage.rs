@@ -0,0 +1,183 @@
+use ggez::{GameError, GameResult};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+
+// Where everything the game keeps between runs goes: the settings with the
+// binding profiles, saved games and replays. Paths are written like resource
+// paths, e.g. "/saves/slot1.ron", and a backend can keep them wherever suits
+// it, a directory that gets synced, a browser's local storage or just memory.
+// Resources the game ships with are still read with ggez's filesystem.
+pub(crate) trait Storage {
+    fn exists(&self, path: &str) -> bool;
+
+    fn read(&self, path: &str) -> GameResult<Vec<u8>>;
+
+    // Replaces whatever was at the path all at once. A crash part way through
+    // must leave the old contents, never half of the new.
+    fn write(&self, path: &str, data: &[u8]) -> GameResult<()>;
+
+    fn delete(&self, path: &str) -> GameResult<()>;
+
+    // Adds to the end of whatever is at the path, for logs that only grow.
+    // Unlike write this can be cut short by a crash.
+    fn append(&self, path: &str, data: &[u8]) -> GameResult<()>;
+
+    // the paths of the files directly in a directory, in order
+    fn list(&self, dir: &str) -> Vec<String>;
+}
+
+// Files under a directory, normally the user data directory ggez picks for
+// the platform
+pub(crate) struct FileStorage {
+    root: PathBuf,
+}
+
+impl FileStorage {
+    pub(crate) fn new(root: PathBuf) -> Self {
+        FileStorage { root }
+    }
+
+    fn resolve(&self, path: &str) -> PathBuf {
+        self.root.join(path.trim_start_matches('/'))
+    }
+}
+
+impl Storage for FileStorage {
+    fn exists(&self, path: &str) -> bool {
+        self.resolve(path).is_file()
+    }
+
+    fn read(&self, path: &str) -> GameResult<Vec<u8>> {
+        Ok(fs::read(self.resolve(path))?)
+    }
+
+    // Writes to a temporary file next to the real one and renames it over the
+    // top once it is safely on disk. The rename either happens or it doesn't.
+    fn write(&self, path: &str, data: &[u8]) -> GameResult<()> {
+        let path = self.resolve(path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut temp = path.clone().into_os_string();
+        temp.push(".tmp");
+        let mut file = fs::File::create(&temp)?;
+        file.write_all(data)?;
+        file.sync_all()?;
+        fs::rename(&temp, &path)?;
+        Ok(())
+    }
+
+    fn delete(&self, path: &str) -> GameResult<()> {
+        Ok(fs::remove_file(self.resolve(path))?)
+    }
+
+    fn append(&self, path: &str, data: &[u8]) -> GameResult<()> {
+        let path = self.resolve(path);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        file.write_all(data)?;
+        Ok(())
+    }
+
+    fn list(&self, dir: &str) -> Vec<String> {
+        let entries = match fs::read_dir(self.resolve(dir)) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+        let mut paths: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| {
+                format!(
+                    "{}/{}",
+                    dir.trim_end_matches('/'),
+                    entry.file_name().to_string_lossy()
+                )
+            })
+            .collect();
+        paths.sort();
+        paths
+    }
+}
+
+// Nothing kept past the end of the run. For platforms without a filesystem,
+// and for the audit's shadow game, which shouldn't be writing the player's
+// files as well.
+#[derive(Default)]
+pub(crate) struct MemoryStorage {
+    files: RefCell<HashMap<String, Vec<u8>>>,
+}
+
+impl Storage for MemoryStorage {
+    fn exists(&self, path: &str) -> bool {
+        self.files.borrow().contains_key(path)
+    }
+
+    fn read(&self, path: &str) -> GameResult<Vec<u8>> {
+        self.files
+            .borrow()
+            .get(path)
+            .cloned()
+            .ok_or_else(|| GameError::ResourceNotFound(path.to_owned(), Vec::new()))
+    }
+
+    fn write(&self, path: &str, data: &[u8]) -> GameResult<()> {
+        self.files
+            .borrow_mut()
+            .insert(path.to_owned(), data.to_vec());
+        Ok(())
+    }
+
+    fn delete(&self, path: &str) -> GameResult<()> {
+        self.files.borrow_mut().remove(path);
+        Ok(())
+    }
+
+    fn append(&self, path: &str, data: &[u8]) -> GameResult<()> {
+        self.files
+            .borrow_mut()
+            .entry(path.to_owned())
+            .or_default()
+            .extend_from_slice(data);
+        Ok(())
+    }
+
+    fn list(&self, dir: &str) -> Vec<String> {
+        let prefix = format!("{}/", dir.trim_end_matches('/'));
+        let mut paths: Vec<String> = self
+            .files
+            .borrow()
+            .keys()
+            .filter(|path| path.starts_with(&prefix) && !path[prefix.len()..].contains('/'))
+            .cloned()
+            .collect();
+        paths.sort();
+        paths
+    }
+}
+
+pub(crate) fn load_ron<T: DeserializeOwned>(storage: &dyn Storage, path: &str) -> GameResult<T> {
+    let bytes = storage.read(path)?;
+    ron::de::from_bytes(&bytes)
+        .map_err(|err| GameError::ResourceLoadError(format!("{}: {}", path, err)))
+}
+
+pub(crate) fn save_ron<T: Serialize>(
+    storage: &dyn Storage,
+    path: &str,
+    value: &T,
+) -> GameResult<()> {
+    let text = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::default())
+        .map_err(|err| GameError::FilesystemError(format!("{}: {}", path, err)))?;
+    storage.write(path, text.as_bytes())
+}
@@ -0,0 +1,511 @@
+use crate::camera::Camera;
+use crate::components::{
+    Acceleration, Animation, Bounded, BoundsPolicy, BoxOffset, Collider, CollisionBox,
+    ControllableTag, ImageHandle, Position, Rotation, Scale, Solid, Transform, Velocity,
+};
+use crate::events::Publish;
+use crate::faction::{self, Faction};
+use crate::graze;
+use crate::hitbox::{self, Hurtbox};
+use crate::listener::{Cue, SoundCues};
+use crate::quality::Quality;
+use crate::resources::{Assets, DeltaTime, Direction, TimeScale};
+use crate::score::PlayerScore;
+use crate::spatial::SpatialGrid;
+use crate::time::TimeMultiplier;
+use crate::tweakables::{self, Tweak, Tweakables};
+use crate::weapons::Projectile;
+use ggez::nalgebra;
+use specs::*;
+
+// The built-in plumbing every game has: moving things and finding what bumps
+// into what. The rest of the game's systems live in their own modules.
+
+// how fast the player's ship flies, in pixels per second
+const PLAYER_SPEED: Tweak = Tweak {
+    name: "player speed",
+    default: 600.0,
+    min: 100.0,
+    max: 1500.0,
+    step: 25.0,
+};
+
+// The movement system sets the velocity of entities with the ControllableTag
+// marker from the Direction, then moves everything with a velocity by however
+// much game time the DeltaTime says passed.
+// When we move an entity, we also need to update its collision component.
+// The player's ship is stopped by anything Solid, the rest fly through.
+pub(crate) struct MovementSystem;
+pub(crate) struct CollisionSystem;
+
+impl MovementSystem {
+    pub(crate) fn new(world: &World) -> Self {
+        tweakables::register(world, &PLAYER_SPEED);
+        MovementSystem
+    }
+}
+
+impl<'a> System<'a> for MovementSystem {
+    type SystemData = (
+        Read<'a, Direction>,
+        Read<'a, Tweakables>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Entities<'a>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Velocity>,
+        ReadStorage<'a, Acceleration>,
+        WriteStorage<'a, CollisionBox>,
+        ReadStorage<'a, BoxOffset>,
+        ReadStorage<'a, ControllableTag>,
+        ReadStorage<'a, TimeMultiplier>,
+        ReadStorage<'a, Solid>,
+        Write<'a, SolidContacts>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            dir,
+            tweakables,
+            delta,
+            time,
+            entities,
+            mut pos,
+            mut vel,
+            accel,
+            mut coll_box,
+            offsets,
+            controlled,
+            multipliers,
+            solid,
+            mut contacts,
+        ) = data;
+
+        let solids: Vec<(Entity, CollisionBox)> = (&entities, &coll_box, &solid)
+            .join()
+            .map(|(entity, coll_box, _)| (entity, *coll_box))
+            .collect();
+        contacts.pairs.clear();
+
+        // the player flies at a steady speed whichever keys are held, or as
+        // fast as the stick is pushed
+        let speed = tweakables.get(&PLAYER_SPEED);
+        for (vel, _) in (&mut vel, &controlled).join() {
+            vel.velocity = dir.heading() * speed;
+        }
+
+        for (entity, pos, vel) in (&entities, &mut pos, &mut vel).join() {
+            let dt = time.scaled(delta.seconds, multipliers.get(entity));
+            if let Some(accel) = accel.get(entity) {
+                vel.velocity += accel.acceleration * dt;
+            }
+            let step = vel.velocity * dt;
+            let offset = BoxOffset::of(offsets.get(entity));
+
+            // if an entity has an updated position, we also need to update it's
+            // collision box.
+            match coll_box.get_mut(entity) {
+                Some(coll_box) if controlled.contains(entity) => {
+                    coll_box.origin = pos.position + offset;
+                    for solid in move_against(coll_box, step, &solids) {
+                        contacts.pairs.push((entity, solid));
+                    }
+                    pos.position = coll_box.origin - offset;
+                }
+                Some(coll_box) => {
+                    pos.position += step;
+                    coll_box.origin = pos.position + offset;
+                }
+                None => pos.position += step,
+            }
+        }
+    }
+}
+
+// Moves the box one axis at a time, pushing it back out of any solid it ends
+// up in along that axis, so flying into a wall at an angle slides along it
+// rather than stopping dead. Anything it's still in after that, a solid put
+// down on top of it say, pushes it out the shortest way. Returns the solids
+// it was pushed out of, each once.
+fn move_against(
+    coll_box: &mut CollisionBox,
+    step: nalgebra::Vector2<f32>,
+    solids: &[(Entity, CollisionBox)],
+) -> Vec<Entity> {
+    let mut met = Vec::new();
+    coll_box.origin.x += step.x;
+    for (entity, solid) in solids {
+        if let Some(out) = hitbox::separation(coll_box, solid) {
+            coll_box.origin.x += out.x;
+            met.push(*entity);
+        }
+    }
+    coll_box.origin.y += step.y;
+    for (entity, solid) in solids {
+        if let Some(out) = hitbox::separation(coll_box, solid) {
+            coll_box.origin.y += out.y;
+            met.push(*entity);
+        }
+    }
+    for (entity, solid) in solids {
+        if let Some(out) = hitbox::minimum_translation(coll_box, solid) {
+            coll_box.origin += out;
+            met.push(*entity);
+        }
+    }
+    met.sort_by_key(|entity| entity.id());
+    met.dedup();
+    met
+}
+
+// Holds, wraps or deletes anything Bounded that the MovementSystem took past
+// the edge of the level, going by its BoundsPolicy. The Camera knows where
+// the edge is, the window for a level without a size of its own.
+pub(crate) struct BoundsSystem;
+
+impl<'a> System<'a> for BoundsSystem {
+    type SystemData = (
+        Read<'a, Camera>,
+        Entities<'a>,
+        ReadStorage<'a, Bounded>,
+        ReadStorage<'a, BoxOffset>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, CollisionBox>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (camera, entities, bounded, offsets, mut pos, mut coll_box) = data;
+        let level = camera.bounds;
+        for (entity, bounded, pos, coll_box) in
+            (&entities, &bounded, &mut pos, &mut coll_box).join()
+        {
+            let (right, bottom) = (level.x + level.w, level.y + level.h);
+            let origin = &mut coll_box.origin;
+            match bounded.policy {
+                BoundsPolicy::Clamp => {
+                    origin.x = origin.x.min(right - coll_box.width).max(level.x);
+                    origin.y = origin.y.min(bottom - coll_box.height).max(level.y);
+                }
+                // only once it's all the way off, so it slides off one side
+                // as it comes on at the other
+                BoundsPolicy::Wrap => {
+                    if origin.x > right {
+                        origin.x = level.x - coll_box.width;
+                    } else if origin.x + coll_box.width < level.x {
+                        origin.x = right;
+                    }
+                    if origin.y > bottom {
+                        origin.y = level.y - coll_box.height;
+                    } else if origin.y + coll_box.height < level.y {
+                        origin.y = bottom;
+                    }
+                }
+                BoundsPolicy::Destroy => {
+                    let gone = origin.x > right
+                        || origin.y > bottom
+                        || origin.x + coll_box.width < level.x
+                        || origin.y + coll_box.height < level.y;
+                    if gone {
+                        entities
+                            .delete(entity)
+                            .unwrap_or_else(|err| println!("bounds error {:?}", err));
+                    }
+                }
+            }
+            pos.position = coll_box.origin - BoxOffset::of(offsets.get(entity));
+        }
+    }
+}
+
+// Moves every Animation on by however much game time passed, the same time
+// the entity moves by
+pub(crate) struct AnimationSystem;
+
+impl<'a> System<'a> for AnimationSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Entities<'a>,
+        WriteStorage<'a, Animation>,
+        ReadStorage<'a, TimeMultiplier>,
+    );
+
+    fn run(&mut self, (delta, time, entities, mut animations, multipliers): Self::SystemData) {
+        for (entity, animation) in (&entities, &mut animations).join() {
+            if animation.frames.is_empty() || animation.frame_time <= 0.0 {
+                continue;
+            }
+            animation.elapsed += time.scaled(delta.seconds, multipliers.get(entity));
+            // a long update can skip over more than one frame
+            while animation.elapsed >= animation.frame_time && !animation.finished() {
+                animation.elapsed -= animation.frame_time;
+                animation.frame = (animation.frame + 1) % animation.frames.len();
+            }
+        }
+    }
+}
+
+// Gathers the Position, Rotation and Scale of everything with a Position into
+// its Transform, giving it one if it has none yet. It runs once everything
+// that moves, turns or scales things has, and again before each frame is
+// drawn so things moved while the game is paused are drawn where they are.
+pub(crate) struct TransformSystem;
+
+impl<'a> System<'a> for TransformSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, Rotation>,
+        ReadStorage<'a, Scale>,
+        WriteStorage<'a, Transform>,
+    );
+
+    fn run(&mut self, (entities, pos, rotations, scales, mut transforms): Self::SystemData) {
+        for (entity, pos, rotation, scale) in
+            (&entities, &pos, rotations.maybe(), scales.maybe()).join()
+        {
+            let transform = Transform::of(pos, rotation, scale);
+            match transforms.get_mut(entity) {
+                Some(existing) => *existing = transform,
+                None => {
+                    transforms.insert(entity, transform).unwrap_or_else(|err| {
+                        println!("transform error {:?}", err);
+                        None
+                    });
+                }
+            }
+        }
+    }
+}
+
+// Fits the CollisionBox of everything with a Scale around its sprite as it's
+// drawn, turned and sized by its Transform. The box is the
+// smallest one the turned sprite fits in, so it's bigger than the sprite at
+// an angle, and doesn't start at the Position, so its BoxOffset is set to
+// where it does. Everything else keeps the box it was given, so ships turning
+// to aim don't grow and shrink as they do.
+pub(crate) struct FitBoxSystem;
+
+impl<'a> System<'a> for FitBoxSystem {
+    type SystemData = (
+        Read<'a, Assets>,
+        Entities<'a>,
+        ReadStorage<'a, Transform>,
+        ReadStorage<'a, Scale>,
+        ReadStorage<'a, ImageHandle>,
+        ReadStorage<'a, Animation>,
+        WriteStorage<'a, CollisionBox>,
+        WriteStorage<'a, BoxOffset>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (assets, entities, transforms, scales, images, animations, mut coll_box, mut offsets) =
+            data;
+
+        // the Scale only picks out which boxes are fitted, the Transform has it
+        for (entity, transform, _, image, animation, coll_box) in (
+            &entities,
+            &transforms,
+            &scales,
+            &images,
+            animations.maybe(),
+            &mut coll_box,
+        )
+            .join()
+        {
+            // the sprite is drawn about its middle, the same as the RenderSystem
+            let (width, height) = match animation.and_then(|animation| animation.current()) {
+                Some(frame) => (frame.w, frame.h),
+                None => assets.size(*image),
+            };
+            let center = transform.position() + nalgebra::Vector2::new(width / 2.0, height / 2.0);
+            let scale = transform.scale;
+            let (width, height) = ((width * scale.x).abs(), (height * scale.y).abs());
+            let angle = transform.rotation;
+            let (sin, cos) = (angle.sin().abs(), angle.cos().abs());
+            let turned =
+                nalgebra::Vector2::new(width * cos + height * sin, width * sin + height * cos);
+
+            coll_box.origin = center - turned / 2.0;
+            coll_box.width = turned.x;
+            coll_box.height = turned.y;
+            offsets
+                .insert(
+                    entity,
+                    BoxOffset {
+                        offset: coll_box.origin - transform.position(),
+                    },
+                )
+                .unwrap_or_else(|err| {
+                    println!("fit box error {:?}", err);
+                    None
+                });
+        }
+    }
+}
+
+// Sent when the CollisionSystem finds two entities touching, their collision
+// boxes overlapping or their Colliders if they have them, or the player's
+// ship up against something Solid, for anything that wants to react to it.
+// The player's ship is a.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct CollisionEvent {
+    pub(crate) a: Entity,
+    pub(crate) b: Entity,
+}
+
+// How many pairs the last collision update found touching, for the debug
+// overlay
+#[derive(Debug, Default)]
+pub(crate) struct CollisionStats {
+    pub(crate) pairs: usize,
+}
+
+// The solids the MovementSystem pushed a player's ship back out of this
+// update, as (ship, solid). Once the FitBoxSystem has fitted the boxes to
+// their sprites again, a ship that was flying into a turning solid can be
+// left a little way off it, so this is what says it rammed the solid rather
+// than where the boxes end up.
+#[derive(Debug, Default)]
+pub(crate) struct SolidContacts {
+    pub(crate) pairs: Vec<(Entity, Entity)>,
+}
+
+impl SolidContacts {
+    fn against(&self, ship: Entity) -> impl Iterator<Item = Entity> + '_ {
+        self.pairs
+            .iter()
+            .filter(move |(pushed, _)| *pushed == ship)
+            .map(|(_, solid)| *solid)
+    }
+}
+
+impl<'a> System<'a> for CollisionSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, LazyUpdate>,
+        Read<'a, Quality>,
+        Write<'a, PlayerScore>,
+        Write<'a, SoundCues>,
+        Write<'a, CollisionStats>,
+        Publish<'a, CollisionEvent>,
+        Read<'a, SpatialGrid>,
+        Read<'a, SolidContacts>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Collider>,
+        ReadStorage<'a, Rotation>,
+        ReadStorage<'a, ControllableTag>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, Hurtbox>,
+        ReadStorage<'a, Solid>,
+        WriteStorage<'a, Projectile>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        //println!("Running the collision system");
+        let (
+            entities,
+            updater,
+            quality,
+            mut score,
+            mut cues,
+            mut stats,
+            mut collisions,
+            grid,
+            contacts,
+            pos,
+            coll_box,
+            colliders,
+            rotations,
+            controlled_storage,
+            factions,
+            hurtboxes,
+            solid,
+            mut projectiles,
+        ) = data;
+
+        // an entity's Collider where it is, the box itself without one
+        let shape = |entity: Entity, coll_box: &CollisionBox| {
+            let angle = rotations.get(entity).map_or(0.0, |rotation| rotation.angle);
+            hitbox::place(colliders.get(entity), coll_box, angle)
+        };
+
+        stats.pairs = 0;
+        // First find the player collision boxes, we don't assume a single player
+        for (player, player_box, _) in (&entities, &coll_box, &controlled_storage).join() {
+            // Now check the entities near it with a collision box that aren't
+            // player controlled. The SpatialGrid narrows it down to those
+            // sharing a cell with the player, which is all that could touch,
+            // along with any solid it was pushed out of on the way.
+            let mut near = grid.near(&hitbox::grown(player_box, hitbox::CONTACT));
+            near.extend(contacts.against(player));
+            near.sort_by_key(|entity| entity.id());
+            near.dedup();
+            for other in near {
+                let coll_box = match coll_box.get(other) {
+                    Some(coll_box) if pos.contains(other) => coll_box,
+                    _ => continue,
+                };
+                if controlled_storage.contains(other) {
+                    continue;
+                }
+                // ships on the same side don't collide with each other
+                if !faction::collides(factions.get(player), factions.get(other)) {
+                    continue;
+                }
+                // The MovementSystem pushes the player's ship back out of
+                // anything solid by its box, so the most it ever does is meet
+                // one or fly into it. Otherwise the boxes overlapping is
+                // enough unless either has a shape of its own.
+                let touching = if solid.contains(other) {
+                    contacts.against(player).any(|solid| solid == other)
+                        || hitbox::touches(player_box, coll_box)
+                } else {
+                    hitbox::overlaps(player_box, coll_box)
+                        && ((!colliders.contains(player) && !colliders.contains(other))
+                            || hitbox::shapes_overlap(
+                                &shape(player, player_box),
+                                &shape(other, coll_box),
+                            ))
+                };
+                if touching {
+                    stats.pairs += 1;
+                    collisions.publish(CollisionEvent {
+                        a: player,
+                        b: other,
+                    });
+                }
+            }
+
+            // A second pass for near misses. Enemy shots that pass just
+            // outside the player's hurtbox count as grazes, and are worth
+            // points and energy. Actual hits are dealt with by the
+            // ProjectileSystem.
+            let hurtbox = match (pos.get(player), hurtboxes.get(player)) {
+                (Some(player_pos), Some(hurtbox)) => hurtbox.0.at(player_pos.position),
+                _ => continue,
+            };
+            for (pos, projectile) in (&pos, &mut projectiles).join() {
+                if !projectile.active
+                    || projectile.grazed
+                    || !faction::hostile(
+                        factions.get(player),
+                        projectile.owner.and_then(|owner| factions.get(owner)),
+                    )
+                    || !graze::grazes(&hurtbox, pos.position)
+                {
+                    continue;
+                }
+                projectile.grazed = true;
+                score.points += graze::GRAZE_POINTS;
+                score.add_energy(graze::GRAZE_ENERGY);
+                graze::spawn_sparks(&entities, &updater, pos.position, quality.spark_count);
+                cues.grazes.push(Cue {
+                    at: pos.position,
+                    velocity: projectile.velocity,
+                });
+            }
+        }
+    }
+}
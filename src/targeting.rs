@@ -0,0 +1,250 @@
+use crate::faction::{self, Faction};
+use crate::floating_text::FloatingText;
+use crate::interpolation::Drawn;
+use crate::stealth::{self, Cloaked, Revealed};
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::weapons::Projectile;
+use crate::{CollisionBox, ControllableTag, Position, Rotation};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::saveload::ConvertSaveload;
+use specs::*;
+use specs_derive::*;
+
+// how far away something can be locked on to, and how far it can then drift
+// before the lock is lost
+const LOCK_RANGE: f32 = 600.0;
+// half angle of the cone in front of the ship that targets are picked from
+const LOCK_CONE: f32 = std::f32::consts::FRAC_PI_4;
+
+// The entity the player currently has locked on. MainState asks for the next
+// target by setting cycle_requested when Tab is pressed, the lock on system then
+// does the actual search as it has access to the world.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct LockOn {
+    pub(crate) target: Option<Entity>,
+    pub(crate) cycle_requested: bool,
+}
+
+// Steers an entity's projectile velocity toward a target. Aim assist attaches
+// this to shots fired while locked on; homing weapons can attach it with a much
+// higher turn rate.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub(crate) struct Homing {
+    // None if the target was gone when the world was last quickloaded
+    pub(crate) target: Option<Entity>,
+    // radians per second
+    pub(crate) turn_rate: f32,
+}
+
+// Homing as it's quicksaved, with the target by its marker
+#[derive(Serialize, Deserialize)]
+pub(crate) struct HomingData<M> {
+    target: Option<M>,
+    turn_rate: f32,
+}
+
+impl<M: Serialize + DeserializeOwned> ConvertSaveload<M> for Homing {
+    type Data = HomingData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        Ok(HomingData {
+            target: self.target.and_then(ids),
+            turn_rate: self.turn_rate,
+        })
+    }
+
+    fn convert_from<F>(data: Self::Data, ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        Ok(Homing {
+            target: data.target.and_then(ids),
+            turn_rate: data.turn_rate,
+        })
+    }
+}
+
+pub(crate) struct LockOnSystem;
+
+impl<'a> System<'a> for LockOnSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, LockOn>,
+        Read<'a, LazyUpdate>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Rotation>,
+        ReadStorage<'a, ControllableTag>,
+        ReadStorage<'a, Cloaked>,
+        ReadStorage<'a, Revealed>,
+        ReadStorage<'a, Faction>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut lock,
+            updater,
+            coll_box,
+            rotation,
+            controlled,
+            cloaked,
+            revealed,
+            factions,
+        ) = data;
+
+        // we don't assume a single player elsewhere, but there is only one lock
+        // so the first player ship is the one doing the targeting
+        let (origin, heading, side) =
+            match (&entities, &coll_box, &rotation, &controlled).join().next() {
+                Some((player, player_box, rotation, _)) => (
+                    player_box.center(),
+                    rotation.heading(),
+                    factions.get(player).cloned(),
+                ),
+                None => {
+                    lock.target = None;
+                    return;
+                }
+            };
+
+        // drop the lock if the target has been removed, has got too far away or
+        // has slipped under a cloak
+        if let Some(target) = lock.target {
+            let in_range = coll_box
+                .get(target)
+                .map_or(false, |b| (b.center() - origin).norm() <= LOCK_RANGE);
+            if !in_range || stealth::is_hidden(cloaked.get(target), revealed.get(target)) {
+                lock.target = None;
+            }
+        }
+
+        if !lock.cycle_requested {
+            return;
+        }
+        lock.cycle_requested = false;
+
+        let mut candidates: Vec<(f32, Entity)> = (
+            &entities,
+            &coll_box,
+            !&controlled,
+            cloaked.maybe(),
+            revealed.maybe(),
+        )
+            .join()
+            .filter(|(entity, _, _, cloak, reveal)| {
+                !stealth::is_hidden(*cloak, *reveal)
+                    && faction::hostile(side.as_ref(), factions.get(*entity))
+            })
+            .filter_map(|(entity, coll_box, _, _, _)| {
+                let to_target = coll_box.center() - origin;
+                let distance = to_target.norm();
+                if distance > 0.0
+                    && distance <= LOCK_RANGE
+                    && heading.angle(&to_target) <= LOCK_CONE
+                {
+                    Some((distance, entity))
+                } else {
+                    None
+                }
+            })
+            .collect();
+        candidates.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+        // move on to the next nearest target, wrapping back to the nearest
+        let current = lock
+            .target
+            .and_then(|target| candidates.iter().position(|(_, e)| *e == target));
+        let next = match current {
+            Some(i) => candidates.get(i + 1).or_else(|| candidates.first()),
+            None => candidates.first(),
+        };
+        lock.target = next.map(|(_, entity)| *entity);
+
+        if let Some(target) = lock.target.and_then(|target| coll_box.get(target)) {
+            FloatingText::spawn(
+                &entities,
+                &updater,
+                target.center() - nalgebra::Vector2::new(0.0, target.height / 2.0),
+                "LOCKED",
+                graphics::Color::new(1.0, 0.2, 0.2, 1.0),
+            );
+        }
+    }
+}
+
+// Turns homing projectiles toward the middle of their target, limited by their
+// turn rate so they curve rather than snap
+pub(crate) struct HomingSystem;
+
+impl<'a> System<'a> for HomingSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        ReadStorage<'a, TimeMultiplier>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Homing>,
+        WriteStorage<'a, Projectile>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, delta, time, multipliers, pos, coll_box, homing, mut projectiles) = data;
+
+        for (entity, pos, homing, projectile) in (&entities, &pos, &homing, &mut projectiles).join()
+        {
+            if !projectile.active {
+                continue;
+            }
+            let dt = time.scaled(delta.seconds, multipliers.get(entity));
+            // the target may have been destroyed since the shot was fired, in
+            // which case the projectile just carries on straight
+            let target = match homing.target.and_then(|target| coll_box.get(target)) {
+                Some(target) => target.center(),
+                None => continue,
+            };
+
+            let desired = target - pos.position;
+            let velocity = projectile.velocity;
+            let cross = velocity.x * desired.y - velocity.y * desired.x;
+            let offset = cross.atan2(velocity.dot(&desired));
+            let max_turn = homing.turn_rate * dt;
+            let turn = offset.max(-max_turn).min(max_turn);
+            projectile.velocity = nalgebra::Rotation2::new(turn) * velocity;
+        }
+    }
+}
+
+// Draws a bracket around whatever is locked on
+pub(crate) fn draw_lock_indicator(ctx: &mut Context, world: &World) -> GameResult<()> {
+    let target = match world.read_resource::<LockOn>().target {
+        Some(target) => target,
+        None => return Ok(()),
+    };
+    let coll_box = world.read_storage::<CollisionBox>();
+    if let Some(b) = coll_box.get(target) {
+        let b = Drawn::new(world).coll_box(target, b);
+        let indicator = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::stroke(2.0),
+            graphics::Rect::new(
+                b.origin.x - 4.0,
+                b.origin.y - 4.0,
+                b.width + 8.0,
+                b.height + 8.0,
+            ),
+            graphics::Color::new(1.0, 0.2, 0.2, 1.0),
+        )?;
+        graphics::draw(ctx, &indicator, graphics::DrawParam::default())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,133 @@
+use crate::spawn_queue::SpawnQueue;
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use specs::*;
+use specs_derive::*;
+use std::collections::VecDeque;
+
+// Something a task does to the world and the entity running it
+type Action = Box<dyn FnOnce(&mut World, Entity) + Send + Sync>;
+
+enum Step {
+    // seconds of the entity's game time
+    Wait(f32),
+    Do(Action),
+}
+
+// A sequence of waits and actions, written out in the order they happen
+// rather than as timers spread across systems, e.g.
+//
+//     let fuse = Task::new()
+//         .repeat(3, |task| task.then(hide).wait(0.1).then(show).wait(0.1))
+//         .wait(0.5)
+//         .then(explode);
+//     world.write_storage::<Tasks>().insert(mine, Tasks::new(fuse));
+//
+// Waits count the entity's own game time, so they stop with the game and
+// follow its TimeMultiplier. Actions go through the SpawnQueue, so they run
+// once the update's systems are done and get the whole world to work with.
+// A task stops when its entity is deleted.
+#[derive(Default)]
+pub struct Task {
+    steps: VecDeque<Step>,
+    // how long the task has been waiting on its first step
+    waited: f32,
+}
+
+impl Task {
+    pub fn new() -> Self {
+        Task::default()
+    }
+
+    pub fn wait(mut self, seconds: f32) -> Self {
+        self.steps.push_back(Step::Wait(seconds));
+        self
+    }
+
+    pub fn then<F: FnOnce(&mut World, Entity) + Send + Sync + 'static>(
+        mut self,
+        action: F,
+    ) -> Self {
+        self.steps.push_back(Step::Do(Box::new(action)));
+        self
+    }
+
+    // the steps the closure adds, that many times over
+    pub fn repeat<F: Fn(Task) -> Task>(self, times: usize, steps: F) -> Self {
+        (0..times).fold(self, |task, _| steps(task))
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.steps.is_empty()
+    }
+
+    // Works through as many steps as the time allows, handing the actions
+    // reached to the queue in order
+    fn advance(&mut self, entity: Entity, mut dt: f32, queue: &mut SpawnQueue) {
+        while let Some(step) = self.steps.pop_front() {
+            match step {
+                Step::Wait(seconds) => {
+                    let left = seconds - self.waited;
+                    if dt < left {
+                        self.waited += dt;
+                        self.steps.push_front(Step::Wait(seconds));
+                        return;
+                    }
+                    // the time left over goes on to the next step
+                    dt -= left.max(0.0);
+                    self.waited = 0.0;
+                }
+                Step::Do(action) => queue.spawn(move |world| {
+                    if world.is_alive(entity) {
+                        action(world, entity);
+                    }
+                }),
+            }
+        }
+    }
+}
+
+// The tasks an entity is running, all at the same time
+#[derive(Component, Default)]
+#[storage(DenseVecStorage)]
+pub struct Tasks {
+    tasks: Vec<Task>,
+}
+
+impl Tasks {
+    pub fn new(task: Task) -> Self {
+        Tasks { tasks: vec![task] }
+    }
+
+    pub fn start(&mut self, task: Task) {
+        self.tasks.push(task);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+}
+
+pub(crate) struct TaskSystem;
+
+impl<'a> System<'a> for TaskSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Write<'a, SpawnQueue>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Tasks>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, delta, time, mut queue, multipliers, mut tasks) = data;
+
+        for (entity, tasks) in (&entities, &mut tasks).join() {
+            let dt = time.scaled(delta.seconds, multipliers.get(entity));
+            for task in &mut tasks.tasks {
+                task.advance(entity, dt, &mut queue);
+            }
+            tasks.tasks.retain(|task| !task.is_done());
+        }
+    }
+}
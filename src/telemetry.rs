@@ -0,0 +1,381 @@
+use crate::events::{self, Subscribe, TrackedReader};
+use crate::faction::Faction;
+use crate::health::DeathEvent;
+use crate::platform;
+use crate::rng::GameRng;
+use crate::settings::Settings;
+use crate::storage::Storage;
+use crate::time::GameClock;
+use crate::waves::WaveDirector;
+use crate::{ControllableTag, Position};
+use ggez::GameResult;
+use specs::*;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Write as _;
+
+// where the session logs are kept in the player's storage
+const TELEMETRY_DIR: &str = "/telemetry";
+// seconds of game time between records of where the player is
+const POSITION_INTERVAL: f64 = 1.0;
+
+// One line of a session log, a flat JSON object with the kind of event and
+// the game time it happened at, e.g.
+//
+//     {"event":"death","t":83.5,"victim":"player","cause":"Red ship","x":310,"y":95}
+struct Record {
+    line: String,
+}
+
+impl Record {
+    fn new(event: &str, t: f64) -> Self {
+        Record {
+            line: format!("{{\"event\":\"{}\",\"t\":{:.2}", event, t),
+        }
+    }
+
+    fn text(mut self, key: &str, value: &str) -> Self {
+        let mut escaped = String::with_capacity(value.len());
+        for c in value.chars() {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                c if c.is_control() => (),
+                c => escaped.push(c),
+            }
+        }
+        self.line += &format!(",\"{}\":\"{}\"", key, escaped);
+        self
+    }
+
+    fn number(mut self, key: &str, value: f64) -> Self {
+        // JSON has no NaN or infinity
+        let value = if value.is_finite() { value } else { 0.0 };
+        self.line += &format!(",\"{}\":{}", key, value);
+        self
+    }
+
+    fn finish(mut self) -> String {
+        self.line.push('}');
+        self.line
+    }
+}
+
+// Gameplay events kept for balancing, when the player has opted in with the
+// telemetry setting. Each game played is a session with its own log, where
+// the events are appended as they happen.
+#[derive(Debug, Default)]
+pub(crate) struct Telemetry {
+    // turned off as well while a replay plays, so only real play is logged
+    pub(crate) enabled: bool,
+    mode: &'static str,
+    // the session's log, named when its first event is recorded
+    path: Option<String>,
+    // the last wave seen, recorded or not
+    wave: u32,
+    // game time the player's position is next recorded at
+    next_position: f64,
+    // shots fired since the weapon usage was last recorded, by who fired them
+    shots: BTreeMap<String, u32>,
+    // lines waiting to be appended to the log
+    pending: Vec<String>,
+}
+
+impl Telemetry {
+    pub(crate) fn new(enabled: bool, mode: &'static str) -> Self {
+        Telemetry {
+            enabled,
+            mode,
+            ..Telemetry::default()
+        }
+    }
+
+    // counts a shot fired by the player or an AI ship
+    pub(crate) fn shot(&mut self, player: bool, faction: Option<&Faction>) {
+        if self.enabled {
+            *self.shots.entry(ship_name(player, faction)).or_insert(0) += 1;
+        }
+    }
+
+    fn record(&mut self, record: Record) {
+        self.pending.push(record.finish());
+    }
+
+    fn record_shots(&mut self, t: f64) {
+        let shots = std::mem::take(&mut self.shots);
+        for (by, count) in shots {
+            let record = Record::new("weapon_usage", t)
+                .text("by", &by)
+                .number("wave", f64::from(self.wave))
+                .number("shots", f64::from(count));
+            self.record(record);
+        }
+    }
+
+    // Records what is left of the session when the game is left or closed
+    pub(crate) fn end(&mut self, clock: &GameClock) {
+        if !self.enabled || self.path.is_none() {
+            return;
+        }
+        self.record_shots(clock.elapsed);
+        let record = Record::new("session_end", clock.elapsed)
+            .number("wave", f64::from(self.wave))
+            .number("played", clock.unscaled);
+        self.record(record);
+    }
+
+    // Appends the events recorded since the last flush to the session's log
+    pub(crate) fn flush(&mut self, storage: &dyn Storage) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+        let mut text = self.pending.join("\n");
+        text.push('\n');
+        self.pending.clear();
+        storage
+            .append(path, text.as_bytes())
+            .unwrap_or_else(|err| println!("telemetry error {:?}", err));
+    }
+}
+
+// who or what an entity was, for the logs
+fn describe(
+    entity: Entity,
+    entities: &Entities,
+    controlled: &ReadStorage<ControllableTag>,
+    factions: &ReadStorage<Faction>,
+) -> String {
+    if !entities.is_alive(entity) {
+        return "destroyed ship".to_owned();
+    }
+    ship_name(controlled.get(entity).is_some(), factions.get(entity))
+}
+
+// the name a ship goes by in the logs
+fn ship_name(player: bool, faction: Option<&Faction>) -> String {
+    match (player, faction) {
+        (true, _) => "player".to_owned(),
+        (false, Some(faction)) => format!("{:?} ship", faction),
+        (false, None) => "unknown".to_owned(),
+    }
+}
+
+// Records waves being reached, ships being destroyed and what destroyed them,
+// and where the player is every second. Weapon usage is counted by the FireSystem and recorded per wave.
+pub(crate) struct TelemetrySystem {
+    deaths: TrackedReader<DeathEvent>,
+}
+
+impl TelemetrySystem {
+    pub(crate) fn new(world: &World) -> Self {
+        TelemetrySystem {
+            deaths: events::subscribe::<DeathEvent>(world, "telemetry"),
+        }
+    }
+}
+
+impl<'a> System<'a> for TelemetrySystem {
+    type SystemData = (
+        Write<'a, Telemetry>,
+        Read<'a, WaveDirector>,
+        Read<'a, GameClock>,
+        Read<'a, GameRng>,
+        Read<'a, Settings>,
+        Subscribe<'a, DeathEvent>,
+        Entities<'a>,
+        ReadStorage<'a, ControllableTag>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, Position>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut telemetry,
+            director,
+            clock,
+            rng,
+            settings,
+            deaths,
+            entities,
+            controlled,
+            factions,
+            pos,
+        ) = data;
+        let t = clock.elapsed;
+
+        // every event is read so they don't pile up, logged or not
+        let deaths: Vec<DeathEvent> = deaths.read(&mut self.deaths).cloned().collect();
+        if !telemetry.enabled {
+            telemetry.wave = director.wave;
+            return;
+        }
+
+        if telemetry.path.is_none() {
+            telemetry.path = Some(format!("{}/{}.jsonl", TELEMETRY_DIR, platform::unix_time()));
+            let record = Record::new("session_start", t)
+                .text("mode", telemetry.mode)
+                .text("difficulty", &format!("{:?}", settings.difficulty))
+                .text("seed", &rng.seed().to_string())
+                .number("wave", f64::from(director.wave));
+            telemetry.record(record);
+        }
+
+        if director.wave != telemetry.wave {
+            telemetry.record_shots(t);
+            telemetry.wave = director.wave;
+            let record = Record::new("wave_reached", t).number("wave", f64::from(director.wave));
+            telemetry.record(record);
+        }
+
+        // where the player spends their time, for the heatmap
+        if t >= telemetry.next_position {
+            telemetry.next_position = t + POSITION_INTERVAL;
+            if let Some((pos, _)) = (&pos, &controlled).join().next() {
+                let record = Record::new("position", t)
+                    .number("x", f64::from(pos.position.x))
+                    .number("y", f64::from(pos.position.y));
+                telemetry.record(record);
+            }
+        }
+
+        for death in deaths {
+            let victim = ship_name(death.player, death.faction.as_ref());
+            let cause = match death.killer {
+                Some(killer) => describe(killer, &entities, &controlled, &factions),
+                // nothing was credited, e.g. the storm
+                None => "hazard".to_owned(),
+            };
+            let mut record = Record::new("death", t)
+                .text("victim", &victim)
+                .text("cause", &cause)
+                .number("wave", f64::from(telemetry.wave));
+            if let Some(at) = death.at {
+                record = record
+                    .number("x", f64::from(at.x))
+                    .number("y", f64::from(at.y));
+            }
+            telemetry.record(record);
+        }
+    }
+}
+
+// One line of a log read back, by field
+pub(crate) type Event = HashMap<String, ron::Value>;
+
+// Every event of every session logged, one line each, as read back from the
+// logs. The lines are flat JSON objects, which happen to be RON maps as well.
+pub(crate) fn read_sessions(storage: &dyn Storage) -> Vec<(String, Vec<Event>)> {
+    let mut sessions = Vec::new();
+    for path in storage.list(TELEMETRY_DIR) {
+        let text = match storage.read(&path) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(err) => {
+                println!("telemetry error {:?}", err);
+                continue;
+            }
+        };
+        let events = text
+            .lines()
+            .filter_map(|line| ron::de::from_str(line).ok())
+            .collect();
+        sessions.push((path, events));
+    }
+    sessions
+}
+
+pub(crate) fn text(event: &Event, key: &str) -> String {
+    match event.get(key) {
+        Some(ron::Value::String(text)) => text.clone(),
+        _ => String::new(),
+    }
+}
+
+pub(crate) fn number(event: &Event, key: &str) -> f64 {
+    match event.get(key) {
+        Some(ron::Value::Number(number)) => number.get(),
+        _ => 0.0,
+    }
+}
+
+// `--telemetry-summary` prints what the logs add up to: how far sessions got,
+// what the player died to and who fired how much
+pub(crate) fn print_summary(storage: &dyn Storage) {
+    let sessions = read_sessions(storage);
+    if sessions.is_empty() {
+        println!("No telemetry logged, turn it on with the telemetry setting or --telemetry");
+        return;
+    }
+
+    let mut played = 0.0;
+    let mut waves = Vec::new();
+    let mut player_deaths: BTreeMap<String, u32> = BTreeMap::new();
+    let mut kills: BTreeMap<String, u32> = BTreeMap::new();
+    let mut shots: BTreeMap<String, u32> = BTreeMap::new();
+    for (_, events) in &sessions {
+        let mut wave = 0.0f64;
+        for event in events {
+            wave = wave.max(number(event, "wave"));
+            match text(event, "event").as_str() {
+                "session_end" => played += number(event, "played"),
+                "death" if text(event, "victim") == "player" => {
+                    *player_deaths.entry(text(event, "cause")).or_insert(0) += 1;
+                }
+                "death" => *kills.entry(text(event, "cause")).or_insert(0) += 1,
+                "weapon_usage" => {
+                    *shots.entry(text(event, "by")).or_insert(0) += number(event, "shots") as u32;
+                }
+                _ => (),
+            }
+        }
+        waves.push(wave as u32);
+    }
+
+    println!("Sessions: {}", sessions.len());
+    println!("Time played: {:.0} minutes", played / 60.0);
+    println!(
+        "Waves reached: best {}, average {:.1}",
+        waves.iter().max().unwrap_or(&0),
+        waves.iter().sum::<u32>() as f32 / waves.len() as f32
+    );
+    println!("Player deaths by cause:");
+    for (cause, count) in &player_deaths {
+        println!("  {:<16} {}", cause, count);
+    }
+    println!("Ships destroyed by:");
+    for (cause, count) in &kills {
+        println!("  {:<16} {}", cause, count);
+    }
+    println!("Shots fired by:");
+    for (by, count) in &shots {
+        println!("  {:<16} {}", by, count);
+    }
+}
+
+// `--telemetry-export <file>` writes every session's events into one JSONL
+// file, each line tagged with the session it came from, for spreadsheets and
+// other tools
+pub(crate) fn export(storage: &dyn Storage, to: &str) -> GameResult<()> {
+    let mut file = fs::File::create(to)?;
+    let mut lines = 0;
+    for path in storage.list(TELEMETRY_DIR) {
+        let session = path
+            .trim_start_matches(TELEMETRY_DIR)
+            .trim_start_matches('/')
+            .trim_end_matches(".jsonl")
+            .to_owned();
+        let text = storage.read(&path)?;
+        for line in String::from_utf8_lossy(&text).lines() {
+            if let Some(fields) = line.strip_prefix('{') {
+                writeln!(file, "{{\"session\":\"{}\",{}", session, fields)?;
+                lines += 1;
+            }
+        }
+    }
+    println!("Exported {} events to {}", lines, to);
+    Ok(())
+}
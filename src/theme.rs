@@ -0,0 +1,219 @@
+use crate::data;
+use ggez::nalgebra;
+use ggez::{filesystem, graphics, Context, GameResult};
+use serde::Deserialize;
+
+pub(crate) const PATH: &str = "/theme.ron";
+
+// red, green, blue and alpha, each from 0 to 1
+type Rgba = (f32, f32, f32, f32);
+
+fn color((r, g, b, a): Rgba) -> graphics::Color {
+    graphics::Color::new(r, g, b, a)
+}
+
+// An image for panels drawn in nine pieces, so it stretches to any size
+// without its corners and edges stretching with it
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct NineSlice {
+    // under resources
+    image: String,
+    // how far in from the image's edges its corners go, in pixels
+    border: f32,
+}
+
+// The theme as /theme.ron gives it. Anything left out keeps the default.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub(crate) struct ThemeFile {
+    text: Rgba,
+    dim: Rgba,
+    message: Rgba,
+    accent: Rgba,
+    panel: Rgba,
+    border: Rgba,
+    shade: Rgba,
+    // a TrueType font under resources, or ggez's own
+    font: Option<String>,
+    font_size: f32,
+    // panels are a plain box in the panel and border colours without one
+    nine_slice: Option<NineSlice>,
+    padding: f32,
+}
+
+impl Default for ThemeFile {
+    fn default() -> Self {
+        ThemeFile {
+            text: (1.0, 1.0, 1.0, 1.0),
+            dim: (0.6, 0.6, 0.6, 1.0),
+            message: (1.0, 1.0, 0.6, 1.0),
+            accent: (0.3, 0.8, 1.0, 1.0),
+            panel: (0.1, 0.1, 0.2, 0.9),
+            border: (1.0, 1.0, 1.0, 1.0),
+            shade: (0.0, 0.0, 0.0, 0.6),
+            font: None,
+            font_size: 16.0,
+            nine_slice: None,
+            padding: 8.0,
+        }
+    }
+}
+
+// How the menus and HUD look, from /theme.ron, so they can be restyled or
+// modded without touching the code. Without the file they look as they
+// always have. Every menu and HUD element draws its text, panels and the
+// shade over the game through here.
+#[derive(Clone)]
+pub(crate) struct UiTheme {
+    pub(crate) text: graphics::Color,
+    // whatever isn't picked in a menu, and the help lines
+    pub(crate) dim: graphics::Color,
+    // a menu's messages and questions
+    pub(crate) message: graphics::Color,
+    // bars and highlights
+    pub(crate) accent: graphics::Color,
+    panel: graphics::Color,
+    border: graphics::Color,
+    // laid over the screen under a menu
+    shade: graphics::Color,
+    font: graphics::Font,
+    font_size: f32,
+    nine_slice: Option<(graphics::Image, f32)>,
+    // between a panel's edge and what's on it
+    pub(crate) padding: f32,
+}
+
+impl Default for UiTheme {
+    fn default() -> Self {
+        UiTheme::new(&ThemeFile::default(), graphics::Font::default(), None)
+    }
+}
+
+impl UiTheme {
+    fn new(
+        file: &ThemeFile,
+        font: graphics::Font,
+        nine_slice: Option<(graphics::Image, f32)>,
+    ) -> Self {
+        UiTheme {
+            text: color(file.text),
+            dim: color(file.dim),
+            message: color(file.message),
+            accent: color(file.accent),
+            panel: color(file.panel),
+            border: color(file.border),
+            shade: color(file.shade),
+            font,
+            font_size: file.font_size,
+            nine_slice,
+            padding: file.padding,
+        }
+    }
+
+    // The theme in resources, or the default look if there isn't one. One
+    // that won't load is reported and the default used instead.
+    pub(crate) fn load(ctx: &mut Context) -> Self {
+        if !filesystem::exists(ctx, PATH) {
+            return UiTheme::default();
+        }
+        UiTheme::read(ctx).unwrap_or_else(|err| {
+            println!("theme error {:?}", err);
+            UiTheme::default()
+        })
+    }
+
+    pub(crate) fn read(ctx: &mut Context) -> GameResult<Self> {
+        let file: ThemeFile = data::load(ctx, PATH)?;
+        let font = match &file.font {
+            Some(path) => graphics::Font::new(ctx, path)?,
+            None => graphics::Font::default(),
+        };
+        let nine_slice = match &file.nine_slice {
+            Some(slice) => Some((graphics::Image::new(ctx, &slice.image)?, slice.border)),
+            None => None,
+        };
+        Ok(UiTheme::new(&file, font, nine_slice))
+    }
+
+    // text in the theme's font
+    pub(crate) fn text<F: Into<graphics::TextFragment>>(&self, text: F) -> graphics::Text {
+        let mut text = graphics::Text::new(text);
+        text.set_font(self.font, graphics::Scale::uniform(self.font_size));
+        text
+    }
+
+    // the shade over everything drawn so far, under a menu
+    pub(crate) fn draw_shade(&self, ctx: &mut Context) -> GameResult<()> {
+        let shade = graphics::Mesh::new_rectangle(
+            ctx,
+            graphics::DrawMode::fill(),
+            graphics::screen_coordinates(ctx),
+            self.shade,
+        )?;
+        graphics::draw(ctx, &shade, graphics::DrawParam::default())
+    }
+
+    // a panel filling the rectangle, on the screen, at some fraction of the
+    // theme's opacity
+    pub(crate) fn draw_panel(
+        &self,
+        ctx: &mut Context,
+        rect: graphics::Rect,
+        alpha: f32,
+    ) -> GameResult<()> {
+        let (image, border) = match &self.nine_slice {
+            Some((image, border)) => (image, *border),
+            None => {
+                let fade = |color: graphics::Color| graphics::Color {
+                    a: color.a * alpha,
+                    ..color
+                };
+                let panel = graphics::MeshBuilder::new()
+                    .rectangle(graphics::DrawMode::fill(), rect, fade(self.panel))
+                    .rectangle(graphics::DrawMode::stroke(1.0), rect, fade(self.border))
+                    .build(ctx)?;
+                return graphics::draw(ctx, &panel, graphics::DrawParam::default());
+            }
+        };
+
+        // the corners are drawn as they are, the edges stretched one way
+        // and the middle both ways
+        let (width, height) = (f32::from(image.width()), f32::from(image.height()));
+        let border = border.min(width / 2.0).min(height / 2.0);
+        let slices = |size: f32, start: f32, length: f32| {
+            [
+                (0.0, border, start, border),
+                (
+                    border,
+                    size - border * 2.0,
+                    start + border,
+                    length - border * 2.0,
+                ),
+                (size - border, border, start + length - border, border),
+            ]
+        };
+        let tint = graphics::Color::new(1.0, 1.0, 1.0, alpha);
+        for &(src_x, src_w, x, w) in slices(width, rect.x, rect.w).iter() {
+            for &(src_y, src_h, y, h) in slices(height, rect.y, rect.h).iter() {
+                if src_w <= 0.0 || src_h <= 0.0 || w <= 0.0 || h <= 0.0 {
+                    continue;
+                }
+                graphics::draw(
+                    ctx,
+                    image,
+                    graphics::DrawParam::default()
+                        .src(graphics::Rect::new(
+                            src_x / width,
+                            src_y / height,
+                            src_w / width,
+                            src_h / height,
+                        ))
+                        .dest(nalgebra::Point2::new(x, y))
+                        .scale(nalgebra::Vector2::new(w / src_w, h / src_h))
+                        .color(tint),
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,101 @@
+use crate::DESIRED_FPS;
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+
+// The game's clocks, moved on once per update. Gameplay goes by the scaled
+// time, which slows with the TimeScale and stops while the game is paused or
+// frozen by a hit stop. Anything that should carry on regardless, like UI
+// animation, goes by the unscaled time.
+#[derive(Debug, Default)]
+pub struct GameClock {
+    // seconds of game time
+    pub elapsed: f64,
+    // real seconds, counted from the first update
+    pub unscaled: f64,
+    // updates run, paused or not
+    pub ticks: u64,
+    pub paused: bool,
+}
+
+impl GameClock {
+    pub(crate) fn advance(&mut self, delta: &DeltaTime, time: &TimeScale, paused: bool) {
+        let dt = f64::from(delta.seconds);
+        self.paused = paused;
+        self.ticks += 1;
+        self.unscaled += dt;
+        if !paused {
+            self.elapsed += dt * f64::from(time.scale);
+        }
+    }
+}
+
+// Seconds of real time one update of the world covers, before any scaling.
+// The game updates at a fixed rate so every run and replay steps the same
+// way, which makes this one DESIRED_FPSth of a second. Systems read it rather
+// than assuming the rate.
+#[derive(Debug)]
+pub struct DeltaTime {
+    pub seconds: f32,
+}
+
+impl Default for DeltaTime {
+    fn default() -> Self {
+        DeltaTime {
+            seconds: 1.0 / DESIRED_FPS as f32,
+        }
+    }
+}
+
+// How fast game time passes compared to real time. Systems that should slow
+// down with the game scale the DeltaTime by this rather than using it as is.
+#[derive(Debug)]
+pub struct TimeScale {
+    pub scale: f32,
+    // the speed outside bullet time, 1 unless the sandbox's slider changes it
+    pub base: f32,
+}
+
+impl Default for TimeScale {
+    fn default() -> Self {
+        TimeScale {
+            scale: 1.0,
+            base: 1.0,
+        }
+    }
+}
+
+impl TimeScale {
+    // seconds of game time a span of real time, usually the DeltaTime, is
+    // for an entity, given its multiplier if it has one. Anything that moves,
+    // animates, thinks or counts down per entity should use this so slowing or
+    // speeding up an entity affects all of it the same.
+    pub fn scaled(&self, dt: f32, multiplier: Option<&TimeMultiplier>) -> f32 {
+        match multiplier {
+            Some(multiplier) if !multiplier.global => dt * multiplier.factor,
+            Some(multiplier) => dt * self.scale * multiplier.factor,
+            None => dt * self.scale,
+        }
+    }
+}
+
+// Runs an entity faster or slower than the rest of the game, for things like
+// stasis traps (a factor near 0) and haste pickups (above 1)
+#[derive(Component, Clone, Copy, Debug, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub struct TimeMultiplier {
+    pub factor: f32,
+    // whether the global TimeScale applies on top of the factor
+    pub global: bool,
+}
+
+impl TimeMultiplier {
+    // keeps an entity running at full speed however time is scaled, like the
+    // player during bullet time
+    pub fn unscaled() -> Self {
+        TimeMultiplier {
+            factor: 1.0,
+            global: false,
+        }
+    }
+}
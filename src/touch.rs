@@ -0,0 +1,188 @@
+use crate::controls::{Action, Aim};
+use crate::Direction;
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+
+// how far the knob can be dragged from where the stick was put down
+const STICK_RADIUS: f32 = 60.0;
+// fraction of the radius the knob has to move before it counts as a direction
+const STICK_DEAD_ZONE: f32 = 0.3;
+const BUTTON_RADIUS: f32 = 35.0;
+const BUTTON_MARGIN: f32 = 20.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TouchButton {
+    // held down like the fire key
+    Fire,
+    // a one-shot command, the same as a bound key or gamepad button
+    Press(Action),
+}
+
+// the buttons down the right hand side of the screen, from the bottom up
+const BUTTONS: [(&str, TouchButton); 3] = [
+    ("FIRE", TouchButton::Fire),
+    ("HIT", TouchButton::Press(Action::Melee)),
+    ("SLOW", TouchButton::Press(Action::BulletTime)),
+];
+
+#[derive(Debug)]
+struct Stick {
+    touch: u64,
+    origin: nalgebra::Point2<f32>,
+    knob: nalgebra::Point2<f32>,
+}
+
+// On-screen controls for touch screens. Touching anywhere on the left half of
+// the screen puts a virtual joystick down there, and the buttons sit in the
+// bottom right corner. They feed the same Direction and Aim as the keyboard,
+// and the buttons trigger the same Actions as key bindings.
+//
+// Every touch has an id so more than one finger can be down at once. ggez only
+// passes on mouse events, which touch screens emulate for the first finger, so
+// for now that finger is all there is.
+#[derive(Debug, Default)]
+pub(crate) struct TouchControls {
+    stick: Option<Stick>,
+    // which touches are holding which buttons down
+    held: Vec<(u64, TouchButton)>,
+}
+
+fn button_center(view: graphics::Rect, index: usize) -> nalgebra::Point2<f32> {
+    let step = BUTTON_RADIUS * 2.0 + BUTTON_MARGIN;
+    nalgebra::Point2::new(
+        view.x + view.w - BUTTON_MARGIN - BUTTON_RADIUS,
+        view.y + view.h - BUTTON_MARGIN - BUTTON_RADIUS - step * index as f32,
+    )
+}
+
+impl TouchControls {
+    // A finger going down, in screen co-ordinates. Returns whether it landed
+    // on one of the controls, and the command to carry out if it pressed one.
+    pub(crate) fn touch_down(
+        &mut self,
+        view: graphics::Rect,
+        touch: u64,
+        point: nalgebra::Point2<f32>,
+    ) -> (bool, Option<Action>) {
+        for (index, (_, button)) in BUTTONS.iter().enumerate() {
+            if (point - button_center(view, index)).norm() <= BUTTON_RADIUS {
+                return match button {
+                    TouchButton::Fire => {
+                        self.held.push((touch, *button));
+                        (true, None)
+                    }
+                    TouchButton::Press(action) => (true, Some(*action)),
+                };
+            }
+        }
+
+        if self.stick.is_none() && point.x < view.x + view.w / 2.0 {
+            self.stick = Some(Stick {
+                touch,
+                origin: point,
+                knob: point,
+            });
+            return (true, None);
+        }
+        (false, None)
+    }
+
+    pub(crate) fn touch_moved(&mut self, touch: u64, point: nalgebra::Point2<f32>) {
+        if let Some(stick) = self.stick.as_mut() {
+            if stick.touch == touch {
+                let offset = point - stick.origin;
+                let offset = if offset.norm() > STICK_RADIUS {
+                    offset.normalize() * STICK_RADIUS
+                } else {
+                    offset
+                };
+                stick.knob = stick.origin + offset;
+            }
+        }
+    }
+
+    pub(crate) fn touch_up(&mut self, touch: u64) {
+        if self
+            .stick
+            .as_ref()
+            .map_or(false, |stick| stick.touch == touch)
+        {
+            self.stick = None;
+        }
+        self.held.retain(|(held, _)| *held != touch);
+    }
+
+    // Copies the stick and the fire button into the player input
+    pub(crate) fn apply(&self, dir: &mut Direction, aim: &mut Aim) {
+        let push = self
+            .stick
+            .as_ref()
+            .map_or(nalgebra::Vector2::new(0.0, 0.0), |stick| {
+                (stick.knob - stick.origin) / STICK_RADIUS
+            });
+        dir.up = push.y < -STICK_DEAD_ZONE;
+        dir.down = push.y > STICK_DEAD_ZONE;
+        dir.left = push.x < -STICK_DEAD_ZONE;
+        dir.right = push.x > STICK_DEAD_ZONE;
+        aim.firing = self
+            .held
+            .iter()
+            .any(|(_, button)| *button == TouchButton::Fire);
+    }
+}
+
+pub(crate) fn draw_touch_controls(ctx: &mut Context, controls: &TouchControls) -> GameResult<()> {
+    let view = graphics::screen_coordinates(ctx);
+    let mut mesh = graphics::MeshBuilder::new();
+
+    for (index, (label, button)) in BUTTONS.iter().enumerate() {
+        let center = button_center(view, index);
+        let pressed = controls.held.iter().any(|(_, held)| held == button);
+        let alpha = if pressed { 0.6 } else { 0.3 };
+        mesh.circle(
+            graphics::DrawMode::fill(),
+            center,
+            BUTTON_RADIUS,
+            0.5,
+            graphics::Color::new(1.0, 1.0, 1.0, alpha),
+        );
+
+        let text = graphics::Text::new(*label);
+        let (width, height) = text.dimensions(ctx);
+        graphics::queue_text(
+            ctx,
+            &text,
+            nalgebra::Point2::new(
+                center.x - width as f32 / 2.0,
+                center.y - height as f32 / 2.0,
+            ),
+            Some(graphics::WHITE),
+        );
+    }
+
+    if let Some(stick) = controls.stick.as_ref() {
+        mesh.circle(
+            graphics::DrawMode::stroke(2.0),
+            stick.origin,
+            STICK_RADIUS,
+            0.5,
+            graphics::Color::new(1.0, 1.0, 1.0, 0.3),
+        );
+        mesh.circle(
+            graphics::DrawMode::fill(),
+            stick.knob,
+            STICK_RADIUS / 3.0,
+            0.5,
+            graphics::Color::new(1.0, 1.0, 1.0, 0.5),
+        );
+    }
+
+    let mesh = mesh.build(ctx)?;
+    graphics::draw(ctx, &mesh, graphics::DrawParam::default())?;
+    graphics::draw_queued_text(
+        ctx,
+        graphics::DrawParam::default(),
+        None,
+        graphics::FilterMode::Linear,
+    )
+}
@@ -0,0 +1,172 @@
+use crate::settings::Settings;
+use crate::time::GameClock;
+use ggez::nalgebra;
+use ggez::{conf, graphics, Context, GameResult};
+use serde::{Deserialize, Serialize};
+use specs::*;
+
+// seconds a transition takes from start to finish
+const DURATION: f64 = 0.8;
+// how many screen pixels across one block is at its most pixelated
+const BLOCK_SIZE: u16 = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum TransitionKind {
+    // the old scene fades to black and the new one fades in from it
+    Fade,
+    // the new scene slides in from the left over the old one
+    Wipe,
+    // the old scene breaks up into blocks and dissolves away
+    Pixelate,
+}
+
+impl Default for TransitionKind {
+    fn default() -> Self {
+        TransitionKind::Fade
+    }
+}
+
+// How systems tell MainState the scene is changing. They flag that a change is
+// coming a little before it happens, so the last frame of the old scene gets
+// captured, and then that it has happened. The change itself never waits on
+// drawing, so the simulation plays out the same however it is drawn.
+#[derive(Debug, Default)]
+pub(crate) struct SceneChange {
+    pub(crate) coming: bool,
+    pub(crate) happened: bool,
+}
+
+// Covers a change of scene, e.g. the next round starting, instead of cutting
+// straight to it. The old scene is captured to a canvas while the change is
+// coming and drawn over the new one as it plays out.
+pub(crate) struct Transition {
+    pub(crate) outgoing: graphics::Canvas,
+    // the outgoing frame shrunk down, drawn back up blocky for Pixelate
+    blocks: graphics::Canvas,
+    kind: TransitionKind,
+    // unscaled game clock time it started, None while there isn't one
+    started_at: Option<f64>,
+}
+
+impl Transition {
+    pub(crate) fn new(ctx: &mut Context) -> GameResult<Self> {
+        let outgoing = graphics::Canvas::with_window_size(ctx)?;
+        let (width, height) = graphics::drawable_size(ctx);
+        let mut blocks = graphics::Canvas::new(
+            ctx,
+            (width as u16 / BLOCK_SIZE).max(1),
+            (height as u16 / BLOCK_SIZE).max(1),
+            conf::NumSamples::Zero,
+        )?;
+        blocks.set_filter(graphics::FilterMode::Nearest);
+        Ok(Transition {
+            outgoing,
+            blocks,
+            kind: TransitionKind::default(),
+            started_at: None,
+        })
+    }
+
+    // Whether the world should be drawn into the outgoing canvas this frame
+    pub(crate) fn capturing(world: &World) -> bool {
+        world.read_resource::<SceneChange>().coming
+    }
+
+    fn start(&mut self, ctx: &mut Context, kind: TransitionKind, now: f64) -> GameResult<()> {
+        self.kind = kind;
+        self.started_at = Some(now);
+        if kind == TransitionKind::Pixelate {
+            let scale = 1.0 / f32::from(BLOCK_SIZE);
+            graphics::set_canvas(ctx, Some(&self.blocks));
+            graphics::clear(ctx, graphics::BLACK);
+            graphics::draw(
+                ctx,
+                &self.outgoing,
+                graphics::DrawParam::default().scale(nalgebra::Vector2::new(scale, scale)),
+            )?;
+            graphics::set_canvas(ctx, None);
+        }
+        Ok(())
+    }
+
+    // Starts a transition if the scene just changed, and draws whichever one
+    // is playing over the new scene
+    pub(crate) fn draw(&mut self, ctx: &mut Context, world: &World) -> GameResult<()> {
+        let now = world.read_resource::<GameClock>().unscaled;
+        let happened = {
+            let mut change = world.write_resource::<SceneChange>();
+            std::mem::replace(&mut change.happened, false)
+        };
+        if happened {
+            let kind = world.read_resource::<Settings>().transition;
+            self.start(ctx, kind, now)?;
+        }
+
+        let started_at = match self.started_at {
+            Some(started_at) => started_at,
+            None => return Ok(()),
+        };
+        let progress = ((now - started_at) / DURATION) as f32;
+        if progress >= 1.0 {
+            self.started_at = None;
+            return Ok(());
+        }
+
+        let view = graphics::screen_coordinates(ctx);
+        let corner = nalgebra::Point2::new(view.x, view.y);
+        match self.kind {
+            TransitionKind::Fade => {
+                // out to black over the first half, in from it over the second
+                if progress < 0.5 {
+                    graphics::draw(
+                        ctx,
+                        &self.outgoing,
+                        graphics::DrawParam::default().dest(corner),
+                    )?;
+                }
+                let black = 1.0 - (progress * 2.0 - 1.0).abs();
+                let cover = graphics::Mesh::new_rectangle(
+                    ctx,
+                    graphics::DrawMode::fill(),
+                    view,
+                    graphics::Color::new(0.0, 0.0, 0.0, black),
+                )?;
+                graphics::draw(ctx, &cover, graphics::DrawParam::default())?;
+            }
+            TransitionKind::Wipe => {
+                // only the part of the old scene the new one hasn't reached
+                graphics::draw(
+                    ctx,
+                    &self.outgoing,
+                    graphics::DrawParam::default()
+                        .src(graphics::Rect::new(progress, 0.0, 1.0 - progress, 1.0))
+                        .dest(nalgebra::Point2::new(view.x + view.w * progress, view.y)),
+                )?;
+            }
+            TransitionKind::Pixelate => {
+                // sharp giving way to blocks over the first half, then the
+                // blocks fading out over the second
+                let scale = f32::from(BLOCK_SIZE);
+                let blocks_alpha = (2.0 - progress * 2.0).min(1.0);
+                graphics::draw(
+                    ctx,
+                    &self.blocks,
+                    graphics::DrawParam::default()
+                        .dest(corner)
+                        .scale(nalgebra::Vector2::new(scale, scale))
+                        .color(graphics::Color::new(1.0, 1.0, 1.0, blocks_alpha)),
+                )?;
+                if progress < 0.5 {
+                    graphics::draw(
+                        ctx,
+                        &self.outgoing,
+                        graphics::DrawParam::default()
+                            .dest(corner)
+                            .color(graphics::Color::new(1.0, 1.0, 1.0, 1.0 - progress * 2.0)),
+                    )?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
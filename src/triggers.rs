@@ -0,0 +1,186 @@
+use crate::ghost::Ghost;
+use crate::music::{Intensity, Layer};
+use crate::notifications::Notifications;
+use crate::prefab::{self, Prefabs};
+use crate::spawn_queue::SpawnQueue;
+use crate::time::GameClock;
+use crate::waves::WaveDirector;
+use crate::{CollisionBox, ControllableTag};
+use ggez::nalgebra;
+use serde::Deserialize;
+use specs::*;
+use std::collections::BTreeMap;
+
+// A rectangle of the level, named so triggers can refer to it
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub(crate) struct Zone {
+    pub(crate) x: f32,
+    pub(crate) y: f32,
+    pub(crate) width: f32,
+    pub(crate) height: f32,
+}
+
+impl Zone {
+    fn contains(&self, point: nalgebra::Point2<f32>) -> bool {
+        point.x >= self.x
+            && point.x <= self.x + self.width
+            && point.y >= self.y
+            && point.y <= self.y + self.height
+    }
+}
+
+// What sets a trigger off
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) enum When {
+    // as soon as the level starts
+    Start,
+    // the player's ship flying into one of the level's zones, by name
+    Enters(String),
+    // a wave at least this far in arriving
+    Wave(u32),
+    // this many seconds of game time in
+    After(f32),
+}
+
+// What a trigger does when it goes off
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) enum Do {
+    // sends in the given wave straight away
+    SendWave(u32),
+    // a prefab from resources/prefabs.ron, with its top left corner here
+    Spawn { prefab: String, x: f32, y: f32 },
+    // a line of dialog, shown as a notification
+    Say(String),
+    // plays a layer of music whatever the intensity, or with None hands the
+    // music back to the intensity
+    Music(Option<Layer>),
+    // stops the clock on the run, for timing it against the best
+    Finish,
+}
+
+// One of a level's triggers, e.g.
+//
+//     (when: Enters("A"), then: [SendWave(3), Say("Here they come!")])
+//
+// A trigger goes off once. A zone trigger can repeat instead, going off every
+// time the player flies back into the zone.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct Trigger {
+    when: When,
+    then: Vec<Do>,
+    #[serde(default)]
+    repeat: bool,
+}
+
+// The level's zones and triggers, and how far along each trigger is
+#[derive(Debug, Default)]
+pub(crate) struct Triggers {
+    zones: BTreeMap<String, Zone>,
+    triggers: Vec<Trigger>,
+    fired: Vec<bool>,
+    // whether the player was in each trigger's zone last update
+    inside: Vec<bool>,
+}
+
+impl Triggers {
+    pub(crate) fn new(zones: BTreeMap<String, Zone>, triggers: Vec<Trigger>) -> Self {
+        for trigger in &triggers {
+            if let When::Enters(zone) = &trigger.when {
+                if !zones.contains_key(zone) {
+                    println!("level error trigger for unknown zone {}", zone);
+                }
+            }
+        }
+        Triggers {
+            zones,
+            fired: vec![false; triggers.len()],
+            inside: vec![false; triggers.len()],
+            triggers,
+        }
+    }
+}
+
+// Runs the level's triggers, so levels can script waves, dialog and music
+// from their RON files rather than each needing code of its own
+pub(crate) struct TriggerScriptSystem;
+
+impl<'a> System<'a> for TriggerScriptSystem {
+    type SystemData = (
+        Write<'a, Triggers>,
+        Write<'a, WaveDirector>,
+        Write<'a, Notifications>,
+        Write<'a, Intensity>,
+        Write<'a, SpawnQueue>,
+        Write<'a, Ghost>,
+        Read<'a, GameClock>,
+        Read<'a, Prefabs>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, ControllableTag>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut triggers,
+            mut director,
+            mut notifications,
+            mut intensity,
+            mut queue,
+            mut ghost,
+            clock,
+            prefabs,
+            coll_box,
+            controlled,
+        ) = data;
+        let triggers = &mut *triggers;
+
+        let player = (&coll_box, &controlled)
+            .join()
+            .next()
+            .map(|(coll_box, _)| coll_box.center());
+
+        for i in 0..triggers.triggers.len() {
+            let trigger = &triggers.triggers[i];
+            let went_off = match &trigger.when {
+                When::Start => true,
+                When::Enters(zone) => {
+                    let inside = match (triggers.zones.get(zone), player) {
+                        (Some(zone), Some(player)) => zone.contains(player),
+                        _ => false,
+                    };
+                    let entered = inside && !triggers.inside[i];
+                    triggers.inside[i] = inside;
+                    entered
+                }
+                When::Wave(wave) => director.wave >= *wave,
+                When::After(seconds) => clock.elapsed >= f64::from(*seconds),
+            };
+            let repeats = trigger.repeat && matches!(trigger.when, When::Enters(_));
+            if !went_off || (triggers.fired[i] && !repeats) {
+                continue;
+            }
+            triggers.fired[i] = true;
+
+            for command in &trigger.then {
+                match command {
+                    Do::SendWave(wave) => {
+                        director.wave = wave.saturating_sub(1);
+                        director.send_now = true;
+                    }
+                    Do::Spawn { prefab, x, y } => match prefabs.get(prefab) {
+                        Some(prefab) => {
+                            let prefab = prefab.clone();
+                            let origin = nalgebra::Point2::new(*x, *y);
+                            queue.spawn(move |world| {
+                                prefab::spawn(world, &prefab, origin);
+                            });
+                        }
+                        None => println!("level error trigger spawns unknown prefab {}", prefab),
+                    },
+                    Do::Say(text) => notifications.push(text),
+                    Do::Music(layer) => intensity.scripted = *layer,
+                    Do::Finish => ghost.finish(clock.elapsed as f32),
+                }
+            }
+        }
+    }
+}
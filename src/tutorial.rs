@@ -0,0 +1,222 @@
+use crate::camera::Camera;
+use crate::controls::{Action, ActiveDevice, Aim};
+use crate::events::{self, Subscribe, TrackedReader};
+use crate::glyphs::{self, Glyphs};
+use crate::health::DamageEvent;
+use crate::settings::Settings;
+use crate::theme::UiTheme;
+use crate::time::DeltaTime;
+use crate::{Assets, ControllableTag, Direction, ImageHandle, Position};
+use ggez::nalgebra;
+use ggez::{graphics, Context, GameResult};
+use serde::Deserialize;
+use specs::*;
+use std::collections::VecDeque;
+
+// a prompt stays up at least this long, in seconds, so it can be read even
+// when the player is already doing what it asks
+const MIN_SHOWN: f32 = 1.0;
+const FADE_IN: f32 = 0.3;
+// how far above the player's ship the prompt sits
+const PROMPT_RISE: f32 = 30.0;
+
+// What brings a prompt up
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) enum Trigger {
+    // as soon as the level starts
+    Start,
+    // the player's ship flying into a circle
+    Zone { x: f32, y: f32, radius: f32 },
+    // the player's ship being hit
+    Damaged,
+    // another prompt being completed, by its id
+    After(String),
+}
+
+// What the player does to complete a prompt
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) enum Goal {
+    Thrust,
+    Fire,
+    Perform(Action),
+    // nothing to do, it goes after this many seconds
+    Read(f32),
+}
+
+// One hint from a level file. Once completed it is kept in the settings by its
+// id, and doesn't come up again in later games.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct Prompt {
+    pub(crate) id: String,
+    pub(crate) text: String,
+    pub(crate) trigger: Trigger,
+    pub(crate) goal: Goal,
+}
+
+// The level's prompts and which of them is showing. Only one shows at a time,
+// the others wait in the order they were triggered.
+#[derive(Debug, Default)]
+pub(crate) struct Prompts {
+    prompts: Vec<Prompt>,
+    triggered: Vec<bool>,
+    queued: VecDeque<usize>,
+    // the prompt showing and how long it has been up for
+    showing: Option<(usize, f32)>,
+    // the actions performed since the prompts were last checked
+    pub(crate) performed: Vec<Action>,
+    // whether a prompt has been completed since the settings were saved
+    unsaved: bool,
+}
+
+impl Prompts {
+    pub(crate) fn new(prompts: Vec<Prompt>) -> Self {
+        Prompts {
+            triggered: vec![false; prompts.len()],
+            prompts,
+            ..Prompts::default()
+        }
+    }
+
+    // true once after prompts are completed, when the settings need saving
+    pub(crate) fn take_unsaved(&mut self) -> bool {
+        std::mem::replace(&mut self.unsaved, false)
+    }
+}
+
+// Brings prompts up when their triggers go off and puts them away when the
+// player does what they ask
+pub(crate) struct PromptSystem {
+    damage: TrackedReader<DamageEvent>,
+}
+
+impl PromptSystem {
+    pub(crate) fn new(world: &World) -> Self {
+        PromptSystem {
+            damage: events::subscribe::<DamageEvent>(world, "tutorial"),
+        }
+    }
+}
+
+impl<'a> System<'a> for PromptSystem {
+    type SystemData = (
+        Read<'a, DeltaTime>,
+        Write<'a, Prompts>,
+        Write<'a, Settings>,
+        Read<'a, Direction>,
+        Read<'a, Aim>,
+        Subscribe<'a, DamageEvent>,
+        Entities<'a>,
+        ReadStorage<'a, ControllableTag>,
+        ReadStorage<'a, Position>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (delta, mut prompts, mut settings, dir, aim, damage, entities, controlled, positions) =
+            data;
+        let prompts = &mut *prompts;
+        let dt = delta.seconds;
+
+        let player = (&entities, &controlled, &positions)
+            .join()
+            .next()
+            .map(|(entity, _, p)| (entity, p.position));
+        // every event is read so they don't pile up, hit or not
+        let damaged = damage
+            .read(&mut self.damage)
+            .filter(|event| Some(event.target) == player.map(|(entity, _)| entity))
+            .count()
+            > 0;
+
+        for (i, prompt) in prompts.prompts.iter().enumerate() {
+            if prompts.triggered[i] || settings.completed_prompts.contains(&prompt.id) {
+                continue;
+            }
+            let triggered = match &prompt.trigger {
+                Trigger::Start => true,
+                Trigger::Zone { x, y, radius } => player.map_or(false, |(_, at)| {
+                    nalgebra::distance(&at, &nalgebra::Point2::new(*x, *y)) <= *radius
+                }),
+                Trigger::Damaged => damaged,
+                Trigger::After(id) => settings.completed_prompts.contains(id),
+            };
+            if triggered {
+                prompts.triggered[i] = true;
+                prompts.queued.push_back(i);
+            }
+        }
+
+        if prompts.showing.is_none() {
+            prompts.showing = prompts.queued.pop_front().map(|i| (i, 0.0));
+        }
+        if let Some((i, shown)) = prompts.showing.as_mut() {
+            *shown += dt;
+            let prompt = &prompts.prompts[*i];
+            let done = match &prompt.goal {
+                Goal::Thrust => dir.heading().norm() > 0.0,
+                Goal::Fire => aim.firing,
+                Goal::Perform(action) => prompts.performed.contains(action),
+                Goal::Read(seconds) => *shown >= *seconds,
+            };
+            if done && *shown >= MIN_SHOWN {
+                settings.completed_prompts.push(prompt.id.clone());
+                prompts.showing = None;
+                prompts.unsaved = true;
+            }
+        }
+        prompts.performed.clear();
+    }
+}
+
+// The prompt showing, in a box above the player's ship, with the keys or
+// buttons it names for the device in use
+pub(crate) fn draw_prompt(ctx: &mut Context, world: &World, glyphs: &Glyphs) -> GameResult<()> {
+    let prompts = world.read_resource::<Prompts>();
+    let (i, shown) = match prompts.showing {
+        Some(showing) => showing,
+        None => return Ok(()),
+    };
+    let controlled = world.read_storage::<ControllableTag>();
+    let positions = world.read_storage::<Position>();
+    let images = world.read_storage::<ImageHandle>();
+    let (p, image) = match (&controlled, &positions, &images).join().next() {
+        Some((_, p, image)) => (p, image),
+        None => return Ok(()),
+    };
+
+    let alpha = (shown / FADE_IN).min(1.0);
+    let theme = world.read_resource::<UiTheme>();
+    let text = glyphs::pieces(
+        &prompts.prompts[i].text,
+        &world.read_resource::<ActiveDevice>().0,
+        &world.read_resource::<Settings>(),
+    );
+    let (width, height) = glyphs.measure(ctx, &theme, &text);
+    // over the ship wherever the camera has it on the screen
+    let above = world
+        .read_resource::<Camera>()
+        .to_screen(nalgebra::Point2::new(
+            p.position.x + world.read_resource::<Assets>().size(*image).0 / 2.0,
+            p.position.y,
+        ));
+    let corner = nalgebra::Point2::new(above.x - width / 2.0, above.y - PROMPT_RISE - height);
+    theme.draw_panel(
+        ctx,
+        graphics::Rect::new(
+            corner.x - theme.padding,
+            corner.y - theme.padding,
+            width + theme.padding * 2.0,
+            height + theme.padding * 2.0,
+        ),
+        alpha,
+    )?;
+    glyphs.draw(
+        ctx,
+        &theme,
+        &text,
+        corner,
+        graphics::Color {
+            a: theme.message.a * alpha,
+            ..theme.message
+        },
+    )
+}
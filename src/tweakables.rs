@@ -0,0 +1,240 @@
+use crate::data;
+#[cfg(feature = "dev-tools")]
+use crate::notifications::Notifications;
+#[cfg(feature = "dev-tools")]
+use crate::platform;
+#[cfg(feature = "dev-tools")]
+use ggez::event::{KeyCode, KeyMods};
+#[cfg(feature = "dev-tools")]
+use ggez::nalgebra;
+use ggez::{filesystem, Context};
+#[cfg(feature = "dev-tools")]
+use ggez::{graphics, GameResult};
+use specs::*;
+use std::collections::BTreeMap;
+
+pub(crate) const PATH: &str = "/tweakables.ron";
+#[cfg(feature = "dev-tools")]
+const OVERLAY_MARGIN: f32 = 10.0;
+
+// A number a system tunes itself by, with where it starts and how far it can
+// be turned, e.g.
+//
+//     const PLAYER_SPEED: Tweak = Tweak {
+//         name: "player speed",
+//         default: 600.0,
+//         min: 100.0,
+//         max: 1500.0,
+//         step: 25.0,
+//     };
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Tweak {
+    pub(crate) name: &'static str,
+    pub(crate) default: f32,
+    pub(crate) min: f32,
+    pub(crate) max: f32,
+    // how far one press of the panel moves it
+    #[cfg_attr(not(feature = "dev-tools"), allow(dead_code))]
+    pub(crate) step: f32,
+}
+
+// The numbers the game is tuned by, which can be changed while it runs
+// rather than recompiling for every try. A system registers its Tweaks when
+// it's made, which lists them on the dev-tools panel, and reads them back each
+// update:
+//
+//     tweakables::register(world, &PLAYER_SPEED);
+//
+//     let speed = tweakables.get(&PLAYER_SPEED);
+//
+// /tweakables.ron gives values to start from in place of the defaults, by
+// name, and the panel saves them back there. A changed value plays back
+// differently in replays recorded without it.
+#[derive(Debug, Default)]
+pub(crate) struct Tweakables {
+    // in the order they were registered
+    tweaks: Vec<(Tweak, f32)>,
+    // from /tweakables.ron, by name
+    saved: BTreeMap<String, f32>,
+}
+
+impl Tweakables {
+    // with the values in /tweakables.ron if there is one, which is reported
+    // and left out if it won't load
+    pub(crate) fn load(ctx: &mut Context) -> Self {
+        let mut tweakables = Tweakables::default();
+        if filesystem::exists(ctx, PATH) {
+            tweakables.saved = data::load(ctx, PATH).unwrap_or_else(|err| {
+                println!("tweakables error {:?}", err);
+                BTreeMap::new()
+            });
+        }
+        tweakables
+    }
+
+    // the value it's turned to, or its default if it was never registered
+    pub(crate) fn get(&self, tweak: &Tweak) -> f32 {
+        self.tweaks
+            .iter()
+            .find(|(registered, _)| registered.name == tweak.name)
+            .map_or(tweak.default, |(_, value)| *value)
+    }
+
+    fn add(&mut self, tweak: &Tweak) {
+        if self
+            .tweaks
+            .iter()
+            .any(|(registered, _)| registered.name == tweak.name)
+        {
+            return;
+        }
+        let value = self
+            .saved
+            .get(tweak.name)
+            .map_or(tweak.default, |value| value.max(tweak.min).min(tweak.max));
+        self.tweaks.push((*tweak, value));
+    }
+
+    // every registered Tweak with its value, for turning in place
+    #[cfg(feature = "debug-ui")]
+    pub(crate) fn tweaks_mut(&mut self) -> impl Iterator<Item = (&Tweak, &mut f32)> {
+        self.tweaks
+            .iter_mut()
+            .map(|(tweak, value)| (&*tweak, value))
+    }
+
+    // moves the i'th registered value on by some number of steps
+    #[cfg(feature = "dev-tools")]
+    fn nudge(&mut self, i: usize, steps: f32) {
+        if let Some((tweak, value)) = self.tweaks.get_mut(i) {
+            *value = (*value + tweak.step * steps).max(tweak.min).min(tweak.max);
+        }
+    }
+
+    #[cfg(feature = "dev-tools")]
+    fn reset(&mut self, i: usize) {
+        if let Some((tweak, value)) = self.tweaks.get_mut(i) {
+            *value = tweak.default;
+        }
+    }
+
+    // Saves every value and says on screen whether it worked
+    #[cfg(feature = "dev-tools")]
+    pub(crate) fn save_noting(&mut self, world: &World) {
+        let message = match self.save() {
+            Ok(()) => format!("Saved {}", PATH),
+            Err(err) => {
+                println!("tweakables error {:?}", err);
+                format!("Couldn't save {}", PATH)
+            }
+        };
+        world.write_resource::<Notifications>().push(&message);
+    }
+
+    // Writes every value into /tweakables.ron in the resources directory,
+    // keeping any the file had for tweaks that aren't registered this game
+    #[cfg(feature = "dev-tools")]
+    fn save(&mut self) -> GameResult<()> {
+        for (tweak, value) in &self.tweaks {
+            self.saved.insert(tweak.name.to_owned(), *value);
+        }
+        let text = ron::ser::to_string_pretty(&self.saved, ron::ser::PrettyConfig::default())
+            .map_err(|err| ggez::GameError::FilesystemError(format!("{}: {}", PATH, err)))?;
+        std::fs::write(platform::resource_dir().join(&PATH[1..]), text)?;
+        Ok(())
+    }
+}
+
+// Lists a Tweak in the world's Tweakables. Registering the same one again
+// leaves it as it is.
+pub(crate) fn register(world: &World, tweak: &Tweak) {
+    world.write_resource::<Tweakables>().add(tweak);
+}
+
+// The Tweakables down the left of the screen, only built with the dev-tools
+// feature. Ctrl+Shift+U opens it. While it's open Ctrl+Up and Ctrl+Down pick
+// a value, Ctrl+Left and Ctrl+Right turn it, ten steps at a time with Shift
+// as well, Ctrl+0 puts it back to its default and Ctrl+S saves them all. The
+// arrows on their own still fly the ship, so a change can be tried straight
+// away.
+#[cfg(feature = "dev-tools")]
+#[derive(Default)]
+pub(crate) struct TweakPanel {
+    visible: bool,
+    selected: usize,
+}
+
+#[cfg(feature = "dev-tools")]
+impl TweakPanel {
+    // Carries out the key if it's one of the panel's, returning false if not
+    pub(crate) fn key_down(&mut self, world: &World, keycode: KeyCode, keymod: KeyMods) -> bool {
+        if !keymod.contains(KeyMods::CTRL) {
+            return false;
+        }
+        let shift = keymod.contains(KeyMods::SHIFT);
+        if keycode == KeyCode::U && shift {
+            self.visible = !self.visible;
+            return true;
+        }
+        if !self.visible {
+            return false;
+        }
+        let mut tweakables = world.write_resource::<Tweakables>();
+        let count = tweakables.tweaks.len().max(1);
+        let steps = if shift { 10.0 } else { 1.0 };
+        match keycode {
+            KeyCode::Up => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Down => self.selected = (self.selected + 1) % count,
+            KeyCode::Left => tweakables.nudge(self.selected, -steps),
+            KeyCode::Right => tweakables.nudge(self.selected, steps),
+            KeyCode::Key0 => tweakables.reset(self.selected),
+            KeyCode::S => tweakables.save_noting(world),
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[cfg(feature = "dev-tools")]
+pub(crate) fn draw_tweak_panel(
+    ctx: &mut Context,
+    world: &World,
+    panel: &TweakPanel,
+) -> GameResult<()> {
+    if !panel.visible {
+        return Ok(());
+    }
+    let tweakables = world.read_resource::<Tweakables>();
+    let mut lines: Vec<String> = tweakables
+        .tweaks
+        .iter()
+        .enumerate()
+        .map(|(i, (tweak, value))| {
+            let marker = if i == panel.selected { ">" } else { " " };
+            let changed = if *value != tweak.default { "*" } else { "" };
+            format!("{} {} {:.2}{}", marker, tweak.name, value, changed)
+        })
+        .collect();
+    if lines.is_empty() {
+        lines.push("Nothing to tweak".to_owned());
+    }
+    let text = graphics::Text::new(lines.join("\n"));
+
+    let view = graphics::screen_coordinates(ctx);
+    let (width, height) = text.dimensions(ctx);
+    let top = view.y + (view.h - height as f32) / 2.0 - OVERLAY_MARGIN;
+    let background = graphics::Mesh::new_rectangle(
+        ctx,
+        graphics::DrawMode::fill(),
+        graphics::Rect::new(
+            view.x,
+            top,
+            width as f32 + OVERLAY_MARGIN * 2.0,
+            height as f32 + OVERLAY_MARGIN * 2.0,
+        ),
+        graphics::Color::new(0.0, 0.0, 0.0, 0.7),
+    )?;
+    graphics::draw(ctx, &background, graphics::DrawParam::default())?;
+    let corner = nalgebra::Point2::new(view.x + OVERLAY_MARGIN, top + OVERLAY_MARGIN);
+    graphics::draw(ctx, &text, graphics::DrawParam::default().dest(corner))
+}
@@ -0,0 +1,55 @@
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::Position;
+use ggez::nalgebra;
+use specs::*;
+use specs_derive::*;
+
+// A Tween moves an entity between two points and fades it between two alpha
+// values over a fixed duration. The tween system owns the entity's Position
+// while the tween runs, renderers ask the tween for the current alpha.
+#[derive(Component, Debug)]
+#[storage(VecStorage)]
+pub(crate) struct Tween {
+    pub(crate) from: nalgebra::Point2<f32>,
+    pub(crate) to: nalgebra::Point2<f32>,
+    pub(crate) from_alpha: f32,
+    pub(crate) to_alpha: f32,
+    // seconds
+    pub(crate) duration: f32,
+    pub(crate) elapsed: f32,
+}
+
+impl Tween {
+    // eased progress through the tween, starting quickly and slowing at the end
+    fn progress(&self) -> f32 {
+        let t = (self.elapsed / self.duration).min(1.0);
+        1.0 - (1.0 - t) * (1.0 - t)
+    }
+
+    pub(crate) fn alpha(&self) -> f32 {
+        self.from_alpha + (self.to_alpha - self.from_alpha) * self.progress()
+    }
+}
+
+pub(crate) struct TweenSystem;
+
+impl<'a> System<'a> for TweenSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Tween>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, delta, time, multipliers, mut pos, mut tweens) = data;
+
+        for (entity, pos, tween) in (&entities, &mut pos, &mut tweens).join() {
+            let dt = time.scaled(delta.seconds, multipliers.get(entity));
+            tween.elapsed = (tween.elapsed + dt).min(tween.duration);
+            pos.position = tween.from + (tween.to - tween.from) * tween.progress();
+        }
+    }
+}
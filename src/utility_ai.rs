@@ -0,0 +1,245 @@
+use crate::ai::AiControlled;
+use crate::cooldowns::{self, Cooldowns};
+use crate::faction::Faction;
+use crate::health::Health;
+use crate::influence::InfluenceMap;
+use crate::weapons::Weapon;
+use crate::CollisionBox;
+use ggez::nalgebra;
+use serde::{Deserialize, Serialize};
+use specs::*;
+use specs_derive::*;
+
+// distances are scored as a fraction of this, anything further counts as 1
+const DISTANCE_SCALE: f32 = 500.0;
+// the range an attacking ship tries to close to
+const ATTACK_RANGE: f32 = 200.0;
+// the action already being carried out gets this much of a boost, so the ship
+// doesn't flip between two actions that score about the same
+const MOMENTUM: f32 = 1.1;
+// influence map threat at which a spot counts as fully dangerous
+const MAX_DANGER: f32 = 2.0;
+// how far a retreating ship looks for somewhere safer
+const RETREAT_RANGE: f32 = 250.0;
+// close enough to a safe spot to count as being there
+const CELL_REACHED: f32 = 25.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub(crate) enum UtilityAction {
+    Attack,
+    Retreat,
+    // circle around the target while the weapon recharges
+    Reposition,
+}
+
+// The things an action's score is worked out from, each one normalised to
+// between 0 and 1
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(crate) enum Consideration {
+    // fraction of health left
+    Health,
+    // distance to the nearest target
+    Distance,
+    // how ready the weapon is to fire again. Weapons don't have ammo counts so
+    // this stands in for ammo.
+    Readiness,
+    // how much hostile influence there is where the ship is
+    Danger,
+}
+
+// Response curves, turning a consideration into how much it counts for an action
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub(crate) enum Curve {
+    Linear,
+    Inverse,
+    Quadratic,
+    InverseQuadratic,
+}
+
+impl Curve {
+    fn apply(self, x: f32) -> f32 {
+        match self {
+            Curve::Linear => x,
+            Curve::Inverse => 1.0 - x,
+            Curve::Quadratic => x * x,
+            Curve::InverseQuadratic => (1.0 - x) * (1.0 - x),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub(crate) struct Candidate {
+    pub(crate) action: UtilityAction,
+    pub(crate) considerations: Vec<(Consideration, Curve)>,
+}
+
+// An alternative to behavior trees for ships that should weigh up their
+// options rather than follow a script. Every think tick each candidate action is
+// scored by multiplying its considerations together, and the best one wins.
+#[derive(Component, Debug, Clone, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(crate) struct UtilityAi {
+    pub(crate) candidates: Vec<Candidate>,
+    pub(crate) current: Option<UtilityAction>,
+}
+
+impl Default for UtilityAi {
+    fn default() -> Self {
+        use self::Consideration::*;
+        use self::Curve::*;
+
+        UtilityAi {
+            candidates: vec![
+                // fight when healthy, close by and ready to shoot
+                Candidate {
+                    action: UtilityAction::Attack,
+                    considerations: vec![
+                        (Health, Linear),
+                        (Distance, Inverse),
+                        (Readiness, Quadratic),
+                    ],
+                },
+                // back off once badly hurt, the more so the closer the target
+                // and the hotter things are
+                Candidate {
+                    action: UtilityAction::Retreat,
+                    considerations: vec![
+                        (Health, InverseQuadratic),
+                        (Distance, Inverse),
+                        (Danger, Linear),
+                    ],
+                },
+                // keep moving while the weapon recharges
+                Candidate {
+                    action: UtilityAction::Reposition,
+                    considerations: vec![(Health, Linear), (Readiness, Inverse)],
+                },
+            ],
+            current: None,
+        }
+    }
+}
+
+// The normalised value of every consideration for one ship
+struct Considered {
+    health: f32,
+    distance: f32,
+    readiness: f32,
+    danger: f32,
+}
+
+impl UtilityAi {
+    fn score(&self, candidate: &Candidate, values: &Considered) -> f32 {
+        let mut score = candidate
+            .considerations
+            .iter()
+            .map(|(consideration, curve)| {
+                let value = match consideration {
+                    Consideration::Health => values.health,
+                    Consideration::Distance => values.distance,
+                    Consideration::Readiness => values.readiness,
+                    Consideration::Danger => values.danger,
+                };
+                curve.apply(value)
+            })
+            .product::<f32>();
+        if self.current == Some(candidate.action) {
+            score *= MOMENTUM;
+        }
+        score
+    }
+}
+
+// Picks the best action for each utility AI ship that is thinking this frame
+// and turns it into AiControlled steering, before the AiSystem runs
+pub(crate) struct UtilityAiSystem;
+
+impl<'a> System<'a> for UtilityAiSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, InfluenceMap>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, Health>,
+        ReadStorage<'a, Weapon>,
+        ReadStorage<'a, Cooldowns>,
+        WriteStorage<'a, AiControlled>,
+        WriteStorage<'a, UtilityAi>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            influence,
+            coll_box,
+            factions,
+            health,
+            weapons,
+            cooldowns,
+            mut ai,
+            mut utility,
+        ) = data;
+
+        for (entity, own_box, ai, utility) in (&entities, &coll_box, &mut ai, &mut utility).join() {
+            if !ai.thinking {
+                continue;
+            }
+            ai.engage = false;
+            ai.steer = nalgebra::Vector2::new(0.0, 0.0);
+
+            let origin = own_box.center();
+            let side = factions.get(entity);
+            let to_target = match ai.target.and_then(|target| coll_box.get(target)) {
+                Some(target) => target.center() - origin,
+                None => {
+                    utility.current = None;
+                    continue;
+                }
+            };
+
+            let values = Considered {
+                health: health.get(entity).map_or(1.0, |h| h.fraction()),
+                distance: (to_target.norm() / DISTANCE_SCALE).min(1.0),
+                readiness: match (weapons.get(entity), cooldowns.get(entity)) {
+                    (Some(_), Some(cooldowns)) => 1.0 - cooldowns.fraction(cooldowns::FIRE),
+                    (Some(_), None) => 1.0,
+                    (None, _) => 0.0,
+                },
+                danger: (influence.threat(side, origin) / MAX_DANGER).min(1.0),
+            };
+
+            let best = utility
+                .candidates
+                .iter()
+                .map(|candidate| (utility.score(candidate, &values), candidate.action))
+                .max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+                .map(|(_, action)| action);
+            utility.current = best;
+
+            match best {
+                Some(UtilityAction::Attack) => {
+                    ai.engage = true;
+                    if to_target.norm() > ATTACK_RANGE {
+                        ai.steer = to_target;
+                    }
+                }
+                Some(UtilityAction::Retreat) => {
+                    // head for the safest spot nearby, or straight away from
+                    // the target if already there
+                    let safest = influence.safest_near(side, origin, RETREAT_RANGE);
+                    ai.steer = if (safest - origin).norm() > CELL_REACHED {
+                        safest - origin
+                    } else {
+                        -to_target
+                    };
+                }
+                Some(UtilityAction::Reposition) => {
+                    // strafe sideways, keeping the guns on the target
+                    ai.engage = true;
+                    ai.steer = nalgebra::Vector2::new(-to_target.y, to_target.x);
+                }
+                None => (),
+            }
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use crate::{BoxOffset, CollisionBox, ImageHandle, Position, Rotation};
+use specs::*;
+use std::collections::HashSet;
+
+// how far a collision box can drift from where its position and BoxOffset put
+// it before it counts, the systems that move ships copy one into the other so
+// anything beyond rounding means one of them was missed
+const DRIFT_TOLERANCE: f32 = 0.01;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Violation {
+    // drawn at the origin, as nothing says where it is
+    ImageWithoutPosition,
+    // collides somewhere other than where it is drawn
+    CollisionBoxDiverged,
+    NanPosition,
+    NanCollisionBox,
+    NanRotation,
+}
+
+// Debug builds check the shape of the world after every maintain, catching the
+// entities a system left half updated before they turn into odd behaviour
+// somewhere else. Each problem is printed with the entity it was found on when
+// it first turns up, and again only if it goes away and comes back.
+//
+// There is no Parent component in the game yet, once entities can hang off
+// each other orphaned parents belong in here too.
+#[derive(Default)]
+pub(crate) struct ValidationSystem {
+    reported: HashSet<(Entity, Violation)>,
+}
+
+impl<'a> System<'a> for ValidationSystem {
+    type SystemData = (
+        Entities<'a>,
+        ReadStorage<'a, Position>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, BoxOffset>,
+        ReadStorage<'a, Rotation>,
+        ReadStorage<'a, ImageHandle>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, pos, coll_box, offsets, rotation, images) = data;
+        let mut found = HashSet::new();
+
+        for (entity, _, _) in (&entities, &images, !&pos).join() {
+            found.insert((entity, Violation::ImageWithoutPosition));
+        }
+
+        for (entity, pos) in (&entities, &pos).join() {
+            if pos.position.x.is_nan() || pos.position.y.is_nan() {
+                found.insert((entity, Violation::NanPosition));
+            }
+        }
+
+        for (entity, coll_box) in (&entities, &coll_box).join() {
+            let origin = coll_box.origin;
+            if origin.x.is_nan()
+                || origin.y.is_nan()
+                || coll_box.width.is_nan()
+                || coll_box.height.is_nan()
+            {
+                found.insert((entity, Violation::NanCollisionBox));
+            }
+        }
+
+        for (entity, pos, coll_box) in (&entities, &pos, &coll_box).join() {
+            // NaN never compares, it has already been reported above
+            let origin = pos.position + BoxOffset::of(offsets.get(entity));
+            if (origin - coll_box.origin).norm() > DRIFT_TOLERANCE {
+                found.insert((entity, Violation::CollisionBoxDiverged));
+            }
+        }
+
+        for (entity, rotation) in (&entities, &rotation).join() {
+            if rotation.angle.is_nan() {
+                found.insert((entity, Violation::NanRotation));
+            }
+        }
+
+        for (entity, violation) in found.difference(&self.reported) {
+            println!(
+                "Validation error on entity {} (generation {}): {:?}",
+                entity.id(),
+                entity.gen().id(),
+                violation
+            );
+        }
+        self.reported = found;
+    }
+}
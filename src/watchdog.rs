@@ -0,0 +1,85 @@
+use crate::profiler::SystemTimes;
+use crate::DESIRED_FPS;
+use specs::*;
+use std::time::{Duration, Instant};
+
+// Keeps an eye on how long each frame takes to update and draw against the
+// time there is for one at DESIRED_FPS. Once a second it reports how many
+// frames went over, along with the worst of them and the system that took
+// longest in it. The frame times are handed on to the QualityController.
+pub(crate) struct FrameWatchdog {
+    started: Instant,
+    frames: u32,
+    over: u32,
+    worst: Duration,
+    worst_system: Option<(&'static str, Duration)>,
+}
+
+impl Default for FrameWatchdog {
+    fn default() -> Self {
+        FrameWatchdog {
+            started: Instant::now(),
+            frames: 0,
+            over: 0,
+            worst: Duration::default(),
+            worst_system: None,
+        }
+    }
+}
+
+fn budget() -> Duration {
+    Duration::from_secs(1) / DESIRED_FPS
+}
+
+fn millis(time: Duration) -> f64 {
+    time.as_secs_f64() * 1000.0
+}
+
+impl FrameWatchdog {
+    // at the start of the frame's update
+    pub(crate) fn begin(&mut self, world: &World) {
+        self.started = Instant::now();
+        world.write_resource::<SystemTimes>().clear();
+    }
+
+    // once the frame has been drawn, before it is presented so waiting on the
+    // display isn't counted. Returns how long the frame took.
+    pub(crate) fn end(&mut self, world: &World) -> Duration {
+        let took = self.started.elapsed();
+        self.frames += 1;
+        if took > budget() {
+            self.over += 1;
+            if took > self.worst {
+                self.worst = took;
+                self.worst_system = world.read_resource::<SystemTimes>().slowest();
+            }
+        }
+
+        if self.frames >= DESIRED_FPS {
+            self.report();
+            self.frames = 0;
+            self.over = 0;
+            self.worst = Duration::default();
+            self.worst_system = None;
+        }
+        took
+    }
+
+    fn report(&self) {
+        if self.over == 0 {
+            return;
+        }
+        let slowest = match self.worst_system {
+            Some((name, time)) => format!("{} took {:.2}ms", name, millis(time)),
+            None => "no systems ran".to_owned(),
+        };
+        println!(
+            "{} of the last {} frames went over the {:.1}ms budget, the worst took {:.1}ms ({})",
+            self.over,
+            self.frames,
+            millis(budget()),
+            millis(self.worst),
+            slowest
+        );
+    }
+}
@@ -0,0 +1,391 @@
+use crate::ai::{AiControlled, Difficulty};
+use crate::faction::Faction;
+use crate::fixed;
+use crate::health::Health;
+use crate::influence::InfluenceMap;
+use crate::notifications::Notifications;
+use crate::patterns::{BulletPattern, PatternLibrary};
+use crate::prefab::{self, FromPrefab, Prefab, Prefabs};
+use crate::score::PlayerScore;
+use crate::settings::Settings;
+use crate::spawner::{ShipBuilder, Spawner};
+use crate::status::{Status, StatusEffects};
+use crate::time::{DeltaTime, GameClock, TimeMultiplier};
+use crate::weapons::Weapon;
+use crate::{CollisionBox, ControllableTag};
+use ggez::nalgebra;
+use serde::Deserialize;
+use specs::*;
+
+// seconds between the last AI ship going down and the next wave arriving
+const WAVE_DELAY: f32 = 3.0;
+// how far apart ships in the same wave are placed
+const WAVE_SPACING: f32 = 60.0;
+// every this many waves a boss comes in as well
+const BOSS_EVERY: u32 = 3;
+// seconds new arrivals are shielded for, so they can't be farmed as they
+// spawn
+const SPAWN_SHIELD: f32 = 2.0;
+
+// Keeps track of the waves of Red AI ships sent at the player
+#[derive(Debug, Default)]
+pub(crate) struct WaveDirector {
+    pub(crate) wave: u32,
+    delay: f32,
+    // sends the next wave in straight away, whatever is left of this one
+    pub(crate) send_now: bool,
+    // no waves come in by themselves, only ones sent in, e.g. in the sandbox
+    pub(crate) held: bool,
+    // seconds since the last wave came in
+    since_wave: f32,
+    // how the waves grow over time, from the level, see Escalation
+    pub(crate) escalation: Option<Escalation>,
+}
+
+// A value that changes over the game, given as (minutes in, value) points,
+// e.g. `[(0.0, 2.0), (5.0, 6.0), (20.0, 12.0)]`. In between points it's
+// somewhere on the line between them, and before the first or after the last
+// it stays where that point is.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(transparent)]
+pub(crate) struct Curve(Vec<(f32, f32)>);
+
+impl Curve {
+    // the value the minutes in, or the fallback for a curve with no points
+    pub(crate) fn at(&self, minutes: f32, fallback: f32) -> f32 {
+        let points = &self.0;
+        let (first, last) = match (points.first(), points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return fallback,
+        };
+        if minutes <= first.0 {
+            return first.1;
+        }
+        if minutes >= last.0 {
+            return last.1;
+        }
+        for pair in points.windows(2) {
+            let ((from, a), (to, b)) = (pair[0], pair[1]);
+            if minutes < to {
+                let t = if to > from {
+                    (minutes - from) / (to - from)
+                } else {
+                    1.0
+                };
+                return a + (b - a) * t;
+            }
+        }
+        last.1
+    }
+}
+
+// A reward for reaching a wave
+#[derive(Clone, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct Milestone {
+    pub(crate) wave: u32,
+    #[serde(default)]
+    pub(crate) points: u32,
+    // health given back to the player
+    #[serde(default)]
+    pub(crate) health: f32,
+}
+
+// How the waves of an endless game grow, from the level's RON file, e.g.
+//
+//     waves: Some((
+//         count: [(0.0, 2.0), (10.0, 10.0)],
+//         speed: [(0.0, 1.0), (10.0, 1.6)],
+//         variety: [(0.0, 1.0), (4.0, 3.0)],
+//         roster: ["wave_ship", "sniper", "rammer"],
+//         interval: [(0.0, 30.0), (10.0, 12.0)],
+//         milestones: [(wave: 5, points: 500, health: 50.0)],
+//     )),
+//
+// The curves go by minutes of game time. Without any escalation a wave is
+// one ship bigger than the last and only comes once the last is gone.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub(crate) struct Escalation {
+    // ships in each wave
+    pub(crate) count: Curve,
+    // how fast the wave's ships run, as a TimeMultiplier
+    pub(crate) speed: Curve,
+    // how many of the roster's prefabs a wave is made from, the first ones
+    // first
+    pub(crate) variety: Curve,
+    pub(crate) roster: Vec<String>,
+    // seconds before the next wave comes in whether or not this one is gone
+    pub(crate) interval: Curve,
+    pub(crate) milestones: Vec<Milestone>,
+    // Red AI ships there can be at once, a wave only tops them up to this
+    pub(crate) max_enemies: usize,
+    // no wave comes in while there are this many entities, for very long
+    // runs that pile up more than the game can keep up with
+    pub(crate) max_entities: usize,
+}
+
+impl Default for Escalation {
+    fn default() -> Self {
+        Escalation {
+            count: Curve::default(),
+            speed: Curve::default(),
+            variety: Curve::default(),
+            roster: Vec::new(),
+            interval: Curve::default(),
+            milestones: Vec::new(),
+            max_enemies: 40,
+            max_entities: 2000,
+        }
+    }
+}
+
+// Sends in a new, bigger wave whenever every AI ship has been destroyed. The
+// wave comes in wherever the influence map says is furthest from the player's
+// side, so ships don't appear right on top of them. Every few waves a boss
+// running the "boss" bullet pattern leads it in.
+//
+// The ships are made from the "wave_ship" and "boss" prefabs, if
+// resources/prefabs.ron has them. Either way they fly for Red under the AI,
+// the wave is over once the AI ships are gone. A level with an Escalation
+// grows the waves by that instead, and hands out its milestone rewards.
+pub(crate) struct WaveSystem;
+
+impl<'a> System<'a> for WaveSystem {
+    type SystemData = (
+        Entities<'a>,
+        Write<'a, WaveDirector>,
+        Write<'a, Notifications>,
+        Write<'a, PlayerScore>,
+        Read<'a, DeltaTime>,
+        Read<'a, GameClock>,
+        Read<'a, InfluenceMap>,
+        Read<'a, Settings>,
+        Read<'a, PatternLibrary>,
+        Read<'a, Prefabs>,
+        ReadExpect<'a, Spawner>,
+        Read<'a, LazyUpdate>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, ControllableTag>,
+        ReadStorage<'a, AiControlled>,
+        WriteStorage<'a, Health>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut director,
+            mut notifications,
+            mut score,
+            delta,
+            clock,
+            influence,
+            settings,
+            library,
+            prefabs,
+            spawner,
+            updater,
+            coll_box,
+            controlled,
+            ai,
+            mut health,
+        ) = data;
+        // borrowed field by field, the plan for a wave borrows the escalation
+        let director = &mut *director;
+        let dt = delta.seconds;
+        let minutes = (clock.elapsed / 60.0) as f32;
+        let enemies = (&ai).join().count();
+        director.since_wave += dt;
+
+        // an escalating wave doesn't wait for the last one to be gone
+        let overdue = match &director.escalation {
+            Some(escalation) => director.since_wave >= escalation.interval.at(minutes, f32::MAX),
+            None => false,
+        };
+        if director.send_now {
+            director.send_now = false;
+        } else if director.held {
+            return;
+        } else if !overdue {
+            if enemies > 0 {
+                director.delay = WAVE_DELAY;
+                return;
+            }
+            director.delay -= dt;
+            if director.delay > 0.0 {
+                return;
+            }
+        }
+
+        let player = match (&coll_box, &controlled).join().next() {
+            Some((player_box, _)) => player_box.center(),
+            None => return,
+        };
+        let (width, height) = spawner.ship_size();
+        let plan = match &director.escalation {
+            Some(escalation) => {
+                // no room for more, try again after a while
+                let room = escalation.max_enemies.saturating_sub(enemies);
+                if room == 0 || (&entities).join().count() >= escalation.max_entities {
+                    director.since_wave = 0.0;
+                    return;
+                }
+                WavePlan::escalating(escalation, minutes, room)
+            }
+            None => WavePlan::growing(director.wave + 1),
+        };
+
+        director.wave += 1;
+        director.delay = WAVE_DELAY;
+        director.since_wave = 0.0;
+        notifications.push(&format!("Wave {}", director.wave));
+        if let Some(escalation) = &director.escalation {
+            for milestone in &escalation.milestones {
+                if milestone.wave == director.wave {
+                    reward(
+                        milestone,
+                        &mut score,
+                        &mut notifications,
+                        &controlled,
+                        &mut health,
+                    );
+                }
+            }
+        }
+
+        let side = Faction::Red;
+        let spawn = influence.spawn_point(Some(&side), player);
+        for i in 0..plan.count {
+            // spread the wave out in a line across the spawn point
+            let offset = (i as f32 - plan.count.saturating_sub(1) as f32 / 2.0) * WAVE_SPACING;
+            let origin =
+                nalgebra::Point2::new(spawn.x + offset - width / 2.0, spawn.y - height / 2.0);
+
+            let ship = spawner
+                .ship(updater.create_entity(&entities))
+                .at_point(origin);
+            let name = plan.prefab(i as usize);
+            let mut ship = enemy(&prefabs, name, default_wave_ship, ship, settings.difficulty)
+                .with(StatusEffects::with(Status::Shielded, SPAWN_SHIELD));
+            if plan.speed != 1.0 {
+                ship = ship.with(TimeMultiplier {
+                    factor: plan.speed,
+                    global: true,
+                });
+            }
+            ship.build();
+        }
+
+        if director.wave % BOSS_EVERY != 0 {
+            return;
+        }
+        let pattern = match library.patterns.get("boss") {
+            Some(pattern) => pattern.clone(),
+            None => return,
+        };
+        notifications.push("Boss incoming!");
+        let origin =
+            nalgebra::Point2::new(spawn.x - width / 2.0, spawn.y - height / 2.0 - WAVE_SPACING);
+        let ship = spawner
+            .ship(updater.create_entity(&entities))
+            .at_point(origin);
+        enemy(&prefabs, "boss", default_boss, ship, settings.difficulty)
+            .with(BulletPattern::new(pattern))
+            .with(StatusEffects::with(Status::Shielded, SPAWN_SHIELD))
+            .build();
+    }
+}
+
+// What the next wave is made up of
+struct WavePlan<'e> {
+    count: u32,
+    speed: f32,
+    // the prefabs the wave's ships take turns being made from
+    prefabs: &'e [String],
+}
+
+impl<'e> WavePlan<'e> {
+    // the usual wave, made only of wave ships
+    fn growing(count: u32) -> Self {
+        WavePlan {
+            count,
+            speed: 1.0,
+            prefabs: &[],
+        }
+    }
+
+    fn escalating(escalation: &'e Escalation, minutes: f32, room: usize) -> Self {
+        let count = escalation.count.at(minutes, 2.0).round().max(1.0) as usize;
+        let variety = escalation.variety.at(minutes, 1.0).round().max(1.0) as usize;
+        WavePlan {
+            count: count.min(room) as u32,
+            speed: escalation.speed.at(minutes, 1.0).max(0.1),
+            prefabs: &escalation.roster[..variety.min(escalation.roster.len())],
+        }
+    }
+
+    fn prefab(&self, ship: usize) -> &str {
+        if self.prefabs.is_empty() {
+            "wave_ship"
+        } else {
+            &self.prefabs[ship % self.prefabs.len()]
+        }
+    }
+}
+
+fn reward(
+    milestone: &Milestone,
+    score: &mut PlayerScore,
+    notifications: &mut Notifications,
+    controlled: &ReadStorage<ControllableTag>,
+    health: &mut WriteStorage<Health>,
+) {
+    score.points += milestone.points;
+    for (health, _) in (health, controlled).join() {
+        health.current = (health.current + fixed::real(milestone.health)).min(health.max);
+    }
+    notifications.push(&format!(
+        "Wave {} reached! +{} points",
+        milestone.wave, milestone.points
+    ));
+}
+
+// A ship made from the named prefab, or the built-in one if there isn't a
+// prefab by that name, but always Red and flown by the AI
+fn enemy<'s, B: Builder>(
+    prefabs: &Prefabs,
+    name: &str,
+    built_in: fn() -> Prefab,
+    ship: ShipBuilder<'s, B>,
+    difficulty: Difficulty,
+) -> ShipBuilder<'s, B> {
+    let (prefab, ship) = match prefabs.get(name) {
+        Some(prefab) => (prefab.clone(), ship.with(FromPrefab(name.to_owned()))),
+        None => (built_in(), ship),
+    };
+    let prefab = Prefab {
+        faction: Some(Faction::Red),
+        ai: true,
+        ..prefab
+    };
+    prefab::apply(&prefab, ship, difficulty)
+}
+
+fn default_wave_ship() -> Prefab {
+    Prefab {
+        weapon: Some(Weapon {
+            fire_delay: 0.8,
+            projectile_speed: 400.0,
+            damage: 10.0,
+        }),
+        ..Prefab::default()
+    }
+}
+
+// the boss only fires its bullet pattern
+fn default_boss() -> Prefab {
+    Prefab {
+        health: 400.0,
+        ..Prefab::default()
+    }
+}
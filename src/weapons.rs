@@ -0,0 +1,452 @@
+use crate::ai::AiControlled;
+use crate::controls::Aim;
+use crate::cooldowns::{self, Cooldowns};
+use crate::events::Publish;
+use crate::faction::{self, Faction};
+use crate::floating_text::FloatingText;
+use crate::health::{self, DamageEvent, Health};
+use crate::hitbox::{self, Hitbox, Hurtbox};
+use crate::listener::{Cue, SoundCues};
+use crate::settings::Settings;
+use crate::stealth::{self, Cloaked};
+use crate::targeting::{Homing, LockOn};
+use crate::telemetry::Telemetry;
+use crate::time::{DeltaTime, TimeMultiplier, TimeScale};
+use crate::{CollisionBox, ControllableTag, Position, Rotation};
+use ggez::graphics;
+use ggez::nalgebra;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
+use specs::saveload::ConvertSaveload;
+use specs::*;
+use specs_derive::*;
+use std::time::{Duration, Instant};
+
+// how long a projectile lives before it is removed, in seconds
+const PROJECTILE_LIFETIME: f32 = 1.5;
+// how many projectiles the stress test fires at once
+#[cfg(feature = "dev-tools")]
+const STRESS_COUNT: usize = 10_000;
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+#[storage(VecStorage)]
+pub(crate) struct Weapon {
+    // seconds between shots, timed by the ship's fire cooldown
+    pub(crate) fire_delay: f32,
+    // pixels per second
+    pub(crate) projectile_speed: f32,
+    // health taken off whatever a projectile hits
+    pub(crate) damage: f32,
+}
+
+// Projectiles are pooled rather than deleted, there can be thousands of them in
+// a busy fight. A spent projectile is marked inactive and its entity handed
+// back to the ProjectilePool to be reused by the next shot, so anything that
+// looks at projectiles needs to skip the inactive ones. That's why a
+// projectile moves by its own velocity and times out by its own time_left
+// rather than having a Velocity and a Lifetime, the MovementSystem would move
+// it twice and the LifetimeSystem would delete it out from under the pool.
+// DenseVecStorage keeps the projectile data packed together for the update.
+#[derive(Component, Debug)]
+#[storage(DenseVecStorage)]
+pub(crate) struct Projectile {
+    // the entity that fired this projectile, if it was still around when
+    // the world was last quickloaded
+    pub(crate) owner: Option<Entity>,
+    pub(crate) velocity: nalgebra::Vector2<f32>,
+    pub(crate) damage: f32,
+    // seconds left before the projectile is spent
+    pub(crate) time_left: f32,
+    pub(crate) active: bool,
+    // set once the shot has grazed a player, so it only pays out once
+    pub(crate) grazed: bool,
+}
+
+// A Projectile as it's quicksaved, with its owner by their marker
+#[derive(Serialize, Deserialize)]
+pub(crate) struct ProjectileData<M> {
+    owner: Option<M>,
+    velocity: nalgebra::Vector2<f32>,
+    damage: f32,
+    time_left: f32,
+    active: bool,
+    grazed: bool,
+}
+
+impl<M: Serialize + DeserializeOwned> ConvertSaveload<M> for Projectile {
+    type Data = ProjectileData<M>;
+    type Error = NoError;
+
+    fn convert_into<F>(&self, ids: F) -> Result<Self::Data, Self::Error>
+    where
+        F: FnMut(Entity) -> Option<M>,
+    {
+        Ok(ProjectileData {
+            owner: self.owner.and_then(ids),
+            velocity: self.velocity,
+            damage: self.damage,
+            time_left: self.time_left,
+            active: self.active,
+            grazed: self.grazed,
+        })
+    }
+
+    fn convert_from<F>(data: Self::Data, ids: F) -> Result<Self, Self::Error>
+    where
+        F: FnMut(M) -> Option<Entity>,
+    {
+        Ok(Projectile {
+            owner: data.owner.and_then(ids),
+            velocity: data.velocity,
+            damage: data.damage,
+            time_left: data.time_left,
+            active: data.active,
+            grazed: data.grazed,
+        })
+    }
+}
+
+// Spent projectile entities waiting to be reused
+#[derive(Debug, Default)]
+pub(crate) struct ProjectilePool {
+    free: Vec<Entity>,
+}
+
+impl ProjectilePool {
+    // Puts a projectile in the world, reusing a spent one if there are any.
+    // The components are written straight into the storages rather than
+    // through LazyUpdate so a projectile fired this frame is live right away.
+    pub(crate) fn spawn(
+        &mut self,
+        entities: &Entities,
+        pos: &mut WriteStorage<Position>,
+        projectiles: &mut WriteStorage<Projectile>,
+        hitboxes: &mut WriteStorage<Hitbox>,
+        position: nalgebra::Point2<f32>,
+        projectile: Projectile,
+    ) -> Entity {
+        let mut entity = None;
+        while let Some(free) = self.free.pop() {
+            if entities.is_alive(free) {
+                entity = Some(free);
+                break;
+            }
+        }
+        let entity = entity.unwrap_or_else(|| entities.create());
+
+        pos.insert(entity, Position { position })
+            .unwrap_or_else(|err| {
+                println!("projectile error {:?}", err);
+                None
+            });
+        projectiles
+            .insert(entity, projectile)
+            .unwrap_or_else(|err| {
+                println!("projectile error {:?}", err);
+                None
+            });
+        hitboxes
+            .insert(entity, Hitbox::projectile())
+            .unwrap_or_else(|err| {
+                println!("projectile error {:?}", err);
+                None
+            });
+        entity
+    }
+
+    pub(crate) fn release(&mut self, entity: Entity, projectile: &mut Projectile) {
+        projectile.active = false;
+        self.free.push(entity);
+    }
+
+    pub(crate) fn free(&self) -> usize {
+        self.free.len()
+    }
+}
+
+// How the last projectile update went, for keeping an eye on performance
+#[derive(Debug, Default)]
+pub(crate) struct ProjectileStats {
+    pub(crate) active: usize,
+    pub(crate) update_time: Duration,
+}
+
+// Fires weapons in the direction their ship faces, when the player or the AI
+// flying it has the trigger held
+pub(crate) struct FireSystem;
+
+impl<'a> System<'a> for FireSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Aim>,
+        Read<'a, LockOn>,
+        Read<'a, Settings>,
+        Write<'a, ProjectilePool>,
+        Write<'a, SoundCues>,
+        Write<'a, Telemetry>,
+        ReadStorage<'a, CollisionBox>,
+        ReadStorage<'a, Rotation>,
+        ReadStorage<'a, ControllableTag>,
+        ReadStorage<'a, AiControlled>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, Weapon>,
+        WriteStorage<'a, Cooldowns>,
+        WriteStorage<'a, Cloaked>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Projectile>,
+        WriteStorage<'a, Hitbox>,
+        WriteStorage<'a, Homing>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            aim,
+            lock,
+            settings,
+            mut pool,
+            mut cues,
+            mut telemetry,
+            coll_box,
+            rotation,
+            controlled,
+            ai,
+            factions,
+            weapons,
+            mut cooldowns,
+            mut cloaked,
+            mut pos,
+            mut projectiles,
+            mut hitboxes,
+            mut homing,
+        ) = data;
+
+        for (owner, coll_box, rotation, weapon, cooldowns) in
+            (&entities, &coll_box, &rotation, &weapons, &mut cooldowns).join()
+        {
+            let player = controlled.get(owner).is_some();
+            let firing = if player {
+                aim.firing
+            } else {
+                ai.get(owner).map_or(false, |ai| ai.firing)
+            };
+            if !firing || !cooldowns.try_use(cooldowns::FIRE, weapon.fire_delay) {
+                continue;
+            }
+            // muzzle flash gives away a cloaked shooter
+            stealth::disrupt(&mut cloaked, owner);
+            telemetry.shot(player, factions.get(owner));
+            // only the player's own shots are heard, a whole wave of enemies
+            // firing would drown everything else out
+            if player {
+                cues.shots.push(Cue {
+                    at: coll_box.center(),
+                    velocity: nalgebra::Vector2::zeros(),
+                });
+            }
+
+            // spawn at the nose of the ship rather than its middle
+            let heading = rotation.heading();
+            let projectile = pool.spawn(
+                &entities,
+                &mut pos,
+                &mut projectiles,
+                &mut hitboxes,
+                coll_box.center() + heading * coll_box.height / 2.0,
+                Projectile {
+                    owner: Some(owner),
+                    velocity: heading * weapon.projectile_speed,
+                    damage: weapon.damage,
+                    time_left: PROJECTILE_LIFETIME,
+                    active: true,
+                    grazed: false,
+                },
+            );
+
+            // aim assist nudges the player's shots toward whatever is locked on
+            if let (true, Some(target)) = (player, lock.target) {
+                if settings.aim_assist > 0.0 {
+                    homing
+                        .insert(
+                            projectile,
+                            Homing {
+                                target: Some(target),
+                                turn_rate: settings.aim_assist,
+                            },
+                        )
+                        .unwrap_or_else(|err| {
+                            println!("homing error {:?}", err);
+                            None
+                        });
+                }
+            }
+        }
+    }
+}
+
+// Updates every projectile in one pass: moves it, ages it and checks the path
+// its hitbox took this frame against every hurtbox. A projectile is
+// used up on the first thing it is allowed to hurt according to the faction
+// rules and passes through anything it isn't. Spent projectiles go back to the
+// pool. How long the update took goes in the ProjectileStats, which the F3
+// overlay shows.
+pub(crate) struct ProjectileSystem;
+
+impl<'a> System<'a> for ProjectileSystem {
+    type SystemData = (
+        Entities<'a>,
+        Read<'a, Settings>,
+        Read<'a, LazyUpdate>,
+        Read<'a, DeltaTime>,
+        Read<'a, TimeScale>,
+        Write<'a, ProjectilePool>,
+        Write<'a, ProjectileStats>,
+        Publish<'a, DamageEvent>,
+        ReadStorage<'a, Hitbox>,
+        ReadStorage<'a, Hurtbox>,
+        ReadStorage<'a, Faction>,
+        ReadStorage<'a, TimeMultiplier>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, Projectile>,
+        WriteStorage<'a, Homing>,
+        WriteStorage<'a, Cloaked>,
+        WriteStorage<'a, Health>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            settings,
+            updater,
+            delta,
+            time,
+            mut pool,
+            mut stats,
+            mut damage_events,
+            hitboxes,
+            hurtboxes,
+            factions,
+            multipliers,
+            mut pos,
+            mut projectiles,
+            mut homing,
+            mut cloaked,
+            mut health,
+        ) = data;
+        let started = Instant::now();
+
+        // gather the targets once rather than joining them for every projectile
+        let targets: Vec<(Entity, CollisionBox, Option<Faction>)> = (&entities, &pos, &hurtboxes)
+            .join()
+            .map(|(entity, pos, hurtbox)| {
+                (
+                    entity,
+                    hurtbox.0.at(pos.position),
+                    factions.get(entity).cloned(),
+                )
+            })
+            .collect();
+
+        let mut active = 0;
+        for (entity, pos, projectile) in (&entities, &mut pos, &mut projectiles).join() {
+            if !projectile.active {
+                continue;
+            }
+
+            // a projectile without a hitbox hits with just its middle
+            let shot = match hitboxes.get(entity) {
+                Some(hitbox) => hitbox.0.at(pos.position),
+                None => CollisionBox {
+                    origin: pos.position,
+                    width: 0.0,
+                    height: 0.0,
+                },
+            };
+            // projectiles follow the time scale whoever fired them, unless
+            // they have been slowed or sped up themselves
+            let dt = time.scaled(delta.seconds, multipliers.get(entity));
+            let delta = projectile.velocity * dt;
+            pos.position += delta;
+            projectile.time_left -= dt;
+
+            let attacker = projectile.owner.and_then(|owner| factions.get(owner));
+            let hit = targets.iter().find(|(target, hurtbox, side)| {
+                Some(*target) != projectile.owner
+                    && faction::can_damage(attacker, side.as_ref(), settings.friendly_fire)
+                    && hitbox::swept_hit(&shot, delta, hurtbox)
+            });
+
+            if let Some((target, _, _)) = hit {
+                stealth::disrupt(&mut cloaked, *target);
+                health::deal_damage(
+                    &mut health,
+                    &mut damage_events,
+                    *target,
+                    projectile.owner,
+                    projectile.damage,
+                );
+                // harmless shots, like the stress test's, don't announce hits
+                if projectile.damage > 0.0 {
+                    FloatingText::spawn(
+                        &entities,
+                        &updater,
+                        pos.position,
+                        "HIT",
+                        graphics::Color::new(1.0, 0.9, 0.3, 1.0),
+                    );
+                }
+            }
+
+            if hit.is_some() || projectile.time_left <= 0.0 {
+                homing.remove(entity);
+                pool.release(entity, projectile);
+            } else {
+                active += 1;
+            }
+        }
+
+        stats.active = active;
+        stats.update_time = started.elapsed();
+    }
+}
+
+// Fires a ring of STRESS_COUNT projectiles out from the player, to see how the
+// projectile update holds up in a bullet hell. Only built with the dev-tools
+// feature, and recorded as an Action so replays and the audit see it too.
+#[cfg(feature = "dev-tools")]
+pub(crate) fn stress_test(world: &World) {
+    let entities = world.entities();
+    let mut pool = world.write_resource::<ProjectilePool>();
+    let mut pos = world.write_storage::<Position>();
+    let mut projectiles = world.write_storage::<Projectile>();
+    let mut hitboxes = world.write_storage::<Hitbox>();
+    let coll_box = world.read_storage::<CollisionBox>();
+    let controlled = world.read_storage::<ControllableTag>();
+
+    let (owner, center) = match (&entities, &coll_box, &controlled).join().next() {
+        Some((owner, coll_box, _)) => (owner, coll_box.center()),
+        None => return,
+    };
+    for i in 0..STRESS_COUNT {
+        let angle = i as f32 / STRESS_COUNT as f32 * 2.0 * std::f32::consts::PI;
+        let heading = nalgebra::Vector2::new(angle.sin(), -angle.cos());
+        pool.spawn(
+            &entities,
+            &mut pos,
+            &mut projectiles,
+            &mut hitboxes,
+            center,
+            Projectile {
+                owner: Some(owner),
+                // spread them across a range of speeds so they don't all
+                // travel as one ring
+                velocity: heading * (100.0 + (i % 50) as f32 * 8.0),
+                damage: 0.0,
+                time_left: PROJECTILE_LIFETIME * 4.0,
+                active: true,
+                grazed: false,
+            },
+        );
+    }
+}